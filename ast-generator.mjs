@@ -19,6 +19,22 @@ const exprs = [
             string: "StringAtom"
         }
     },
+    {
+        name: "InterpolatedStringExpr",
+        ename: "InterpolatedString",
+        format_custom: `{
+            f.write_str("(interpolate ")?;
+            for part in &self.parts {
+                fmt::Display::fmt(part, f)?;
+                f.write_str(", ")?;
+            };
+            f.write_str(")")?;
+        }; Ok(())\n`,
+        fields: {
+            token: "Token",
+            parts: "Vec<'a, Expr<'a>>",
+        }
+    },
     {
         name: "VarExpr",
         ename: "Var",
@@ -58,7 +74,17 @@ const exprs = [
     {
         name: "InfixExpr",
         ename: "Infix",
-        format: "({} {} {})", fargs: "self.operator.lexeme, self.left, self.right",
+        // long chains like 'a + b + c + ...' are printed in a loop, see chain.rs
+        format_custom: `{
+            let (chain, first) = self.chain(|_| true);
+            for link in &chain {
+                f.write_fmt(format_args!("({} ", link.operator.lexeme))?;
+            }
+            fmt::Display::fmt(first, f)?;
+            for link in chain.iter().rev() {
+                f.write_fmt(format_args!(" {})", link.right))?;
+            }
+        }; Ok(())\n`,
         fields: {
             left: "Expr<'a>",
             operator: "Token",
@@ -114,12 +140,89 @@ const exprs = [
     {
         name: "AnynFnDeclExpr",
         ename: "AnynFnDecl",
-        format: "(fn ({}) {})", fargs: `self.parameters.iter().join(", "), self.body`,
+        format_custom: `
+            f.write_str("(fn (")?;
+            // the defaults belong to the last parameters
+            let first_default = self.parameters.len() - self.defaults.len();
+            for (i, param) in self.parameters.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                f.write_fmt(format_args!("{}", param.lexeme))?;
+                if i >= first_default {
+                    f.write_fmt(format_args!(" := {}", self.defaults[i - first_default]))?;
+                }
+            }
+            if let Some(rest) = &self.rest_parameter {
+                if !self.parameters.is_empty() {
+                    f.write_str(", ")?;
+                }
+                f.write_fmt(format_args!("{}...", rest.lexeme))?;
+            }
+            f.write_fmt(format_args!(") {})", self.body))\n`,
         fields: {
             fn_token: "Token",
             parameters: "Vec<'a, Token>",
+            defaults: "Vec<'a, Expr<'a>>",
+            // collects the arguments after the parameters into a list
+            rest_parameter: "Option<Token>",
             body: "BlockStmt<'a>",
         }
+    },
+    {
+        name: "BlockExpr",
+        ename: "Block",
+        format: "(do {})", fargs: "self.block",
+        fields: {
+            do_token: "Token",
+            block: "BlockStmt<'a>",
+        }
+    },
+    {
+        name: "IfExpr",
+        ename: "If",
+        format_custom: `
+            f.write_fmt(format_args!("(if-expr {} then {}", self.condition, self.then_block))?;
+            if let Some(ec) = &self.else_clause {
+                f.write_fmt(format_args!(" else {}", ec))?;
+            }
+            f.write_str(")")\n`,
+        fields: {
+            if_token: "Token",
+            condition: "Expr<'a>",
+            then_block: "BlockStmt<'a>",
+            else_token: "Option<Token>",
+            // a block expression, or another if expression for 'else if'
+            else_clause: "Option<Expr<'a>>",
+        }
+    },
+    {
+        name: "RecordExpr",
+        ename: "Record",
+        format_custom: `
+            f.write_fmt(format_args!("(record {}", self.name.lexeme))?;
+            for (field, value) in self.fields.iter().zip(&self.values) {
+                f.write_fmt(format_args!(" {}: {}", field.lexeme, value))?;
+            }
+            f.write_str(")")\n`,
+        fields: {
+            name: "Token",
+            brace_open: "Token",
+            // the field names, in the order they were written, each with the value at the same index
+            fields: "Vec<'a, Token>",
+            values: "Vec<'a, Expr<'a>>",
+            brace_close: "Token",
+        }
+    },
+    {
+        name: "FieldExpr",
+        ename: "Field",
+        format: "(. {} {})", fargs: "self.record, self.field.lexeme",
+        fields: {
+            record: "Expr<'a>",
+            dot: "Token",
+            field: "Token",
+        }
     }
 ];
 
@@ -127,10 +230,10 @@ const stmts = [
     {
         name: "PrintStmt",
         ename: "Print",
-        format: "(print {})", fargs: "self.inner",
+        format: "(print {})", fargs: `self.exprs.iter().join(", ")`,
         fields: {
             print_token: "Token",
-            inner: "Expr<'a>",
+            exprs: "Vec<'a, Expr<'a>>",
         }
     },
     {
@@ -138,24 +241,42 @@ const stmts = [
         ename: "Return",
         format_custom: `{
             f.write_str("(return")?;
-            if let Some(return_val) = &self.return_val {
-                f.write_fmt(format_args!(" {}", return_val))?;
+            if !self.return_vals.is_empty() {
+                f.write_fmt(format_args!(" {}", self.return_vals.iter().join(", ")))?;
             }
             f.write_char(')')?;
         }Ok(())`,
         fields: {
             return_token: "Token",
-            return_val: "Option<Expr<'a>>",
+            // several values are returned as a list, which 'let a, b := f()' unpacks
+            return_vals: "Vec<'a, Expr<'a>>",
         }
     },
     {
         name: "VarDeclStmt",
         ename: "VarDecl",
-        format: "({} {} {})", fargs: "self.var_token.lexeme, self.identifier.lexeme, self.init_expr",
+        format_custom: `{
+            f.write_fmt(format_args!("({} {}", self.var_token.lexeme, self.identifier.lexeme))?;
+            if let Some(init_expr) = &self.init_expr {
+                f.write_fmt(format_args!(" {}", init_expr))?;
+            }
+            f.write_char(')')?;
+        }Ok(())`,
         fields: {
             var_token: "Token",
             identifier: "Token",
-            init_expr: "Expr<'a>",
+            init_expr: "Option<Expr<'a>>",
+        }
+    },
+    {
+        name: "MultiVarDeclStmt",
+        ename: "MultiVarDecl",
+        format: "({} ({}) ({}))", fargs: `self.var_token.lexeme, self.identifiers.iter().map(|i| &i.lexeme).join(", "), self.init_exprs.iter().join(", ")`,
+        fields: {
+            var_token: "Token",
+            identifiers: "Vec<'a, Token>",
+            assignment_operator: "Token",
+            init_exprs: "Vec<'a, Expr<'a>>",
         }
     },
     {
@@ -217,6 +338,17 @@ const stmts = [
             block: "BlockStmt<'a>",
         }
     },
+    {
+        name: "ForStmt",
+        ename: "For",
+        format: "(for {} in {} {})", fargs: "self.variable.lexeme, self.iterable, self.block",
+        fields: {
+            for_token: "Token",
+            variable: "Token",
+            iterable: "Expr<'a>",
+            block: "BlockStmt<'a>",
+        }
+    },
     {
         name: "ExprStmt",
         ename: "ExprStmt",
@@ -225,23 +357,91 @@ const stmts = [
             expr: "Expr<'a>"
         }
     },
+    {
+        name: "MultiAssignStmt",
+        ename: "MultiAssign",
+        format: "(:= ({}) ({}))", fargs: `self.targets.iter().join(", "), self.values.iter().join(", ")`,
+        fields: {
+            targets: "Vec<'a, Expr<'a>>",
+            assignment_operator: "Token",
+            values: "Vec<'a, Expr<'a>>",
+        }
+    },
     {
         name: "FnDeclStmt",
         ename: "FnDecl",
-        format: "(fn {} ({}) {})", fargs: `self.name.lexeme, self.parameters.iter().map(|p| &p.lexeme).join(", "), self.body`,
+        format_custom: `
+            f.write_fmt(format_args!("(fn {} (", self.name.lexeme))?;
+            // the defaults belong to the last parameters
+            let first_default = self.parameters.len() - self.defaults.len();
+            for (i, param) in self.parameters.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                f.write_fmt(format_args!("{}", param.lexeme))?;
+                if i >= first_default {
+                    f.write_fmt(format_args!(" := {}", self.defaults[i - first_default]))?;
+                }
+            }
+            if let Some(rest) = &self.rest_parameter {
+                if !self.parameters.is_empty() {
+                    f.write_str(", ")?;
+                }
+                f.write_fmt(format_args!("{}...", rest.lexeme))?;
+            }
+            f.write_fmt(format_args!(") {})", self.body))\n`,
         fields: {
             fn_token: "Token",
             name: "Token",
             parameters: "Vec<'a, Token>",
+            defaults: "Vec<'a, Expr<'a>>",
+            // collects the arguments after the parameters into a list
+            rest_parameter: "Option<Token>",
             body: "BlockStmt<'a>",
         }
+    },
+    {
+        name: "StructDeclStmt",
+        ename: "StructDecl",
+        format: "(struct {} ({}))", fargs: `self.name.lexeme, self.fields.iter().map(|f| &f.lexeme).join(", ")`,
+        fields: {
+            struct_token: "Token",
+            name: "Token",
+            fields: "Vec<'a, Token>",
+        }
+    },
+    {
+        name: "ImportStmt",
+        ename: "Import",
+        format: "(import {})", fargs: "self.path.lexeme",
+        fields: {
+            import_token: "Token",
+            // the string literal with the path of the module
+            path: "Token",
+        }
+    },
+    {
+        name: "AssertStmt",
+        ename: "Assert",
+        format_custom: `{
+            f.write_fmt(format_args!("(assert {}", self.condition))?;
+            if let Some(message) = &self.message {
+                f.write_fmt(format_args!(", {}", message))?;
+            }
+            f.write_char(')')?;
+        }Ok(())`,
+        fields: {
+            assert_token: "Token",
+            condition: "Expr<'a>",
+            message: "Option<Expr<'a>>",
+        }
     }
 ]
 
 function structContainsLifeTime(struct) {
     let structLifetime = false;
     for (const val of Object.values(struct.fields)) {
-        if (val.includes("<'a>")) {
+        if (val.includes("'a")) {
             structLifetime = true;
         }
     }
@@ -295,7 +495,6 @@ function createExprs(exprs) {
             super::*,
             crate::compiler::{lexical_analysis::Token, string_handling::StringAtom},
             bumpalo::collections::Vec,
-            itertools::Itertools,
             std::fmt::{self, Debug},
         };
     
@@ -0,0 +1,21 @@
+use super::*;
+use crate::compiler::lexical_analysis::Token;
+
+impl<'a> InfixExpr<'a> {
+    /// The infix expressions down the left side of this one, starting with this one,
+    /// for as long as `continues` accepts their operator, and the operand the chain starts with.
+    /// Operators of the same precedence nest on the left, so 'a + b - c' is this one's chain,
+    /// which lets long chains be walked in a loop instead of recursing once per operator.
+    pub fn chain(&self, continues: impl Fn(&Token) -> bool) -> (std::vec::Vec<&Self>, &Expr<'a>) {
+        let mut chain = vec![self];
+        let mut first = &self.left;
+        while let Expr::Infix(left) = first {
+            if !continues(&left.operator) {
+                break;
+            }
+            chain.push(left);
+            first = &left.left;
+        }
+        (chain, first)
+    }
+}
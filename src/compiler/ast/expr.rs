@@ -1,9 +1,7 @@
-
 use {
     super::*,
     crate::compiler::{lexical_analysis::Token, string_handling::StringAtom},
     bumpalo::collections::Vec,
-    itertools::Itertools,
     std::fmt::{self, Debug},
 };
 
@@ -11,6 +9,7 @@ use {
 pub enum Expr<'a> {
     Number(&'a NumberExpr),
     String(&'a StringExpr),
+    InterpolatedString(&'a InterpolatedStringExpr<'a>),
     Var(&'a VarExpr),
     Bool(&'a BoolExpr),
     Group(&'a GroupExpr<'a>),
@@ -20,6 +19,10 @@ pub enum Expr<'a> {
     Subscript(&'a SubscriptExpr<'a>),
     Call(&'a CallExpr<'a>),
     AnynFnDecl(&'a AnynFnDeclExpr<'a>),
+    Block(&'a BlockExpr<'a>),
+    If(&'a IfExpr<'a>),
+    Record(&'a RecordExpr<'a>),
+    Field(&'a FieldExpr<'a>),
 }
 
 impl<'a> fmt::Display for Expr<'a> {
@@ -27,6 +30,7 @@ impl<'a> fmt::Display for Expr<'a> {
         match self {
             Expr::Number(e) => fmt::Display::fmt(e, f),
             Expr::String(e) => fmt::Display::fmt(e, f),
+            Expr::InterpolatedString(e) => fmt::Display::fmt(e, f),
             Expr::Var(e) => fmt::Display::fmt(e, f),
             Expr::Bool(e) => fmt::Display::fmt(e, f),
             Expr::Group(e) => fmt::Display::fmt(e, f),
@@ -36,6 +40,10 @@ impl<'a> fmt::Display for Expr<'a> {
             Expr::Subscript(e) => fmt::Display::fmt(e, f),
             Expr::Call(e) => fmt::Display::fmt(e, f),
             Expr::AnynFnDecl(e) => fmt::Display::fmt(e, f),
+            Expr::Block(e) => fmt::Display::fmt(e, f),
+            Expr::If(e) => fmt::Display::fmt(e, f),
+            Expr::Record(e) => fmt::Display::fmt(e, f),
+            Expr::Field(e) => fmt::Display::fmt(e, f),
         }
     }
 }
@@ -84,6 +92,36 @@ impl fmt::Display for StringExpr {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct InterpolatedStringExpr<'a> {
+    pub token: Token,
+    pub parts: Vec<'a, Expr<'a>>,
+}
+
+impl<'a> InterpolatedStringExpr<'a> {
+    pub fn new(token: Token, parts: Vec<'a, Expr<'a>>) -> InterpolatedStringExpr<'a> {
+        InterpolatedStringExpr { token, parts }
+    }
+
+    pub fn into_expr(self, arena: &'a bumpalo::Bump) -> Expr<'a> {
+        Expr::InterpolatedString(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for InterpolatedStringExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        {
+            f.write_str("(interpolate ")?;
+            for part in &self.parts {
+                fmt::Display::fmt(part, f)?;
+                f.write_str(", ")?;
+            }
+            f.write_str(")")?;
+        };
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VarExpr {
     pub identifier: Token,
@@ -199,10 +237,17 @@ impl<'a> InfixExpr<'a> {
 
 impl<'a> fmt::Display for InfixExpr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!(
-            "({} {} {})",
-            self.operator.lexeme, self.left, self.right
-        ))
+        {
+            let (chain, first) = self.chain(|_| true);
+            for link in &chain {
+                f.write_fmt(format_args!("({} ", link.operator.lexeme))?;
+            }
+            fmt::Display::fmt(first, f)?;
+            for link in chain.iter().rev() {
+                f.write_fmt(format_args!(" {})", link.right))?;
+            }
+        };
+        Ok(())
     }
 }
 
@@ -325,6 +370,8 @@ impl<'a> fmt::Display for CallExpr<'a> {
 pub struct AnynFnDeclExpr<'a> {
     pub fn_token: Token,
     pub parameters: Vec<'a, Token>,
+    pub defaults: Vec<'a, Expr<'a>>,
+    pub rest_parameter: Option<Token>,
     pub body: BlockStmt<'a>,
 }
 
@@ -332,11 +379,15 @@ impl<'a> AnynFnDeclExpr<'a> {
     pub fn new(
         fn_token: Token,
         parameters: Vec<'a, Token>,
+        defaults: Vec<'a, Expr<'a>>,
+        rest_parameter: Option<Token>,
         body: BlockStmt<'a>,
     ) -> AnynFnDeclExpr<'a> {
         AnynFnDeclExpr {
             fn_token,
             parameters,
+            defaults,
+            rest_parameter,
             body,
         }
     }
@@ -347,11 +398,155 @@ impl<'a> AnynFnDeclExpr<'a> {
 }
 
 impl<'a> fmt::Display for AnynFnDeclExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(fn (")?;
+        // the defaults belong to the last parameters
+        let first_default = self.parameters.len() - self.defaults.len();
+        for (i, param) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_fmt(format_args!("{}", param.lexeme))?;
+            if i >= first_default {
+                f.write_fmt(format_args!(" := {}", self.defaults[i - first_default]))?;
+            }
+        }
+        if let Some(rest) = &self.rest_parameter {
+            if !self.parameters.is_empty() {
+                f.write_str(", ")?;
+            }
+            f.write_fmt(format_args!("{}...", rest.lexeme))?;
+        }
+        f.write_fmt(format_args!(") {})", self.body))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockExpr<'a> {
+    pub do_token: Token,
+    pub block: BlockStmt<'a>,
+}
+
+impl<'a> BlockExpr<'a> {
+    pub fn new(do_token: Token, block: BlockStmt<'a>) -> BlockExpr<'a> {
+        BlockExpr { do_token, block }
+    }
+
+    pub fn into_expr(self, arena: &'a bumpalo::Bump) -> Expr<'a> {
+        Expr::Block(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for BlockExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("(do {})", self.block))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IfExpr<'a> {
+    pub if_token: Token,
+    pub condition: Expr<'a>,
+    pub then_block: BlockStmt<'a>,
+    pub else_token: Option<Token>,
+    pub else_clause: Option<Expr<'a>>,
+}
+
+impl<'a> IfExpr<'a> {
+    pub fn new(
+        if_token: Token,
+        condition: Expr<'a>,
+        then_block: BlockStmt<'a>,
+        else_token: Option<Token>,
+        else_clause: Option<Expr<'a>>,
+    ) -> IfExpr<'a> {
+        IfExpr {
+            if_token,
+            condition,
+            then_block,
+            else_token,
+            else_clause,
+        }
+    }
+
+    pub fn into_expr(self, arena: &'a bumpalo::Bump) -> Expr<'a> {
+        Expr::If(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for IfExpr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
-            "(fn ({}) {})",
-            self.parameters.iter().join(", "),
-            self.body
-        ))
+            "(if-expr {} then {}",
+            self.condition, self.then_block
+        ))?;
+        if let Some(ec) = &self.else_clause {
+            f.write_fmt(format_args!(" else {}", ec))?;
+        }
+        f.write_str(")")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordExpr<'a> {
+    pub name: Token,
+    pub brace_open: Token,
+    pub fields: Vec<'a, Token>,
+    pub values: Vec<'a, Expr<'a>>,
+    pub brace_close: Token,
+}
+
+impl<'a> RecordExpr<'a> {
+    pub fn new(
+        name: Token,
+        brace_open: Token,
+        fields: Vec<'a, Token>,
+        values: Vec<'a, Expr<'a>>,
+        brace_close: Token,
+    ) -> RecordExpr<'a> {
+        RecordExpr {
+            name,
+            brace_open,
+            fields,
+            values,
+            brace_close,
+        }
+    }
+
+    pub fn into_expr(self, arena: &'a bumpalo::Bump) -> Expr<'a> {
+        Expr::Record(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for RecordExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("(record {}", self.name.lexeme))?;
+        for (field, value) in self.fields.iter().zip(&self.values) {
+            f.write_fmt(format_args!(" {}: {}", field.lexeme, value))?;
+        }
+        f.write_str(")")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldExpr<'a> {
+    pub record: Expr<'a>,
+    pub dot: Token,
+    pub field: Token,
+}
+
+impl<'a> FieldExpr<'a> {
+    pub fn new(record: Expr<'a>, dot: Token, field: Token) -> FieldExpr<'a> {
+        FieldExpr { record, dot, field }
+    }
+
+    pub fn into_expr(self, arena: &'a bumpalo::Bump) -> Expr<'a> {
+        Expr::Field(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for FieldExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("(. {} {})", self.record, self.field.lexeme))
     }
 }
@@ -1,4 +1,3 @@
-
 use {
     super::*,
     crate::compiler::lexical_analysis::Token,
@@ -12,13 +11,19 @@ pub enum Stmt<'a> {
     Print(&'a PrintStmt<'a>),
     Return(&'a ReturnStmt<'a>),
     VarDecl(&'a VarDeclStmt<'a>),
+    MultiVarDecl(&'a MultiVarDeclStmt<'a>),
     Block(&'a BlockStmt<'a>),
     StmtList(&'a StmtList<'a>),
     Program(&'a ProgramStmt<'a>),
     If(&'a IfStmt<'a>),
     While(&'a WhileStmt<'a>),
+    For(&'a ForStmt<'a>),
     ExprStmt(&'a ExprStmt<'a>),
+    MultiAssign(&'a MultiAssignStmt<'a>),
     FnDecl(&'a FnDeclStmt<'a>),
+    StructDecl(&'a StructDeclStmt<'a>),
+    Import(&'a ImportStmt),
+    Assert(&'a AssertStmt<'a>),
 }
 
 impl<'a> fmt::Display for Stmt<'a> {
@@ -27,13 +32,19 @@ impl<'a> fmt::Display for Stmt<'a> {
             Stmt::Print(e) => fmt::Display::fmt(e, f),
             Stmt::Return(e) => fmt::Display::fmt(e, f),
             Stmt::VarDecl(e) => fmt::Display::fmt(e, f),
+            Stmt::MultiVarDecl(e) => fmt::Display::fmt(e, f),
             Stmt::Block(e) => fmt::Display::fmt(e, f),
             Stmt::StmtList(e) => fmt::Display::fmt(e, f),
             Stmt::Program(e) => fmt::Display::fmt(e, f),
             Stmt::If(e) => fmt::Display::fmt(e, f),
             Stmt::While(e) => fmt::Display::fmt(e, f),
+            Stmt::For(e) => fmt::Display::fmt(e, f),
             Stmt::ExprStmt(e) => fmt::Display::fmt(e, f),
+            Stmt::MultiAssign(e) => fmt::Display::fmt(e, f),
             Stmt::FnDecl(e) => fmt::Display::fmt(e, f),
+            Stmt::StructDecl(e) => fmt::Display::fmt(e, f),
+            Stmt::Import(e) => fmt::Display::fmt(e, f),
+            Stmt::Assert(e) => fmt::Display::fmt(e, f),
         }
     }
 }
@@ -41,12 +52,12 @@ impl<'a> fmt::Display for Stmt<'a> {
 #[derive(Debug, Clone)]
 pub struct PrintStmt<'a> {
     pub print_token: Token,
-    pub inner: Expr<'a>,
+    pub exprs: Vec<'a, Expr<'a>>,
 }
 
 impl<'a> PrintStmt<'a> {
-    pub fn new(print_token: Token, inner: Expr<'a>) -> PrintStmt<'a> {
-        PrintStmt { print_token, inner }
+    pub fn new(print_token: Token, exprs: Vec<'a, Expr<'a>>) -> PrintStmt<'a> {
+        PrintStmt { print_token, exprs }
     }
 
     pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
@@ -56,21 +67,21 @@ impl<'a> PrintStmt<'a> {
 
 impl<'a> fmt::Display for PrintStmt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("(print {})", self.inner))
+        f.write_fmt(format_args!("(print {})", self.exprs.iter().join(", ")))
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ReturnStmt<'a> {
     pub return_token: Token,
-    pub return_val: Option<Expr<'a>>,
+    pub return_vals: Vec<'a, Expr<'a>>,
 }
 
 impl<'a> ReturnStmt<'a> {
-    pub fn new(return_token: Token, return_val: Option<Expr<'a>>) -> ReturnStmt<'a> {
+    pub fn new(return_token: Token, return_vals: Vec<'a, Expr<'a>>) -> ReturnStmt<'a> {
         ReturnStmt {
             return_token,
-            return_val,
+            return_vals,
         }
     }
 
@@ -83,8 +94,8 @@ impl<'a> fmt::Display for ReturnStmt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         {
             f.write_str("(return")?;
-            if let Some(return_val) = &self.return_val {
-                f.write_fmt(format_args!(" {}", return_val))?;
+            if !self.return_vals.is_empty() {
+                f.write_fmt(format_args!(" {}", self.return_vals.iter().join(", ")))?;
             }
             f.write_char(')')?;
         }
@@ -96,11 +107,15 @@ impl<'a> fmt::Display for ReturnStmt<'a> {
 pub struct VarDeclStmt<'a> {
     pub var_token: Token,
     pub identifier: Token,
-    pub init_expr: Expr<'a>,
+    pub init_expr: Option<Expr<'a>>,
 }
 
 impl<'a> VarDeclStmt<'a> {
-    pub fn new(var_token: Token, identifier: Token, init_expr: Expr<'a>) -> VarDeclStmt<'a> {
+    pub fn new(
+        var_token: Token,
+        identifier: Token,
+        init_expr: Option<Expr<'a>>,
+    ) -> VarDeclStmt<'a> {
         VarDeclStmt {
             var_token,
             identifier,
@@ -114,10 +129,56 @@ impl<'a> VarDeclStmt<'a> {
 }
 
 impl<'a> fmt::Display for VarDeclStmt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        {
+            f.write_fmt(format_args!(
+                "({} {}",
+                self.var_token.lexeme, self.identifier.lexeme
+            ))?;
+            if let Some(init_expr) = &self.init_expr {
+                f.write_fmt(format_args!(" {}", init_expr))?;
+            }
+            f.write_char(')')?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiVarDeclStmt<'a> {
+    pub var_token: Token,
+    pub identifiers: Vec<'a, Token>,
+    pub assignment_operator: Token,
+    pub init_exprs: Vec<'a, Expr<'a>>,
+}
+
+impl<'a> MultiVarDeclStmt<'a> {
+    pub fn new(
+        var_token: Token,
+        identifiers: Vec<'a, Token>,
+        assignment_operator: Token,
+        init_exprs: Vec<'a, Expr<'a>>,
+    ) -> MultiVarDeclStmt<'a> {
+        MultiVarDeclStmt {
+            var_token,
+            identifiers,
+            assignment_operator,
+            init_exprs,
+        }
+    }
+
+    pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::MultiVarDecl(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for MultiVarDeclStmt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
-            "({} {} {})",
-            self.var_token.lexeme, self.identifier.lexeme, self.init_expr
+            "({} ({}) ({}))",
+            self.var_token.lexeme,
+            self.identifiers.iter().map(|i| &i.lexeme).join(", "),
+            self.init_exprs.iter().join(", ")
         ))
     }
 }
@@ -272,6 +333,43 @@ impl<'a> fmt::Display for WhileStmt<'a> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ForStmt<'a> {
+    pub for_token: Token,
+    pub variable: Token,
+    pub iterable: Expr<'a>,
+    pub block: BlockStmt<'a>,
+}
+
+impl<'a> ForStmt<'a> {
+    pub fn new(
+        for_token: Token,
+        variable: Token,
+        iterable: Expr<'a>,
+        block: BlockStmt<'a>,
+    ) -> ForStmt<'a> {
+        ForStmt {
+            for_token,
+            variable,
+            iterable,
+            block,
+        }
+    }
+
+    pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::For(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for ForStmt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "(for {} in {} {})",
+            self.variable.lexeme, self.iterable, self.block
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExprStmt<'a> {
     pub expr: Expr<'a>,
@@ -293,11 +391,48 @@ impl<'a> fmt::Display for ExprStmt<'a> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct MultiAssignStmt<'a> {
+    pub targets: Vec<'a, Expr<'a>>,
+    pub assignment_operator: Token,
+    pub values: Vec<'a, Expr<'a>>,
+}
+
+impl<'a> MultiAssignStmt<'a> {
+    pub fn new(
+        targets: Vec<'a, Expr<'a>>,
+        assignment_operator: Token,
+        values: Vec<'a, Expr<'a>>,
+    ) -> MultiAssignStmt<'a> {
+        MultiAssignStmt {
+            targets,
+            assignment_operator,
+            values,
+        }
+    }
+
+    pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::MultiAssign(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for MultiAssignStmt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "(:= ({}) ({}))",
+            self.targets.iter().join(", "),
+            self.values.iter().join(", ")
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FnDeclStmt<'a> {
     pub fn_token: Token,
     pub name: Token,
     pub parameters: Vec<'a, Token>,
+    pub defaults: Vec<'a, Expr<'a>>,
+    pub rest_parameter: Option<Token>,
     pub body: BlockStmt<'a>,
 }
 
@@ -306,12 +441,16 @@ impl<'a> FnDeclStmt<'a> {
         fn_token: Token,
         name: Token,
         parameters: Vec<'a, Token>,
+        defaults: Vec<'a, Expr<'a>>,
+        rest_parameter: Option<Token>,
         body: BlockStmt<'a>,
     ) -> FnDeclStmt<'a> {
         FnDeclStmt {
             fn_token,
             name,
             parameters,
+            defaults,
+            rest_parameter,
             body,
         }
     }
@@ -322,12 +461,116 @@ impl<'a> FnDeclStmt<'a> {
 }
 
 impl<'a> fmt::Display for FnDeclStmt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("(fn {} (", self.name.lexeme))?;
+        // the defaults belong to the last parameters
+        let first_default = self.parameters.len() - self.defaults.len();
+        for (i, param) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_fmt(format_args!("{}", param.lexeme))?;
+            if i >= first_default {
+                f.write_fmt(format_args!(" := {}", self.defaults[i - first_default]))?;
+            }
+        }
+        if let Some(rest) = &self.rest_parameter {
+            if !self.parameters.is_empty() {
+                f.write_str(", ")?;
+            }
+            f.write_fmt(format_args!("{}...", rest.lexeme))?;
+        }
+        f.write_fmt(format_args!(") {})", self.body))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDeclStmt<'a> {
+    pub struct_token: Token,
+    pub name: Token,
+    pub fields: Vec<'a, Token>,
+}
+
+impl<'a> StructDeclStmt<'a> {
+    pub fn new(struct_token: Token, name: Token, fields: Vec<'a, Token>) -> StructDeclStmt<'a> {
+        StructDeclStmt {
+            struct_token,
+            name,
+            fields,
+        }
+    }
+
+    pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::StructDecl(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for StructDeclStmt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
-            "(fn {} ({}) {})",
+            "(struct {} ({}))",
             self.name.lexeme,
-            self.parameters.iter().map(|p| &p.lexeme).join(", "),
-            self.body
+            self.fields.iter().map(|f| &f.lexeme).join(", ")
         ))
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct ImportStmt {
+    pub import_token: Token,
+    pub path: Token,
+}
+
+impl ImportStmt {
+    pub fn new(import_token: Token, path: Token) -> ImportStmt {
+        ImportStmt { import_token, path }
+    }
+
+    pub fn into_stmt<'a>(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::Import(arena.alloc(self))
+    }
+}
+
+impl fmt::Display for ImportStmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("(import {})", self.path.lexeme))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssertStmt<'a> {
+    pub assert_token: Token,
+    pub condition: Expr<'a>,
+    pub message: Option<Expr<'a>>,
+}
+
+impl<'a> AssertStmt<'a> {
+    pub fn new(
+        assert_token: Token,
+        condition: Expr<'a>,
+        message: Option<Expr<'a>>,
+    ) -> AssertStmt<'a> {
+        AssertStmt {
+            assert_token,
+            condition,
+            message,
+        }
+    }
+
+    pub fn into_stmt(self, arena: &'a bumpalo::Bump) -> Stmt<'a> {
+        Stmt::Assert(arena.alloc(self))
+    }
+}
+
+impl<'a> fmt::Display for AssertStmt<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        {
+            f.write_fmt(format_args!("(assert {}", self.condition))?;
+            if let Some(message) = &self.message {
+                f.write_fmt(format_args!(", {}", message))?;
+            }
+            f.write_char(')')?;
+        }
+        Ok(())
+    }
+}
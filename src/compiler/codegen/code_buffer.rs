@@ -0,0 +1,45 @@
+use crate::compiler::lexical_analysis::TokenPos;
+
+/// The code of a function being generated, along with the source position of every byte.
+/// The fields are private, so every write goes through `push`, which keeps the two in sync.
+#[derive(Debug, Default)]
+pub(super) struct CodeBuffer {
+    code: Vec<u8>,
+    code_map: Vec<TokenPos>,
+}
+
+impl CodeBuffer {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn push(&mut self, byte: u8, pos: TokenPos) {
+        self.code.push(byte);
+        self.code_map.push(pos);
+        debug_assert_eq!(
+            self.code.len(),
+            self.code_map.len(),
+            "code and code map diverged"
+        );
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Overwrites the 4 bytes at the address, which have to have been pushed already,
+    /// so the code map stays as it is.
+    pub(super) fn patch_u32(&mut self, address: usize, value: u32) {
+        self.code[address..address + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Throws away everything after the first `len` bytes.
+    pub(super) fn truncate(&mut self, len: usize) {
+        self.code.truncate(len);
+        self.code_map.truncate(len);
+    }
+
+    pub(super) fn into_parts(self) -> (Vec<u8>, Vec<TokenPos>) {
+        (self.code, self.code_map)
+    }
+}
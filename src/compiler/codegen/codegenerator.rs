@@ -1,66 +1,192 @@
-use std::{collections::hash_map::Entry, convert::TryInto, fmt};
+use std::{collections::hash_map::Entry, convert::TryInto, fmt, mem, rc::Rc};
 
 use ahash::AHashMap;
 
-use super::error::{CodeGenError, Result};
+use super::{
+    code_buffer::CodeBuffer,
+    effects,
+    error::{CodeGenError, Result},
+    struct_table::StructTable,
+    warning::Warning,
+};
 
 use crate::{
     compiler::{
         ast::*,
-        lexical_analysis::{Token, TokenPos, TokenType},
+        lexical_analysis::{token_groups, CustomOperator, Token, TokenPos, TokenType},
         string_handling::StringAtom,
     },
-    executable::{CahnFunction, Executable, Instruction},
+    executable::{CahnFunction, Capture, Executable, Instruction, TYPE_NAMES},
+    runtime::{builtins::BUILTINS, NativeFunction},
+    utils::hash_string,
 };
 
+// the parts of a function declaration, named or anonymous
+struct FunctionDecl<'f, 'b> {
+    fn_token: &'f Token,
+    name: Option<&'f Token>,
+    parameters: &'f [Token],
+    defaults: &'f [Expr<'b>],
+    rest_parameter: Option<&'f Token>,
+    body: &'f BlockStmt<'b>,
+}
+
+impl<'f, 'b> FunctionDecl<'f, 'b> {
+    fn named(fds: &'f FnDeclStmt<'b>) -> Self {
+        FunctionDecl {
+            fn_token: &fds.fn_token,
+            name: Some(&fds.name),
+            parameters: &fds.parameters,
+            defaults: &fds.defaults,
+            rest_parameter: fds.rest_parameter.as_ref(),
+            body: &fds.body,
+        }
+    }
+}
+
 #[derive(Clone)]
 struct Local {
     name: Option<StringAtom>,
+    // the stack slot relative to the frame pointer, which can be above the number of locals
+    // if there were temporary values on the stack when the local was declared.
+    slot: usize,
     scope_level: usize,
+    is_const: bool,
+    // the value of a constant that is known while compiling, used to prune branches
+    known_bool: Option<bool>,
+    declared_at: TokenPos,
+    // whether the local is ever read, unused locals are warned about
+    is_used: bool,
+    // whether a closure captured the local, so it has to be closed when it goes out of scope
+    is_captured: bool,
+}
+
+// a variable of an enclosing function, that the function uses
+#[derive(Debug, Clone)]
+struct Upvalue {
+    capture: Capture,
+    is_const: bool,
+    declared_at: TokenPos,
+}
+
+// the state of a function that's halfway compiled, while a function declared in it is compiled.
+// the inner function looks through them to find the variables it captures.
+#[derive(Debug, Default)]
+struct EnclosingFunction {
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Global {
+    index: u32,
+    is_const: bool,
+    known_bool: Option<bool>,
+    // none if the global has only been referenced from functions so far
+    declared_at: Option<TokenPos>,
+    // whether code assigning to the global was generated, a constant declared after that
+    // can't be folded, the assignment could run after the declaration.
+    is_assigned: bool,
+}
+
+enum Variable {
+    Local(usize),
+    Upvalue(usize),
+    Global(u32),
+    Native(usize),
 }
 
 impl fmt::Debug for Local {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.name {
             Some(name) => f.write_fmt(format_args!(
-                "Local(name: {}, level: {})",
-                name, self.scope_level
+                "Local(name: {}, slot: {}, level: {}, const: {})",
+                name, self.slot, self.scope_level, self.is_const
+            )),
+            None => f.write_fmt(format_args!(
+                "AnonymousLocal(slot: {}, level: {})",
+                self.slot, self.scope_level
             )),
-            None => f.write_fmt(format_args!("AnonymousLocal(level: {})", self.scope_level)),
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct CodeGenOptions {
+    /// leave out the code of branches whose condition is known while compiling.
+    pub optimize: bool,
+    /// make the warnings students trip over errors, and require ';' between statements on one line.
+    pub strict: bool,
+    /// the codes of warnings that shouldn't be reported, like "shadowed-variable".
+    pub allowed_lints: Vec<String>,
+    /// warn about if, else and while bodies without statements.
+    pub warn_empty_blocks: bool,
+    /// warn about while loops that are always true, whose body can't stop them or have an effect.
+    pub warn_infinite_loops: bool,
+    /// operators the source can use, each calling the native it's bound to.
+    pub custom_operators: Vec<CustomOperator>,
+}
+
 pub struct CodeGenerator<'a> {
     // shared data
     num_consts: &'a mut Vec<f64>,
-    num_consts_map: &'a mut AHashMap<StringAtom, usize>,
+    num_consts_map: &'a mut AHashMap<u64, usize>,
 
     string_data: &'a mut String,
-    string_data_map: &'a mut AHashMap<StringAtom, (u32, u32)>,
+    // keyed by the hash of the string, so equal strings share their bytes however they're added
+    string_data_map: &'a mut AHashMap<u64, (u32, u32)>,
     source_file_name: &'a str,
 
     functions: &'a mut Vec<CahnFunction>,
+    natives: &'a [NativeFunction],
+
+    globals: &'a mut AHashMap<StringAtom, Global>,
+    global_names: &'a mut Vec<String>,
+
+    structs: &'a mut StructTable,
+
+    options: CodeGenOptions,
+    warnings: &'a mut Vec<Warning>,
 
     // function unique data
-    code: Vec<u8>,
-    code_map: Vec<TokenPos>,
+    code: CodeBuffer,
     current_source_position: TokenPos,
 
     locals: Vec<Local>,
+    // the indices of the named locals, by name, the innermost last.
+    // so declaring a local doesn't have to look through all the others.
+    local_names: AHashMap<StringAtom, Vec<usize>>,
+    upvalues: Vec<Upvalue>,
+    // the functions this one is declared in, the innermost last
+    enclosing: Vec<EnclosingFunction>,
     scope_level: usize,
+    // the number of values on the stack at this point of the function
+    stack_height: usize,
+
+    // whether this generates the top level script function, or a function declared in it
+    is_toplevel: bool,
 }
 
 impl<'a> CodeGenerator<'a> {
-    pub fn new(
+    #[allow(clippy::too_many_arguments)]
+    fn new(
         num_consts: &'a mut Vec<f64>,
-        num_consts_map: &'a mut AHashMap<StringAtom, usize>,
+        num_consts_map: &'a mut AHashMap<u64, usize>,
 
         string_data: &'a mut String,
-        string_data_map: &'a mut AHashMap<StringAtom, (u32, u32)>,
+        string_data_map: &'a mut AHashMap<u64, (u32, u32)>,
         source_file_name: &'a str,
 
         functions: &'a mut Vec<CahnFunction>,
+        natives: &'a [NativeFunction],
+
+        globals: &'a mut AHashMap<StringAtom, Global>,
+        global_names: &'a mut Vec<String>,
+
+        structs: &'a mut StructTable,
+
+        options: CodeGenOptions,
+        warnings: &'a mut Vec<Warning>,
     ) -> Self {
         Self {
             num_consts,
@@ -69,24 +195,59 @@ impl<'a> CodeGenerator<'a> {
             string_data_map,
             source_file_name,
             functions,
-
-            code: vec![],
-            code_map: vec![],
+            natives,
+            globals,
+            global_names,
+            structs,
+            options,
+            warnings,
+
+            code: CodeBuffer::new(),
             current_source_position: TokenPos::new(1, 1),
             locals: vec![],
+            local_names: AHashMap::new(),
+            upvalues: vec![],
+            enclosing: vec![],
             scope_level: 0,
+            stack_height: 0,
+            is_toplevel: true,
         }
     }
 
-    pub fn from_parent(parent: &'a mut CodeGenerator) -> Self {
-        Self::new(
+    // the parent's locals and upvalues are moved into the child, until end_child gives them back.
+    fn from_parent(parent: &'a mut CodeGenerator) -> Self {
+        let mut enclosing = mem::take(&mut parent.enclosing);
+        enclosing.push(EnclosingFunction {
+            locals: mem::take(&mut parent.locals),
+            upvalues: mem::take(&mut parent.upvalues),
+        });
+
+        let mut child = Self::new(
             parent.num_consts,
             parent.num_consts_map,
             parent.string_data,
             parent.string_data_map,
             parent.source_file_name,
             parent.functions,
-        )
+            parent.natives,
+            parent.globals,
+            parent.global_names,
+            parent.structs,
+            parent.options.clone(),
+            parent.warnings,
+        );
+        child.is_toplevel = false;
+        child.enclosing = enclosing;
+        child
+    }
+
+    fn end_child(&mut self, mut enclosing: Vec<EnclosingFunction>) {
+        let parent = enclosing
+            .pop()
+            .expect("the child should hold its parent's state");
+        self.locals = parent.locals;
+        self.upvalues = parent.upvalues;
+        self.enclosing = enclosing;
     }
 
     fn begin_scope(&mut self) {
@@ -97,50 +258,348 @@ impl<'a> CodeGenerator<'a> {
         self.scope_level -= 1;
 
         while matches!(self.locals.last(), Some(local) if local.scope_level > self.scope_level) {
+            self.emit_close_local_instruction();
+            self.emit_instruction(Instruction::Pop);
+            self.pop_local();
+        }
+    }
+
+    // like end_scope, but the value on top of the stack is kept, with the locals popped from under it.
+    fn end_scope_keeping_top(&mut self) {
+        self.scope_level -= 1;
+
+        while matches!(self.locals.last(), Some(local) if local.scope_level > self.scope_level) {
+            self.emit_close_local_instruction();
+            self.emit_instruction(Instruction::Swap);
             self.emit_instruction(Instruction::Pop);
-            self.locals.pop();
+            self.pop_local();
+        }
+    }
+
+    // the last local is moved off the stack if it was captured, so the closures can still use it.
+    // this has to happen while the local is still in its slot.
+    fn emit_close_local_instruction(&mut self) {
+        if let Some(local) = self.locals.last().filter(|local| local.is_captured) {
+            let slot = local.slot as u16;
+            self.emit_instruction(Instruction::CloseUpvalue);
+            self.emit_bytes(&slot.to_le_bytes());
+        }
+    }
+
+    fn pop_local(&mut self) {
+        let local = self.locals.pop().expect("there should be a local to pop");
+        if let Some(name) = &local.name {
+            if let Some(indices) = self.local_names.get_mut(name) {
+                indices.pop();
+            }
+        }
+        if local.is_used {
+            return;
+        }
+
+        // names starting with '_' are meant to be unused
+        if let Some(name) = local.name {
+            if !name.run_on_str(|name| name.starts_with('_')) {
+                self.warnings.push(Warning::UnusedVariable {
+                    name: name.run_on_str(str::to_owned),
+                    declared_at: local.declared_at,
+                });
+            }
         }
     }
 
+    // locals are declared once their value has been pushed, so they take the slot on top of the stack.
     fn declare_anonymous_local(&mut self) -> usize {
         let local_index = self.locals.len();
         self.locals.push(Local {
             name: None,
+            slot: self.stack_height - 1,
             scope_level: self.scope_level,
+            is_const: false,
+            known_bool: None,
+            declared_at: self.current_source_position,
+            is_used: true,
+            is_captured: false,
         });
         local_index
     }
 
-    fn declare_local(&mut self, name: &StringAtom) -> usize {
+    fn declare_local(
+        &mut self,
+        identifier: &Token,
+        is_const: bool,
+        known_bool: Option<bool>,
+    ) -> Result<usize> {
+        self.declare_local_in_slot(self.stack_height - 1, identifier, is_const, known_bool)
+    }
+
+    fn declare_local_in_slot(
+        &mut self,
+        slot: usize,
+        identifier: &Token,
+        is_const: bool,
+        known_bool: Option<bool>,
+    ) -> Result<usize> {
         let local_index = self.locals.len();
+
+        // locals are addressed with at most 16 bits
+        if slot > u16::MAX as usize {
+            return Err(CodeGenError::TooManyLocals {
+                token: identifier.clone(),
+                max: u16::MAX as usize + 1,
+            });
+        }
+
+        // slot 0 holds the function itself, its name was checked where the function was declared
+        if slot > 0 {
+            self.check_redeclaration(identifier)?;
+            self.check_shadowing(identifier);
+        }
+
+        self.local_names
+            .entry(identifier.lexeme.clone())
+            .or_default()
+            .push(local_index);
         self.locals.push(Local {
-            name: Some(name.clone()),
+            name: Some(identifier.lexeme.clone()),
+            slot,
             scope_level: self.scope_level,
+            is_const,
+            known_bool,
+            declared_at: identifier.pos,
+            is_used: false,
+            is_captured: false,
         });
-        local_index
+        Ok(local_index)
     }
 
-    fn get_local_index_by_token(&mut self, identifier: &Token) -> Result<usize> {
-        match self.get_local_index(&identifier.lexeme) {
-            Some(index) => Ok(index),
-            None => Err(CodeGenError::UnresolvedVariable {
-                var_token: identifier.clone(),
+    // two locals of the same name in one scope would leave the first one unreachable,
+    // which is most likely a mistake, shadowing only makes sense in an inner scope.
+    fn check_redeclaration(&self, identifier: &Token) -> Result<()> {
+        // the locals of inner scopes are gone, so only the innermost one can be in this scope
+        let declared_at = self
+            .local_names
+            .get(&identifier.lexeme)
+            .and_then(|indices| indices.last())
+            .map(|&index| &self.locals[index])
+            .filter(|local| local.scope_level == self.scope_level && local.slot > 0)
+            .map(|local| local.declared_at);
+
+        match declared_at {
+            Some(declared_at) => Err(CodeGenError::DuplicateDeclaration {
+                token: identifier.clone(),
+                declared_at,
             }),
+            None => Ok(()),
         }
     }
 
-    fn get_local_index(&mut self, name: &StringAtom) -> Option<usize> {
-        self.locals
-            .iter()
-            .enumerate()
+    // warns if the new local hides a local of an outer scope, or a global.
+    // names starting with '_' are meant to shadow.
+    fn check_shadowing(&mut self, identifier: &Token) {
+        let name = &identifier.lexeme;
+        if name.run_on_str(|name| name.starts_with('_')) {
+            return;
+        }
+
+        let outer_local = self
+            .local_names
+            .get(name)
+            .into_iter()
+            .flatten()
             .rev()
-            .filter(|(_index, entry)| entry.name.is_some())
-            .find(|(_index, entry)| entry.name.as_ref().unwrap() == name)
-            .map(|(index, _entry)| index)
+            .map(|&index| &self.locals[index])
+            .find(|local| local.scope_level < self.scope_level)
+            .map(|local| local.declared_at);
+        let global = || self.globals.get(name).and_then(|global| global.declared_at);
+
+        if let Some(shadowed_at) = outer_local.or_else(global) {
+            self.warnings.push(Warning::ShadowedVariable {
+                name: name.run_on_str(str::to_owned),
+                declared_at: identifier.pos,
+                shadowed_at,
+            });
+        }
+    }
+
+    fn get_native_index(&self, name: &StringAtom) -> Option<usize> {
+        name.run_on_str(|name| self.natives.iter().position(|native| native.name == name))
+    }
+
+    // declarations directly in the program body are globals, the program body is scope 1.
+    fn is_global_scope(&self) -> bool {
+        self.is_toplevel && self.scope_level == 1
+    }
+
+    fn get_global_index(&mut self, identifier: &Token) -> Result<u32> {
+        if let Some(global) = self.globals.get(&identifier.lexeme) {
+            return Ok(global.index);
+        }
+
+        let index =
+            self.global_names
+                .len()
+                .try_into()
+                .map_err(|_| CodeGenError::TooManyGlobals {
+                    max: u32::MAX as usize,
+                })?;
+
+        self.global_names
+            .push(identifier.lexeme.run_on_str(str::to_owned));
+        self.globals.insert(
+            identifier.lexeme.clone(),
+            Global {
+                index,
+                is_const: false,
+                known_bool: None,
+                declared_at: None,
+                is_assigned: false,
+            },
+        );
+        Ok(index)
+    }
+
+    fn define_global(
+        &mut self,
+        identifier: &Token,
+        is_const: bool,
+        known_bool: Option<bool>,
+    ) -> Result<()> {
+        let index = self.get_global_index(identifier)?;
+        let global = self.globals[&identifier.lexeme];
+
+        // conditions using the constant may have been folded, so it has to keep its value
+        if let (true, Some(declared_at)) = (global.is_const, global.declared_at) {
+            return Err(CodeGenError::DuplicateDeclaration {
+                token: identifier.clone(),
+                declared_at,
+            });
+        }
+
+        self.globals.insert(
+            identifier.lexeme.clone(),
+            Global {
+                index,
+                is_const,
+                known_bool: known_bool.filter(|_| !global.is_assigned),
+                declared_at: Some(identifier.pos),
+                is_assigned: global.is_assigned,
+            },
+        );
+
+        self.emit_instruction(Instruction::DefineGlobal);
+        self.emit_bytes(&index.to_le_bytes());
+        Ok(())
+    }
+
+    // binds the value on top of the stack to the name, as a global or a local depending on the scope.
+    fn define_variable(
+        &mut self,
+        identifier: &Token,
+        is_const: bool,
+        known_bool: Option<bool>,
+    ) -> Result<()> {
+        if self.is_global_scope() {
+            self.define_global(identifier, is_const, known_bool)
+        } else {
+            self.declare_local(identifier, is_const, known_bool)
+                .map(|_| ())
+        }
+    }
+
+    // locals shadow globals, which shadow natives.
+    fn resolve_variable(&mut self, identifier: &Token) -> Result<Variable> {
+        if let Some(index) = self.get_local_index(&identifier.lexeme) {
+            return Ok(Variable::Local(index));
+        }
+
+        if let Some(index) = self.resolve_upvalue(identifier)? {
+            return Ok(Variable::Upvalue(index));
+        }
+
+        if self.structs.contains(&identifier.lexeme) {
+            return Err(CodeGenError::Unsupported {
+                feature: "structs as values",
+                token: identifier.clone(),
+            });
+        }
+
+        if let Some(global) = self.globals.get(&identifier.lexeme) {
+            return Ok(Variable::Global(global.index));
+        }
+
+        if let Some(index) = self.get_native_index(&identifier.lexeme) {
+            return Ok(Variable::Native(index));
+        }
+
+        // functions can use globals that are declared after them,
+        // so if the global doesn't exist, it's an error at runtime instead.
+        if !self.is_toplevel {
+            return Ok(Variable::Global(self.get_global_index(identifier)?));
+        }
+
+        Err(CodeGenError::UnresolvedVariable {
+            var_token: identifier.clone(),
+        })
+    }
+
+    // the struct a call constructs a record of, with its name and field count.
+    // locals and captured variables shadow structs, like they shadow globals.
+    fn called_struct<'c>(&mut self, callee: &'c Expr) -> Result<Option<(&'c Token, u32, usize)>> {
+        let identifier = match callee {
+            Expr::Var(ve) => &ve.identifier,
+            _ => return Ok(None),
+        };
+        if self.get_local_index(&identifier.lexeme).is_some()
+            || self.resolve_upvalue(identifier)?.is_some()
+        {
+            return Ok(None);
+        }
+        Ok(self
+            .structs
+            .get(&identifier.lexeme)
+            .map(|(index, fields)| (identifier, index, fields.len())))
+    }
+
+    fn get_local_index(&mut self, name: &StringAtom) -> Option<usize> {
+        find_local(&self.locals, name)
     }
 
-    fn get_local(&self, index: usize) -> Option<&Local> {
-        self.locals.get(index)
+    // finds the variable in the innermost enclosing function that has it as a local.
+    // every function between that one and this one captures it too, so their closures can pass it on.
+    fn resolve_upvalue(&mut self, identifier: &Token) -> Result<Option<usize>> {
+        let name = &identifier.lexeme;
+        let level = match self
+            .enclosing
+            .iter()
+            .rposition(|function| find_local(&function.locals, name).is_some())
+        {
+            Some(level) => level,
+            None => return Ok(None),
+        };
+
+        let locals = &mut self.enclosing[level].locals;
+        let local_index = find_local(locals, name).unwrap();
+        let local = &mut locals[local_index];
+        local.is_captured = true;
+        local.is_used = true;
+
+        let mut upvalue = Upvalue {
+            capture: Capture {
+                is_local: true,
+                index: local.slot as u16,
+            },
+            is_const: local.is_const,
+            declared_at: local.declared_at,
+        };
+        for function in &mut self.enclosing[level + 1..] {
+            let index = add_upvalue(&mut function.upvalues, upvalue.clone(), identifier)?;
+            upvalue.capture = Capture {
+                is_local: false,
+                index: index as u16,
+            };
+        }
+        add_upvalue(&mut self.upvalues, upvalue, identifier).map(Some)
     }
 
     fn set_source_pos(&mut self, pos: TokenPos) {
@@ -148,8 +607,7 @@ impl<'a> CodeGenerator<'a> {
     }
 
     fn emit_byte(&mut self, byte: u8) {
-        self.code.push(byte);
-        self.code_map.push(self.current_source_position);
+        self.code.push(byte, self.current_source_position);
     }
 
     fn emit_bytes(&mut self, bytes: &[u8]) {
@@ -158,6 +616,10 @@ impl<'a> CodeGenerator<'a> {
 
     fn emit_instruction(&mut self, instruction: Instruction) {
         self.emit_byte(instruction as u8);
+        // the effect of Invoke and PrintN depends on their operand, which their caller accounts for
+        if let Some((pops, pushes)) = instruction.stack_effect() {
+            self.stack_height = self.stack_height - pops + pushes;
+        }
     }
 
     fn emit_load_num_lit_instruction(&mut self, num: u8) {
@@ -206,8 +668,43 @@ impl<'a> CodeGenerator<'a> {
         target: &Expr<'b>,
         source: &Expr<'b>,
     ) -> Result<()> {
+        // the record is evaluated before the value, which is swapped under it for SetField
+        if let Expr::Field(fe) = target {
+            self.visit_expr(&fe.record)?;
+            self.visit_expr(source)?;
+            self.set_source_pos(fe.dot.pos);
+            self.emit_instruction(Instruction::Swap);
+            return self.emit_set_field_instruction(fe);
+        }
+
+        self.visit_expr(source)?;
+        // assignments are expressions, so the value stays on the stack
+        self.emit_store_instructions(target, true)
+    }
+
+    // sets the field of the record on top of the stack to the value below it, leaving the value
+    fn emit_set_field_instruction<'b>(&mut self, fe: &FieldExpr<'b>) -> Result<()> {
+        let (field_index, slot) = self.structs.field_index(&fe.field)?;
+        self.set_source_pos(fe.dot.pos);
+        self.emit_instruction(Instruction::SetField);
+        self.emit_bytes(&field_index.to_le_bytes());
+        self.emit_bytes(&slot.to_le_bytes());
+        Ok(())
+    }
+
+    // stores the value on top of the stack in the target, popping it unless keep_value is set.
+    fn emit_store_instructions<'b>(&mut self, target: &Expr<'b>, keep_value: bool) -> Result<()> {
         let identifier = match target {
             Expr::Var(ve) => &ve.identifier,
+            // the value is already on the stack, so the record is evaluated after it
+            Expr::Field(fe) => {
+                self.visit_expr(&fe.record)?;
+                self.emit_set_field_instruction(fe)?;
+                if !keep_value {
+                    self.emit_instruction(Instruction::Pop);
+                }
+                return Ok(());
+            }
             other => {
                 return Err(CodeGenError::InvalidAssignmentTarget {
                     message: format!("{}", other),
@@ -215,52 +712,111 @@ impl<'a> CodeGenerator<'a> {
             }
         };
 
-        self.visit_expr(source)?;
-
         self.set_source_pos(identifier.pos);
-        let local = self.get_local_index_by_token(&identifier)?;
-        self.emit_instruction(Instruction::Dup);
-        self.emit_set_local_instruction(local);
+
+        match self.resolve_variable(identifier)? {
+            Variable::Local(local) => {
+                if self.locals[local].is_const {
+                    return Err(CodeGenError::AssignmentToConst {
+                        token: identifier.clone(),
+                        declared_at: self.locals[local].declared_at,
+                    });
+                }
+
+                if keep_value {
+                    self.emit_instruction(Instruction::Dup);
+                }
+                self.emit_set_local_instruction(self.locals[local].slot);
+            }
+
+            Variable::Upvalue(index) => {
+                let upvalue = &self.upvalues[index];
+                if upvalue.is_const {
+                    return Err(CodeGenError::AssignmentToConst {
+                        token: identifier.clone(),
+                        declared_at: upvalue.declared_at,
+                    });
+                }
+
+                if keep_value {
+                    self.emit_instruction(Instruction::Dup);
+                }
+                self.emit_instruction(Instruction::SetUpvalue);
+                self.emit_byte(index as u8);
+            }
+
+            Variable::Global(index) => {
+                if let Some(global) = self.globals.get_mut(&identifier.lexeme) {
+                    if let (true, Some(declared_at)) = (global.is_const, global.declared_at) {
+                        return Err(CodeGenError::AssignmentToConst {
+                            token: identifier.clone(),
+                            declared_at,
+                        });
+                    }
+                    global.is_assigned = true;
+                }
+
+                if keep_value {
+                    self.emit_instruction(Instruction::Dup);
+                }
+                self.emit_instruction(Instruction::SetGlobal);
+                self.emit_bytes(&index.to_le_bytes());
+            }
+
+            Variable::Native(_) => {
+                return Err(CodeGenError::InvalidAssignmentTarget {
+                    message: format!("{} is a native function", identifier.lexeme),
+                })
+            }
+        }
         Ok(())
     }
 
-    fn emit_load_number_instruction(&mut self, number: f64, lexeme: StringAtom) {
+    // returns the index of the number in the constant pool, adding it if it isn't there.
+    // constants are deduplicated by their bits, with every NaN stored as the same NaN.
+    fn add_number_constant(&mut self, number: f64) -> usize {
+        let number = if number.is_nan() { f64::NAN } else { number };
+
+        match self.num_consts_map.entry(number.to_bits()) {
+            Entry::Occupied(entry) => *entry.get(),
+
+            Entry::Vacant(entry) => {
+                self.num_consts.push(number);
+                let inserted_index = self.num_consts.len() - 1;
+                *entry.insert(inserted_index)
+            }
+        }
+    }
+
+    fn emit_load_number_instruction(&mut self, number: f64) -> Result<()> {
         if number >= u8::MIN as f64 && number <= u8::MAX as f64 && number.fract() == 0.0 {
             let number = number as u8;
             self.emit_load_num_lit_instruction(number);
         } else {
-            let index = match self.num_consts_map.entry(lexeme) {
-                Entry::Occupied(entry) => *entry.get(),
-
-                Entry::Vacant(entry) => {
-                    self.num_consts.push(number);
-                    let inserted_index = self.num_consts.len() - 1;
-                    *entry.insert(inserted_index)
-                }
-            };
+            let index = self.add_number_constant(number);
 
             if index <= u8::MAX as usize {
                 self.emit_instruction(Instruction::LoadConstNum);
                 self.emit_byte(index as u8);
-                return;
+                return Ok(());
             }
 
             if index <= u16::MAX as usize {
                 self.emit_instruction(Instruction::LoadConstNumW);
                 self.emit_bytes(&(index as u16).to_le_bytes());
-                return;
+                return Ok(());
             }
 
-            assert!(
-                index <= u32::MAX as usize,
-                "too many number constants! Cahn only supports op to {}, but got {}",
-                u32::MAX,
-                index
-            );
+            if index > u32::MAX as usize {
+                return Err(CodeGenError::TooManyConstants {
+                    max: u32::MAX as usize,
+                });
+            }
 
             self.emit_instruction(Instruction::LoadConstNumWW);
             self.emit_bytes(&(index as u32).to_le_bytes());
         }
+        Ok(())
     }
 
     fn emit_load_string_literal_instruction(&mut self, string: &StringAtom) {
@@ -271,6 +827,62 @@ impl<'a> CodeGenerator<'a> {
         self.emit_bytes(&end_index.to_le_bytes());
     }
 
+    // functions that don't capture any variables don't need a closure
+    fn emit_function_value(&mut self, function_index: u32) {
+        if self.functions[function_index as usize].captures.is_empty() {
+            self.emit_load_function_instruction(function_index);
+        } else {
+            self.emit_instruction(Instruction::Closure);
+            self.emit_bytes(&function_index.to_le_bytes());
+        }
+    }
+
+    // the values have to be in the order of the struct's fields.
+    fn emit_record_instructions<'b>(
+        &mut self,
+        struct_index: u32,
+        pos: TokenPos,
+        values: &[&Expr<'b>],
+    ) -> Result<()> {
+        self.set_source_pos(pos);
+        self.emit_instruction(Instruction::CreateRecord);
+        self.emit_bytes(&struct_index.to_le_bytes());
+
+        for value in values {
+            self.visit_expr(value)?;
+            self.emit_instruction(Instruction::RecordPush);
+        }
+        Ok(())
+    }
+
+    // the values are evaluated in the order they're given, onto the stack,
+    // then copied onto the record in the order of the struct's fields and popped from under it.
+    fn emit_reordered_record_instructions<'b>(
+        &mut self,
+        struct_index: u32,
+        re: &RecordExpr<'b>,
+        order: &[usize],
+    ) -> Result<()> {
+        let first_slot = self.stack_height;
+        for value in &re.values {
+            self.visit_expr(value)?;
+        }
+
+        self.set_source_pos(re.brace_open.pos);
+        self.emit_instruction(Instruction::CreateRecord);
+        self.emit_bytes(&struct_index.to_le_bytes());
+        for &given in order {
+            self.emit_get_local_instruction(first_slot + given);
+            self.emit_instruction(Instruction::RecordPush);
+        }
+
+        for _ in &re.values {
+            self.emit_instruction(Instruction::Swap);
+            self.emit_instruction(Instruction::Pop);
+        }
+        Ok(())
+    }
+
     fn emit_load_function_instruction(&mut self, function_index: u32) -> usize {
         self.emit_instruction(Instruction::LoadFunction);
         let function_index_address = self.code.len();
@@ -279,40 +891,111 @@ impl<'a> CodeGenerator<'a> {
     }
 
     fn patch_load_function_instruction(&mut self, address: usize, new_function_index: u32) {
-        let bytes = new_function_index.to_le_bytes();
-        self.code[address] = bytes[0];
-        self.code[address + 1] = bytes[1];
-        self.code[address + 2] = bytes[2];
-        self.code[address + 3] = bytes[3];
+        self.code.patch_u32(address, new_function_index);
     }
 
-    fn add_string_slice<'b>(&mut self, string: &'b str) -> (u32, u32) {
+    fn add_string_slice(&mut self, string: &str) -> (u32, u32) {
+        let hash = hash_string(string);
+        if let Some(&(start_index, end_index)) = self.string_data_map.get(&hash) {
+            // a different string with the same hash gets its own copy
+            if &self.string_data[start_index as usize..end_index as usize] == string {
+                return (start_index, end_index);
+            }
+        }
+
         let start_index = self.string_data.len() as u32;
         self.string_data.push_str(string);
         let end_index = self.string_data.len() as u32;
 
+        self.string_data_map
+            .entry(hash)
+            .or_insert((start_index, end_index));
         (start_index, end_index)
     }
 
     fn add_string(&mut self, string: &StringAtom) -> (u32, u32) {
-        let string_data_map = &mut self.string_data_map;
-        let string_data = &mut self.string_data;
+        string.run_on_str(|str| self.add_string_slice(str))
+    }
 
-        let entry = string_data_map.entry(string.clone());
+    fn emit_list_instructions<'b>(&mut self, pos: TokenPos, elements: &[Expr<'b>]) -> Result<()> {
+        self.set_source_pos(pos);
+        match elements.len() {
+            0 => self.emit_instruction(Instruction::CreateList),
 
-        match entry {
-            Entry::Occupied(entry) => entry.get().clone(),
+            len if len <= u8::MAX as usize => {
+                self.emit_instruction(Instruction::CreateListWithCap);
+                self.emit_byte(len as u8);
+            }
 
-            Entry::Vacant(entry) => {
-                let start_index = string_data.len() as u32;
+            len if len <= u16::MAX as usize => {
+                self.emit_instruction(Instruction::CreateListWithCapW);
+                self.emit_bytes(&u16::to_le_bytes(len as u16));
+            }
+
+            _ => {
+                self.emit_instruction(Instruction::CreateListWithCapW);
+                self.emit_bytes(&u16::MAX.to_le_bytes());
+            }
+        }
+
+        for elem in elements {
+            self.visit_expr(elem)?;
+            self.emit_instruction(Instruction::ListPush);
+        }
+        Ok(())
+    }
+
+    // check_assignment_count makes sure the count fits the operand
+    fn emit_unpack_instruction(&mut self, count: usize) {
+        self.emit_instruction(Instruction::Unpack);
+        self.emit_byte(count as u8);
+        self.stack_height += count - 1;
+    }
+
+    // the callee and arguments are replaced by the given number of results
+    fn emit_call_instructions<'b>(&mut self, ce: &CallExpr<'b>, results: usize) -> Result<()> {
+        if ce.args.len() > u8::MAX as usize {
+            return Err(CodeGenError::TooManyArguments {
+                token: ce.paren_open.clone(),
+                max: u8::MAX as usize,
+            });
+        }
 
-                string.run_on_str(|str| string_data.push_str(str));
+        self.visit_expr(&ce.callee)?;
+        for arg in &ce.args {
+            self.visit_expr(arg)?;
+        }
 
-                let end_index = self.string_data.len() as u32;
+        self.set_source_pos(ce.paren_open.pos);
+        if results == 1 {
+            self.emit_instruction(Instruction::Invoke);
+            self.emit_byte(ce.args.len() as u8);
+        } else {
+            self.emit_instruction(Instruction::InvokeMany);
+            self.emit_byte(ce.args.len() as u8);
+            self.emit_byte(results as u8);
+        }
+        self.stack_height = self.stack_height - ce.args.len() - 1 + results;
+        Ok(())
+    }
 
-                let slice = (start_index, end_index);
-                entry.insert(slice);
-                slice
+    // the value of a multiple declaration or assignment with a single value.
+    // a call returns the values itself, anything else has to be a list of them.
+    fn visit_unpacked_expr<'b>(
+        &mut self,
+        expr: &Expr<'b>,
+        count: usize,
+        pos: TokenPos,
+    ) -> Result<()> {
+        match expr {
+            Expr::Call(ce) if self.called_struct(&ce.callee)?.is_none() => {
+                self.emit_call_instructions(ce, count)
+            }
+            _ => {
+                self.visit_expr(expr)?;
+                self.set_source_pos(pos);
+                self.emit_unpack_instruction(count);
+                Ok(())
             }
         }
     }
@@ -324,51 +1007,303 @@ impl<'a> CodeGenerator<'a> {
         patch_adress
     }
 
-    fn patch_jump_instruction(&mut self, adress: usize, jump_location: usize) {
-        assert!(
-            jump_location <= u32::MAX as usize,
-            "jump adress ({}) is over {}",
-            jump_location,
-            u32::MAX,
-        );
+    fn patch_jump_instruction(&mut self, adress: usize, jump_location: usize) -> Result<()> {
+        if jump_location > u32::MAX as usize {
+            return Err(CodeGenError::CodeTooLarge {
+                max: u32::MAX as usize,
+            });
+        }
 
-        let bytes = jump_location.to_le_bytes();
-        self.code[adress] = bytes[0];
-        self.code[adress + 1] = bytes[1];
-        self.code[adress + 2] = bytes[2];
-        self.code[adress + 3] = bytes[3];
+        self.code.patch_u32(adress, jump_location as u32);
+        Ok(())
     }
 
-    fn visit_expr<'b>(&mut self, expr: &Expr<'b>) -> Result<()> {
-        match expr {
-            Expr::Group(ge) => self.visit_expr(&ge.inner)?,
+    // compiles comparisons against a bool literal, like 'x == true', into a single
+    // ConstTrue/ConstFalse instead of loading the bool and using Equal.
+    // returns false if the expression isn't such a comparison.
+    fn emit_bool_comparison_instructions<'b>(&mut self, ie: &InfixExpr<'b>) -> Result<bool> {
+        if !matches!(
+            ie.operator.token_type,
+            TokenType::DoubleEqual | TokenType::BangEqual
+        ) {
+            return Ok(false);
+        }
 
-            Expr::Bool(be) => {
-                self.set_source_pos(be.token.pos);
-                self.emit_instruction(if be.value {
-                    Instruction::LoadTrue
-                } else {
-                    Instruction::LoadFalse
-                })
-            }
+        let (operand, constant) = match (&ie.left, &ie.right) {
+            (operand, Expr::Bool(be)) | (Expr::Bool(be), operand) => (operand, be.value),
+            _ => return Ok(false),
+        };
 
-            Expr::Number(ne) => {
-                self.set_source_pos(ne.token.pos);
-                self.emit_load_number_instruction(ne.number, ne.token.lexeme.clone())
-            }
+        self.visit_expr(operand)?;
 
-            Expr::String(se) => {
-                self.set_source_pos(se.token.pos);
-                self.emit_load_string_literal_instruction(&se.string);
-            }
+        self.set_source_pos(ie.operator.pos);
+        self.emit_instruction(if constant {
+            Instruction::ConstTrue
+        } else {
+            Instruction::ConstFalse
+        });
 
-            Expr::Prefix(pe) => {
-                self.visit_expr(&pe.inner)?;
+        if ie.operator.token_type == TokenType::BangEqual {
+            self.emit_instruction(Instruction::Not);
+        }
+        Ok(true)
+    }
 
-                self.set_source_pos(pe.operator.pos);
-                self.emit_instruction(match pe.operator.token_type {
+    // 'a ⊕ b' calls the native the operator is bound to with a and b. the native is loaded
+    // by its index, so a variable with the same name as the native doesn't change the operator.
+    fn custom_operator_native(&self, operator: &Token) -> Result<usize> {
+        let function = operator.lexeme.run_on_str(|symbol| {
+            self.options
+                .custom_operators
+                .iter()
+                .find(|op| op.symbol == symbol)
+                .map(|op| op.function.clone())
+        });
+        let native_index = function.as_ref().and_then(|function| {
+            self.natives
+                .iter()
+                .position(|native| native.name == function)
+        });
+        native_index.ok_or_else(|| CodeGenError::UnboundOperator {
+            token: operator.clone(),
+            function: function.unwrap_or_default(),
+        })
+    }
+
+    // operators of the same precedence nest on the left, like '((a + b) - c) + d',
+    // so the chain is compiled in a loop, long chains would overflow the stack otherwise.
+    // comparisons, assignments and exponents don't chain, their operands are visited as usual.
+    fn emit_infix_chain_instructions<'b>(&mut self, ie: &InfixExpr<'b>) -> Result<()> {
+        let (chain, first) = ie.chain(|operator| {
+            !matches!(
+                operator.token_type,
+                TokenType::ColonEqual | TokenType::DoubleStar
+            ) && !token_groups::COMPARISON_OPERATORS.contains(&operator.token_type)
+        });
+
+        // the natives of custom operators go below their left operand,
+        // so the outermost operator's native is loaded first
+        for link in &chain {
+            if link.operator.token_type == TokenType::CustomOperator {
+                let native_index = self.custom_operator_native(&link.operator)?;
+                // errors in the native point at the operator
+                self.set_source_pos(link.operator.pos);
+                self.emit_instruction(Instruction::LoadNative);
+                self.emit_bytes(&(native_index as u32).to_le_bytes());
+            }
+        }
+
+        self.visit_expr(first)?;
+        for link in chain.iter().rev() {
+            match link.operator.token_type {
+                TokenType::And | TokenType::Or => self.emit_logical_instructions(link)?,
+
+                TokenType::CustomOperator => {
+                    self.visit_expr(&link.right)?;
+                    self.set_source_pos(link.operator.pos);
+                    self.emit_instruction(Instruction::Invoke);
+                    self.emit_byte(2);
+                    // the native and the operands are replaced by the result
+                    self.stack_height -= 2;
+                }
+
+                _ => {
+                    self.visit_expr(&link.right)?;
+                    self.set_source_pos(link.operator.pos);
+                    self.emit_operator_instructions(&link.operator);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_operator_instructions(&mut self, operator: &Token) {
+        let instruction = match operator.token_type {
+            TokenType::Plus => Instruction::Add,
+            TokenType::Minus => Instruction::Sub,
+            TokenType::Star => Instruction::Mul,
+            TokenType::Slash => Instruction::Div,
+            TokenType::DoubleSlash => Instruction::FloorDiv,
+            TokenType::Percent => Instruction::Modulo,
+            TokenType::DoubleStar => Instruction::Pow,
+            TokenType::Ampersand => Instruction::BitAnd,
+            TokenType::Pipe => Instruction::BitOr,
+            TokenType::Caret => Instruction::BitXor,
+            TokenType::DoubleLess => Instruction::Shl,
+            TokenType::DoubleGreater => Instruction::Shr,
+
+            TokenType::DoubleEqual => Instruction::Equal,
+            TokenType::BangEqual => {
+                self.emit_instruction(Instruction::Equal);
+                Instruction::Not
+            }
+            TokenType::Less => Instruction::LessThan,
+            TokenType::LessEqual => Instruction::LessThanOrEqual,
+            TokenType::Greater => Instruction::GreaterThan,
+            TokenType::GreaterEqual => Instruction::GreaterThanOrEqual,
+            TokenType::In => Instruction::Contains,
+            TokenType::DoubleDot => Instruction::Concat,
+
+            other => panic!("this token type should not be a infix expr: {:?}", other),
+        };
+        self.emit_instruction(instruction);
+    }
+
+    // compiles 'and' and 'or', once the left side is on the stack. The right side is only
+    // evaluated if the left side didn't already decide the result, in which case the left side
+    // is the result.
+    fn emit_logical_instructions<'b>(&mut self, ie: &InfixExpr<'b>) -> Result<()> {
+        self.set_source_pos(ie.operator.pos);
+        // the jump leaves the left side on the stack, as it's the result when it short circuits
+        let short_circuit_jump = self.emit_jump_instruction(match ie.operator.token_type {
+            TokenType::And => Instruction::JumpIfFalsePeek,
+            _ => Instruction::JumpIfTruePeek,
+        });
+
+        // the left side didn't decide it, so the right side is the result
+        self.emit_instruction(Instruction::Pop);
+        self.visit_expr(&ie.right)?;
+
+        self.patch_jump_instruction(short_circuit_jump, self.code.len())
+    }
+
+    // the value of a condition, if it can be known while compiling.
+    // only bool literals and constants declared with them are known, combined with not, and, or.
+    fn fold_condition<'b>(&mut self, expr: &Expr<'b>) -> Option<bool> {
+        match expr {
+            Expr::Bool(be) => Some(be.value),
+
+            Expr::Group(ge) => self.fold_condition(&ge.inner),
+
+            Expr::Prefix(pe) if pe.operator.token_type == TokenType::Not => {
+                self.fold_condition(&pe.inner).map(|value| !value)
+            }
+
+            Expr::Infix(ie) if matches!(ie.operator.token_type, TokenType::And | TokenType::Or) => {
+                // folded from the first operand on, in a loop like the chain is compiled
+                let (chain, first) = ie.chain(|operator| {
+                    matches!(operator.token_type, TokenType::And | TokenType::Or)
+                });
+                let mut value = self.fold_condition(first)?;
+                for link in chain.iter().rev() {
+                    let right = self.fold_condition(&link.right)?;
+                    value = if link.operator.token_type == TokenType::And {
+                        value && right
+                    } else {
+                        value || right
+                    };
+                }
+                Some(value)
+            }
+
+            // captured variables and natives are never known, even if a constant global has
+            // the same name, and errors are reported when the condition is compiled
+            Expr::Var(ve) => match self.resolve_variable(&ve.identifier).ok()? {
+                Variable::Local(index) => {
+                    self.locals[index].is_used = true;
+                    self.locals[index].known_bool
+                }
+                Variable::Global(_) => self
+                    .globals
+                    .get(&ve.identifier.lexeme)
+                    .and_then(|global| global.known_bool),
+                _ => None,
+            },
+
+            _ => None,
+        }
+    }
+
+    // folds the condition of an if or while, warning if it's constant.
+    // returns the value only if the dead branch should be pruned.
+    fn fold_branch_condition<'b>(&mut self, condition: &Expr<'b>, token: &Token) -> Option<bool> {
+        let value = self.fold_condition(condition)?;
+        self.warnings.push(Warning::ConstantCondition {
+            token: token.clone(),
+            value,
+            pruned: self.options.optimize,
+        });
+        Some(value).filter(|_| self.options.optimize)
+    }
+
+    fn check_empty_block(&mut self, block: &BlockStmt) {
+        if self.options.warn_empty_blocks && block.statements.stmts.is_empty() {
+            self.warnings.push(Warning::EmptyBlock {
+                pos: block.brace_open.pos,
+                end: block.brace_close.end_pos(),
+            });
+        }
+    }
+
+    fn check_infinite_loop(&mut self, ws: &WhileStmt) {
+        if self.options.warn_infinite_loops
+            && self.fold_condition(&ws.condition) == Some(true)
+            && !effects::block_has_effect(&ws.block)
+        {
+            self.warnings.push(Warning::LikelyInfiniteLoop {
+                pos: ws.while_token.pos,
+            });
+        }
+    }
+
+    // compiles code that never runs, so its errors are still reported, then throws the code away.
+    // any locals it declares are out of scope again once it's done, so no slots are taken up.
+    fn check_dead_code(&mut self, gen: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let code_len = self.code.len();
+        let stack_height = self.stack_height;
+        gen(self)?;
+        self.code.truncate(code_len);
+        self.stack_height = stack_height;
+        Ok(())
+    }
+
+    fn visit_expr<'b>(&mut self, expr: &Expr<'b>) -> Result<()> {
+        match expr {
+            Expr::Group(ge) => self.visit_expr(&ge.inner)?,
+
+            Expr::Bool(be) => {
+                self.set_source_pos(be.token.pos);
+                self.emit_instruction(if be.value {
+                    Instruction::LoadTrue
+                } else {
+                    Instruction::LoadFalse
+                })
+            }
+
+            Expr::Number(ne) => {
+                self.set_source_pos(ne.token.pos);
+                self.emit_load_number_instruction(ne.number)?
+            }
+
+            Expr::String(se) => {
+                self.set_source_pos(se.token.pos);
+                self.emit_load_string_literal_instruction(&se.string);
+            }
+
+            Expr::InterpolatedString(ise) => {
+                // the parser makes sure the first part is a string,
+                // so concatenating the rest onto it gives a string.
+                let (first, rest) = ise
+                    .parts
+                    .split_first()
+                    .expect("Parser shouldn't create empty interpolated strings");
+
+                self.visit_expr(first)?;
+                for part in rest {
+                    self.visit_expr(part)?;
+                    self.set_source_pos(ise.token.pos);
+                    self.emit_instruction(Instruction::Concat);
+                }
+            }
+
+            Expr::Prefix(pe) => {
+                self.visit_expr(&pe.inner)?;
+
+                self.set_source_pos(pe.operator.pos);
+                self.emit_instruction(match pe.operator.token_type {
                     TokenType::Minus => Instruction::Negate,
                     TokenType::Not => Instruction::Not,
+                    TokenType::TypeOf => Instruction::TypeOf,
                     other => panic!("this token type should not be a prefix expr: {:?}", other),
                 });
             }
@@ -376,66 +1311,39 @@ impl<'a> CodeGenerator<'a> {
             Expr::Infix(ie) => {
                 if ie.operator.token_type == TokenType::ColonEqual {
                     self.emit_assignment_instructions(&ie.left, &ie.right)?;
-                } else {
-                    self.visit_expr(&ie.left)?;
-                    self.visit_expr(&ie.right)?;
-
-                    self.set_source_pos(ie.operator.pos);
-
-                    self.emit_instruction(match ie.operator.token_type {
-                        TokenType::Plus => Instruction::Add,
-                        TokenType::Minus => Instruction::Sub,
-                        TokenType::Star => Instruction::Mul,
-                        TokenType::Slash => Instruction::Div,
-                        TokenType::Percent => Instruction::Modulo,
-
-                        TokenType::DoubleEqual => Instruction::Equal,
-                        TokenType::Less => Instruction::LessThan,
-                        TokenType::LessEqual => Instruction::LessThanOrEqual,
-                        TokenType::Greater => Instruction::GreaterThan,
-                        TokenType::GreaterEqual => Instruction::GreaterThanOrEqual,
-                        TokenType::DoubleDot => Instruction::Concat,
-
-                        other => panic!("this token type should not be a infix expr: {:?}", other),
-                    });
+                } else if !self.emit_bool_comparison_instructions(ie)? {
+                    self.emit_infix_chain_instructions(ie)?;
                 }
             }
 
             Expr::Var(ve) => {
-                let stack_offset = self.get_local_index_by_token(&ve.identifier)?;
                 self.set_source_pos(ve.identifier.pos);
-                self.emit_get_local_instruction(stack_offset);
-            }
-
-            Expr::List(le) => {
-                let len = le.elements.len();
-
-                self.set_source_pos(le.bracket_open.pos);
-                match len {
-                    0 => self.emit_instruction(Instruction::CreateList),
 
-                    len if len <= u8::MAX as usize => {
-                        self.emit_instruction(Instruction::CreateListWithCap);
-                        self.emit_byte(len as u8);
+                match self.resolve_variable(&ve.identifier)? {
+                    Variable::Local(local) => {
+                        self.locals[local].is_used = true;
+                        self.emit_get_local_instruction(self.locals[local].slot)
                     }
 
-                    len if len <= u16::MAX as usize => {
-                        self.emit_instruction(Instruction::CreateListWithCapW);
-                        self.emit_bytes(&u16::to_le_bytes(len as u16));
+                    Variable::Upvalue(index) => {
+                        self.emit_instruction(Instruction::GetUpvalue);
+                        self.emit_byte(index as u8);
                     }
 
-                    _ => {
-                        self.emit_instruction(Instruction::CreateListWithCapW);
-                        self.emit_bytes(&u16::MAX.to_le_bytes());
+                    Variable::Global(index) => {
+                        self.emit_instruction(Instruction::GetGlobal);
+                        self.emit_bytes(&index.to_le_bytes());
                     }
-                }
 
-                for elem in &le.elements {
-                    self.visit_expr(elem)?;
-                    self.emit_instruction(Instruction::ListPush);
+                    Variable::Native(index) => {
+                        self.emit_instruction(Instruction::LoadNative);
+                        self.emit_bytes(&(index as u32).to_le_bytes());
+                    }
                 }
             }
 
+            Expr::List(le) => self.emit_list_instructions(le.bracket_open.pos, &le.elements)?,
+
             Expr::Subscript(se) => {
                 self.visit_expr(&se.subscriptee)?;
                 self.visit_expr(&se.index)?;
@@ -443,9 +1351,99 @@ impl<'a> CodeGenerator<'a> {
                 self.emit_instruction(Instruction::ListGetIndex);
             }
 
-            Expr::Call(_ce) => unimplemented!(),
-            Expr::AnynFnDecl(_) => {
-                unimplemented!("anynomous function declarations are really not implemented")
+            Expr::Call(ce) if self.called_struct(&ce.callee)?.is_some() => {
+                let (name, struct_index, field_count) = self.called_struct(&ce.callee)?.unwrap();
+                if ce.args.len() != field_count {
+                    return Err(CodeGenError::FieldCountMismatch {
+                        token: name.clone(),
+                        expected: field_count,
+                        got: ce.args.len(),
+                    });
+                }
+
+                let values: Vec<&Expr> = ce.args.iter().collect();
+                self.emit_record_instructions(struct_index, ce.paren_open.pos, &values)?;
+            }
+
+            Expr::Call(ce) => self.emit_call_instructions(ce, 1)?,
+
+            Expr::Record(re) => {
+                let (struct_index, fields) = match self.structs.get(&re.name.lexeme) {
+                    Some((index, fields)) => (index, fields.to_vec()),
+                    None => {
+                        return Err(CodeGenError::UnknownStruct {
+                            token: re.name.clone(),
+                        })
+                    }
+                };
+
+                for (index, field) in re.fields.iter().enumerate() {
+                    if re.fields[..index]
+                        .iter()
+                        .any(|earlier| earlier.lexeme == field.lexeme)
+                    {
+                        return Err(CodeGenError::DuplicateField {
+                            token: field.clone(),
+                        });
+                    }
+                    if !fields.contains(&field.lexeme) {
+                        return Err(CodeGenError::NotAField {
+                            token: field.clone(),
+                            struct_name: re.name.lexeme.run_on_str(str::to_owned),
+                        });
+                    }
+                }
+
+                // where each field's value is given, in the order the struct declares them
+                let mut order = vec![];
+                for field in &fields {
+                    match re.fields.iter().position(|given| &given.lexeme == field) {
+                        Some(index) => order.push(index),
+                        None => {
+                            return Err(CodeGenError::MissingField {
+                                token: re.name.clone(),
+                                field: field.run_on_str(str::to_owned),
+                            })
+                        }
+                    }
+                }
+
+                if order
+                    .iter()
+                    .enumerate()
+                    .all(|(field, &given)| field == given)
+                {
+                    let values: Vec<_> = re.values.iter().collect();
+                    self.emit_record_instructions(struct_index, re.brace_open.pos, &values)?;
+                } else {
+                    self.emit_reordered_record_instructions(struct_index, re, &order)?;
+                }
+            }
+
+            Expr::Field(fe) => {
+                self.visit_expr(&fe.record)?;
+                let (field_index, slot) = self.structs.field_index(&fe.field)?;
+                self.set_source_pos(fe.dot.pos);
+                self.emit_instruction(Instruction::GetField);
+                self.emit_bytes(&field_index.to_le_bytes());
+                self.emit_bytes(&slot.to_le_bytes());
+            }
+
+            Expr::Block(be) => self.visit_block_value(&be.block)?,
+
+            Expr::If(ie) => self.visit_if_expr(ie)?,
+
+            Expr::AnynFnDecl(afde) => {
+                let function_index = self.gen_function(FunctionDecl {
+                    fn_token: &afde.fn_token,
+                    name: None,
+                    parameters: &afde.parameters,
+                    defaults: &afde.defaults,
+                    rest_parameter: afde.rest_parameter.as_ref(),
+                    body: &afde.body,
+                })?;
+                self.set_source_pos(afde.fn_token.pos);
+                self.emit_function_value(function_index);
             }
         };
 
@@ -459,14 +1457,149 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    // the program leaves its result on the stack, which is the value of its last statement,
+    // if that's an expression statement. otherwise it's nil.
     fn visit_program_stmt<'b>(&mut self, prog_stmt: &ProgramStmt<'b>) -> Result<()> {
         self.begin_scope();
-        self.visit_stmt_list(&prog_stmt.statements)?;
+
+        let stmts = &prog_stmt.statements.stmts;
+        let last_expr = match stmts.last() {
+            Some(Stmt::ExprStmt(es)) => Some(&es.expr),
+            _ => None,
+        };
+        let leading_stmts = if last_expr.is_some() {
+            &stmts[..stmts.len() - 1]
+        } else {
+            &stmts[..]
+        };
+
+        // structs only exist while compiling, so they can be used anywhere in the program
+        for stmt in leading_stmts {
+            if let Stmt::StructDecl(sds) = stmt {
+                self.structs.declare(sds)?;
+            }
+        }
+
+        // top level functions are defined before anything else runs, so they can be called before
+        // their declaration, and call each other. their code is still generated in order,
+        // so they see the constants declared before them.
+        let hoisted = hoisted_functions(leading_stmts);
+        let mut function_addresses = vec![];
+        for (stmt, &is_hoisted) in leading_stmts.iter().zip(&hoisted) {
+            if let (Stmt::FnDecl(fds), true) = (stmt, is_hoisted) {
+                self.set_source_pos(fds.fn_token.pos);
+                function_addresses.push(self.emit_load_function_instruction(0));
+                self.define_global(&fds.name, false, None)?;
+            }
+        }
+
+        let mut function_addresses = function_addresses.into_iter();
+        for (stmt, &is_hoisted) in leading_stmts.iter().zip(&hoisted) {
+            match stmt {
+                Stmt::FnDecl(fds) if is_hoisted => {
+                    let function_index = self.gen_function(FunctionDecl::named(fds))?;
+                    let address = function_addresses.next().unwrap();
+                    self.patch_load_function_instruction(address, function_index);
+                }
+                stmt => self.visit_stmt(stmt)?,
+            }
+        }
+
+        match last_expr {
+            Some(expr) => self.visit_expr(expr)?,
+            None => {
+                self.set_source_pos(prog_stmt.eof_token.pos);
+                self.emit_instruction(Instruction::LoadNil);
+            }
+        }
+        // the program's value is left on the stack, for the VM to find once it has halted
+        self.emit_instruction(Instruction::Halt);
+
         self.set_source_pos(prog_stmt.eof_token.pos);
-        self.end_scope();
+        self.end_scope_keeping_top();
         Ok(())
     }
 
+    // the value of a block in a do or if expression is its last statement,
+    // if that's an expression statement. otherwise it's nil.
+    fn visit_block_value<'b>(&mut self, block: &BlockStmt<'b>) -> Result<()> {
+        self.set_source_pos(block.brace_open.pos);
+        self.begin_scope();
+
+        let stmts = &block.statements.stmts;
+        let last_expr = match stmts.last() {
+            Some(Stmt::ExprStmt(es)) => Some(&es.expr),
+            _ => None,
+        };
+        let leading_stmts = if last_expr.is_some() {
+            &stmts[..stmts.len() - 1]
+        } else {
+            &stmts[..]
+        };
+
+        for stmt in leading_stmts {
+            self.visit_stmt(stmt)?;
+        }
+
+        match last_expr {
+            Some(expr) => self.visit_expr(expr)?,
+            None => {
+                self.set_source_pos(block.brace_close.pos);
+                self.emit_instruction(Instruction::LoadNil);
+            }
+        }
+
+        self.set_source_pos(block.brace_close.pos);
+        self.end_scope_keeping_top();
+        Ok(())
+    }
+
+    // only one of the branches runs, each leaving the value of its block on the stack.
+    fn visit_if_expr<'b>(&mut self, ie: &IfExpr<'b>) -> Result<()> {
+        match self.fold_branch_condition(&ie.condition, &ie.if_token) {
+            Some(true) => {
+                self.visit_block_value(&ie.then_block)?;
+                if let Some(else_clause) = &ie.else_clause {
+                    self.check_dead_code(|cg| cg.visit_expr(else_clause))?;
+                }
+            }
+
+            Some(false) => {
+                self.check_dead_code(|cg| cg.visit_block_value(&ie.then_block))?;
+                self.visit_else_value(ie)?;
+            }
+
+            None => {
+                self.visit_expr(&ie.condition)?;
+                self.set_source_pos(ie.if_token.pos);
+                let then_jump = self.emit_jump_instruction(Instruction::JumpIfFalse);
+
+                self.visit_block_value(&ie.then_block)?;
+                self.set_source_pos(ie.then_block.brace_close.pos);
+                let else_jump = self.emit_jump_instruction(Instruction::Jump);
+                // the else branch starts without the value of the then branch
+                self.stack_height -= 1;
+
+                self.patch_jump_instruction(then_jump, self.code.len())?;
+                self.visit_else_value(ie)?;
+                self.patch_jump_instruction(else_jump, self.code.len())?;
+            }
+        }
+        Ok(())
+    }
+
+    // an if expression without an else is nil when the condition is false
+    fn visit_else_value<'b>(&mut self, ie: &IfExpr<'b>) -> Result<()> {
+        match &ie.else_clause {
+            Some(else_clause) => self.visit_expr(else_clause),
+            None => {
+                self.set_source_pos(ie.then_block.brace_close.pos);
+                self.emit_instruction(Instruction::LoadNil);
+                Ok(())
+            }
+        }
+    }
+
     fn visit_block_stmt<'b>(&mut self, block_stmt: &BlockStmt<'b>) -> Result<()> {
         self.set_source_pos(block_stmt.brace_open.pos);
         self.begin_scope();
@@ -476,8 +1609,80 @@ impl<'a> CodeGenerator<'a> {
         Ok(())
     }
 
+    // a single value is unpacked into the targets, like the list 'return a, b' returns.
+    // literals that can't be unpacked into that many targets are caught here,
+    // other values are checked when they're unpacked.
+    fn check_assignment_count<'b>(
+        &self,
+        operator: &Token,
+        targets: usize,
+        values: &[Expr<'b>],
+    ) -> Result<()> {
+        let count = match values {
+            [Expr::List(le)] if targets <= u8::MAX as usize => le.elements.len(),
+            [Expr::Number(_)]
+            | [Expr::String(_)]
+            | [Expr::InterpolatedString(_)]
+            | [Expr::Bool(_)] => 1,
+            [_] if targets <= u8::MAX as usize => targets,
+            values => values.len(),
+        };
+        if targets != count {
+            return Err(CodeGenError::AssignmentCountMismatch {
+                token: operator.clone(),
+                targets,
+                values: count,
+            });
+        }
+        Ok(())
+    }
+
+    fn visit_if_stmt<'b>(&mut self, is: &IfStmt<'b>) -> Result<()> {
+        self.visit_expr(&is.condition)?;
+
+        self.set_source_pos(is.if_token.pos);
+        let then_jump = self.emit_jump_instruction(Instruction::JumpIfFalse);
+
+        self.visit_block_stmt(&is.then_clause)?;
+
+        let mut else_jump = None;
+
+        if let Some(ref else_token) = is.else_token {
+            self.set_source_pos(else_token.pos);
+            else_jump = Some(self.emit_jump_instruction(Instruction::Jump));
+        }
+
+        self.patch_jump_instruction(then_jump, self.code.len())?;
+
+        if let (Some(else_block), Some(else_jump)) = (&is.else_clause, else_jump) {
+            self.visit_stmt(else_block)?;
+            self.patch_jump_instruction(else_jump, self.code.len())?;
+        }
+        Ok(())
+    }
+
     fn visit_stmt<'b>(&mut self, stmt: &Stmt<'b>) -> Result<()> {
-        Ok(match stmt {
+        let stack_height = self.stack_height;
+        let local_count = self.locals.len();
+
+        self.visit_stmt_inner(stmt)?;
+
+        // a statement leaves nothing on the stack but the locals it declares,
+        // the program is the exception, it leaves its result.
+        // anything else means the code reads the wrong slots, so it's caught here instead.
+        if !matches!(stmt, Stmt::Program(_)) {
+            debug_assert_eq!(
+                self.stack_height + local_count,
+                stack_height + self.locals.len(),
+                "the stack isn't balanced after the statement {}",
+                stmt
+            );
+        }
+        Ok(())
+    }
+
+    fn visit_stmt_inner<'b>(&mut self, stmt: &Stmt<'b>) -> Result<()> {
+        match stmt {
             Stmt::Program(ps) => self.visit_program_stmt(ps)?,
 
             Stmt::Block(bs) => self.visit_block_stmt(bs)?,
@@ -485,55 +1690,159 @@ impl<'a> CodeGenerator<'a> {
             Stmt::StmtList(sl) => self.visit_stmt_list(sl)?,
 
             Stmt::Print(ps) => {
-                self.visit_expr(&ps.inner)?;
+                if ps.exprs.len() > u8::MAX as usize {
+                    return Err(CodeGenError::TooManyArguments {
+                        token: ps.print_token.clone(),
+                        max: u8::MAX as usize,
+                    });
+                }
+
+                for expr in &ps.exprs {
+                    self.visit_expr(expr)?;
+                }
                 self.set_source_pos(ps.print_token.pos);
-                self.emit_instruction(Instruction::Print);
+
+                if ps.exprs.len() == 1 {
+                    self.emit_instruction(Instruction::Print);
+                } else {
+                    self.emit_instruction(Instruction::PrintN);
+                    self.emit_byte(ps.exprs.len() as u8);
+                    self.stack_height -= ps.exprs.len();
+                }
             }
 
             Stmt::VarDecl(vds) => {
-                self.visit_expr(&vds.init_expr)?;
+                match &vds.init_expr {
+                    Some(init_expr) => self.visit_expr(init_expr)?,
+                    None => {
+                        self.set_source_pos(vds.var_token.pos);
+                        self.emit_instruction(Instruction::LoadNil);
+                    }
+                }
                 self.set_source_pos(vds.var_token.pos);
-                self.declare_local(&vds.identifier.lexeme);
+                let is_const = vds.var_token.token_type == TokenType::Const;
+                let known_bool = match &vds.init_expr {
+                    Some(init_expr) if is_const => self.fold_condition(init_expr),
+                    _ => None,
+                };
+                self.define_variable(&vds.identifier, is_const, known_bool)?;
             }
 
-            Stmt::If(is) => {
-                self.visit_expr(&is.condition)?;
-
-                self.set_source_pos(is.if_token.pos);
-                let then_jump = self.emit_jump_instruction(Instruction::JumpIfFalse);
+            Stmt::MultiVarDecl(mvds) => {
+                self.check_assignment_count(
+                    &mvds.assignment_operator,
+                    mvds.identifiers.len(),
+                    &mvds.init_exprs,
+                )?;
+
+                let is_const = mvds.var_token.token_type == TokenType::Const;
+                let mut known_bools = Vec::with_capacity(mvds.identifiers.len());
+                if let [init_expr] = mvds.init_exprs.as_slice() {
+                    let count = mvds.identifiers.len();
+                    self.visit_unpacked_expr(init_expr, count, mvds.var_token.pos)?;
+                    known_bools.resize(count, None);
+                } else {
+                    for init_expr in &mvds.init_exprs {
+                        self.visit_expr(init_expr)?;
+                        known_bools.push(if is_const {
+                            self.fold_condition(init_expr)
+                        } else {
+                            None
+                        });
+                    }
+                }
 
-                self.visit_block_stmt(&is.then_clause)?;
+                self.set_source_pos(mvds.var_token.pos);
+                let declarations = mvds.identifiers.iter().zip(known_bools);
+                if self.is_global_scope() {
+                    // globals are defined from the top of the stack, so the last one comes first
+                    for (identifier, known_bool) in declarations.rev() {
+                        self.define_global(identifier, is_const, known_bool)?;
+                    }
+                } else {
+                    // the values already are in the right stack slots
+                    let first_slot = self.stack_height - mvds.identifiers.len();
+                    for (slot, (identifier, known_bool)) in (first_slot..).zip(declarations) {
+                        self.declare_local_in_slot(slot, identifier, is_const, known_bool)?;
+                    }
+                }
+            }
 
-                let mut else_jump = None;
+            Stmt::MultiAssign(mas) => {
+                self.check_assignment_count(
+                    &mas.assignment_operator,
+                    mas.targets.len(),
+                    &mas.values,
+                )?;
+
+                // every value is evaluated before any target is assigned, so 'a, b := b, a' swaps
+                if let [value] = mas.values.as_slice() {
+                    let count = mas.targets.len();
+                    self.visit_unpacked_expr(value, count, mas.assignment_operator.pos)?;
+                } else {
+                    for value in &mas.values {
+                        self.visit_expr(value)?;
+                    }
+                }
+                for target in mas.targets.iter().rev() {
+                    self.emit_store_instructions(target, false)?;
+                }
+            }
 
-                if let Some(ref else_token) = is.else_token {
-                    self.set_source_pos(else_token.pos);
-                    else_jump = Some(self.emit_jump_instruction(Instruction::Jump));
+            Stmt::If(is) => {
+                self.check_empty_block(&is.then_clause);
+                if let Some(Stmt::Block(else_block)) = &is.else_clause {
+                    self.check_empty_block(else_block);
                 }
 
-                self.patch_jump_instruction(then_jump, self.code.len());
+                match self.fold_branch_condition(&is.condition, &is.if_token) {
+                    Some(true) => {
+                        self.visit_block_stmt(&is.then_clause)?;
+                        if let Some(else_block) = &is.else_clause {
+                            self.check_dead_code(|cg| cg.visit_stmt(else_block))?;
+                        }
+                    }
+
+                    Some(false) => {
+                        self.check_dead_code(|cg| cg.visit_block_stmt(&is.then_clause))?;
+                        if let Some(else_block) = &is.else_clause {
+                            self.visit_stmt(else_block)?;
+                        }
+                    }
 
-                if let Some(else_block) = &is.else_clause {
-                    self.visit_stmt(else_block)?;
-                    self.patch_jump_instruction(else_jump.unwrap(), self.code.len());
+                    None => self.visit_if_stmt(is)?,
                 }
             }
 
             Stmt::While(ws) => {
+                self.check_empty_block(&ws.block);
+
                 let start_adress = self.code.len();
-                assert!(
-                    start_adress <= u32::MAX as usize,
-                    "while statement start is too out on the adress space."
-                );
+                if start_adress > u32::MAX as usize {
+                    return Err(CodeGenError::CodeTooLarge {
+                        max: u32::MAX as usize,
+                    });
+                }
                 // the adress where our while statement starts
                 let start_adress = start_adress as u32;
 
-                // compile the condition
-                self.visit_expr(&ws.condition)?;
+                let condition = self.fold_branch_condition(&ws.condition, &ws.while_token);
+                self.check_infinite_loop(ws);
 
-                // if the condition was false, we need to jump over the entire body, which emit the instruction for here.
-                self.set_source_pos(ws.while_token.pos);
-                let loop_done_adress = self.emit_jump_instruction(Instruction::JumpIfFalse);
+                // the body never runs, so the loop compiles to nothing
+                if condition == Some(false) {
+                    return self.check_dead_code(|cg| cg.visit_block_stmt(&ws.block));
+                }
+
+                // compile the condition, unless it's always true
+                let mut loop_done_adress = None;
+                if condition.is_none() {
+                    self.visit_expr(&ws.condition)?;
+
+                    // if the condition was false, we need to jump over the entire body, which emit the instruction for here.
+                    self.set_source_pos(ws.while_token.pos);
+                    loop_done_adress = Some(self.emit_jump_instruction(Instruction::JumpIfFalse));
+                }
 
                 // compile the body
                 self.visit_block_stmt(&ws.block)?;
@@ -544,49 +1853,310 @@ impl<'a> CodeGenerator<'a> {
                 self.emit_bytes(&start_adress.to_le_bytes());
 
                 // know we know were to jump to, to skip the body, so we patch the first jump.
-                self.patch_jump_instruction(loop_done_adress, self.code.len());
+                if let Some(loop_done_adress) = loop_done_adress {
+                    self.patch_jump_instruction(loop_done_adress, self.code.len())?;
+                }
+            }
+
+            Stmt::For(fs) => {
+                self.check_empty_block(&fs.block);
+
+                // the iterator lives in a hidden local for as long as the loop runs,
+                // so the loop is in a scope of its own, even at the top level.
+                self.begin_scope();
+                self.visit_expr(&fs.iterable)?;
+                self.set_source_pos(fs.for_token.pos);
+                self.emit_instruction(Instruction::IterInit);
+                self.declare_anonymous_local();
+
+                let start_adress = self.code.len();
+                if start_adress > u32::MAX as usize {
+                    return Err(CodeGenError::CodeTooLarge {
+                        max: u32::MAX as usize,
+                    });
+                }
+
+                // pushes the next element, or jumps past the body once there are none left
+                let loop_done_adress = self.emit_jump_instruction(Instruction::IterNext);
+
+                // every iteration gets its own variable, so closures capture the element they saw
+                self.begin_scope();
+                self.declare_local(&fs.variable, false, None)?;
+                self.visit_block_stmt(&fs.block)?;
+                self.end_scope();
+
+                self.set_source_pos(fs.block.brace_close.pos);
+                self.emit_instruction(Instruction::Jump);
+                self.emit_bytes(&(start_adress as u32).to_le_bytes());
+
+                // the element isn't pushed when IterNext jumps here
+                self.patch_jump_instruction(loop_done_adress, self.code.len())?;
+                self.end_scope();
             }
 
             Stmt::ExprStmt(es) => {
                 self.visit_expr(&es.expr)?;
+
+                // only calls, assignments and blocks can have an effect
+                let has_effect = match &es.expr {
+                    Expr::Call(_) | Expr::Block(_) | Expr::If(_) => true,
+                    Expr::Infix(ie) => ie.operator.token_type == TokenType::ColonEqual,
+                    _ => false,
+                };
+                if !has_effect {
+                    self.warnings.push(Warning::DiscardedExpression {
+                        pos: self.current_source_position,
+                    });
+                }
+
                 // statements are supposed to have a stack effect of 0, so we pop
                 self.emit_instruction(Instruction::Pop);
             }
 
-            Stmt::FnDecl(_fn_decl_stmt) => unimplemented!(),
-            Stmt::Return(_) => unimplemented!(),
-        })
+            Stmt::FnDecl(fds) => {
+                let function_index = self.gen_function(FunctionDecl::named(fds))?;
+                self.set_source_pos(fds.fn_token.pos);
+                self.emit_function_value(function_index);
+                self.define_variable(&fds.name, false, None)?;
+            }
+
+            // top level structs are declared before the program is compiled
+            Stmt::StructDecl(sds) => {
+                if !self.is_global_scope() {
+                    return Err(CodeGenError::Unsupported {
+                        feature: "struct declarations inside blocks and functions",
+                        token: sds.struct_token.clone(),
+                    });
+                }
+            }
+
+            Stmt::Assert(ast) => {
+                self.visit_expr(&ast.condition)?;
+                match &ast.message {
+                    Some(message) => self.visit_expr(message)?,
+                    None => {
+                        self.set_source_pos(ast.assert_token.pos);
+                        self.emit_instruction(Instruction::LoadNil);
+                    }
+                }
+                self.set_source_pos(ast.assert_token.pos);
+                self.emit_instruction(Instruction::Assert);
+            }
+
+            // the modules are put in place of their imports by ModuleParser
+            Stmt::Import(is) => {
+                return Err(CodeGenError::Unsupported {
+                    feature: if self.is_global_scope() {
+                        "imports without a module loader"
+                    } else {
+                        "imports inside blocks and functions"
+                    },
+                    token: is.import_token.clone(),
+                });
+            }
+
+            Stmt::Return(rs) => {
+                if self.is_toplevel {
+                    return Err(CodeGenError::Unsupported {
+                        feature: "return statements outside of functions",
+                        token: rs.return_token.clone(),
+                    });
+                }
+
+                let return_vals = rs.return_vals.as_slice();
+                match return_vals {
+                    [] => {
+                        self.set_source_pos(rs.return_token.pos);
+                        self.emit_instruction(Instruction::LoadNil);
+                    }
+                    [return_val] => self.visit_expr(return_val)?,
+                    return_vals if return_vals.len() > u8::MAX as usize => {
+                        return Err(CodeGenError::TooManyReturnValues {
+                            token: rs.return_token.clone(),
+                            max: u8::MAX as usize,
+                        });
+                    }
+                    return_vals => {
+                        for return_val in return_vals {
+                            self.visit_expr(return_val)?;
+                        }
+                    }
+                }
+
+                self.set_source_pos(rs.return_token.pos);
+                if return_vals.len() > 1 {
+                    // the caller takes as many of them as it needs
+                    self.emit_instruction(Instruction::ReturnMany);
+                    self.emit_byte(return_vals.len() as u8);
+                    self.stack_height -= return_vals.len();
+                } else {
+                    self.emit_instruction(Instruction::Return);
+                }
+            }
+        };
+        Ok(())
+    }
+
+    // generates the function and adds it to the executable, returns its index.
+    fn gen_function<'b>(&mut self, fn_decl: FunctionDecl<'_, 'b>) -> Result<u32> {
+        // the rest parameter takes a slot like any other parameter
+        let is_variadic = fn_decl.rest_parameter.is_some();
+        let param_count = fn_decl.parameters.len() + is_variadic as usize;
+        if param_count > u8::MAX as usize {
+            return Err(CodeGenError::TooManyParameters {
+                count: param_count,
+                max: u8::MAX as usize,
+            });
+        }
+
+        // the index is taken before the body is compiled, so the functions are numbered in the order
+        // their 'fn' comes in the source, and the numbering doesn't depend on how the code is generated.
+        let function_index = self.functions.len();
+        if function_index >= u32::MAX as usize {
+            return Err(CodeGenError::TooManyFunctions {
+                max: u32::MAX as usize,
+            });
+        }
+        self.functions
+            .push(CahnFunction::new_anonymous(0, vec![], vec![]));
+
+        // global functions find themselves as a global,
+        // functions declared in a block are locals, which the body can't see.
+        let local_name = fn_decl.name.filter(|_| !self.is_global_scope());
+
+        let mut fcg = CodeGenerator::from_parent(self);
+        fcg.set_source_pos(fn_decl.fn_token.pos);
+
+        // the first stack slot holds the function being called, the arguments follow.
+        // naming it after a local function lets the function call itself.
+        fcg.stack_height = 1;
+        if let Some(name) = local_name {
+            fcg.declare_local(name, false, None)?;
+        } else {
+            fcg.declare_anonymous_local();
+        }
+        let first_default = fn_decl.parameters.len() - fn_decl.defaults.len();
+        for param in &fn_decl.parameters[..first_default] {
+            fcg.stack_height += 1;
+            fcg.declare_local(param, false, None)?;
+        }
+
+        // calls that supply every argument jump over the defaults,
+        // the others start at the default of the first missing parameter.
+        let mut default_entries = vec![];
+        if !fn_decl.defaults.is_empty() {
+            let skip_defaults = fcg.emit_jump_instruction(Instruction::Jump);
+            for (param, default) in fn_decl.parameters[first_default..]
+                .iter()
+                .zip(fn_decl.defaults)
+            {
+                default_entries.push(fcg.code.len() as u32);
+                fcg.visit_expr(default)?;
+                fcg.declare_local(param, false, None)?;
+            }
+            let body_start = fcg.code.len();
+            fcg.patch_jump_instruction(skip_defaults, body_start)?;
+        }
+
+        // the VM collects the extra arguments into a list before the call
+        if let Some(rest) = fn_decl.rest_parameter {
+            fcg.stack_height += 1;
+            fcg.declare_local(rest, false, None)?;
+        }
+
+        // parameters don't have to be used
+        for local in &mut fcg.locals {
+            local.is_used = true;
+        }
+
+        fcg.visit_stmt_list(&fn_decl.body.statements)?;
+        // the function body isn't a scope of its own, so its locals are checked here
+        while !fcg.locals.is_empty() {
+            fcg.pop_local();
+        }
+
+        // functions that don't return anything return nil
+        fcg.set_source_pos(fn_decl.body.brace_close.pos);
+        fcg.emit_instruction(Instruction::LoadNil);
+        fcg.emit_instruction(Instruction::Return);
+
+        let captures = fcg.upvalues.iter().map(|upvalue| upvalue.capture).collect();
+        let enclosing = mem::take(&mut fcg.enclosing);
+
+        let fn_name = fn_decl.name.map(|name| fcg.add_string(&name.lexeme));
+        let (code, code_map) = fcg.code.into_parts();
+        let function = match fn_name {
+            Some(fn_name) => CahnFunction::new(
+                param_count as u8,
+                code,
+                code_map,
+                fn_name.0 as usize,
+                fn_name.1 as usize,
+            ),
+            None => CahnFunction::new_anonymous(param_count as u8, code, code_map),
+        }
+        .with_default_entries(default_entries)
+        .variadic(is_variadic)
+        .with_captures(captures);
+
+        self.end_child(enclosing);
+        self.functions[function_index] = function;
+        Ok(function_index as u32)
     }
 
     fn gen_toplevel_func<'b>(mut self, prog_stmt: &ProgramStmt<'b>) -> Result<CahnFunction> {
         // reserve first stack slot for top level script function
-        self.declare_anonymous_local();
         let patch_here = self.emit_load_function_instruction(0);
+        self.declare_anonymous_local();
         let fn_name = self.add_string_slice("CahnMain");
 
         self.visit_program_stmt(prog_stmt)?;
 
-        self.patch_load_function_instruction(
-            patch_here,
+        let function_index =
             self.functions
                 .len()
                 .try_into()
-                .expect("To many functions!!!"),
-        );
+                .map_err(|_| CodeGenError::TooManyFunctions {
+                    max: u32::MAX as usize,
+                })?;
+        self.patch_load_function_instruction(patch_here, function_index);
 
+        let (code, code_map) = self.code.into_parts();
         Ok(CahnFunction::new(
             0,
-            self.code,
-            self.code_map,
+            code,
+            code_map,
             fn_name.0 as usize,
             fn_name.1 as usize,
         ))
     }
 
-    pub fn gen_executable<'b>(
+    pub fn gen_executable(cahn_source_file: String, prog: &ProgramStmt) -> Result<Executable> {
+        Self::gen_executable_with_natives(cahn_source_file, prog, &[])
+    }
+
+    /// Generates an executable, where the given natives can be called by name.
+    pub fn gen_executable_with_natives(
         cahn_source_file: String,
-        prog: &'b ProgramStmt,
+        prog: &ProgramStmt,
+        natives: &[NativeFunction],
     ) -> Result<Executable> {
+        Self::gen_executable_with_options(
+            cahn_source_file,
+            prog,
+            natives,
+            CodeGenOptions::default(),
+        )
+        .map(|(exec, _warnings)| exec)
+    }
+
+    /// Generates an executable with the given options, along with the warnings found on the way.
+    pub fn gen_executable_with_options(
+        cahn_source_file: String,
+        prog: &ProgramStmt,
+        natives: &[NativeFunction],
+        options: CodeGenOptions,
+    ) -> Result<(Executable, Vec<Warning>)> {
         let mut num_consts = vec![];
         let mut num_consts_map = AHashMap::new();
 
@@ -595,23 +2165,225 @@ impl<'a> CodeGenerator<'a> {
 
         let mut functions = vec![];
 
-        let fcg = CodeGenerator::new(
+        let mut globals = AHashMap::new();
+        let mut global_names = vec![];
+
+        let mut structs = StructTable::new();
+
+        let mut warnings = vec![];
+
+        // the host's natives come first, so they can shadow builtins
+        let natives: Vec<NativeFunction> = natives.iter().chain(BUILTINS).copied().collect();
+
+        let mut fcg = CodeGenerator::new(
             &mut num_consts,
             &mut num_consts_map,
             &mut string_data,
             &mut string_data_map,
             &cahn_source_file,
             &mut functions,
+            &natives,
+            &mut globals,
+            &mut global_names,
+            &mut structs,
+            options.clone(),
+            &mut warnings,
         );
 
+        for type_name in TYPE_NAMES {
+            fcg.add_string_slice(type_name);
+        }
+
         let main_func = fcg.gen_toplevel_func(prog)?;
         functions.push(main_func);
 
-        Ok(Executable::new(
+        warnings.retain(|warning| {
+            !options
+                .allowed_lints
+                .iter()
+                .any(|code| code == warning.code())
+        });
+
+        if options.strict {
+            if let Some(warning) = warnings.iter().find(|warning| warning.is_strict_error()) {
+                return Err(CodeGenError::StrictWarning {
+                    warning: warning.clone(),
+                });
+            }
+        }
+
+        let exec = Executable::new(
             num_consts,
             string_data,
             cahn_source_file,
-            functions,
-        ))
+            functions.into_iter().map(Rc::new).collect(),
+            natives,
+            global_names,
+        );
+        let (struct_layouts, field_names) = structs.into_parts();
+        Ok((exec.with_structs(struct_layouts, field_names), warnings))
+    }
+}
+
+// whether each statement is a function declaration that can be moved to the top of the program.
+// names that are declared more than once stay where they are, as the order matters for them.
+fn hoisted_functions(stmts: &[Stmt]) -> Vec<bool> {
+    let mut declaration_counts: AHashMap<&StringAtom, usize> = AHashMap::new();
+    for stmt in stmts {
+        let names: Vec<&StringAtom> = match stmt {
+            Stmt::FnDecl(fds) => vec![&fds.name.lexeme],
+            Stmt::VarDecl(vds) => vec![&vds.identifier.lexeme],
+            Stmt::MultiVarDecl(mvds) => mvds.identifiers.iter().map(|id| &id.lexeme).collect(),
+            _ => vec![],
+        };
+        for name in names {
+            *declaration_counts.entry(name).or_insert(0) += 1;
+        }
+    }
+
+    stmts
+        .iter()
+        .map(|stmt| matches!(stmt, Stmt::FnDecl(fds) if declaration_counts[&fds.name.lexeme] == 1))
+        .collect()
+}
+
+fn find_local(locals: &[Local], name: &StringAtom) -> Option<usize> {
+    locals
+        .iter()
+        .rposition(|local| local.name.as_ref() == Some(name))
+}
+
+// returns the index of the upvalue, reusing it if the function already captures the variable.
+fn add_upvalue(upvalues: &mut Vec<Upvalue>, upvalue: Upvalue, identifier: &Token) -> Result<usize> {
+    if let Some(index) = upvalues
+        .iter()
+        .position(|existing| existing.capture == upvalue.capture)
+    {
+        return Ok(index);
+    }
+
+    // upvalues are addressed with 8 bits
+    if upvalues.len() > u8::MAX as usize {
+        return Err(CodeGenError::TooManyUpvalues {
+            token: identifier.clone(),
+            max: u8::MAX as usize + 1,
+        });
+    }
+    upvalues.push(upvalue);
+    Ok(upvalues.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::AHashMap;
+
+    use super::{CodeGenOptions, CodeGenerator, StructTable};
+
+    #[test]
+    fn nans_share_one_constant() {
+        let mut num_consts = vec![];
+        let mut num_consts_map = AHashMap::new();
+        let mut string_data = String::new();
+        let mut string_data_map = AHashMap::new();
+        let mut functions = vec![];
+        let mut globals = AHashMap::new();
+        let mut global_names = vec![];
+        let mut structs = StructTable::new();
+        let mut warnings = vec![];
+
+        let mut cg = CodeGenerator::new(
+            &mut num_consts,
+            &mut num_consts_map,
+            &mut string_data,
+            &mut string_data_map,
+            "test",
+            &mut functions,
+            &[],
+            &mut globals,
+            &mut global_names,
+            &mut structs,
+            CodeGenOptions::default(),
+            &mut warnings,
+        );
+
+        let zero = 0.0;
+        let nans = [
+            zero / zero,
+            f64::INFINITY - f64::INFINITY,
+            -f64::NAN,
+            f64::from_bits(f64::NAN.to_bits() | 1),
+        ];
+        let indices: Vec<_> = nans
+            .iter()
+            .map(|nan| cg.add_number_constant(*nan))
+            .collect();
+        assert_eq!(indices, [0, 0, 0, 0]);
+        assert_eq!(cg.add_number_constant(1.5), 1);
+
+        assert_eq!(num_consts.len(), 2);
+        assert_eq!(num_consts[0].to_bits(), f64::NAN.to_bits());
+    }
+
+    // every statement and expression kind, so each emit path is covered
+    const EVERY_KIND: &str = "
+let a := 1
+const b := true
+let c, d := 2, 3
+c, d := d, c
+print a, c
+print \"s\" .. a
+{
+    let inner := [1, 2, 3]
+    print inner[0]
+}
+if a < 2 and b { print a } else if not b { print 0 } else { print -a }
+let i := 0
+while i < 2 { i := i + 1 }
+while false { print i }
+fn named(x, y := 2) {
+    let z := x ** y // 2 % 3
+    return fn() { return z + 1 }
+}
+fn variadic(rest...) { return rest }
+print named(1)(), variadic(1, 2)
+let value := do { let t := 4\nt * 2 }
+value
+";
+
+    #[test]
+    fn code_map_has_a_position_for_every_byte() {
+        let lines = EVERY_KIND.lines().count();
+        for optimize in [false, true].iter() {
+            let options = CodeGenOptions {
+                optimize: *optimize,
+                ..CodeGenOptions::default()
+            };
+            let (exec, _) =
+                crate::compile_with_options(EVERY_KIND, "test".into(), &[], options).unwrap();
+
+            for function in &exec.functions {
+                assert_eq!(function.code.len(), function.code_map.len());
+                for pos in &function.code_map {
+                    assert!(pos.line >= 1 && pos.line <= lines, "bad line in {}", pos);
+                    assert!(pos.column >= 1, "bad column in {}", pos);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn string_data_has_one_copy_of_each_string() {
+        let source = "
+fn twice() { return 1 }
+let g := do {
+    fn twice() { return 2 }
+    twice
+}
+print \"twice\", \"CahnMain\", \"list\", twice() + g()
+";
+        let exec = crate::compile(source, "test".into()).unwrap();
+        assert_eq!(exec.string_data.matches("CahnMain").count(), 1);
+        assert_eq!(exec.string_data.matches("twice").count(), 1);
+        assert_eq!(exec.string_data.matches("list").count(), 1);
     }
 }
@@ -0,0 +1,67 @@
+use crate::compiler::{ast::*, lexical_analysis::TokenType};
+
+// whether running the block can make a difference or end the loop it's the body of.
+// returning, printing, asserting, importing, calling and assigning all count,
+// calls because whatever they call might do any of those.
+pub fn block_has_effect(block: &BlockStmt) -> bool {
+    block.statements.stmts.iter().any(stmt_has_effect)
+}
+
+fn stmt_has_effect(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Print(_)
+        | Stmt::Return(_)
+        | Stmt::Assert(_)
+        | Stmt::Import(_)
+        | Stmt::MultiAssign(_) => true,
+
+        Stmt::VarDecl(vds) => vds.init_expr.as_ref().is_some_and(expr_has_effect),
+        Stmt::MultiVarDecl(mvds) => mvds.init_exprs.iter().any(expr_has_effect),
+        Stmt::Block(bs) => block_has_effect(bs),
+        Stmt::StmtList(sl) => sl.stmts.iter().any(stmt_has_effect),
+        Stmt::Program(ps) => ps.statements.stmts.iter().any(stmt_has_effect),
+        Stmt::If(is) => {
+            expr_has_effect(&is.condition)
+                || block_has_effect(&is.then_clause)
+                || is.else_clause.as_ref().is_some_and(stmt_has_effect)
+        }
+        Stmt::While(ws) => expr_has_effect(&ws.condition) || block_has_effect(&ws.block),
+        Stmt::For(fs) => expr_has_effect(&fs.iterable) || block_has_effect(&fs.block),
+        Stmt::ExprStmt(es) => expr_has_effect(&es.expr),
+        // the body and default parameters only run when the function is called
+        Stmt::FnDecl(_) | Stmt::StructDecl(_) => false,
+    }
+}
+
+fn expr_has_effect(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(_) => true,
+
+        Expr::Number(_) | Expr::String(_) | Expr::Var(_) | Expr::Bool(_) | Expr::AnynFnDecl(_) => {
+            false
+        }
+        Expr::InterpolatedString(ise) => ise.parts.iter().any(expr_has_effect),
+        Expr::Group(ge) => expr_has_effect(&ge.inner),
+        Expr::Prefix(pe) => expr_has_effect(&pe.inner),
+        Expr::Infix(ie) => {
+            let (chain, first) = ie.chain(|_| true);
+            // custom operators call natives
+            chain.iter().any(|link| {
+                matches!(
+                    link.operator.token_type,
+                    TokenType::ColonEqual | TokenType::CustomOperator
+                ) || expr_has_effect(&link.right)
+            }) || expr_has_effect(first)
+        }
+        Expr::List(le) => le.elements.iter().any(expr_has_effect),
+        Expr::Subscript(se) => expr_has_effect(&se.subscriptee) || expr_has_effect(&se.index),
+        Expr::Block(be) => block_has_effect(&be.block),
+        Expr::If(ie) => {
+            expr_has_effect(&ie.condition)
+                || block_has_effect(&ie.then_block)
+                || ie.else_clause.as_ref().is_some_and(expr_has_effect)
+        }
+        Expr::Record(re) => re.values.iter().any(expr_has_effect),
+        Expr::Field(fe) => expr_has_effect(&fe.record),
+    }
+}
@@ -1,6 +1,9 @@
 use thiserror::Error;
 
-use crate::compiler::lexical_analysis::Token;
+use crate::compiler::{
+    codegen::Warning,
+    lexical_analysis::{Token, TokenPos},
+};
 
 #[derive(Error, Debug)]
 pub enum CodeGenError {
@@ -11,8 +14,115 @@ pub enum CodeGenError {
     // todo there should be an ast node included in this
     InvalidAssignmentTarget { message: String },
 
+    #[error("can't assign to constant '{}' at {}, it was declared at {}", .token.lexeme, .token.pos, .declared_at)]
+    AssignmentToConst { token: Token, declared_at: TokenPos },
+
+    #[error("can't assign {} values to {} targets at {}", .values, .targets, .token.pos)]
+    AssignmentCountMismatch {
+        token: Token,
+        targets: usize,
+        values: usize,
+    },
+
     #[error("too many parameters, cahn supports up to {}, but {} were declared", .max, .count)]
     TooManyParameters { count: usize, max: usize },
+
+    #[error("too many arguments at {}, cahn supports up to {}", .token.pos, .max)]
+    TooManyArguments { token: Token, max: usize },
+
+    #[error("too many return values at {}, cahn supports up to {}", .token.pos, .max)]
+    TooManyReturnValues { token: Token, max: usize },
+
+    #[error("too many local variables at {}, cahn supports up to {}", .token.pos, .max)]
+    TooManyLocals { token: Token, max: usize },
+
+    #[error("too many variables captured at {}, cahn supports up to {} per function", .token.pos, .max)]
+    TooManyUpvalues { token: Token, max: usize },
+
+    #[error("'{}' at {} was already declared in the same scope at {}", .token.lexeme, .token.pos, .declared_at)]
+    DuplicateDeclaration { token: Token, declared_at: TokenPos },
+
+    #[error("struct '{}' at {} was already declared at {}", .token.lexeme, .token.pos, .declared_at)]
+    StructRedeclared { token: Token, declared_at: TokenPos },
+
+    #[error("there is no struct '{}', at {}", .token.lexeme, .token.pos)]
+    UnknownStruct { token: Token },
+
+    #[error("field '{}' is given twice, at {}", .token.lexeme, .token.pos)]
+    DuplicateField { token: Token },
+
+    #[error("no struct has a field '{}', at {}", .token.lexeme, .token.pos)]
+    UnknownField { token: Token },
+
+    #[error("{} has no field '{}', at {}", .struct_name, .token.lexeme, .token.pos)]
+    NotAField { token: Token, struct_name: String },
+
+    #[error("the {} at {} is missing field '{}'", .token.lexeme, .token.pos, .field)]
+    MissingField { token: Token, field: String },
+
+    #[error("{} has {} fields, but {} values were given at {}", .token.lexeme, .expected, .got, .token.pos)]
+    FieldCountMismatch {
+        token: Token,
+        expected: usize,
+        got: usize,
+    },
+
+    #[error("too many number constants, cahn supports up to {}", .max)]
+    TooManyConstants { max: usize },
+
+    #[error("too many globals, cahn supports up to {}", .max)]
+    TooManyGlobals { max: usize },
+
+    #[error("too many functions, cahn supports up to {}", .max)]
+    TooManyFunctions { max: usize },
+
+    #[error("the compiled code is too large, cahn supports functions of up to {} bytes", .max)]
+    CodeTooLarge { max: usize },
+
+    #[error("{} [{}], which is an error in strict mode", .warning, .warning.code())]
+    StrictWarning { warning: Warning },
+
+    #[error("the operator '{}' at {} is bound to '{}', which isn't a native function", .token.lexeme, .token.pos, .function)]
+    UnboundOperator { token: Token, function: String },
+
+    #[error("{} are not supported yet, at {}", .feature, .token.pos)]
+    Unsupported { feature: &'static str, token: Token },
+}
+
+impl CodeGenError {
+    /// The start and (exclusive) end of the source the error is about,
+    /// the errors about limits of the whole program don't have one.
+    pub fn range(&self) -> Option<(TokenPos, TokenPos)> {
+        match self {
+            CodeGenError::UnresolvedVariable { var_token } => Some(var_token.range()),
+
+            CodeGenError::AssignmentToConst { token, .. }
+            | CodeGenError::AssignmentCountMismatch { token, .. }
+            | CodeGenError::TooManyArguments { token, .. }
+            | CodeGenError::TooManyReturnValues { token, .. }
+            | CodeGenError::TooManyLocals { token, .. }
+            | CodeGenError::TooManyUpvalues { token, .. }
+            | CodeGenError::DuplicateDeclaration { token, .. }
+            | CodeGenError::StructRedeclared { token, .. }
+            | CodeGenError::UnknownStruct { token }
+            | CodeGenError::DuplicateField { token }
+            | CodeGenError::UnknownField { token }
+            | CodeGenError::NotAField { token, .. }
+            | CodeGenError::MissingField { token, .. }
+            | CodeGenError::FieldCountMismatch { token, .. }
+            | CodeGenError::UnboundOperator { token, .. }
+            | CodeGenError::Unsupported { token, .. } => Some(token.range()),
+
+            CodeGenError::StrictWarning { warning } => Some(warning.range()),
+
+            CodeGenError::InvalidAssignmentTarget { .. }
+            | CodeGenError::TooManyParameters { .. }
+            | CodeGenError::TooManyConstants { .. }
+            | CodeGenError::TooManyGlobals { .. }
+            | CodeGenError::TooManyFunctions { .. }
+            | CodeGenError::CodeTooLarge { .. } => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, CodeGenError>;
@@ -1,4 +1,10 @@
+mod code_buffer;
 mod codegenerator;
+mod effects;
 mod error;
+mod struct_table;
+mod warning;
 
-pub use codegenerator::CodeGenerator;
+pub use codegenerator::{CodeGenOptions, CodeGenerator};
+pub use error::CodeGenError;
+pub use warning::Warning;
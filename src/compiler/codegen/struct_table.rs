@@ -0,0 +1,110 @@
+use ahash::AHashMap;
+
+use super::error::{CodeGenError, Result};
+use crate::{
+    compiler::{
+        ast::StructDeclStmt,
+        lexical_analysis::{Token, TokenPos},
+        string_handling::StringAtom,
+    },
+    executable::StructLayout,
+};
+
+struct Struct {
+    index: u32,
+    fields: Vec<StringAtom>,
+    declared_at: TokenPos,
+}
+
+/// The structs of the program, with the layout of their fields.
+/// Every field name gets an index, shared by all structs with a field of that name,
+/// which is what field accesses compile to, along with the slot the first struct
+/// declaring the field stores it in.
+#[derive(Default)]
+pub(super) struct StructTable {
+    structs: AHashMap<StringAtom, Struct>,
+    layouts: Vec<StructLayout>,
+
+    field_indices: AHashMap<StringAtom, u32>,
+    field_names: Vec<String>,
+    // indexed like field_names
+    field_slots: Vec<u32>,
+}
+
+impl StructTable {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn declare(&mut self, sds: &StructDeclStmt) -> Result<()> {
+        if let Some(existing) = self.structs.get(&sds.name.lexeme) {
+            return Err(CodeGenError::StructRedeclared {
+                token: sds.name.clone(),
+                declared_at: existing.declared_at,
+            });
+        }
+
+        let mut fields: Vec<StringAtom> = vec![];
+        let mut layout = vec![];
+        for field in &sds.fields {
+            if fields.contains(&field.lexeme) {
+                return Err(CodeGenError::DuplicateField {
+                    token: field.clone(),
+                });
+            }
+            let slot = fields.len() as u32;
+            fields.push(field.lexeme.clone());
+            layout.push(self.field_index_or_insert(&field.lexeme, slot));
+        }
+
+        self.structs.insert(
+            sds.name.lexeme.clone(),
+            Struct {
+                index: self.layouts.len() as u32,
+                fields,
+                declared_at: sds.name.pos,
+            },
+        );
+        self.layouts.push(StructLayout {
+            name: sds.name.lexeme.run_on_str(str::to_owned),
+            fields: layout,
+        });
+        Ok(())
+    }
+
+    /// The index of the struct, along with its fields in the order its records store them.
+    pub(super) fn get(&self, name: &StringAtom) -> Option<(u32, &[StringAtom])> {
+        self.structs
+            .get(name)
+            .map(|s| (s.index, s.fields.as_slice()))
+    }
+
+    pub(super) fn contains(&self, name: &StringAtom) -> bool {
+        self.structs.contains_key(name)
+    }
+
+    /// The index of the field name, which some struct has to have a field of,
+    /// and the slot records are expected to store it in.
+    pub(super) fn field_index(&self, field: &Token) -> Result<(u32, u32)> {
+        self.field_indices
+            .get(&field.lexeme)
+            .map(|&index| (index, self.field_slots[index as usize]))
+            .ok_or_else(|| CodeGenError::UnknownField {
+                token: field.clone(),
+            })
+    }
+
+    fn field_index_or_insert(&mut self, name: &StringAtom, slot: u32) -> u32 {
+        let field_names = &mut self.field_names;
+        let field_slots = &mut self.field_slots;
+        *self.field_indices.entry(name.clone()).or_insert_with(|| {
+            field_names.push(name.run_on_str(str::to_owned));
+            field_slots.push(slot);
+            field_names.len() as u32 - 1
+        })
+    }
+
+    pub(super) fn into_parts(self) -> (Vec<StructLayout>, Vec<String>) {
+        (self.layouts, self.field_names)
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::compiler::lexical_analysis::{Token, TokenPos};
+
+/// Problems that don't stop the program from compiling, but are most likely mistakes.
+#[derive(Debug, Clone)]
+pub enum Warning {
+    /// The condition of an if or while is always the same, so one of its branches is dead.
+    /// `pruned` is set if the dead branch was left out of the bytecode.
+    ConstantCondition {
+        token: Token,
+        value: bool,
+        pruned: bool,
+    },
+
+    /// An expression statement, whose value is thrown away, without having any effect.
+    DiscardedExpression { pos: TokenPos },
+
+    /// A local variable that is never read.
+    UnusedVariable { name: String, declared_at: TokenPos },
+
+    /// A variable with the same name as a local of an outer scope, or a global, hiding it.
+    ShadowedVariable {
+        name: String,
+        declared_at: TokenPos,
+        shadowed_at: TokenPos,
+    },
+
+    /// The body of an if, else or while without any statements, often a misplaced brace.
+    /// `end` is right after its closing brace.
+    EmptyBlock { pos: TokenPos, end: TokenPos },
+
+    /// A while loop whose condition is always true, and whose body doesn't return, print,
+    /// call or assign anything, so it can't stop and makes no difference while it runs.
+    LikelyInfiniteLoop { pos: TokenPos },
+}
+
+impl Warning {
+    /// The codes of every kind of warning, which is what they can be allowed by.
+    pub const CODES: &'static [&'static str] = &[
+        "constant-condition",
+        "discarded-expression",
+        "unused-variable",
+        "shadowed-variable",
+        "empty-block",
+        "likely-infinite-loop",
+    ];
+
+    /// A short name for the kind of warning, that stays the same between versions.
+    pub fn code(&self) -> &'static str {
+        // a new code also goes in CODES
+        match self {
+            Warning::ConstantCondition { .. } => "constant-condition",
+            Warning::DiscardedExpression { .. } => "discarded-expression",
+            Warning::UnusedVariable { .. } => "unused-variable",
+            Warning::ShadowedVariable { .. } => "shadowed-variable",
+            Warning::EmptyBlock { .. } => "empty-block",
+            Warning::LikelyInfiniteLoop { .. } => "likely-infinite-loop",
+        }
+    }
+
+    /// The start and (exclusive) end of the source the warning is about.
+    /// The end of a discarded expression isn't known, so its range is empty.
+    pub fn range(&self) -> (TokenPos, TokenPos) {
+        // variable names and keywords don't span lines
+        let name_range = |name: &str, pos: TokenPos| {
+            (
+                pos,
+                TokenPos {
+                    column: pos.column + name.chars().count(),
+                    ..pos
+                },
+            )
+        };
+
+        match self {
+            Warning::ConstantCondition { token, .. } => token.range(),
+            Warning::DiscardedExpression { pos } => (*pos, *pos),
+            Warning::UnusedVariable { name, declared_at } => name_range(name, *declared_at),
+            Warning::ShadowedVariable {
+                name, declared_at, ..
+            } => name_range(name, *declared_at),
+            Warning::EmptyBlock { pos, end } => (*pos, *end),
+            Warning::LikelyInfiniteLoop { pos } => name_range("while", *pos),
+        }
+    }
+
+    /// Whether the warning is an error when compiling in strict mode.
+    pub fn is_strict_error(&self) -> bool {
+        matches!(
+            self,
+            Warning::DiscardedExpression { .. } | Warning::UnusedVariable { .. }
+        )
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ConstantCondition {
+                token,
+                value,
+                pruned,
+            } => {
+                f.write_fmt(format_args!(
+                    "the condition of the {} at {} is always {}",
+                    token.lexeme, token.pos, value
+                ))?;
+                if *pruned {
+                    f.write_str(", the code that never runs was left out")?;
+                }
+                Ok(())
+            }
+
+            Warning::DiscardedExpression { pos } => f.write_fmt(format_args!(
+                "the value of the expression at {} is never used",
+                pos
+            )),
+
+            Warning::UnusedVariable { name, declared_at } => f.write_fmt(format_args!(
+                "the variable '{}' declared at {} is never used, prefix it with '_' if that's intended",
+                name, declared_at
+            )),
+
+            Warning::ShadowedVariable {
+                name,
+                declared_at,
+                shadowed_at,
+            } => f.write_fmt(format_args!(
+                "the variable '{}' declared at {} shadows the one declared at {}, \
+                 prefix it with '_' if that's intended",
+                name, declared_at, shadowed_at
+            )),
+
+            Warning::EmptyBlock { pos, .. } => f.write_fmt(format_args!(
+                "the block at {} is empty, is a brace misplaced?",
+                pos
+            )),
+
+            Warning::LikelyInfiniteLoop { pos } => f.write_fmt(format_args!(
+                "the while loop at {} never stops, and does nothing while it runs",
+                pos
+            )),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+/// The built in operators a custom operator binds like, from left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorPrecedence {
+    /// Like `..`, the loosest.
+    Concatenation,
+    /// Like `&`, `|`, `^`, `<<` and `>>`.
+    Bitwise,
+    /// Like `+` and `-`.
+    Addition,
+    /// Like `*`, `/`, `//` and `%`, the tightest.
+    Multiplication,
+}
+
+/// An infix operator the host adds to the language, for embedding cahn as a DSL.
+/// `a ⊕ b` calls the function named `function` with a and b, so the host registers
+/// a native of that name along with the operator.
+///
+/// The symbol is either a word, like `dot`, which is lexed like a keyword,
+/// or a run of other chars without whitespace, like `⊕` or `<+>`.
+/// Symbols take precedence over the built in operators they start with,
+/// and words over identifiers, but not over the built in keywords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomOperator {
+    pub symbol: String,
+    pub function: String,
+    pub precedence: OperatorPrecedence,
+}
+
+impl CustomOperator {
+    pub fn new(symbol: &str, function: &str, precedence: OperatorPrecedence) -> Self {
+        CustomOperator {
+            symbol: symbol.to_string(),
+            function: function.to_string(),
+            precedence,
+        }
+    }
+
+    // words are lexed like identifiers, everything else is matched char by char
+    pub(super) fn is_word(&self) -> bool {
+        let mut chars = self.symbol.chars();
+        matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+    }
+}
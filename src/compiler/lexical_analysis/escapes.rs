@@ -0,0 +1,133 @@
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EscapeError {
+    #[error("unknown escape '\\{}'", .0)]
+    UnknownEscape(char),
+
+    #[error("'\\' at the end of the literal doesn't escape anything")]
+    TrailingBackslash,
+
+    #[error("'\\x' needs two hex digits of at most 7f, like '\\x41'")]
+    MalformedHexEscape,
+
+    #[error("'\\u' needs one to six hex digits in braces, like '\\u{{1F600}}'")]
+    MalformedUnicodeEscape,
+
+    #[error("{:x} isn't a unicode scalar value", .0)]
+    InvalidCodepoint(u32),
+}
+
+/// Replaces the escapes in the text between the quotes of a literal with the chars they stand for.
+/// String and char literals both decode their escapes here, so they always agree:
+/// `\n`, `\r`, `\t`, `\0`, `\\`, `\"`, `\'`, `\x41` (ascii only) and `\u{1F600}`.
+pub fn decode_escapes(raw: &str) -> Result<String, EscapeError> {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next().ok_or(EscapeError::TrailingBackslash)? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 && byte.is_ascii() => byte as char,
+                    _ => return Err(EscapeError::MalformedHexEscape),
+                }
+            }
+
+            'u' => {
+                if chars.next() != Some('{') {
+                    return Err(EscapeError::MalformedUnicodeEscape);
+                }
+                let mut digits = String::new();
+                let mut is_closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        is_closed = true;
+                        break;
+                    }
+                    digits.push(c);
+                }
+                if !is_closed
+                    || digits.is_empty()
+                    || digits.len() > 6
+                    || !digits.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    return Err(EscapeError::MalformedUnicodeEscape);
+                }
+                let codepoint = u32::from_str_radix(&digits, 16)
+                    .map_err(|_| EscapeError::MalformedUnicodeEscape)?;
+                char::from_u32(codepoint).ok_or(EscapeError::InvalidCodepoint(codepoint))?
+            }
+
+            other => return Err(EscapeError::UnknownEscape(other)),
+        };
+        decoded.push(escaped);
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_escapes, EscapeError};
+
+    #[test]
+    fn escapes_decode() {
+        assert_eq!(
+            decode_escapes(r#"a\n\t\r\0\\\"\'b"#).unwrap(),
+            "a\n\t\r\0\\\"'b"
+        );
+        assert_eq!(decode_escapes(r"\x41\x7f").unwrap(), "A\x7f");
+        assert_eq!(decode_escapes(r"\u{1F600}\u{e9}").unwrap(), "😀é");
+        assert_eq!(decode_escapes("no escapes").unwrap(), "no escapes");
+    }
+
+    #[test]
+    fn bad_escapes_are_errors() {
+        assert_eq!(decode_escapes(r"\q"), Err(EscapeError::UnknownEscape('q')));
+        assert_eq!(decode_escapes("\\"), Err(EscapeError::TrailingBackslash));
+        assert_eq!(decode_escapes(r"\x4"), Err(EscapeError::MalformedHexEscape));
+        assert_eq!(
+            decode_escapes(r"\x80"),
+            Err(EscapeError::MalformedHexEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\xg1"),
+            Err(EscapeError::MalformedHexEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\u41"),
+            Err(EscapeError::MalformedUnicodeEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\u{41"),
+            Err(EscapeError::MalformedUnicodeEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\u{}"),
+            Err(EscapeError::MalformedUnicodeEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\u{1234567}"),
+            Err(EscapeError::MalformedUnicodeEscape)
+        );
+        assert_eq!(
+            decode_escapes(r"\u{D800}"),
+            Err(EscapeError::InvalidCodepoint(0xD800))
+        );
+    }
+}
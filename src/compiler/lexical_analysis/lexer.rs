@@ -1,6 +1,6 @@
 use crate::compiler::string_handling::{StringAtom, StringInterner};
 
-use super::{token::TokenPos, Token, TokenType};
+use super::{token::TokenPos, CustomOperator, Token, TokenType};
 use std::cell::Cell;
 
 #[derive(Debug)]
@@ -14,11 +14,15 @@ pub struct Lexer<'a> {
 
     interner: StringInterner,
     keyword_atoms: KeywordAtoms,
+    custom_operators: &'a [CustomOperator],
+    // set once a token didn't fit in the interner, every token after that is InternerFull
+    interner_full: Cell<bool>,
 }
 
 #[derive(Debug)]
 struct KeywordAtoms {
     k_let: StringAtom,
+    k_const: StringAtom,
     k_nil: StringAtom,
     k_if: StringAtom,
     k_else: StringAtom,
@@ -29,14 +33,22 @@ struct KeywordAtoms {
     k_or: StringAtom,
     k_not: StringAtom,
     k_while: StringAtom,
+    k_do: StringAtom,
+    k_for: StringAtom,
     k_fn: StringAtom,
     k_return: StringAtom,
+    k_struct: StringAtom,
+    k_in: StringAtom,
+    k_import: StringAtom,
+    k_typeof: StringAtom,
+    k_assert: StringAtom,
 }
 
 impl KeywordAtoms {
     fn with_interner(interner: &StringInterner) -> Self {
         KeywordAtoms {
             k_let: interner.intern("let"),
+            k_const: interner.intern("const"),
             k_nil: interner.intern("nil"),
             k_if: interner.intern("if"),
             k_else: interner.intern("else"),
@@ -47,27 +59,56 @@ impl KeywordAtoms {
             k_or: interner.intern("or"),
             k_not: interner.intern("not"),
             k_while: interner.intern("while"),
+            k_do: interner.intern("do"),
+            k_for: interner.intern("for"),
             k_fn: interner.intern("fn"),
             k_return: interner.intern("return"),
+            k_struct: interner.intern("struct"),
+            k_in: interner.intern("in"),
+            k_import: interner.intern("import"),
+            k_typeof: interner.intern("typeof"),
+            k_assert: interner.intern("assert"),
         }
     }
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source_string: &'a str, interner: StringInterner) -> Self {
+        Self::with_start_pos(source_string, interner, TokenPos::new(1, 1))
+    }
+
+    // for lexing source that is embedded in a larger file, like the expressions in an
+    // interpolated string. the tokens get their position in the larger file.
+    pub fn with_start_pos(source_string: &'a str, interner: StringInterner, pos: TokenPos) -> Self {
         Lexer {
             source_string,
             start_index: Cell::new(0),
             current_index: Cell::new(0),
 
-            start_pos: Cell::new(TokenPos::new(1, 1)),
-            current_pos: Cell::new(TokenPos::new(1, 1)),
+            start_pos: Cell::new(pos),
+            current_pos: Cell::new(pos),
 
             keyword_atoms: KeywordAtoms::with_interner(&interner),
+            custom_operators: &[],
             interner,
+            interner_full: Cell::new(false),
         }
     }
 
+    /// Lexes the symbols of the operators as `TokenType::CustomOperator`.
+    pub fn with_custom_operators(mut self, custom_operators: &'a [CustomOperator]) -> Self {
+        self.custom_operators = custom_operators;
+        self
+    }
+
+    pub fn interner(&self) -> &StringInterner {
+        &self.interner
+    }
+
+    pub fn custom_operators(&self) -> &'a [CustomOperator] {
+        self.custom_operators
+    }
+
     fn peek_char(&self) -> Option<char> {
         let x = &self.source_string[self.current_index.get()..];
         let c = x.chars().next();
@@ -87,15 +128,7 @@ impl<'a> Lexer<'a> {
             self.current_index
                 .set(self.current_index.get() + c.len_utf8());
 
-            if c == '\n' {
-                self.current_pos
-                    .set(TokenPos::new(self.current_pos.get().line + 1, 1));
-            } else {
-                self.current_pos.set(TokenPos::new(
-                    self.current_pos.get().line,
-                    self.current_pos.get().column + 1,
-                ));
-            }
+            self.current_pos.set(self.current_pos.get().after(c));
         }
         c
     }
@@ -108,7 +141,7 @@ impl<'a> Lexer<'a> {
                 }
 
                 // skip comments
-                Some(c) if c == '#' => {
+                Some('#') => {
                     self.advance(); // skip '#'
                     if self.mmatch('/') {
                         let mut comment_level = 1;
@@ -136,7 +169,7 @@ impl<'a> Lexer<'a> {
                             }
                         }
                     } else {
-                        while !self.mmatch('\n') && !self.peek_char().is_none() {
+                        while !self.mmatch('\n') && self.peek_char().is_some() {
                             self.advance();
                         }
                     }
@@ -147,12 +180,26 @@ impl<'a> Lexer<'a> {
     }
 
     fn make_token(&self, token_type: TokenType) -> Token {
+        let lexeme = &self.source_string[self.start_index.get()..self.current_index.get()];
+        match self.interner.try_intern(lexeme) {
+            Ok(lexeme) => Token {
+                pos: self.start_pos.get(),
+                token_type,
+                lexeme,
+            },
+            Err(_) => {
+                self.interner_full.set(true);
+                self.interner_full_token()
+            }
+        }
+    }
+
+    fn interner_full_token(&self) -> Token {
         Token {
             pos: self.start_pos.get(),
-            token_type,
-            lexeme: self
-                .interner
-                .intern(&self.source_string[self.start_index.get()..self.current_index.get()]),
+            token_type: TokenType::InternerFull,
+            // takes up no space, so it always fits
+            lexeme: self.interner.intern(""),
         }
     }
 
@@ -169,30 +216,81 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn finish_number(&self) -> Token {
-        while matches!(self.peek_char(), Some(c) if c.is_ascii_digit()) {
+    // digits, which may be separated by single underscores, like 1_000.
+    // returns false if an underscore isn't followed by a digit.
+    fn eat_digits(&self) -> bool {
+        loop {
+            match (self.peek_char(), self.peek_next()) {
+                (Some(c), _) if c.is_ascii_digit() => {}
+                (Some('_'), Some(n)) if n.is_ascii_digit() => {}
+                (Some('_'), _) => {
+                    self.advance();
+                    return false;
+                }
+                _ => return true,
+            }
             self.advance();
         }
+    }
+
+    fn finish_number(&self) -> Token {
+        let mut well_formed = self.eat_digits();
 
         match (self.peek_char(), self.peek_next()) {
-            (Some(c), Some(n)) if c == '.' && n.is_ascii_digit() => {
-                self.advance();
+            (Some(c), Some(n)) if well_formed && c == '.' && n.is_ascii_digit() => {
                 self.advance();
-                while matches!(self.peek_char(), Some(c) if c.is_ascii_digit()) {
-                    self.advance();
-                }
+                well_formed = self.eat_digits();
             }
             _ => {}
         };
 
-        self.make_token(TokenType::Number)
+        // an exponent, like 2.5e-3
+        if well_formed && matches!(self.peek_char(), Some('e') | Some('E')) {
+            self.advance();
+            if matches!(self.peek_char(), Some('+') | Some('-')) {
+                self.advance();
+            }
+            well_formed = matches!(self.peek_char(), Some(c) if c.is_ascii_digit());
+            if well_formed {
+                well_formed = self.eat_digits();
+            }
+        }
+
+        if well_formed {
+            self.make_token(TokenType::Number)
+        } else {
+            self.make_token(TokenType::MalformedNumber)
+        }
     }
 
-    fn finish_string(&self) -> Token {
-        while !self.mmatch('"') {
-            self.advance();
+    fn finish_string(&self, token_type: TokenType) -> Token {
+        self.finish_quoted('"', token_type)
+    }
+
+    // strings and chars end at the first unescaped quote, the escapes are decoded by the parser.
+    // raw strings don't have escapes, so a backslash before their quote doesn't matter.
+    fn finish_quoted(&self, quote: char, token_type: TokenType) -> Token {
+        while !self.mmatch(quote) {
+            // we ran out of characters before the string was closed
+            let c = match self.advance() {
+                Some(c) => c,
+                None => return self.make_token(TokenType::UnterminatedString),
+            };
+            if c == '\\' && token_type != TokenType::RawString {
+                self.advance();
+            }
         }
-        self.make_token(TokenType::String)
+        self.make_token(token_type)
+    }
+
+    // the length of the longest custom operator symbol the rest of the source starts with
+    fn custom_symbol_len(&self) -> Option<usize> {
+        let rest = &self.source_string[self.current_index.get()..];
+        self.custom_operators
+            .iter()
+            .filter(|op| !op.symbol.is_empty() && !op.is_word() && rest.starts_with(&op.symbol))
+            .map(|op| op.symbol.len())
+            .max()
     }
 
     fn finish_identifier(&self) -> Token {
@@ -200,11 +298,15 @@ impl<'a> Lexer<'a> {
             self.advance();
         }
         let mut token = self.make_token(TokenType::Identifier);
+        if token.token_type == TokenType::InternerFull {
+            return token;
+        }
 
         let keywords = &self.keyword_atoms;
 
         token.token_type = match &token.lexeme {
             w if w == &keywords.k_let => TokenType::Let,
+            w if w == &keywords.k_const => TokenType::Const,
             w if w == &keywords.k_nil => TokenType::Nil,
             w if w == &keywords.k_if => TokenType::If,
             w if w == &keywords.k_else => TokenType::Else,
@@ -215,18 +317,43 @@ impl<'a> Lexer<'a> {
             w if w == &keywords.k_or => TokenType::Or,
             w if w == &keywords.k_not => TokenType::Not,
             w if w == &keywords.k_while => TokenType::While,
+            w if w == &keywords.k_do => TokenType::Do,
+            w if w == &keywords.k_for => TokenType::For,
             w if w == &keywords.k_fn => TokenType::Fn,
             w if w == &keywords.k_return => TokenType::Return,
+            w if w == &keywords.k_struct => TokenType::Struct,
+            w if w == &keywords.k_in => TokenType::In,
+            w if w == &keywords.k_import => TokenType::Import,
+            w if w == &keywords.k_typeof => TokenType::TypeOf,
+            w if w == &keywords.k_assert => TokenType::Assert,
+            w if self
+                .custom_operators
+                .iter()
+                .any(|op| op.is_word() && w.run_on_str(|w| w == op.symbol)) =>
+            {
+                TokenType::CustomOperator
+            }
             _ => TokenType::Identifier,
         };
         token
     }
 
     pub fn lex_token(&self) -> Token {
+        if self.interner_full.get() {
+            return self.interner_full_token();
+        }
+
         self.skip_whitespace();
         self.start_index.set(self.current_index.get());
         self.start_pos.set(self.current_pos.get());
 
+        if let Some(symbol_len) = self.custom_symbol_len() {
+            while self.current_index.get() - self.start_index.get() < symbol_len {
+                self.advance();
+            }
+            return self.make_token(TokenType::CustomOperator);
+        }
+
         let c = match self.advance() {
             None => return self.make_token(TokenType::Eof),
             Some(c) => c,
@@ -247,10 +374,20 @@ impl<'a> Lexer<'a> {
 
             '=' if self.mmatch('=') => self.make_token(TokenType::DoubleEqual),
 
-            '"' => self.finish_string(),
+            '"' => self.finish_string(TokenType::String),
+
+            '\'' => self.finish_quoted('\'', TokenType::Char),
+
+            // raw strings, r"C:\temp" keeps its backslashes
+            'r' if self.mmatch('"') => self.finish_string(TokenType::RawString),
 
             ',' => self.make_token(TokenType::Comma),
-            '.' if self.mmatch('.') => self.make_token(TokenType::DoubleDot),
+            '.' if self.mmatch('.') => self.make_token(if self.mmatch('.') {
+                TokenType::TripleDot
+            } else {
+                TokenType::DoubleDot
+            }),
+            '.' => self.make_token(TokenType::Dot),
 
             '%' => self.make_token(TokenType::Percent),
 
@@ -270,23 +407,35 @@ impl<'a> Lexer<'a> {
 
             '<' => self.make_token(if self.mmatch('=') {
                 TokenType::LessEqual
+            } else if self.mmatch('<') {
+                TokenType::DoubleLess
             } else {
                 TokenType::Less
             }),
 
             '>' => self.make_token(if self.mmatch('=') {
                 TokenType::GreaterEqual
+            } else if self.mmatch('>') {
+                TokenType::DoubleGreater
             } else {
                 TokenType::Greater
             }),
 
-            ':' if self.mmatch('=') => self.make_token(TokenType::ColonEqual),
+            '&' => self.make_token(TokenType::Ampersand),
+            '|' => self.make_token(TokenType::Pipe),
+            '^' => self.make_token(TokenType::Caret),
+
+            ':' => self.make_token(if self.mmatch('=') {
+                TokenType::ColonEqual
+            } else {
+                TokenType::Colon
+            }),
 
             '!' if self.mmatch('=') => self.make_token(TokenType::BangEqual),
 
             c if c.is_ascii_digit() => self.finish_number(),
 
-            c if c.is_alphabetic() => self.finish_identifier(),
+            c if c.is_alphabetic() || c == '_' => self.finish_identifier(),
 
             _ => self.make_token(TokenType::BadCharacter),
         }
@@ -310,4 +459,97 @@ mod tests {
         assert_eq!(lexer.lex_token().token_type, TokenType::Minus);
         assert_eq!(lexer.lex_token().token_type, TokenType::Number);
     }
+
+    #[test]
+    fn raw_string_test() {
+        let source = r#"r"\n" r rx"" r"unterminated"#;
+        let interner = StringInterner::new();
+
+        let lexer = Lexer::new(source, interner);
+
+        let raw = lexer.lex_token();
+        assert_eq!(raw.token_type, TokenType::RawString);
+        raw.lexeme.run_on_str(|str| assert_eq!(str, r#"r"\n""#));
+
+        assert_eq!(lexer.lex_token().token_type, TokenType::Identifier);
+        assert_eq!(lexer.lex_token().token_type, TokenType::Identifier);
+        assert_eq!(lexer.lex_token().token_type, TokenType::String);
+        assert_eq!(lexer.lex_token().token_type, TokenType::UnterminatedString);
+    }
+
+    // how a keyword is spelled, None for token types that aren't keywords.
+    // there is no wildcard, so a new token type has to be sorted in here.
+    fn keyword_spelling(token_type: TokenType) -> Option<&'static str> {
+        use TokenType::*;
+        match token_type {
+            Let => Some("let"),
+            Const => Some("const"),
+            Nil => Some("nil"),
+            If => Some("if"),
+            Else => Some("else"),
+            Print => Some("print"),
+            True => Some("true"),
+            False => Some("false"),
+            And => Some("and"),
+            Or => Some("or"),
+            Not => Some("not"),
+            While => Some("while"),
+            Do => Some("do"),
+            For => Some("for"),
+            Fn => Some("fn"),
+            Return => Some("return"),
+            Struct => Some("struct"),
+            In => Some("in"),
+            Import => Some("import"),
+            TypeOf => Some("typeof"),
+            Assert => Some("assert"),
+
+            Plus | Minus | Star | Slash | Percent | Dot | DoubleDot | TripleDot | DoubleStar
+            | DoubleSlash | Ampersand | Pipe | Caret | DoubleLess | DoubleGreater
+            | CustomOperator | Identifier | Number | String | RawString | Char | ParenOpen
+            | ParenClose | BracketOpen | BracketClose | BraceOpen | BraceClose | Comma | Colon
+            | BangEqual | DoubleEqual | ColonEqual | Less | LessEqual | Greater | GreaterEqual
+            | Eof | Semicolon | BadCharacter | UnterminatedString | MalformedNumber
+            | InternerFull => None,
+        }
+    }
+
+    #[test]
+    fn every_keyword_is_lexed() {
+        use TokenType::*;
+        // every token type keyword_spelling spells
+        let keywords = [
+            Let, Const, Nil, If, Else, Print, True, False, And, Or, Not, While, Do, For, Fn,
+            Return, Struct, In, Import, TypeOf, Assert,
+        ];
+
+        for &keyword in &keywords {
+            let spelling = keyword_spelling(keyword).unwrap();
+            let lexer = Lexer::new(spelling, StringInterner::new());
+            assert_eq!(lexer.lex_token().token_type, keyword, "{}", spelling);
+            assert_eq!(lexer.lex_token().token_type, Eof, "{}", spelling);
+
+            // only the whole word is the keyword
+            let longer = format!("{}_", spelling);
+            let lexer = Lexer::new(&longer, StringInterner::new());
+            assert_eq!(lexer.lex_token().token_type, Identifier, "{}", longer);
+        }
+    }
+
+    #[test]
+    fn number_test() {
+        let source = "1e10 2.5E-3 1_000_000 1e+2 1e 1_ 1e- 2.5_";
+        let interner = StringInterner::new();
+
+        let lexer = Lexer::new(source, interner);
+        for expected in &["1e10", "2.5E-3", "1_000_000", "1e+2"] {
+            let token = lexer.lex_token();
+            assert_eq!(token.token_type, TokenType::Number);
+            token.lexeme.run_on_str(|str| assert_eq!(&str, expected));
+        }
+        for _ in 0..4 {
+            assert_eq!(lexer.lex_token().token_type, TokenType::MalformedNumber);
+        }
+        assert_eq!(lexer.lex_token().token_type, TokenType::Eof);
+    }
 }
@@ -1,5 +1,9 @@
+mod custom_operators;
+mod escapes;
 mod lexer;
 mod token;
 
+pub use custom_operators::{CustomOperator, OperatorPrecedence};
+pub use escapes::{decode_escapes, EscapeError};
 pub use lexer::Lexer;
 pub use token::{token_groups, Token, TokenPos, TokenType};
@@ -9,13 +9,25 @@ pub enum TokenType {
     Star,
     Slash,
     Percent,
+    Dot,
     DoubleDot,
+    TripleDot,
     DoubleStar,
     DoubleSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    DoubleLess,
+    DoubleGreater,
+    // an operator registered by the host, the lexeme says which
+    CustomOperator,
 
     Identifier,
     Number,
     String,
+    RawString,
+    // a single char in single quotes, which is a string of that char
+    Char,
     True,
     False,
     Nil,
@@ -30,8 +42,10 @@ pub enum TokenType {
     BraceClose,
 
     Let,
+    Const,
 
     Comma,
+    Colon,
 
     BangEqual,
     DoubleEqual,
@@ -44,20 +58,31 @@ pub enum TokenType {
 
     Fn,
     Return,
+    Struct,
+    Import,
 
     If,
     Else,
     While,
+    Do,
+    For,
 
     And,
     Or,
     Not,
+    In,
+    TypeOf,
 
     Print,
+    Assert,
 
     Eof,
     Semicolon,
     BadCharacter,
+    UnterminatedString,
+    MalformedNumber,
+    // the source didn't fit in the interner
+    InternerFull,
 }
 
 pub mod token_groups {
@@ -73,8 +98,11 @@ pub mod token_groups {
         Greater,
         GreaterEqual,
         BangEqual,
+        In,
     ];
-    pub const PREFIX_OPERATORS: &[TokenType] = &[Not, Minus];
+    pub const BITWISE_OPERATORS: &[TokenType] =
+        &[Ampersand, Pipe, Caret, DoubleLess, DoubleGreater];
+    pub const PREFIX_OPERATORS: &[TokenType] = &[Not, Minus, TypeOf];
 }
 
 impl fmt::Display for TokenType {
@@ -83,15 +111,43 @@ impl fmt::Display for TokenType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TokenPos {
     pub line: usize,
     pub column: usize,
+    /// The file the position is in, 0 for the program's own file and the others for the
+    /// modules it imports, see `Executable::source_file_of`.
+    pub file: usize,
 }
 
 impl TokenPos {
     pub fn new(line: usize, column: usize) -> Self {
-        TokenPos { line, column }
+        TokenPos {
+            line,
+            column,
+            file: 0,
+        }
+    }
+
+    /// The same position, in another file.
+    pub fn in_file(self, file: usize) -> Self {
+        TokenPos { file, ..self }
+    }
+
+    /// The position of the character after `c`, if `c` is at this position.
+    pub fn after(self, c: char) -> Self {
+        if c == '\n' {
+            TokenPos {
+                line: self.line + 1,
+                column: 1,
+                ..self
+            }
+        } else {
+            TokenPos {
+                column: self.column + 1,
+                ..self
+            }
+        }
     }
 }
 
@@ -114,6 +170,20 @@ pub struct Token {
     pub lexeme: StringAtom,
 }
 
+impl Token {
+    /// The position right after the last character of the token,
+    /// the lexeme of strings and comments can span several lines.
+    pub fn end_pos(&self) -> TokenPos {
+        self.lexeme
+            .run_on_str(|str| str.chars().fold(self.pos, TokenPos::after))
+    }
+
+    /// The start and (exclusive) end of the token.
+    pub fn range(&self) -> (TokenPos, TokenPos) {
+        (self.pos, self.end_pos())
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
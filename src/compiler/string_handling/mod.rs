@@ -1,3 +1,3 @@
 mod string_interner;
 
-pub use string_interner::{Atom as StringAtom, RCInterner as StringInterner};
+pub use string_interner::{Atom as StringAtom, InternerError, RCInterner as StringInterner};
@@ -7,6 +7,7 @@ use std::{
 };
 
 use intmap::IntMap;
+use thiserror::Error;
 
 use crate::utils::hash_string;
 
@@ -14,6 +15,34 @@ use crate::utils::hash_string;
 pub struct Interner {
     strings: RefCell<IntMap<(usize, usize)>>,
     big_string: RefCell<String>,
+    // try_intern fails instead of growing big_string past this many bytes
+    max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Error)]
+pub enum InternerError {
+    #[error("can't intern {} more bytes, the interner is limited to {} bytes", .len, .max_bytes)]
+    Full { len: usize, max_bytes: usize },
+
+    #[error("can't reset the interner, {} atoms or clones of it are still around", .count)]
+    InUse { count: usize },
+}
+
+impl Interner {
+    /// The number of bytes of all the interned strings.
+    pub fn bytes_used(&self) -> usize {
+        self.big_string.borrow().len()
+    }
+
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Frees the memory the interner reserved for strings it didn't get.
+    /// Atoms only hold indices, so this doesn't affect them.
+    pub fn shrink_to_fit(&self) {
+        self.big_string.borrow_mut().shrink_to_fit();
+    }
 }
 
 impl Drop for Interner {
@@ -33,22 +62,80 @@ impl Deref for RCInterner {
     }
 }
 
+impl Default for RCInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RCInterner {
     pub fn new() -> Self {
+        Self::with_limit(None)
+    }
+
+    /// An interner whose `try_intern` fails once the interned strings would take up
+    /// more than `max_bytes`, so a huge source can't exhaust the memory.
+    pub fn with_max_bytes(max_bytes: usize) -> Self {
+        Self::with_limit(Some(max_bytes))
+    }
+
+    fn with_limit(max_bytes: Option<usize>) -> Self {
         RCInterner(Rc::new(Interner {
             strings: RefCell::new(IntMap::new()),
             big_string: RefCell::new(String::new()),
+            max_bytes,
         }))
     }
 
-    pub fn intern<'a, 'b>(&'a self, str_to_intern: &'b str) -> Atom {
+    /// Interns the string, ignoring the byte limit.
+    /// Meant for the few strings the compiler makes itself, anything read from
+    /// the source should go through `try_intern`.
+    pub fn intern(&self, str_to_intern: &str) -> Atom {
+        self.intern_with_limit(str_to_intern, None)
+            .expect("interning without a limit can't fail")
+    }
+
+    /// Interns the string, unless that would grow the interner past its byte limit.
+    /// Strings that are already interned never fail.
+    pub fn try_intern(&self, str_to_intern: &str) -> Result<Atom, InternerError> {
+        self.intern_with_limit(str_to_intern, self.max_bytes)
+    }
+
+    /// Empties the interner, so it can be reused without holding on to the old strings.
+    /// The atoms of the old strings would point at whatever replaces them, so resetting
+    /// fails while any atom, or any other clone of the interner, is still around.
+    /// Those all hold on to the same Rc, which is how they're counted.
+    pub fn reset(&mut self) -> Result<(), InternerError> {
+        let count = Rc::strong_count(&self.0) - 1;
+        let interner = Rc::get_mut(&mut self.0).ok_or(InternerError::InUse { count })?;
+        interner.strings.get_mut().clear();
+        interner.big_string.get_mut().clear();
+        Ok(())
+    }
+
+    fn intern_with_limit(
+        &self,
+        str_to_intern: &str,
+        max_bytes: Option<usize>,
+    ) -> Result<Atom, InternerError> {
         let hash = hash_string(str_to_intern);
 
         let res = self.strings.borrow().get(hash).map(|(x, y)| (*x, *y));
         match res {
-            Some((start_index, end_index)) => Atom::new(start_index, end_index, Rc::clone(&self.0)),
+            Some((start_index, end_index)) => {
+                Ok(Atom::new(start_index, end_index, Rc::clone(&self.0)))
+            }
 
             None => {
+                if let Some(max_bytes) = max_bytes {
+                    if self.bytes_used() + str_to_intern.len() > max_bytes {
+                        return Err(InternerError::Full {
+                            len: str_to_intern.len(),
+                            max_bytes,
+                        });
+                    }
+                }
+
                 let start_index = self.big_string.borrow().len();
                 self.big_string.borrow_mut().push_str(str_to_intern);
                 let end_index = self.big_string.borrow().len();
@@ -56,7 +143,7 @@ impl RCInterner {
                 self.strings
                     .borrow_mut()
                     .insert(hash, (start_index, end_index));
-                Atom::new(start_index, end_index, Rc::clone(&self.0))
+                Ok(Atom::new(start_index, end_index, Rc::clone(&self.0)))
             }
         }
     }
@@ -156,7 +243,7 @@ impl Hash for Atom {
 
 #[cfg(test)]
 mod test {
-    use crate::compiler::string_handling::StringInterner;
+    use crate::compiler::string_handling::{InternerError, StringInterner};
     #[test]
     fn test_interner() {
         let interner = StringInterner::new();
@@ -193,4 +280,43 @@ mod test {
         assert_eq!(interner.big_string.borrow().clone(), "hej meddig");
         assert_eq!(atom3, atom4);
     }
+
+    #[test]
+    fn limited_interner() {
+        let interner = StringInterner::with_max_bytes(10);
+        let atom = interner.try_intern("hej med").unwrap();
+        assert!(matches!(
+            interner.try_intern("dig!"),
+            Err(InternerError::Full {
+                len: 4,
+                max_bytes: 10
+            })
+        ));
+        // nothing was added by the failed attempt, and known strings don't need room
+        assert_eq!(interner.bytes_used(), 7);
+        assert_eq!(interner.try_intern("hej med").unwrap(), atom);
+        assert!(interner.try_intern("dig").is_ok());
+        assert_eq!(interner.bytes_used(), 10);
+        // intern is for the compiler's own strings, which don't count against the limit
+        assert_eq!(interner.intern("!").to_string(), "!");
+    }
+
+    #[test]
+    fn reset_needs_every_atom_gone() {
+        let mut interner = StringInterner::new();
+        let atom = interner.intern("hej med dig");
+        let clone = interner.clone();
+        assert!(matches!(
+            interner.reset(),
+            Err(InternerError::InUse { count: 2 })
+        ));
+        assert_eq!(atom.to_string(), "hej med dig");
+
+        drop(atom);
+        drop(clone);
+        interner.reset().unwrap();
+        assert_eq!(interner.bytes_used(), 0);
+        interner.shrink_to_fit();
+        assert_eq!(interner.intern("dig").to_string(), "dig");
+    }
 }
@@ -1,4 +1,4 @@
-use crate::compiler::lexical_analysis::Token;
+use crate::compiler::lexical_analysis::{Token, TokenPos};
 
 use thiserror::Error;
 #[derive(Debug, Error)]
@@ -14,6 +14,55 @@ pub enum ParseError {
 
     #[error("chaining assignment operators is not supported: {}", .operator)]
     ChainingAssignmentOperator { operator: Token },
+
+    #[error("statements on the same line need a ';' between them, at {}", .token)]
+    MissingSeparator { token: Token },
+
+    #[error("program is nested too deeply at {}", .token)]
+    NestingTooDeep { token: Token },
+
+    #[error("the source is too large at {}, it can take up at most {} bytes", .token.pos, .max_bytes)]
+    InternerFull { token: Token, max_bytes: usize },
+
+    #[error("the '{{' at {} is never closed", .brace_open.pos)]
+    UnclosedBrace { brace_open: Token, eof: Token },
+
+    #[error("can't find module '{}', imported at {}", .path, .token.pos)]
+    ModuleNotFound { token: Token, path: String },
+
+    #[error("circular import at {}: {}", .token.pos, .chain.join(" -> "))]
+    CircularImport { token: Token, chain: Vec<String> },
+
+    #[error("in module '{}': {}", .path, .error)]
+    InModule {
+        path: String,
+        error: Box<ParseError>,
+    },
+}
+
+impl ParseError {
+    /// The start and (exclusive) end of the source the error is about.
+    pub fn range(&self) -> (TokenPos, TokenPos) {
+        match self {
+            ParseError::BadToken { token, .. }
+            | ParseError::UnexpectedToken { token, .. }
+            | ParseError::MissingSeparator { token }
+            | ParseError::NestingTooDeep { token }
+            | ParseError::InternerFull { token, .. } => token.range(),
+
+            ParseError::ChainingComparisonOperator { operator }
+            | ParseError::ChainingAssignmentOperator { operator } => operator.range(),
+
+            ParseError::UnclosedBrace { brace_open, eof } => (brace_open.pos, eof.end_pos()),
+
+            ParseError::ModuleNotFound { token, .. } | ParseError::CircularImport { token, .. } => {
+                token.range()
+            }
+
+            // the range is in the module's source
+            ParseError::InModule { error, .. } => error.range(),
+        }
+    }
 }
 
-pub type Result<'a, T> = std::result::Result<T, ParseError>;
+pub type Result<T> = std::result::Result<T, ParseError>;
@@ -1,4 +1,7 @@
 mod error;
+mod modules;
 mod parser;
 
+pub use error::ParseError;
+pub use modules::{ModuleLoader, ModuleParser};
 pub use parser::Parser;
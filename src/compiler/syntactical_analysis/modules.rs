@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use ahash::AHashSet;
+
+use crate::compiler::{
+    ast::{ImportStmt, ProgramStmt, Stmt, StmtList},
+    lexical_analysis::{CustomOperator, Lexer, TokenPos},
+    string_handling::StringInterner,
+    syntactical_analysis::{
+        error::{ParseError, Result},
+        Parser,
+    },
+};
+
+/// Where the sources of imported modules come from.
+/// Gets the path of the module, relative to the file importing it,
+/// and returns its source, or None if there is no such module.
+pub type ModuleLoader<'l> = dyn Fn(&str) -> Option<String> + 'l;
+
+/// Parses a program along with every module it imports, into a single program.
+/// The statements of a module take the place of the first import of it, so its functions
+/// and globals are globals of the program. Later imports of the same module are left out.
+/// The positions of a module's tokens are in its own file, see `TokenPos::file`.
+pub struct ModuleParser<'a, 'l> {
+    arena: &'a bumpalo::Bump,
+    interner: StringInterner,
+    loader: &'l ModuleLoader<'l>,
+    strict: bool,
+    custom_operators: &'a [CustomOperator],
+
+    loaded: AHashSet<String>,
+    // the modules that are being parsed, each one imported by the one before it
+    chain: Vec<String>,
+    // the paths of the loaded modules, the one at index i is file i + 1
+    module_files: Vec<String>,
+}
+
+impl<'a, 'l> fmt::Debug for ModuleParser<'a, 'l> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "ModuleParser(strict: {}, loaded: {:?}, chain: {:?})",
+            self.strict, self.loaded, self.chain
+        ))
+    }
+}
+
+impl<'a, 'l> ModuleParser<'a, 'l> {
+    pub fn new(
+        arena: &'a bumpalo::Bump,
+        interner: StringInterner,
+        loader: &'l ModuleLoader<'l>,
+    ) -> Self {
+        ModuleParser {
+            arena,
+            interner,
+            loader,
+            strict: false,
+            custom_operators: &[],
+            loaded: AHashSet::new(),
+            chain: vec![],
+            module_files: vec![],
+        }
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Lets the program and its modules use the operators, see `CustomOperator`.
+    pub fn custom_operators(mut self, custom_operators: &'a [CustomOperator]) -> Self {
+        self.custom_operators = custom_operators;
+        self
+    }
+
+    pub fn parse_program(self, source: &'a str, file_name: &str) -> Result<ProgramStmt<'a>> {
+        self.parse_program_with_modules(source, file_name)
+            .map(|(program, _)| program)
+    }
+
+    /// Like `parse_program`, but also gives the paths of the imported modules, in the order of
+    /// their file index, so the first one is file 1. See `Executable::with_module_files`.
+    pub fn parse_program_with_modules(
+        mut self,
+        source: &'a str,
+        file_name: &str,
+    ) -> Result<(ProgramStmt<'a>, Vec<String>)> {
+        let program = self.parse_file(source, 0)?;
+
+        let mut stmts = bumpalo::vec![in self.arena];
+        self.include(&program, &normalize(Path::new(file_name)), &mut stmts)?;
+        Ok((
+            ProgramStmt::new(StmtList::new(stmts), program.eof_token),
+            self.module_files,
+        ))
+    }
+
+    fn parse_file(&self, source: &'a str, file: usize) -> Result<ProgramStmt<'a>> {
+        let start_pos = TokenPos::default().in_file(file);
+        let lexer = Lexer::with_start_pos(source, self.interner.clone(), start_pos)
+            .with_custom_operators(self.custom_operators);
+        Parser::new(lexer, self.arena)
+            .strict(self.strict)
+            .parse_program()
+    }
+
+    // adds the statements of the file to the program, with its imports replaced by the modules.
+    fn include(
+        &mut self,
+        file: &ProgramStmt<'a>,
+        path: &str,
+        stmts: &mut bumpalo::collections::Vec<'a, Stmt<'a>>,
+    ) -> Result<()> {
+        self.loaded.insert(path.to_owned());
+        self.chain.push(path.to_owned());
+        for stmt in &file.statements.stmts {
+            match stmt {
+                Stmt::Import(is) => self.import(is, path, stmts)?,
+                other => stmts.push(other.clone()),
+            }
+        }
+        self.chain.pop();
+        Ok(())
+    }
+
+    fn import(
+        &mut self,
+        is: &ImportStmt,
+        importer: &str,
+        stmts: &mut bumpalo::collections::Vec<'a, Stmt<'a>>,
+    ) -> Result<()> {
+        let written_path = is.path.lexeme.cut(1, 1).run_on_str(str::to_owned);
+        let path = resolve(importer, &written_path);
+
+        if let Some(start) = self.chain.iter().position(|p| *p == path) {
+            let mut chain = self.chain[start..].to_vec();
+            chain.push(path);
+            return Err(ParseError::CircularImport {
+                token: is.path.clone(),
+                chain,
+            });
+        }
+        if self.loaded.contains(&path) {
+            return Ok(());
+        }
+
+        let source = (self.loader)(&path).ok_or_else(|| ParseError::ModuleNotFound {
+            token: is.path.clone(),
+            path: path.clone(),
+        })?;
+        let source = self.arena.alloc_str(&source);
+
+        let in_module = |error| match error {
+            // the error is already about the module it happened in
+            ParseError::InModule { .. } | ParseError::CircularImport { .. } => error,
+            error => ParseError::InModule {
+                path: path.clone(),
+                error: Box::new(error),
+            },
+        };
+        self.module_files.push(path.clone());
+        let module = self
+            .parse_file(source, self.module_files.len())
+            .map_err(in_module)?;
+        self.include(&module, &path, stmts).map_err(in_module)
+    }
+}
+
+// the path of the imported module, relative to the directory of the file importing it.
+fn resolve(importer: &str, path: &str) -> String {
+    let directory = Path::new(importer)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    normalize(&directory.join(path))
+}
+
+// removes the '.' and 'dir/..' parts of the path, so every path of a module is the same.
+fn normalize(path: &Path) -> String {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
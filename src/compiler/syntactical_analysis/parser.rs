@@ -2,17 +2,39 @@ use string_handling::StringInterner;
 
 use crate::compiler::{
     ast::*,
-    lexical_analysis::{token_groups, Lexer, Token, TokenType},
+    lexical_analysis::{
+        decode_escapes, token_groups, Lexer, OperatorPrecedence, Token, TokenPos, TokenType,
+    },
     string_handling,
     syntactical_analysis::error::{ParseError, Result},
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+
+// the parser, code generator and ast printer are all recursive,
+// so the depth of the tree has to be bounded to not overflow the stack.
+const MAX_NESTING_DEPTH: usize = 256;
+
+// a chain of calls, subscripts and fields only makes the code generator and ast printer recurse,
+// but nested expressions and blocks also make the parser itself recurse,
+// which uses a lot more stack per level, so they cost more of the depth.
+// chains of infix operators, like 'a + b + c', are walked in a loop, so they don't count.
+const RECURSION_COST: usize = 5;
 
 #[derive(Debug)]
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     peek_token: RefCell<Token>,
+    // the token after the peek token, only lexed when it's looked at
+    second_peek_token: RefCell<Option<Token>>,
     arena: &'a bumpalo::Bump,
+    depth: Cell<usize>,
+
+    // strict mode requires a ';' between statements on the same line
+    strict: bool,
+    // the line the last consumed token ended on, only tracked in strict mode
+    last_line: Cell<usize>,
+    // off in if and while conditions, where 'if p { ... }' starts the body rather than a record
+    record_literals: Cell<bool>,
 }
 
 impl<'a> Parser<'a> {
@@ -22,30 +44,61 @@ impl<'a> Parser<'a> {
             lexer,
             arena,
             peek_token: RefCell::new(t),
+            second_peek_token: RefCell::new(None),
+            depth: Cell::new(0),
+            strict: false,
+            last_line: Cell::new(0),
+            record_literals: Cell::new(true),
         }
     }
 
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
     pub fn from_str(source: &'a str, arena: &'a bumpalo::Bump, interner: StringInterner) -> Self {
         let lexer = Lexer::new(source, interner);
         Self::new(lexer, arena)
     }
 
+    // clones the token, checking the type with peek_type is cheaper.
     fn peek_token(&self) -> Token {
         self.peek_token.borrow().clone()
     }
 
+    fn peek_type(&self) -> TokenType {
+        self.peek_token.borrow().token_type
+    }
+
+    fn peek_line(&self) -> usize {
+        self.peek_token.borrow().pos.line
+    }
+
+    fn second_peek_type(&self) -> TokenType {
+        self.second_peek_token
+            .borrow_mut()
+            .get_or_insert_with(|| self.lexer.lex_token())
+            .token_type
+    }
+
     fn advance_token(&self) -> Token {
-        let peek_token = self.peek_token();
-        *self.peek_token.borrow_mut() = self.lexer.lex_token();
+        let next_token = self.second_peek_token.borrow_mut().take();
+        let next_token = next_token.unwrap_or_else(|| self.lexer.lex_token());
+        let peek_token = self.peek_token.replace(next_token);
+        if self.strict {
+            // strings can span multiple lines
+            self.last_line.set(peek_token.end_pos().line);
+        }
         peek_token
     }
 
     fn check_ttype(&self, expected: TokenType) -> bool {
-        self.peek_token().token_type == expected
+        self.peek_type() == expected
     }
 
     fn check_ttype_any(&self, expected: &[TokenType]) -> bool {
-        expected.iter().any(|etype| self.check_ttype(*etype))
+        expected.contains(&self.peek_type())
     }
 
     fn check_advance(&self, expected: TokenType) -> Option<Token> {
@@ -57,13 +110,11 @@ impl<'a> Parser<'a> {
     }
 
     fn check_advance_any(&self, expected: &[TokenType]) -> Option<Token> {
-        for etype in expected {
-            match self.check_advance(*etype) {
-                Some(t) => return Some(t),
-                None => {}
-            }
+        if self.check_ttype_any(expected) {
+            Some(self.advance_token())
+        } else {
+            None
         }
-        None
     }
 
     fn expect<T: FnOnce() -> String>(&self, expected: TokenType, message_func: T) -> Result<Token> {
@@ -77,14 +128,86 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // like expect, but running out of source reports the brace that was never closed
+    fn expect_closing_brace<T: FnOnce() -> String>(
+        &self,
+        brace_open: &Token,
+        message_func: T,
+    ) -> Result<Token> {
+        if self.check_ttype(TokenType::Eof) {
+            return Err(ParseError::UnclosedBrace {
+                brace_open: brace_open.clone(),
+                eof: self.advance_token(),
+            });
+        }
+        self.expect(TokenType::BraceClose, message_func)
+    }
+
+    // goes one level deeper into the tree, the caller is responsible for
+    // resetting the depth once it's done with the subtree.
+    fn descend(&self, token: &Token) -> Result<()> {
+        self.descend_by(token, 1)
+    }
+
+    // like descend, but for subtrees the parser has to recurse on.
+    fn recurse(&self, token: &Token) -> Result<()> {
+        self.descend_by(token, RECURSION_COST)
+    }
+
+    fn descend_by(&self, token: &Token, cost: usize) -> Result<()> {
+        let depth = self.depth.get() + cost;
+        if depth > MAX_NESTING_DEPTH {
+            return Err(ParseError::NestingTooDeep {
+                token: token.clone(),
+            });
+        }
+        self.depth.set(depth);
+        Ok(())
+    }
+
+    fn allowing_record_literals<T>(
+        &self,
+        allowed: bool,
+        parse: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let outer = self.record_literals.replace(allowed);
+        let result = parse();
+        self.record_literals.set(outer);
+        result
+    }
+
+    // a record in a condition has to be put in parentheses, 'if (Point { x: 1 }) { ... }'
+    fn parse_condition(&self) -> Result<Expr<'a>> {
+        self.allowing_record_literals(false, || self.parse_expression())
+    }
+
     pub fn parse_program(&self) -> Result<ProgramStmt<'a>> {
-        let exprs = self.parse_statement_list()?;
-        let eof = self.expect(TokenType::Eof, || "The program should end here".into())?;
-        Ok(ProgramStmt::new(exprs, eof))
+        (|| {
+            let exprs = self.parse_statement_list()?;
+            let eof = self.expect(TokenType::Eof, || "The program should end here".into())?;
+            Ok(ProgramStmt::new(exprs, eof))
+        })()
+        .map_err(|err| self.explain_interner_full(err))
+    }
+
+    // once the interner is full the lexer only returns InternerFull tokens, which the parser
+    // trips over wherever it is, so the error it reports is replaced with the actual problem.
+    fn explain_interner_full(&self, err: ParseError) -> ParseError {
+        if !self.check_ttype(TokenType::InternerFull) {
+            return err;
+        }
+        ParseError::InternerFull {
+            token: self.peek_token(),
+            max_bytes: self
+                .lexer
+                .interner()
+                .max_bytes()
+                .expect("only an interner with a limit can be full"),
+        }
     }
 
     fn parse_statement_list(&self) -> Result<StmtList<'a>> {
-        let mut stmts = bumpalo::vec![in self.arena; self.parse_statement()?];
+        let mut stmts = bumpalo::vec![in self.arena];
 
         while !self.check_ttype_any(token_groups::BLOCK_ENDINGS) {
             stmts.push(self.parse_statement()?);
@@ -94,29 +217,103 @@ impl<'a> Parser<'a> {
     }
 
     fn finish_block_stmt(&self, brace_open: Token) -> Result<BlockStmt<'a>> {
-        let content = self.parse_statement_list()?;
-        let brace_close = self.expect(TokenType::BraceClose, || {
-            "expected '}' to close block".into()
-        })?;
+        let depth = self.depth.get();
+        self.recurse(&brace_open)?;
+
+        let content = self.allowing_record_literals(true, || self.parse_statement_list())?;
+        let brace_close =
+            self.expect_closing_brace(&brace_open, || "expected '}' to close block".into())?;
+
+        self.depth.set(depth);
         Ok(BlockStmt::new(brace_open, content, brace_close))
     }
 
-    fn finish_var_decl_statement(&self, var_token: Token) -> Result<VarDeclStmt<'a>> {
+    fn finish_var_decl_statement(&self, var_token: Token) -> Result<Stmt<'a>> {
         let ident = self.expect(TokenType::Identifier, || {
             "expected identifier after variable declaration".into()
         })?;
 
-        let _assignment_operator = self.expect(TokenType::ColonEqual, || {
-            "expected := after variable name".into()
+        if self.check_ttype(TokenType::Comma) {
+            return Ok(self
+                .finish_multi_var_decl_statement(var_token, ident)?
+                .into_stmt(self.arena));
+        }
+
+        // without an initializer the variable starts out as nil,
+        // which is useless for a constant, so those always need one
+        let expr = if var_token.token_type == TokenType::Const {
+            self.expect(TokenType::ColonEqual, || {
+                "expected := after constant name".into()
+            })?;
+            Some(self.parse_expression()?)
+        } else if self.check_advance(TokenType::ColonEqual).is_some() {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        Ok(VarDeclStmt::new(var_token, ident, expr).into_stmt(self.arena))
+    }
+
+    // 'let a, b := 1, 2', the first identifier has already been parsed.
+    fn finish_multi_var_decl_statement(
+        &self,
+        var_token: Token,
+        first_ident: Token,
+    ) -> Result<MultiVarDeclStmt<'a>> {
+        let mut identifiers = bumpalo::vec![in self.arena; first_ident];
+        while self.check_advance(TokenType::Comma).is_some() {
+            identifiers.push(self.expect(TokenType::Identifier, || {
+                "expected identifier after ',' in variable declaration".into()
+            })?);
+        }
+
+        let assignment_operator = self.expect(TokenType::ColonEqual, || {
+            "expected := after variable names, declaring multiple variables needs values".into()
         })?;
 
-        let expr = self.parse_expression()?;
+        let init_exprs = self.parse_expression_list()?;
 
-        Ok(VarDeclStmt::new(var_token, ident, expr))
+        Ok(MultiVarDeclStmt::new(
+            var_token,
+            identifiers,
+            assignment_operator,
+            init_exprs,
+        ))
+    }
+
+    // 'a, b := b, a', the first target has already been parsed.
+    // assigning multiple values is a statement, so unlike ':=' it doesn't leave a value behind.
+    fn finish_multi_assign_statement(&self, first_target: Expr<'a>) -> Result<MultiAssignStmt<'a>> {
+        let mut targets = bumpalo::vec![in self.arena; first_target];
+        while let Some(comma) = self.check_advance(TokenType::Comma) {
+            // the targets can't contain ':=', as that separates them from the values
+            let depth = self.depth.get();
+            self.recurse(&comma)?;
+            targets.push(self.parse_and()?);
+            self.depth.set(depth);
+        }
+
+        let assignment_operator = self.expect(TokenType::ColonEqual, || {
+            "expected := after assignment targets".into()
+        })?;
+
+        let values = self.parse_expression_list()?;
+
+        Ok(MultiAssignStmt::new(targets, assignment_operator, values))
+    }
+
+    // one or more expressions separated by commas
+    fn parse_expression_list(&self) -> Result<bumpalo::collections::Vec<'a, Expr<'a>>> {
+        let mut exprs = bumpalo::vec![in self.arena; self.parse_expression()?];
+        while self.check_advance(TokenType::Comma).is_some() {
+            exprs.push(self.parse_expression()?);
+        }
+        Ok(exprs)
     }
 
     fn finish_if_stmt(&self, if_token: Token) -> Result<IfStmt<'a>> {
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
 
         let brace_open = self.expect(TokenType::BraceOpen, || {
             "expected '{' after if-condition".into()
@@ -146,7 +343,7 @@ impl<'a> Parser<'a> {
     }
 
     fn finish_while_stmt(&self, while_token: Token) -> Result<WhileStmt<'a>> {
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
 
         let brace_open = self.expect(TokenType::BraceOpen, || {
             "expected '{' after condition in while statement".into()
@@ -157,15 +354,41 @@ impl<'a> Parser<'a> {
         Ok(WhileStmt::new(while_token, condition, while_body))
     }
 
-    fn finish_fn_decl_stmt(&self, fn_token: Token) -> Result<FnDeclStmt<'a>> {
-        let identifier = self.expect(TokenType::Identifier, || {
-            "expected function name after 'fn' in statement".into()
+    fn finish_for_stmt(&self, for_token: Token) -> Result<ForStmt<'a>> {
+        let variable = self.expect(TokenType::Identifier, || {
+            "expected a variable name after 'for'".into()
+        })?;
+
+        self.expect(TokenType::In, || {
+            "expected 'in' after the variable in for statement".into()
+        })?;
+
+        let iterable = self.parse_condition()?;
+
+        let brace_open = self.expect(TokenType::BraceOpen, || {
+            "expected '{' after the iterated value in for statement".into()
         })?;
 
+        let for_body = self.finish_block_stmt(brace_open)?;
+
+        Ok(ForStmt::new(for_token, variable, iterable, for_body))
+    }
+
+    // parses the parameters of a function, from the '(' to the ')'.
+    // returns the parameters, the defaults of the last ones, and the rest parameter.
+    fn parse_parameter_list(
+        &self,
+    ) -> Result<(
+        bumpalo::collections::Vec<'a, Token>,
+        bumpalo::collections::Vec<'a, Expr<'a>>,
+        Option<Token>,
+    )> {
         let mut parameters = bumpalo::vec![in self.arena];
+        let mut defaults = bumpalo::vec![in self.arena];
+        let mut rest_parameter = None;
 
         let _paren_open = self.expect(TokenType::ParenOpen, || {
-            "expected '(' after function name".into()
+            "expected '(' before the parameter list".into()
         })?;
 
         loop {
@@ -173,8 +396,31 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            parameters
-                .push(self.expect(TokenType::Identifier, || "expected paramater name".into())?);
+            let parameter =
+                self.expect(TokenType::Identifier, || "expected paramater name".into())?;
+
+            // the rest parameter has to be the last one, so the ')' has to follow
+            if self.check_advance(TokenType::TripleDot).is_some() {
+                if !defaults.is_empty() {
+                    return Err(ParseError::UnexpectedToken {
+                        message: "a rest parameter can't follow parameters with a default value"
+                            .into(),
+                        token: parameter,
+                    });
+                }
+                rest_parameter = Some(parameter);
+                break;
+            }
+
+            if self.check_advance(TokenType::ColonEqual).is_some() {
+                defaults.push(self.parse_expression()?);
+            } else if !defaults.is_empty() {
+                return Err(ParseError::UnexpectedToken {
+                    message: "parameters after one with a default value need a default too".into(),
+                    token: parameter,
+                });
+            }
+            parameters.push(parameter);
 
             if self.check_advance(TokenType::Comma).is_none() {
                 break;
@@ -185,21 +431,80 @@ impl<'a> Parser<'a> {
             "expected ')' after parameter list".into()
         })?;
 
+        Ok((parameters, defaults, rest_parameter))
+    }
+
+    fn finish_fn_decl_stmt(&self, fn_token: Token) -> Result<FnDeclStmt<'a>> {
+        let identifier = self.expect(TokenType::Identifier, || {
+            "expected function name after 'fn' in statement".into()
+        })?;
+
+        let (parameters, defaults, rest_parameter) = self.parse_parameter_list()?;
+
         let brace_open = self.expect(TokenType::BraceOpen, || "expected function body".into())?;
         let fn_body = self.finish_block_stmt(brace_open)?;
 
-        Ok(FnDeclStmt::new(fn_token, identifier, parameters, fn_body))
+        Ok(FnDeclStmt::new(
+            fn_token,
+            identifier,
+            parameters,
+            defaults,
+            rest_parameter,
+            fn_body,
+        ))
     }
 
     fn finish_anyn_fn_decl_expr(&self, fn_token: Token) -> Result<AnynFnDeclExpr<'a>> {
-        unimplemented!("{}", fn_token)
+        let (parameters, defaults, rest_parameter) = self.parse_parameter_list()?;
+
+        let brace_open = self.expect(TokenType::BraceOpen, || "expected function body".into())?;
+        let fn_body = self.finish_block_stmt(brace_open)?;
+
+        Ok(AnynFnDeclExpr::new(
+            fn_token,
+            parameters,
+            defaults,
+            rest_parameter,
+            fn_body,
+        ))
+    }
+
+    fn finish_struct_decl_stmt(&self, struct_token: Token) -> Result<StructDeclStmt<'a>> {
+        let name = self.expect(TokenType::Identifier, || {
+            "expected struct name after 'struct'".into()
+        })?;
+        let brace_open = self.expect(TokenType::BraceOpen, || {
+            "expected '{' before the fields of the struct".into()
+        })?;
+
+        let mut fields = bumpalo::vec![in self.arena];
+        while !self.check_ttype(TokenType::BraceClose) {
+            fields.push(self.expect(TokenType::Identifier, || "expected field name".into())?);
+            if self.check_advance(TokenType::Comma).is_none() {
+                break;
+            }
+        }
+
+        self.expect_closing_brace(&brace_open, || {
+            "expected '}' after the fields of the struct".into()
+        })?;
+
+        Ok(StructDeclStmt::new(struct_token, name, fields))
+    }
+
+    // 'import "utils.cahn"', the path is taken as written, escapes and all.
+    fn finish_import_stmt(&self, import_token: Token) -> Result<ImportStmt> {
+        let path = self.expect(TokenType::String, || {
+            "expected the path of the module as a string after 'import'".into()
+        })?;
+        Ok(ImportStmt::new(import_token, path))
     }
 
     fn parse_statement(&self) -> Result<Stmt<'a>> {
-        let node = match self.peek_token().token_type {
-            TokenType::Let => self
-                .finish_var_decl_statement(self.advance_token())?
-                .into_stmt(self.arena),
+        let node = match self.peek_type() {
+            TokenType::Let | TokenType::Const => {
+                self.finish_var_decl_statement(self.advance_token())?
+            }
 
             TokenType::Print => self
                 .finish_print_statement(self.advance_token())?
@@ -217,6 +522,10 @@ impl<'a> Parser<'a> {
                 .finish_while_stmt(self.advance_token())?
                 .into_stmt(self.arena),
 
+            TokenType::For => self
+                .finish_for_stmt(self.advance_token())?
+                .into_stmt(self.arena),
+
             TokenType::Fn => self
                 .finish_fn_decl_stmt(self.advance_token())?
                 .into_stmt(self.arena),
@@ -225,35 +534,155 @@ impl<'a> Parser<'a> {
                 .finish_return_statement(self.advance_token())?
                 .into_stmt(self.arena),
 
-            _ => ExprStmt::new(self.parse_expression()?).into_stmt(self.arena),
+            TokenType::Struct => self
+                .finish_struct_decl_stmt(self.advance_token())?
+                .into_stmt(self.arena),
+
+            TokenType::Import => self
+                .finish_import_stmt(self.advance_token())?
+                .into_stmt(self.arena),
+
+            TokenType::Assert => self
+                .finish_assert_statement(self.advance_token())?
+                .into_stmt(self.arena),
+
+            _ => {
+                let expr = self.parse_expression()?;
+                if self.check_ttype(TokenType::Comma) {
+                    if let Expr::Infix(ie) = &expr {
+                        if ie.operator.token_type == TokenType::ColonEqual {
+                            return Err(ParseError::UnexpectedToken {
+                                message:
+                                    "multiple values need as many targets, like 'a, b := 1, 2'"
+                                        .into(),
+                                token: self.advance_token(),
+                            });
+                        }
+                    }
+                    self.finish_multi_assign_statement(expr)?
+                        .into_stmt(self.arena)
+                } else {
+                    ExprStmt::new(expr).into_stmt(self.arena)
+                }
+            }
         };
 
         // eat optional semicolons
-        while self.check_advance(TokenType::Semicolon).is_some() {}
+        let mut separated = false;
+        while self.check_advance(TokenType::Semicolon).is_some() {
+            separated = true;
+        }
+
+        if self.strict
+            && !separated
+            && !self.check_ttype_any(token_groups::BLOCK_ENDINGS)
+            && self.peek_line() == self.last_line.get()
+        {
+            return Err(ParseError::MissingSeparator {
+                token: self.advance_token(),
+            });
+        }
 
         Ok(node)
     }
 
     fn finish_print_statement(&self, print_token: Token) -> Result<PrintStmt<'a>> {
-        let expr = self.parse_expression()?;
-        Ok(PrintStmt::new(print_token, expr))
+        let exprs = self.parse_expression_list()?;
+        Ok(PrintStmt::new(print_token, exprs))
     }
 
-    fn finish_return_statement(&self, return_token: Token) -> Result<ReturnStmt<'a>> {
-        let expr = if self.check_ttype_any(token_groups::BLOCK_ENDINGS) {
+    fn finish_assert_statement(&self, assert_token: Token) -> Result<AssertStmt<'a>> {
+        let condition = self.parse_expression()?;
+        let message = if self.check_advance(TokenType::Comma).is_some() {
+            Some(self.parse_expression()?)
+        } else {
             None
+        };
+        Ok(AssertStmt::new(assert_token, condition, message))
+    }
+
+    fn finish_return_statement(&self, return_token: Token) -> Result<ReturnStmt<'a>> {
+        let exprs = if self.check_ttype_any(token_groups::BLOCK_ENDINGS) {
+            bumpalo::vec![in self.arena]
         } else {
-            Some(self.parse_expression()?)
+            self.parse_expression_list()?
         };
-        Ok(ReturnStmt::new(return_token, expr))
+        Ok(ReturnStmt::new(return_token, exprs))
+    }
+
+    fn finish_block_expression(&self, do_token: Token) -> Result<BlockExpr<'a>> {
+        let brace_open = self.expect(TokenType::BraceOpen, || "expected '{' after 'do'".into())?;
+        let block = self.finish_block_stmt(brace_open)?;
+        Ok(BlockExpr::new(do_token, block))
+    }
+
+    // like an if statement, but each branch yields the value of its block, nil without an else.
+    fn finish_if_expression(&self, if_token: Token) -> Result<IfExpr<'a>> {
+        let condition = self.parse_condition()?;
+
+        let brace_open = self.expect(TokenType::BraceOpen, || {
+            "expected '{' after if-condition".into()
+        })?;
+        let then_block = self.finish_block_stmt(brace_open)?;
+
+        let else_token = self.check_advance(TokenType::Else);
+        let else_clause = match else_token {
+            Some(ref else_token) => Some(match self.check_advance(TokenType::If) {
+                Some(else_if_token) => self
+                    .finish_if_expression(else_if_token)?
+                    .into_expr(self.arena),
+                None => {
+                    let brace_open =
+                        self.expect(TokenType::BraceOpen, || "expected '{' after else".into())?;
+                    let else_block = self.finish_block_stmt(brace_open)?;
+                    BlockExpr::new(else_token.clone(), else_block).into_expr(self.arena)
+                }
+            }),
+            None => None,
+        };
+
+        Ok(IfExpr::new(
+            if_token,
+            condition,
+            then_block,
+            else_token,
+            else_clause,
+        ))
+    }
+
+    // 'Point { x: 1, y: 2 }', the fields can be given in any order.
+    fn finish_record_expression(&self, name: Token) -> Result<RecordExpr<'a>> {
+        let brace_open = self.advance_token();
+
+        let mut fields = bumpalo::vec![in self.arena];
+        let mut values = bumpalo::vec![in self.arena];
+        while !self.check_ttype(TokenType::BraceClose) {
+            fields.push(self.expect(TokenType::Identifier, || "expected field name".into())?);
+            self.expect(TokenType::Colon, || "expected ':' after field name".into())?;
+            values.push(self.parse_expression()?);
+            if self.check_advance(TokenType::Comma).is_none() {
+                break;
+            }
+        }
+
+        let brace_close =
+            self.expect_closing_brace(&brace_open, || "expected '}' to close the record".into())?;
+
+        Ok(RecordExpr::new(
+            name,
+            brace_open,
+            fields,
+            values,
+            brace_close,
+        ))
     }
 
     fn finish_group_expression(&self, paren_open: Token) -> Result<GroupExpr<'a>> {
-        let expr = self.parse_expression()?;
+        let expr = self.allowing_record_literals(true, || self.parse_expression())?;
         let paren_close = self.expect(TokenType::ParenClose, || {
             String::from("expected a closing parenthesis")
         })?;
-        return Ok(GroupExpr::new(paren_open, expr, paren_close));
+        Ok(GroupExpr::new(paren_open, expr, paren_close))
     }
 
     fn finish_list_expression(&self, bracket_open: Token) -> Result<ListExpr<'a>> {
@@ -295,7 +724,11 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression(&self) -> Result<Expr<'a>> {
-        self.parse_assignment()
+        let depth = self.depth.get();
+        self.recurse(&self.peek_token.borrow())?;
+        let expr = self.parse_assignment()?;
+        self.depth.set(depth);
+        Ok(expr)
     }
 
     fn parse_assignment(&self) -> Result<Expr<'a>> {
@@ -335,30 +768,80 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    // 'x not in list' is parsed as 'not (x in list)', so this takes the 'not' and leaves the 'in'
+    fn check_advance_not_in(&self) -> Option<Token> {
+        if self.check_ttype(TokenType::Not) && self.second_peek_type() == TokenType::In {
+            Some(self.advance_token())
+        } else {
+            None
+        }
+    }
+
     fn parse_comparison(&self) -> Result<Expr<'a>> {
         let expr = self.parse_concatenation()?;
+        let negation = self.check_advance_not_in();
 
         if let Some(operator) = self.check_advance_any(token_groups::COMPARISON_OPERATORS) {
             let right_expr = self.parse_concatenation()?;
 
-            if let Some(chained_operator) =
-                self.check_advance_any(token_groups::COMPARISON_OPERATORS)
+            if let Some(chained_operator) = self
+                .check_advance_any(token_groups::COMPARISON_OPERATORS)
+                .or_else(|| self.check_advance_not_in())
             {
                 return Err(ParseError::ChainingComparisonOperator {
                     operator: chained_operator,
                 });
             }
 
-            return Ok(InfixExpr::new(expr, operator, right_expr).into_expr(self.arena));
+            let comparison = InfixExpr::new(expr, operator, right_expr).into_expr(self.arena);
+            return Ok(match negation {
+                Some(not) => PrefixExpr::new(not, comparison).into_expr(self.arena),
+                None => comparison,
+            });
         }
         Ok(expr)
     }
 
+    // takes a custom operator, if the next token is one that binds at the precedence
+    fn check_advance_custom(&self, precedence: OperatorPrecedence) -> Option<Token> {
+        if !self.check_ttype(TokenType::CustomOperator) {
+            return None;
+        }
+        let symbol = self.peek_token.borrow().lexeme.clone();
+        let binds_here = self.lexer.custom_operators().iter().any(|op| {
+            op.precedence == precedence && symbol.run_on_str(|symbol| symbol == op.symbol)
+        });
+        if binds_here {
+            Some(self.advance_token())
+        } else {
+            None
+        }
+    }
+
     fn parse_concatenation(&self) -> Result<Expr<'a>> {
+        let mut expr = self.parse_bitwise()?;
+
+        while let Some(operator) = self
+            .check_advance(TokenType::DoubleDot)
+            .or_else(|| self.check_advance_custom(OperatorPrecedence::Concatenation))
+        {
+            let right = self.parse_bitwise()?;
+            expr = InfixExpr::new(expr, operator, right).into_expr(self.arena);
+        }
+
+        Ok(expr)
+    }
+
+    // all bitwise operators bind the same, looser than arithmetic, so 1 + 2 << 3 is 24
+    fn parse_bitwise(&self) -> Result<Expr<'a>> {
         let mut expr = self.parse_addition()?;
 
-        while let Some(operator) = self.check_advance(TokenType::DoubleDot) {
-            expr = InfixExpr::new(expr, operator, self.parse_addition()?).into_expr(self.arena);
+        while let Some(operator) = self
+            .check_advance_any(token_groups::BITWISE_OPERATORS)
+            .or_else(|| self.check_advance_custom(OperatorPrecedence::Bitwise))
+        {
+            let right = self.parse_addition()?;
+            expr = InfixExpr::new(expr, operator, right).into_expr(self.arena);
         }
 
         Ok(expr)
@@ -367,23 +850,31 @@ impl<'a> Parser<'a> {
     fn parse_addition(&self) -> Result<Expr<'a>> {
         let mut expr = self.parse_multiplication()?;
 
-        while let Some(operator) = self.check_advance_any(&[TokenType::Plus, TokenType::Minus]) {
-            expr =
-                InfixExpr::new(expr, operator, self.parse_multiplication()?).into_expr(self.arena);
+        while let Some(operator) = self
+            .check_advance_any(&[TokenType::Plus, TokenType::Minus])
+            .or_else(|| self.check_advance_custom(OperatorPrecedence::Addition))
+        {
+            let right = self.parse_multiplication()?;
+            expr = InfixExpr::new(expr, operator, right).into_expr(self.arena);
         }
+
         Ok(expr)
     }
 
     fn parse_multiplication(&self) -> Result<Expr<'a>> {
         let mut expr = self.parse_unary()?;
 
-        while let Some(operator) = self.check_advance_any(&[
-            TokenType::Star,
-            TokenType::Slash,
-            TokenType::DoubleSlash,
-            TokenType::Percent,
-        ]) {
-            expr = InfixExpr::new(expr, operator, self.parse_unary()?).into_expr(self.arena);
+        while let Some(operator) = self
+            .check_advance_any(&[
+                TokenType::Star,
+                TokenType::Slash,
+                TokenType::DoubleSlash,
+                TokenType::Percent,
+            ])
+            .or_else(|| self.check_advance_custom(OperatorPrecedence::Multiplication))
+        {
+            let right = self.parse_unary()?;
+            expr = InfixExpr::new(expr, operator, right).into_expr(self.arena);
         }
 
         Ok(expr)
@@ -391,7 +882,11 @@ impl<'a> Parser<'a> {
 
     fn parse_unary(&self) -> Result<Expr<'a>> {
         if let Some(operator) = self.check_advance_any(token_groups::PREFIX_OPERATORS) {
-            Ok(PrefixExpr::new(operator, self.parse_unary()?).into_expr(self.arena))
+            let depth = self.depth.get();
+            self.recurse(&operator)?;
+            let inner = self.parse_unary()?;
+            self.depth.set(depth);
+            Ok(PrefixExpr::new(operator, inner).into_expr(self.arena))
         } else {
             self.parse_exponent()
         }
@@ -401,18 +896,24 @@ impl<'a> Parser<'a> {
         let expr = self.parse_call()?;
 
         if let Some(operator) = self.check_advance(TokenType::DoubleStar) {
-            Ok(InfixExpr::new(expr, operator, self.parse_unary()?).into_expr(self.arena))
+            let depth = self.depth.get();
+            self.recurse(&operator)?;
+            let right_expr = self.parse_unary()?;
+            self.depth.set(depth);
+            Ok(InfixExpr::new(expr, operator, right_expr).into_expr(self.arena))
         } else {
             Ok(expr)
         }
     }
 
     fn parse_call(&self) -> Result<Expr<'a>> {
+        let depth = self.depth.get();
         let mut expr = self.parse_atom()?;
 
         'outer: while let Some(open) =
-            self.check_advance_any(&[TokenType::ParenOpen, TokenType::BracketOpen])
+            self.check_advance_any(&[TokenType::ParenOpen, TokenType::BracketOpen, TokenType::Dot])
         {
+            self.descend(&open)?;
             match open.token_type {
                 TokenType::BracketOpen => {
                     let bracket_open = open;
@@ -426,6 +927,13 @@ impl<'a> Parser<'a> {
                         .into_expr(self.arena);
                 }
 
+                TokenType::Dot => {
+                    let field = self.expect(TokenType::Identifier, || {
+                        "expected field name after '.'".into()
+                    })?;
+                    expr = FieldExpr::new(expr, open, field).into_expr(self.arena);
+                }
+
                 TokenType::ParenOpen => {
                     let paren_open = open;
                     // zero arg
@@ -470,9 +978,172 @@ impl<'a> Parser<'a> {
                 _ => unreachable!(),
             }
         }
+
+        self.depth.set(depth);
         Ok(expr)
     }
 
+    // strings can embed expressions, "n is {n}", which are parsed by a parser of their own.
+    // '{{' and '}}' are literal braces.
+    fn finish_string_expression(&self, token: Token) -> Result<Expr<'a>> {
+        // cut is for removing ""
+        let string = token.lexeme.cut(1, 1);
+        let contents = string.run_on_str(str::to_owned);
+
+        if !contents.contains(['{', '}']) {
+            let string = if contents.contains('\\') {
+                let decoded = self.decode_literal(&contents, &token)?;
+                self.lexer.interner().intern(&decoded)
+            } else {
+                string
+            };
+            return Ok(StringExpr::new(token, string).into_expr(self.arena));
+        }
+
+        let mut parts = bumpalo::vec![in self.arena];
+        let mut literal = String::new();
+
+        // position of the character we're looking at, starts right after the '"'
+        let mut pos = token.pos.after('"');
+        let mut chars = contents.char_indices().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            let char_pos = pos;
+            pos = pos.after(c);
+
+            match c {
+                '{' | '}' if matches!(chars.peek(), Some((_, next)) if *next == c) => {
+                    chars.next();
+                    pos = pos.after(c);
+                    literal.push(c);
+                }
+
+                '{' => {
+                    let expr_start = index + 1;
+                    let expr_pos = pos;
+
+                    let mut depth = 1;
+                    let mut expr_end = None;
+                    for (index, c) in chars.by_ref() {
+                        pos = pos.after(c);
+                        match c {
+                            '{' => depth += 1,
+                            '}' if depth == 1 => {
+                                expr_end = Some(index);
+                                break;
+                            }
+                            '}' => depth -= 1,
+                            _ => {}
+                        }
+                    }
+
+                    let expr_end = expr_end.ok_or_else(|| ParseError::BadToken {
+                        message: format!("'{{' at {} is never closed in string", char_pos),
+                        token: token.clone(),
+                    })?;
+
+                    // the result should always be a string, so the first part has to be one
+                    if !literal.is_empty() || parts.is_empty() {
+                        let decoded = self.decode_literal(&literal, &token)?;
+                        parts.push(self.make_string_expr(&decoded, token.pos));
+                        literal.clear();
+                    }
+
+                    let source = self.arena.alloc_str(&contents[expr_start..expr_end]);
+                    parts.push(self.parse_embedded_expression(source, expr_pos)?);
+                }
+
+                '}' => {
+                    return Err(ParseError::BadToken {
+                        message: format!(
+                            "unmatched '}}' at {} in string, use '}}}}' for a literal brace",
+                            char_pos
+                        ),
+                        token,
+                    })
+                }
+
+                // escapes are decoded once the literal part is complete, the char after the
+                // backslash belongs to the escape, like the braces of '\u{..}' do
+                '\\' => {
+                    literal.push(c);
+                    if let Some((_, escaped)) = chars.next() {
+                        pos = pos.after(escaped);
+                        literal.push(escaped);
+                        if escaped == 'u' && matches!(chars.peek(), Some((_, '{'))) {
+                            for (_, c) in chars.by_ref() {
+                                pos = pos.after(c);
+                                literal.push(c);
+                                if c == '}' {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() || parts.is_empty() {
+            let decoded = self.decode_literal(&literal, &token)?;
+            parts.push(self.make_string_expr(&decoded, token.pos));
+        }
+
+        Ok(InterpolatedStringExpr::new(token, parts).into_expr(self.arena))
+    }
+
+    // a char literal is a string of one char, with the same escapes as strings.
+    fn finish_char_expression(&self, token: Token) -> Result<Expr<'a>> {
+        // cut is for removing ''
+        let contents = token.lexeme.cut(1, 1).run_on_str(str::to_owned);
+        let char = self.decode_literal(&contents, &token)?;
+        if char.chars().count() != 1 {
+            return Err(ParseError::BadToken {
+                message: "a char literal has to contain exactly one char".into(),
+                token,
+            });
+        }
+        let char = self.lexer.interner().intern(&char);
+        Ok(StringExpr::new(token, char).into_expr(self.arena))
+    }
+
+    // decodes the escapes of a string or char literal, as the lexer leaves them in
+    fn decode_literal(&self, contents: &str, token: &Token) -> Result<String> {
+        decode_escapes(contents).map_err(|err| ParseError::BadToken {
+            message: err.to_string(),
+            token: token.clone(),
+        })
+    }
+
+    fn make_string_expr(&self, string: &str, pos: TokenPos) -> Expr<'a> {
+        let token = Token {
+            pos,
+            token_type: TokenType::String,
+            lexeme: self.lexer.interner().intern(&format!("\"{}\"", string)),
+        };
+        let string = token.lexeme.cut(1, 1);
+        StringExpr::new(token, string).into_expr(self.arena)
+    }
+
+    fn parse_embedded_expression(&self, source: &'a str, pos: TokenPos) -> Result<Expr<'a>> {
+        let lexer = Lexer::with_start_pos(source, self.lexer.interner().clone(), pos)
+            .with_custom_operators(self.lexer.custom_operators());
+        let parser = Parser::new(lexer, self.arena);
+        // the embedded expression is part of this tree, so it shares the depth limit
+        parser.depth.set(self.depth.get());
+
+        (|| {
+            let expr = parser.parse_expression()?;
+            parser.expect(TokenType::Eof, || {
+                "expected '}' after the expression in string".into()
+            })?;
+            Ok(expr)
+        })()
+        .map_err(|err| parser.explain_interner_full(err))
+    }
+
     fn parse_atom(&self) -> Result<Expr<'a>> {
         let token = self.advance_token();
 
@@ -481,25 +1152,57 @@ impl<'a> Parser<'a> {
                 token.clone(),
                 token
                     .lexeme
-                    .run_on_str(|str| str.parse())
+                    .run_on_str(|str| str.replace('_', "").parse())
                     .expect("Lexer shouldn't tokenize invalid numbers"),
             )
             .into_expr(self.arena),
 
-            TokenType::String => {
-                // cut is for removing ""
-                StringExpr::new(token.clone(), token.lexeme.cut(1, 1)).into_expr(self.arena)
+            TokenType::String => self.finish_string_expression(token)?,
+
+            TokenType::Char => self.finish_char_expression(token)?,
+
+            TokenType::RawString => {
+                // cut is for removing r""
+                StringExpr::new(token.clone(), token.lexeme.cut(2, 1)).into_expr(self.arena)
             }
 
             TokenType::True => BoolExpr::new(token, true).into_expr(self.arena),
             TokenType::False => BoolExpr::new(token, false).into_expr(self.arena),
+            // the '{' of a record has to be on the line of its name,
+            // so a block on the line after a variable isn't taken for one
+            TokenType::Identifier
+                if self.record_literals.get()
+                    && self.check_ttype(TokenType::BraceOpen)
+                    && self.peek_line() == token.pos.line =>
+            {
+                self.finish_record_expression(token)?.into_expr(self.arena)
+            }
             TokenType::Identifier => VarExpr::new(token).into_expr(self.arena),
 
             TokenType::Fn => self.finish_anyn_fn_decl_expr(token)?.into_expr(self.arena),
 
             TokenType::ParenOpen => self.finish_group_expression(token)?.into_expr(self.arena),
 
+            TokenType::Do => self.finish_block_expression(token)?.into_expr(self.arena),
+
+            TokenType::If => self.finish_if_expression(token)?.into_expr(self.arena),
+
             TokenType::BracketOpen => self.finish_list_expression(token)?.into_expr(self.arena),
+
+            TokenType::UnterminatedString => {
+                return Err(ParseError::BadToken {
+                    message: "string is never terminated".into(),
+                    token,
+                })
+            }
+
+            TokenType::MalformedNumber => return Err(ParseError::BadToken {
+                message:
+                    "malformed number, an exponent needs digits and '_' can only separate digits"
+                        .into(),
+                token,
+            }),
+
             _ => {
                 return Err(ParseError::BadToken {
                     message: "expected either a literal, a variable or (".into(),
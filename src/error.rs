@@ -0,0 +1,73 @@
+use std::any::Any;
+
+use thiserror::Error;
+
+use crate::{
+    compiler::{
+        codegen::CodeGenError, lexical_analysis::TokenPos, syntactical_analysis::ParseError,
+    },
+    runtime::error::RuntimeError,
+};
+
+#[derive(Debug, Error)]
+pub enum CahnError {
+    #[error("parse error: {}", .0)]
+    Parse(#[from] ParseError),
+
+    #[error("compile error: {}", .0)]
+    CodeGen(#[from] CodeGenError),
+
+    #[error("runtime error: {}", .0)]
+    Runtime(#[from] RuntimeError),
+}
+
+pub type Result<T> = std::result::Result<T, CahnError>;
+
+/// The message the cahn binary prints when it panics, which is always a bug in cahn.
+/// `payload` is the panic's payload, its text is included when it has one.
+pub fn internal_error_message(payload: &(dyn Any + Send)) -> String {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown error"
+    };
+    format!("internal error in cahn, please report: {}", message)
+}
+
+/// The message followed by the line of source at the position, with a caret under its column:
+///
+/// ```text
+/// error: TypeError: ...
+///  --> main.cahn:2:9
+///   |
+/// 2 | print 1 + "a"
+///   |         ^
+/// ```
+///
+/// The snippet is left out if the source doesn't have the position's line.
+pub fn render_snippet(message: &str, source: &str, source_file: &str, pos: TokenPos) -> String {
+    let mut rendered = format!("error: {}\n --> {}:{}\n", message, source_file, pos);
+    // lines start at 1
+    let line = match source.lines().nth(pos.line.saturating_sub(1)) {
+        Some(line) => line,
+        None => return rendered,
+    };
+
+    let gutter = " ".repeat(pos.line.to_string().len());
+    // tabs are kept, so the caret lines up with the line above it
+    let indent: String = line
+        .chars()
+        .take(pos.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    rendered.push_str(&format!(
+        "{gutter} |\n{line_number} | {line}\n{gutter} | {indent}^\n",
+        gutter = gutter,
+        line_number = pos.line,
+        line = line,
+        indent = indent,
+    ));
+    rendered
+}
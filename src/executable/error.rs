@@ -0,0 +1,58 @@
+use thiserror::Error;
+
+use crate::executable::Instruction;
+
+#[derive(Error, Debug)]
+pub enum BytecodeError {
+    #[error("bytecode ended unexpectedly")]
+    UnexpectedEnd,
+
+    #[error("invalid instruction byte {} at {}", .byte, .index)]
+    InvalidInstruction { byte: u8, index: usize },
+
+    #[error("instruction at {} doesn't have all of its operands", .index)]
+    TruncatedInstruction { index: usize },
+
+    #[error("bytecode has {} trailing bytes", .count)]
+    TrailingBytes { count: usize },
+
+    #[error("malformed function: {}", .message)]
+    Malformed { message: String },
+
+    #[error("{:?} at {} doesn't fit the executable: {}", .instruction, .index, .message)]
+    BadReference {
+        instruction: Instruction,
+        index: usize,
+        message: String,
+    },
+
+    #[error("{} isn't the start of an instruction", .index)]
+    NotAnInstruction { index: usize },
+
+    #[error("there is no function at index {}, the executable has {}", .index, .count)]
+    NoSuchFunction { index: usize, count: usize },
+
+    #[error("the function can't replace function {}: {}", .index, .message)]
+    IncompatibleReplacement { index: usize, message: String },
+
+    #[error("not a Cahn bytecode file")]
+    NotBytecode,
+
+    #[error("bytecode format version {}, but only version {} is supported", .found, .supported)]
+    UnsupportedVersion { found: u8, supported: u8 },
+
+    #[error(
+        "the bytecode was compiled by cahn_lang {} for instruction set {}, but only instruction set {} is supported",
+        .compiler_version, .found, .supported
+    )]
+    UnsupportedInstructionSet {
+        found: u32,
+        supported: u32,
+        compiler_version: String,
+    },
+
+    #[error("the bytecode uses a native function '{}', which isn't available", .name)]
+    UnknownNative { name: String },
+}
+
+pub type Result<T> = std::result::Result<T, BytecodeError>;
@@ -3,10 +3,13 @@ use std::fmt::Write;
 use {
     crate::{
         compiler::lexical_analysis::TokenPos,
-        executable::{Executable, Instruction},
+        executable::{
+            error::{BytecodeError, Result},
+            Executable, Instruction,
+        },
         utils::PanickingByteBufferReader,
     },
-    std::{fmt, mem},
+    std::{convert::TryInto, fmt, mem, result},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -47,9 +50,28 @@ impl<'a> fmt::Display for FormatableFunctionName<'a> {
     }
 }
 
+/// How a closure finds one of the variables it captures, when it's created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture {
+    /// Whether the variable is a local of the function creating the closure,
+    /// or one that function captured itself.
+    pub is_local: bool,
+    /// The slot of the local, or the index of the creating function's capture.
+    pub index: u16,
+}
+
 #[derive(Clone)]
 pub struct CahnFunction {
     pub param_count: u8,
+    /// Where to start running the function when the last parameters were left out.
+    /// The code from entry `i` on computes the defaults of the last `len - i` parameters,
+    /// and jumps over them when the call supplies every argument.
+    pub default_entries: Vec<u32>,
+    /// Whether the last parameter collects the arguments after the others into a list.
+    pub is_variadic: bool,
+    /// The variables of enclosing functions the function uses, which closures of it capture.
+    /// Functions without any are loaded as plain functions instead of closures.
+    pub captures: Vec<Capture>,
     pub code: Vec<u8>,
     pub code_map: Vec<TokenPos>,
     pub name: FunctionName,
@@ -64,6 +86,9 @@ impl CahnFunction {
     ) -> Self {
         Self {
             param_count,
+            default_entries: vec![],
+            is_variadic: false,
+            captures: vec![],
             code,
             code_map,
             name,
@@ -88,9 +113,267 @@ impl CahnFunction {
         Self::new_helper(param_count, code, code_map, FunctionName::Anonymous)
     }
 
+    /// Sets where to start when parameters with a default value are left out.
+    pub fn with_default_entries(mut self, default_entries: Vec<u32>) -> Self {
+        self.default_entries = default_entries;
+        self
+    }
+
+    pub fn variadic(mut self, is_variadic: bool) -> Self {
+        self.is_variadic = is_variadic;
+        self
+    }
+
+    pub fn with_captures(mut self, captures: Vec<Capture>) -> Self {
+        self.captures = captures;
+        self
+    }
+
+    /// The number of arguments a call needs at least,
+    /// parameters with a default value and the rest parameter can be left out.
+    pub fn min_param_count(&self) -> u8 {
+        self.param_count - self.default_entries.len() as u8 - self.is_variadic as u8
+    }
+
+    /// Where to start running the function when it's called with the given number of arguments,
+    /// or None if that's the wrong number.
+    pub fn entry_point(&self, arg_count: usize) -> Option<usize> {
+        let min = self.min_param_count() as usize;
+        if arg_count == self.param_count as usize {
+            Some(0)
+        } else if arg_count >= min && arg_count < self.param_count as usize {
+            Some(self.default_entries[arg_count - min] as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the instruction at the code index with a Breakpoint, returning its byte
+    /// so `clear_breakpoint` can put it back. The operands of the instruction stay,
+    /// so the function can't be decoded until the breakpoint is cleared.
+    pub fn set_breakpoint(&mut self, index: usize) -> Result<u8> {
+        if !decode(&self.code)?
+            .iter()
+            .any(|&(start, _, _)| start == index)
+        {
+            return Err(BytecodeError::NotAnInstruction { index });
+        }
+        Ok(std::mem::replace(
+            &mut self.code[index],
+            Instruction::Breakpoint as u8,
+        ))
+    }
+
+    /// Puts back the instruction byte `set_breakpoint` returned.
+    pub fn clear_breakpoint(&mut self, index: usize, original: u8) {
+        debug_assert_eq!(self.code[index], Instruction::Breakpoint as u8);
+        self.code[index] = original;
+    }
+
     pub fn fmt<'a>(&'a self, exec: &'a Executable) -> FormatableCahnFunction<'a> {
         FormatableCahnFunction { func: self, exec }
     }
+
+    /// The instruction starting at the code index, which has to be the start of an instruction.
+    pub fn fmt_instruction<'a>(
+        &'a self,
+        exec: &'a Executable,
+        index: usize,
+    ) -> FormatableInstruction<'a> {
+        FormatableInstruction {
+            func: self,
+            exec,
+            index,
+        }
+    }
+
+    /// Serializes the function on its own, so it can be loaded into a running VM.
+    /// Constants, strings, functions, globals and natives are referenced by index,
+    /// so the bytes only make sense together with the executable they were compiled for.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            self.param_count,
+            self.is_variadic as u8,
+            self.default_entries.len() as u8,
+        ];
+        for entry in &self.default_entries {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.captures.len() as u16).to_le_bytes());
+        for capture in &self.captures {
+            bytes.push(capture.is_local as u8);
+            bytes.extend_from_slice(&capture.index.to_le_bytes());
+        }
+
+        match self.name {
+            FunctionName::Anonymous => bytes.push(0),
+            FunctionName::Named {
+                start_index,
+                end_index,
+            } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(start_index as u32).to_le_bytes());
+                bytes.extend_from_slice(&(end_index as u32).to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&(self.code.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.code);
+
+        // the code map has an entry for every byte of code, so it doesn't need a length
+        for pos in &self.code_map {
+            bytes.extend_from_slice(&(pos.line as u32).to_le_bytes());
+            bytes.extend_from_slice(&(pos.column as u32).to_le_bytes());
+            bytes.extend_from_slice(&(pos.file as u32).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Loads a function written by `to_bytes`.
+    /// The code is checked to consist of whole, valid instructions,
+    /// but whether it fits an executable is only checked by `Executable::check_function`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader { bytes, i: 0 };
+
+        let param_count = reader.read_u8()?;
+
+        let is_variadic = match reader.read_u8()? {
+            0 => false,
+            1 => true,
+            flag => {
+                return Err(BytecodeError::Malformed {
+                    message: format!("unknown variadic flag {}", flag),
+                })
+            }
+        };
+
+        // the rest parameter and those with a default value can be left out
+        let default_count = reader.read_u8()?;
+        let optional_count = default_count as usize + is_variadic as usize;
+        if optional_count > param_count as usize {
+            return Err(BytecodeError::Malformed {
+                message: format!(
+                    "{} parameters are optional, but there are only {}",
+                    optional_count, param_count
+                ),
+            });
+        }
+        let default_entries = (0..default_count)
+            .map(|_| reader.read_u32())
+            .collect::<Result<_>>()?;
+
+        let capture_count = reader.read_u16()?;
+        let captures = (0..capture_count)
+            .map(|_| {
+                let is_local = match reader.read_u8()? {
+                    0 => false,
+                    1 => true,
+                    flag => {
+                        return Err(BytecodeError::Malformed {
+                            message: format!("unknown capture flag {}", flag),
+                        })
+                    }
+                };
+                let index = reader.read_u16()?;
+                Ok(Capture { is_local, index })
+            })
+            .collect::<Result<_>>()?;
+
+        let name = match reader.read_u8()? {
+            0 => FunctionName::Anonymous,
+            1 => FunctionName::Named {
+                start_index: reader.read_u32()? as usize,
+                end_index: reader.read_u32()? as usize,
+            },
+            tag => {
+                return Err(BytecodeError::Malformed {
+                    message: format!("unknown function name tag {}", tag),
+                })
+            }
+        };
+
+        let code_len = reader.read_u32()? as usize;
+        let code = reader.read_bytes(code_len)?.to_vec();
+
+        let mut code_map = Vec::with_capacity(code_len);
+        for _ in 0..code_len {
+            let line = reader.read_u32()? as usize;
+            let column = reader.read_u32()? as usize;
+            let file = reader.read_u32()? as usize;
+            code_map.push(TokenPos::new(line, column).in_file(file));
+        }
+
+        if reader.i < bytes.len() {
+            return Err(BytecodeError::TrailingBytes {
+                count: bytes.len() - reader.i,
+            });
+        }
+
+        decode(&code)?;
+
+        Ok(Self::new_helper(param_count, code, code_map, name)
+            .with_default_entries(default_entries)
+            .variadic(is_variadic)
+            .with_captures(captures))
+    }
+}
+
+// a reader for untrusted bytes, which errors instead of panicking
+pub(super) struct ByteReader<'a> {
+    pub(super) bytes: &'a [u8],
+    pub(super) i: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(super) fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self
+            .i
+            .checked_add(count)
+            .ok_or(BytecodeError::UnexpectedEnd)?;
+        let bytes = self
+            .bytes
+            .get(self.i..end)
+            .ok_or(BytecodeError::UnexpectedEnd)?;
+        self.i = end;
+        Ok(bytes)
+    }
+
+    pub(super) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(super) fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub(super) fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+}
+
+/// Splits code into its instructions, returning the index of each one along with its operands.
+pub(super) fn decode(code: &[u8]) -> Result<Vec<(usize, Instruction, &[u8])>> {
+    let mut instructions = vec![];
+    let mut i = 0;
+
+    while i < code.len() {
+        let instruction =
+            Instruction::from_byte(code[i]).ok_or(BytecodeError::InvalidInstruction {
+                byte: code[i],
+                index: i,
+            })?;
+
+        let operands = code
+            .get(i + 1..i + 1 + instruction.operand_len())
+            .ok_or(BytecodeError::TruncatedInstruction { index: i })?;
+
+        instructions.push((i, instruction, operands));
+        i += 1 + operands.len();
+    }
+
+    Ok(instructions)
 }
 
 pub struct FormatableCahnFunction<'a> {
@@ -117,112 +400,194 @@ impl<'a> fmt::Display for FormatableCahnFunction<'a> {
 impl<'a> fmt::Debug for FormatableCahnFunction<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
-            "<CahnFunction name=\"{}\" parameters={}>\n",
+            "<CahnFunction name=\"{}\" parameters={} defaults={:?} captures={:?}>\n",
             self.func.name.fmt(&self.exec.string_data),
-            self.func.param_count
+            self.func.param_count,
+            self.func.default_entries,
+            self.func.captures
         ))?;
 
-        let code = &self.func.code;
         let code_map = &self.func.code_map;
-        let num_consts = &self.exec.num_consts;
-        let string_data = &self.exec.string_data;
-
-        let mut code_reader = PanickingByteBufferReader::new(code);
-
-        while !code_reader.is_at_end() {
-            let start_index = code_reader.current_index();
-            let code_pos = code_map[start_index];
-            let instruction: Instruction = unsafe { mem::transmute(code_reader.read_u8()) };
+        let mut index = 0;
 
+        while index < self.func.code.len() {
             f.write_fmt(format_args!(
-                "{}:{} \t{}\t{:?}",
-                self.exec.source_file, code_pos, start_index, instruction
+                "{}:{} \t{}\t",
+                self.exec.source_file_of(code_map[index]),
+                code_map[index],
+                index
             ))?;
-
-            match instruction {
-                Instruction::LoadLitNum => {
-                    f.write_fmt(format_args!("    '{}'", code_reader.read_u8()))?
-                }
-
-                Instruction::LoadConstNum => {
-                    let index = code_reader.read_u8();
-                    let val = self.exec.num_consts[index as usize];
-                    f.write_fmt(format_args!("    {} '{}'", index, val))?;
-                }
-                Instruction::LoadConstNumW => {
-                    let index = code_reader.read_u16_le();
-                    let val = num_consts[index as usize];
-                    f.write_fmt(format_args!("    {} '{}'", index, val))?;
-                }
-                Instruction::LoadConstNumWW => {
-                    let index = code_reader.read_u32_le();
-                    let val = num_consts[index as usize];
-                    f.write_fmt(format_args!("    {} '{}'", index, val))?;
-                }
-                Instruction::JumpIfFalse | Instruction::Jump => {
-                    let jump_location = code_reader.read_u32_le();
-                    f.write_fmt(format_args!("    {}", jump_location))?;
-                }
-
-                Instruction::GetLocal | Instruction::SetLocal | Instruction::CreateListWithCap => {
-                    f.write_fmt(format_args!("    {}", code_reader.read_u8()))?;
-                }
-
-                Instruction::LoadFunction => {
-                    let func_index = code_reader.read_u32_le() as usize;
-                    let func = &self.exec.functions[func_index];
-                    f.write_fmt(format_args!(
-                        "     {} '{}'",
-                        func_index,
-                        func.fmt(self.exec)
-                    ))?;
-                }
-
-                Instruction::GetLocalW
-                | Instruction::SetLocalW
-                | Instruction::CreateListWithCapW => {
-                    f.write_fmt(format_args!("    {}", code_reader.read_u16_le()))?;
-                }
-
-                Instruction::LoadStringLiteral => {
-                    let start_index = code_reader.read_u32_le() as usize;
-                    let end_index = code_reader.read_u32_le() as usize;
-
-                    f.write_fmt(format_args!(
-                        "    {}..{} '{}'",
-                        start_index,
-                        end_index,
-                        &string_data[start_index..end_index]
-                    ))?;
-                }
-
-                Instruction::CreateList => {}
-                Instruction::ListPush => {}
-                Instruction::Modulo => {}
-                Instruction::Add => {}
-                Instruction::Mul => {}
-                Instruction::Sub => {}
-                Instruction::Div => {}
-                Instruction::Negate => {}
-                Instruction::Not => {}
-                Instruction::LoadTrue => {}
-                Instruction::LoadFalse => {}
-                Instruction::LoadNil => {}
-                Instruction::LessThan => {}
-                Instruction::GreaterThan => {}
-                Instruction::LessThanOrEqual => {}
-                Instruction::GreaterThanOrEqual => {}
-                Instruction::Equal => {}
-                Instruction::Dup => {}
-                Instruction::Pop => {}
-                Instruction::Print => {}
-                Instruction::Concat => {}
-                Instruction::ListGetIndex => {}
-            }
-
+            index = fmt_instruction(f, self.exec, &self.func.code, index)?;
             f.write_char('\n')?;
         }
         f.write_str("</CahnFunction>\n")?;
         Ok(())
     }
 }
+
+/// A single instruction of a function, with its decoded operands, like "GetGlobal    0 'x'".
+pub struct FormatableInstruction<'a> {
+    func: &'a CahnFunction,
+    exec: &'a Executable,
+    index: usize,
+}
+
+impl<'a> fmt::Display for FormatableInstruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_instruction(f, self.exec, &self.func.code, self.index).map(|_| ())
+    }
+}
+
+// writes the instruction starting at the index, returns the index of the next one.
+fn fmt_instruction(
+    f: &mut fmt::Formatter<'_>,
+    exec: &Executable,
+    code: &[u8],
+    index: usize,
+) -> result::Result<usize, fmt::Error> {
+    let mut code_reader = PanickingByteBufferReader::starting_at(code, index);
+    let instruction: Instruction = unsafe { mem::transmute(code_reader.read_u8()) };
+    f.write_fmt(format_args!("{:?}", instruction))?;
+
+    match instruction {
+        Instruction::LoadLitNum => f.write_fmt(format_args!("    '{}'", code_reader.read_u8()))?,
+
+        Instruction::LoadConstNum => {
+            let index = code_reader.read_u8();
+            let val = exec.num_consts[index as usize];
+            f.write_fmt(format_args!("    {} '{}'", index, val))?;
+        }
+        Instruction::LoadConstNumW => {
+            let index = code_reader.read_u16_le();
+            let val = exec.num_consts[index as usize];
+            f.write_fmt(format_args!("    {} '{}'", index, val))?;
+        }
+        Instruction::LoadConstNumWW => {
+            let index = code_reader.read_u32_le();
+            let val = exec.num_consts[index as usize];
+            f.write_fmt(format_args!("    {} '{}'", index, val))?;
+        }
+        Instruction::JumpIfFalse
+        | Instruction::JumpIfFalsePeek
+        | Instruction::JumpIfTruePeek
+        | Instruction::Jump
+        | Instruction::IterNext => {
+            let jump_location = code_reader.read_u32_le();
+            f.write_fmt(format_args!("    {}", jump_location))?;
+        }
+
+        Instruction::GetLocal
+        | Instruction::SetLocal
+        | Instruction::CreateListWithCap
+        | Instruction::Invoke
+        | Instruction::ReturnMany
+        | Instruction::PrintN
+        | Instruction::Unpack
+        | Instruction::GetUpvalue
+        | Instruction::SetUpvalue => {
+            f.write_fmt(format_args!("    {}", code_reader.read_u8()))?;
+        }
+
+        Instruction::LoadFunction | Instruction::Closure => {
+            let func_index = code_reader.read_u32_le() as usize;
+            let func = &exec.functions[func_index];
+            f.write_fmt(format_args!("     {} '{}'", func_index, func.fmt(exec)))?;
+        }
+
+        Instruction::DefineGlobal | Instruction::SetGlobal | Instruction::GetGlobal => {
+            let index = code_reader.read_u32_le() as usize;
+            let name = &exec.global_names[index];
+            f.write_fmt(format_args!("    {} '{}'", index, name))?;
+        }
+
+        Instruction::CreateRecord => {
+            let index = code_reader.read_u32_le() as usize;
+            let name = &exec.structs[index].name;
+            f.write_fmt(format_args!("    {} '{}'", index, name))?;
+        }
+
+        Instruction::GetField | Instruction::SetField => {
+            let index = code_reader.read_u32_le() as usize;
+            let slot = code_reader.read_u32_le();
+            let name = &exec.field_names[index];
+            f.write_fmt(format_args!("    {} '{}' @{}", index, name, slot))?;
+        }
+
+        Instruction::LoadNative => {
+            let native_index = code_reader.read_u32_le() as usize;
+            let native = &exec.natives[native_index];
+            f.write_fmt(format_args!("    {} '{}'", native_index, native.name))?;
+        }
+
+        Instruction::GetLocalW
+        | Instruction::SetLocalW
+        | Instruction::CreateListWithCapW
+        | Instruction::CloseUpvalue => {
+            f.write_fmt(format_args!("    {}", code_reader.read_u16_le()))?;
+        }
+
+        Instruction::LoadStringLiteral => {
+            let start_index = code_reader.read_u32_le() as usize;
+            let end_index = code_reader.read_u32_le() as usize;
+
+            f.write_fmt(format_args!(
+                "    {}..{} '{}'",
+                start_index,
+                end_index,
+                &exec.string_data[start_index..end_index]
+            ))?;
+        }
+
+        Instruction::InvokeMany => {
+            let arg_count = code_reader.read_u8();
+            let results = code_reader.read_u8();
+            f.write_fmt(format_args!("    {} {}", arg_count, results))?;
+        }
+
+        Instruction::CreateList => {}
+        Instruction::ListPush => {}
+        Instruction::RecordPush => {}
+        Instruction::Modulo => {}
+        Instruction::BitAnd => {}
+        Instruction::BitOr => {}
+        Instruction::BitXor => {}
+        Instruction::Shl => {}
+        Instruction::Shr => {}
+        Instruction::Add => {}
+        Instruction::Mul => {}
+        Instruction::Sub => {}
+        Instruction::Div => {}
+        Instruction::FloorDiv => {}
+        Instruction::Pow => {}
+        Instruction::Negate => {}
+        Instruction::Not => {}
+        Instruction::TypeOf => {}
+        Instruction::LoadTrue => {}
+        Instruction::LoadFalse => {}
+        Instruction::LoadNil => {}
+        Instruction::LessThan => {}
+        Instruction::GreaterThan => {}
+        Instruction::LessThanOrEqual => {}
+        Instruction::GreaterThanOrEqual => {}
+        Instruction::Equal => {}
+        Instruction::ConstTrue => {}
+        Instruction::ConstFalse => {}
+        Instruction::Contains => {}
+        Instruction::Dup => {}
+        Instruction::Pop => {}
+        Instruction::Swap => {}
+        Instruction::DupUnder => {}
+        Instruction::Rot => {}
+        Instruction::Print => {}
+        Instruction::Return => {}
+        Instruction::Assert => {}
+        Instruction::Nop => {}
+        Instruction::Breakpoint => {}
+        Instruction::Halt => {}
+        Instruction::Concat => {}
+        Instruction::ListGetIndex => {}
+        Instruction::IterInit => {}
+    }
+
+    Ok(code_reader.current_index())
+}
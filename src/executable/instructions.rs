@@ -1,49 +1,223 @@
+// defines the instruction enum from a table, which also holds each instruction's operand length,
+// stack effect and description, so that data lives in one place.
+// the order of the table is the order of the bytes, so bytes can be turned back into instructions
+// without transmuting.
+macro_rules! instructions {
+    ($($name:ident($operand_len:expr, $stack_effect:expr) $description:literal,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u8)]
+        pub enum Instruction {
+            $($name,)*
+        }
+
+        impl Instruction {
+            /// Every instruction, indexed by its byte.
+            pub const ALL: &'static [Instruction] = &[$(Instruction::$name,)*];
+
+            /// The table every instruction's info is read from, indexed by its byte.
+            const INFO: &'static [InstructionInfo] = &[$(InstructionInfo {
+                instruction: Instruction::$name,
+                name: stringify!($name),
+                operand_len: $operand_len,
+                stack_effect: $stack_effect,
+                description: $description,
+            },)*];
+        }
+    };
+}
+
+/// Everything there is to know about an instruction, for tools like disassemblers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-pub enum Instruction {
-    Negate,
-    Not,
-    Add,
-    Mul,
-    Sub,
-    Div,
-    Modulo,
-    Concat,
-
-    LessThan,
-    GreaterThan,
-    LessThanOrEqual,
-    GreaterThanOrEqual,
-    Equal,
-
-    CreateList,
-    CreateListWithCap,
-    CreateListWithCapW,
-    ListPush,
-    ListGetIndex,
-
-    LoadTrue,
-    LoadFalse,
-    LoadNil,
-
-    LoadStringLiteral,
-    LoadLitNum,
-    LoadConstNum,
-    LoadConstNumW,
-    LoadConstNumWW,
-
-    SetLocal,
-    SetLocalW,
-    GetLocal,
-    GetLocalW,
-
-    LoadFunction,
-
-    Dup,
-    Pop,
-
-    Print,
-
-    Jump,
-    JumpIfFalse,
+pub struct InstructionInfo {
+    pub instruction: Instruction,
+    pub name: &'static str,
+    /// The number of operand bytes that follow the instruction.
+    pub operand_len: usize,
+    /// How many values the instruction pops, and how many it pushes afterwards.
+    pub stack_effect: Option<(usize, usize)>,
+    pub description: &'static str,
+}
+
+instructions! {
+    Negate(0, Some((1, 1))) "negates the number on top of the stack",
+    Not(0, Some((1, 1))) "replaces the top of the stack with whether it's falsy",
+    TypeOf(0, Some((1, 1))) "replaces the top of the stack with the name of its type",
+    Add(0, Some((2, 1))) "pops two numbers and pushes their sum",
+    Mul(0, Some((2, 1))) "pops two numbers and pushes their product",
+    Sub(0, Some((2, 1))) "pops two numbers and pushes the first minus the second",
+    Div(0, Some((2, 1))) "pops two numbers and pushes the first divided by the second",
+    FloorDiv(0, Some((2, 1))) "pops two numbers and pushes their quotient, rounded down",
+    Modulo(0, Some((2, 1))) "pops two numbers and pushes the remainder of their division",
+    BitAnd(0, Some((2, 1))) "pops two whole numbers and pushes their bitwise and",
+    BitOr(0, Some((2, 1))) "pops two whole numbers and pushes their bitwise or",
+    BitXor(0, Some((2, 1))) "pops two whole numbers and pushes their bitwise exclusive or",
+    Shl(0, Some((2, 1))) "pops two whole numbers and pushes the first shifted left by the second",
+    Shr(0, Some((2, 1))) "pops two whole numbers and pushes the first shifted right by the second, keeping its sign",
+    Pow(0, Some((2, 1))) "pops two numbers and pushes the first raised to the second",
+    Concat(0, Some((2, 1))) "pops two values and pushes their display strings joined together",
+
+    LessThan(0, Some((2, 1))) "pops two numbers and pushes whether the first is smaller",
+    GreaterThan(0, Some((2, 1))) "pops two numbers and pushes whether the first is larger",
+    LessThanOrEqual(0, Some((2, 1))) "pops two numbers and pushes whether the first is smaller or equal",
+    GreaterThanOrEqual(0, Some((2, 1))) "pops two numbers and pushes whether the first is larger or equal",
+    Equal(0, Some((2, 1))) "pops two values and pushes whether they're equal",
+    ConstTrue(0, Some((1, 1))) "replaces the top of the stack with whether it's true, like LoadTrue followed by Equal",
+    ConstFalse(0, Some((1, 1))) "replaces the top of the stack with whether it's false, like LoadFalse followed by Equal",
+    Contains(0, Some((2, 1))) "pops a list or string and the value below it, and pushes whether the value is an element or substring of it",
+
+    CreateList(0, Some((0, 1))) "pushes a new, empty list",
+    CreateListWithCap(1, Some((0, 1))) "pushes a new, empty list with room for the operand's number of elements",
+    CreateListWithCapW(2, Some((0, 1))) "like CreateListWithCap, with a 16 bit operand",
+    ListPush(0, Some((2, 1))) "pops a value and appends it to the list below it",
+    ListGetIndex(0, Some((2, 1))) "pops a list and an index, and pushes the element at the index",
+
+    CreateRecord(4, Some((0, 1))) "pushes a new record of the struct at the operand's index, without any fields yet",
+    RecordPush(0, Some((2, 1))) "pops a value and sets the next field of the record below it to it",
+    GetField(8, Some((1, 1))) "pops a record, and pushes its field with the name at the first operand's index, which is expected in the slot of the second",
+    SetField(8, Some((2, 1))) "pops a record and the value below it, sets the record's field with the name at the first operand's index, which is expected in the slot of the second, to the value, and pushes the value",
+
+    LoadTrue(0, Some((0, 1))) "pushes true",
+    LoadFalse(0, Some((0, 1))) "pushes false",
+    LoadNil(0, Some((0, 1))) "pushes nil",
+
+    LoadStringLiteral(8, Some((0, 1))) "pushes the string between the two 32 bit indices into the string data",
+    LoadLitNum(1, Some((0, 1))) "pushes the operand as a number",
+    LoadConstNum(1, Some((0, 1))) "pushes the number constant at the operand's index",
+    LoadConstNumW(2, Some((0, 1))) "like LoadConstNum, with a 16 bit index",
+    LoadConstNumWW(4, Some((0, 1))) "like LoadConstNum, with a 32 bit index",
+
+    SetLocal(1, Some((1, 0))) "pops a value into the local at the operand's slot",
+    SetLocalW(2, Some((1, 0))) "like SetLocal, with a 16 bit slot",
+    GetLocal(1, Some((0, 1))) "pushes the local at the operand's slot",
+    GetLocalW(2, Some((0, 1))) "like GetLocal, with a 16 bit slot",
+
+    SetUpvalue(1, Some((1, 0))) "pops a value into the variable the running closure captured at the operand's index",
+    GetUpvalue(1, Some((0, 1))) "pushes the variable the running closure captured at the operand's index",
+    CloseUpvalue(2, Some((0, 0))) "moves the locals from the operand's 16 bit slot up out of the stack, into the upvalues of the closures that captured them",
+
+    DefineGlobal(4, Some((1, 0))) "pops a value into the global at the operand's index, defining it",
+    SetGlobal(4, Some((1, 0))) "pops a value into the global at the operand's index, which has to be defined",
+    GetGlobal(4, Some((0, 1))) "pushes the global at the operand's index, which has to be defined",
+
+    LoadFunction(4, Some((0, 1))) "pushes the function at the operand's index",
+    Closure(4, Some((0, 1))) "pushes a closure of the function at the operand's index, capturing the variables it lists",
+    LoadNative(4, Some((0, 1))) "pushes the native function at the operand's index",
+
+    Invoke(1, None) "calls the function below the operand's number of arguments, which are popped along with it, and pushes the result",
+    InvokeMany(2, None) "like Invoke, but pushes the second operand's number of results, a single result has to be a list of that many elements",
+    Return(0, Some((1, 0))) "pops the result, and returns it to the caller",
+    ReturnMany(1, None) "pops the operand's number of results, and returns them to the caller, as a list if it takes a single result",
+    Assert(0, Some((2, 0))) "pops a message and a condition, and fails with the message if the condition is falsy",
+    Nop(0, Some((0, 0))) "does nothing, takes the place of removed instructions so the code doesn't shift",
+    Breakpoint(0, Some((0, 0))) "stops the VM before the instruction a debugger replaced with it",
+    Halt(0, Some((0, 0))) "ends the program, the value on top of the stack is its result",
+
+    Dup(0, Some((1, 2))) "pushes a copy of the top of the stack",
+    Pop(0, Some((1, 0))) "pops the top of the stack",
+    Swap(0, Some((2, 2))) "swaps the top two values, [a b] -> [b a]",
+    DupUnder(0, Some((2, 3))) "pushes a copy of the second value from the top, [a b] -> [a b a]",
+    Rot(0, Some((3, 3))) "moves the third value from the top to the top, [a b c] -> [b c a]",
+
+    Print(0, Some((1, 0))) "pops a value and prints it on its own line",
+    PrintN(1, None) "pops the operand's number of values and prints them on one line, separated by spaces",
+    Unpack(1, None) "pops a list, which has to have the operand's number of elements, and pushes its elements",
+
+    Jump(4, Some((0, 0))) "continues at the operand's code index",
+    JumpIfFalse(4, Some((1, 0))) "pops a value, and continues at the operand's code index if it's falsy",
+    JumpIfFalsePeek(4, Some((1, 1))) "continues at the operand's code index if the top of the stack is falsy, leaving it there",
+    JumpIfTruePeek(4, Some((1, 1))) "continues at the operand's code index if the top of the stack is truthy, leaving it there",
+
+    IterInit(0, Some((1, 1))) "replaces the list or string on top of the stack with an iterator over its elements",
+    IterNext(4, Some((1, 2))) "pushes the next element of the iterator on top of the stack, or continues at the operand's code index once it's exhausted",
+}
+
+/// Goes up whenever instructions are added, removed or change what they do,
+/// code made for another instruction set can't be run.
+pub const INSTRUCTION_SET_VERSION: u32 = 2;
+
+impl Instruction {
+    pub fn from_byte(byte: u8) -> Option<Instruction> {
+        Self::ALL.get(byte as usize).copied()
+    }
+
+    pub fn info(self) -> &'static InstructionInfo {
+        &Self::INFO[self as usize]
+    }
+
+    /// The info of every instruction, indexed by its byte.
+    pub fn describe_all() -> &'static [InstructionInfo] {
+        Self::INFO
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    pub fn describe(self) -> &'static str {
+        self.info().description
+    }
+
+    /// The number of operand bytes that follow the instruction.
+    pub fn operand_len(self) -> usize {
+        self.info().operand_len
+    }
+
+    /// How many values the instruction pops, and how many it pushes afterwards.
+    /// None for the instructions that pop or push as many values as their operands say.
+    pub fn stack_effect(self) -> Option<(usize, usize)> {
+        self.info().stack_effect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Instruction;
+
+    #[test]
+    fn bytes_round_trip() {
+        for (byte, instruction) in Instruction::ALL.iter().enumerate() {
+            assert_eq!(*instruction as usize, byte);
+            assert_eq!(Instruction::from_byte(byte as u8), Some(*instruction));
+        }
+        assert_eq!(Instruction::from_byte(Instruction::ALL.len() as u8), None);
+    }
+
+    #[test]
+    fn every_instruction_is_described() {
+        let table = Instruction::describe_all();
+        assert_eq!(table.len(), Instruction::ALL.len());
+
+        let mut names = HashSet::new();
+        for (byte, info) in table.iter().enumerate() {
+            assert_eq!(info.instruction as usize, byte);
+            assert_eq!(info.name, format!("{:?}", info.instruction));
+            assert!(
+                names.insert(info.name),
+                "{} is in the table twice",
+                info.name
+            );
+            assert!(
+                !info.description.is_empty(),
+                "{} has no description",
+                info.name
+            );
+        }
+    }
+
+    #[test]
+    fn only_instructions_with_counts_have_variable_effects() {
+        for info in Instruction::describe_all() {
+            let is_variable = matches!(
+                info.instruction,
+                Instruction::Invoke
+                    | Instruction::InvokeMany
+                    | Instruction::ReturnMany
+                    | Instruction::PrintN
+                    | Instruction::Unpack
+            );
+            assert_eq!(info.stack_effect.is_none(), is_variable, "{}", info.name);
+        }
+    }
 }
@@ -1,19 +1,58 @@
+pub mod error;
 mod function;
 mod instructions;
+mod serialization;
+mod stack_heights;
+mod tree_shaking;
 
-pub use function::CahnFunction;
-pub use instructions::Instruction;
+pub use error::BytecodeError;
+pub use function::{CahnFunction, Capture, FunctionName};
+pub use instructions::{Instruction, InstructionInfo, INSTRUCTION_SET_VERSION};
 
-use std::fmt;
+use std::{convert::TryInto, fmt, rc::Rc};
 
+use error::Result;
+
+use crate::{compiler::lexical_analysis::TokenPos, runtime::NativeFunction};
+
+/// The names `type` and `typeof` give values. The code generator puts them at the start of
+/// the string data, so the names are string literals, equal to literals with the same text.
+pub const TYPE_NAMES: &[&str] = &[
+    "number", "bool", "nil", "string", "list", "function", "record",
+];
+
+/// The version of cahn_lang compiling executables, they remember it.
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A compiled program. Running it never changes it, so it can be run any number of times,
+/// by any number of VMs at once. A VM that replaces functions works on its own copy.
+/// The functions are shared through `Rc`, so the VMs have to be on the same thread.
 #[derive(Clone)]
 pub struct Executable {
     pub num_consts: Vec<f64>,
 
-    pub functions: Vec<CahnFunction>,
+    // shared with the VM, so functions can be replaced while older versions are still running.
+    // they're in the order their 'fn' comes in the source, imported modules where they're imported,
+    // and the top level function is last, so the same source always gives the same indices.
+    pub functions: Vec<Rc<CahnFunction>>,
+
+    pub natives: Vec<NativeFunction>,
+
+    // the names of the globals, indexed by the global instructions
+    pub global_names: Vec<String>,
+
+    pub structs: Vec<StructLayout>,
+    // the names of the fields of every struct, indexed by GetField
+    pub field_names: Vec<String>,
 
     pub source_file: String,
+    // the files of the imported modules, a position in file i + 1 is in the one at index i
+    pub module_files: Vec<String>,
     pub string_data: String,
+
+    // what the executable was compiled with, which is only different once it's deserialized
+    compiler_version: String,
+    instruction_set_version: u32,
 }
 
 impl Executable {
@@ -24,31 +63,341 @@ impl Executable {
 
         source_file: String,
 
-        functions: Vec<CahnFunction>,
+        functions: Vec<Rc<CahnFunction>>,
+
+        natives: Vec<NativeFunction>,
+
+        global_names: Vec<String>,
     ) -> Self {
         Executable {
             string_data,
             source_file,
+            module_files: vec![],
             num_consts,
             functions,
+            natives,
+            global_names,
+            structs: vec![],
+            field_names: vec![],
+            compiler_version: COMPILER_VERSION.to_string(),
+            instruction_set_version: INSTRUCTION_SET_VERSION,
+        }
+    }
+
+    pub fn with_structs(mut self, structs: Vec<StructLayout>, field_names: Vec<String>) -> Self {
+        self.structs = structs;
+        self.field_names = field_names;
+        self
+    }
+
+    pub fn with_module_files(mut self, module_files: Vec<String>) -> Self {
+        self.module_files = module_files;
+        self
+    }
+
+    /// The file the position is in, the source file or one of the modules it imports.
+    pub fn source_file_of(&self, pos: TokenPos) -> &str {
+        match pos.file.checked_sub(1) {
+            Some(module) => self.module_files.get(module).unwrap_or(&self.source_file),
+            None => &self.source_file,
+        }
+    }
+
+    /// The version of cahn_lang the executable was compiled with.
+    pub fn compiler_version(&self) -> &str {
+        &self.compiler_version
+    }
+
+    /// The version of the instruction set the executable's code is in.
+    pub fn instruction_set_version(&self) -> u32 {
+        self.instruction_set_version
+    }
+
+    /// Whether another version of cahn_lang compiled the executable.
+    /// It can still be run, as long as the instruction set is the same.
+    pub fn compiled_by_other_version(&self) -> bool {
+        self.compiler_version != COMPILER_VERSION
+    }
+
+    /// Where the type name is in the string data, None for names that aren't in `TYPE_NAMES`,
+    /// or if the executable wasn't made by the code generator.
+    pub fn type_name_slice(&self, name: &str) -> Option<(u32, u32)> {
+        let mut start_index = 0;
+        for &type_name in TYPE_NAMES {
+            let end_index = start_index + type_name.len();
+            if type_name == name {
+                return match self.string_data.get(start_index..end_index) {
+                    Some(slice) if slice == name => Some((start_index as u32, end_index as u32)),
+                    _ => None,
+                };
+            }
+            start_index = end_index;
+        }
+        None
+    }
+
+    /// Where the struct's records store the field, None if the struct doesn't have it.
+    /// The slot the field access was compiled with is checked first,
+    /// only structs storing the field in another slot have to be searched.
+    pub fn field_index(&self, struct_index: u32, field: u32, slot: u32) -> Option<usize> {
+        let fields = &self.structs[struct_index as usize].fields;
+        if fields.get(slot as usize) == Some(&field) {
+            return Some(slot as usize);
+        }
+        fields.iter().position(|&name| name == field)
+    }
+
+    /// Checks that everything the function refers to exists in this executable,
+    /// so it can safely replace one of the functions.
+    pub fn check_function(&self, func: &CahnFunction) -> Result<()> {
+        self.check_code(func, false)
+    }
+
+    /// Like `check_function`, for the top level function, the last one.
+    /// Nothing calls it, so it starts on an empty stack, without parameters or captures.
+    pub fn check_main_function(&self, func: &CahnFunction) -> Result<()> {
+        if func.param_count > 0 || func.is_variadic || !func.default_entries.is_empty() {
+            return Err(BytecodeError::Malformed {
+                message: format!(
+                    "the top level function takes {} parameters, but nothing passes it any",
+                    func.param_count
+                ),
+            });
+        }
+        if !func.captures.is_empty() {
+            return Err(BytecodeError::Malformed {
+                message: format!(
+                    "the top level function captures {} variables, but nothing encloses it",
+                    func.captures.len()
+                ),
+            });
         }
+        self.check_code(func, true)
     }
+
+    fn check_code(&self, func: &CahnFunction, is_main: bool) -> Result<()> {
+        if func.code_map.len() != func.code.len() {
+            return Err(BytecodeError::Malformed {
+                message: format!(
+                    "the code map has {} entries, but the code has {} bytes",
+                    func.code_map.len(),
+                    func.code.len()
+                ),
+            });
+        }
+
+        if let function::FunctionName::Named {
+            start_index,
+            end_index,
+        } = func.name
+        {
+            if self.string_data.get(start_index..end_index).is_none() {
+                return Err(BytecodeError::Malformed {
+                    message: format!(
+                        "the name {}..{} isn't in the string data",
+                        start_index, end_index
+                    ),
+                });
+            }
+        }
+
+        let instructions = function::decode(&func.code)?;
+
+        // the VM only stops at a Halt, so running off the end of the code has to be impossible
+        match instructions.last() {
+            Some(&(_, Instruction::Return | Instruction::ReturnMany | Instruction::Halt, _)) => {}
+            Some(&(_, instruction, _)) => {
+                return Err(BytecodeError::Malformed {
+                    message: format!(
+                        "the code ends with {:?} instead of Return or Halt",
+                        instruction
+                    ),
+                })
+            }
+            None => {
+                return Err(BytecodeError::Malformed {
+                    message: "the code is empty".into(),
+                })
+            }
+        }
+
+        // the top level function isn't called, so it has to stop with Halt
+        if is_main {
+            if let Some(&(index, _, _)) = instructions.iter().find(|&&(_, instruction, _)| {
+                matches!(instruction, Instruction::Return | Instruction::ReturnMany)
+            }) {
+                return Err(BytecodeError::Malformed {
+                    message: format!(
+                        "the top level function returns at {}, but there's no caller to return to",
+                        index
+                    ),
+                });
+            }
+        }
+
+        // the rest parameter and those with a default value can be left out
+        let optional_count = func.default_entries.len() + func.is_variadic as usize;
+        if optional_count > func.param_count as usize {
+            return Err(BytecodeError::Malformed {
+                message: format!(
+                    "{} parameters are optional, but there are only {}",
+                    optional_count, func.param_count
+                ),
+            });
+        }
+        for &entry in &func.default_entries {
+            if !instructions
+                .iter()
+                .any(|&(start, _, _)| start == entry as usize)
+            {
+                return Err(BytecodeError::Malformed {
+                    message: format!(
+                        "the default entry {} isn't the start of an instruction",
+                        entry
+                    ),
+                });
+            }
+        }
+
+        for &(index, instruction, operands) in &instructions {
+            let operand = match operands.len() {
+                0 => 0,
+                1 => operands[0] as usize,
+                2 => u16::from_le_bytes(operands.try_into().unwrap()) as usize,
+                _ => u32::from_le_bytes(operands[..4].try_into().unwrap()) as usize,
+            };
+
+            let bad_reference = |message: String| BytecodeError::BadReference {
+                instruction,
+                index,
+                message,
+            };
+
+            let check_index = |len: usize, what: &str| {
+                if operand < len {
+                    Ok(())
+                } else {
+                    Err(bad_reference(format!(
+                        "there is no {} at index {}, there are {}",
+                        what, operand, len
+                    )))
+                }
+            };
+
+            match instruction {
+                Instruction::LoadConstNum
+                | Instruction::LoadConstNumW
+                | Instruction::LoadConstNumWW => check_index(self.num_consts.len(), "constant")?,
+
+                Instruction::LoadFunction => {
+                    check_index(self.functions.len(), "function")?;
+                    // without a closure, the function would have nothing to capture from
+                    if !self.functions[operand].captures.is_empty() {
+                        return Err(bad_reference(
+                            "the function captures variables, so it needs a Closure".into(),
+                        ));
+                    }
+                }
+
+                Instruction::Closure => {
+                    check_index(self.functions.len(), "function")?;
+                    // captures that aren't locals come from the captures of this function
+                    let inner = &self.functions[operand];
+                    for capture in inner.captures.iter().filter(|capture| !capture.is_local) {
+                        if capture.index as usize >= func.captures.len() {
+                            return Err(bad_reference(format!(
+                                "the function captures capture {}, but there are only {}",
+                                capture.index,
+                                func.captures.len()
+                            )));
+                        }
+                    }
+                }
+
+                Instruction::GetUpvalue | Instruction::SetUpvalue => {
+                    check_index(func.captures.len(), "capture")?
+                }
+
+                Instruction::LoadNative => check_index(self.natives.len(), "native")?,
+
+                Instruction::CreateRecord => check_index(self.structs.len(), "struct")?,
+                Instruction::GetField | Instruction::SetField => {
+                    check_index(self.field_names.len(), "field name")?
+                }
+
+                Instruction::DefineGlobal | Instruction::SetGlobal | Instruction::GetGlobal => {
+                    check_index(self.global_names.len(), "global")?
+                }
+
+                Instruction::Jump
+                | Instruction::JumpIfFalse
+                | Instruction::JumpIfFalsePeek
+                | Instruction::JumpIfTruePeek
+                | Instruction::IterNext => {
+                    let lands_on_instruction =
+                        instructions.iter().any(|&(start, _, _)| start == operand);
+                    if !lands_on_instruction {
+                        return Err(bad_reference(format!(
+                            "{} isn't the start of an instruction",
+                            operand
+                        )));
+                    }
+                }
+
+                Instruction::LoadStringLiteral => {
+                    let end_index = u32::from_le_bytes(operands[4..].try_into().unwrap()) as usize;
+                    if self.string_data.get(operand..end_index).is_none() {
+                        return Err(bad_reference(format!(
+                            "{}..{} isn't in the string data",
+                            operand, end_index
+                        )));
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        stack_heights::check_stack_heights(func, &instructions, &self.functions, is_main)
+    }
+}
+
+/// The fields of a struct, in the order its records store them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+    pub name: String,
+    // indices into the executable's field names
+    pub fields: Vec<u32>,
 }
 
 impl fmt::Debug for Executable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
             "\n<CahnExecutable>
+COMPILER: cahn_lang {} (instruction set {})
+
 NUM_CONSTS: {:?}
 
 STRING_DATA: '{}'
+
+NATIVES: {:?}
+
+GLOBALS: {:?}
+
+STRUCTS: {:?}
     
 FUNCTIONS\n",
-            self.num_consts, self.string_data,
+            self.compiler_version,
+            self.instruction_set_version,
+            self.num_consts,
+            self.string_data,
+            self.natives,
+            self.global_names,
+            self.structs,
         ))?;
 
         for func in &self.functions {
-            fmt::Debug::fmt(&func.fmt(&self), f)?;
+            fmt::Debug::fmt(&func.fmt(self), f)?;
         }
 
         f.write_str("</CahnExecutable>\n")?;
@@ -0,0 +1,223 @@
+use std::{convert::TryInto, rc::Rc};
+
+use crate::{
+    executable::{
+        error::{BytecodeError, Result},
+        function::ByteReader,
+        CahnFunction, Executable, StructLayout, INSTRUCTION_SET_VERSION,
+    },
+    runtime::{builtins::BUILTINS, NativeFunction},
+};
+
+// the start of every bytecode file, followed by the format version
+const MAGIC: &[u8] = b"CAHNBC";
+const FORMAT_VERSION: u8 = 3;
+
+impl Executable {
+    /// Writes the whole executable as bytes, which `deserialize` turns back into it,
+    /// so a program can be run without compiling it again.
+    /// Natives are written by name, as the functions implementing them can't be.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+
+        write_str(&mut bytes, &self.compiler_version);
+        bytes.extend_from_slice(&self.instruction_set_version.to_le_bytes());
+
+        write_len(&mut bytes, self.num_consts.len());
+        for num in &self.num_consts {
+            bytes.extend_from_slice(&num.to_le_bytes());
+        }
+
+        write_str(&mut bytes, &self.string_data);
+        write_str(&mut bytes, &self.source_file);
+        write_len(&mut bytes, self.module_files.len());
+        for file in &self.module_files {
+            write_str(&mut bytes, file);
+        }
+
+        write_len(&mut bytes, self.natives.len());
+        for native in &self.natives {
+            write_str(&mut bytes, native.name);
+        }
+
+        write_len(&mut bytes, self.global_names.len());
+        for name in &self.global_names {
+            write_str(&mut bytes, name);
+        }
+
+        write_len(&mut bytes, self.field_names.len());
+        for name in &self.field_names {
+            write_str(&mut bytes, name);
+        }
+
+        write_len(&mut bytes, self.structs.len());
+        for layout in &self.structs {
+            write_str(&mut bytes, &layout.name);
+            write_len(&mut bytes, layout.fields.len());
+            for field in &layout.fields {
+                bytes.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+
+        write_len(&mut bytes, self.functions.len());
+        for func in &self.functions {
+            let func_bytes = func.to_bytes();
+            write_len(&mut bytes, func_bytes.len());
+            bytes.extend_from_slice(&func_bytes);
+        }
+
+        bytes
+    }
+
+    /// Loads an executable written by `serialize`, with only the builtins as natives.
+    /// Everything the functions refer to is checked to exist, like `check_function` does.
+    /// Executables compiled by other versions of cahn_lang load, as long as their instruction
+    /// set is the same, see `compiled_by_other_version`.
+    pub fn deserialize(bytes: &[u8]) -> Result<Executable> {
+        Executable::deserialize_with_natives(bytes, &[])
+    }
+
+    /// Like `deserialize`, for executables compiled with the host's own natives.
+    /// The natives are looked up by name, the host's first, like the code generator does.
+    pub fn deserialize_with_natives(
+        bytes: &[u8],
+        natives: &[NativeFunction],
+    ) -> Result<Executable> {
+        let mut reader = ByteReader { bytes, i: 0 };
+
+        if reader.read_bytes(MAGIC.len()).ok() != Some(MAGIC) {
+            return Err(BytecodeError::NotBytecode);
+        }
+        let version = reader.read_u8()?;
+        if version != FORMAT_VERSION {
+            return Err(BytecodeError::UnsupportedVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+
+        let compiler_version = read_string(&mut reader)?;
+        let instruction_set_version = reader.read_u32()?;
+        if instruction_set_version != INSTRUCTION_SET_VERSION {
+            return Err(BytecodeError::UnsupportedInstructionSet {
+                found: instruction_set_version,
+                supported: INSTRUCTION_SET_VERSION,
+                compiler_version,
+            });
+        }
+
+        let num_consts = (0..reader.read_u32()?)
+            .map(|_| {
+                let num_bytes = reader.read_bytes(8)?;
+                Ok(f64::from_le_bytes(num_bytes.try_into().unwrap()))
+            })
+            .collect::<Result<_>>()?;
+
+        let string_data = read_string(&mut reader)?;
+        let source_file = read_string(&mut reader)?;
+        let module_files = (0..reader.read_u32()?)
+            .map(|_| read_string(&mut reader))
+            .collect::<Result<_>>()?;
+
+        let native_functions = (0..reader.read_u32()?)
+            .map(|_| {
+                let name = read_string(&mut reader)?;
+                natives
+                    .iter()
+                    .chain(BUILTINS)
+                    .find(|native| native.name == name)
+                    .copied()
+                    .ok_or(BytecodeError::UnknownNative { name })
+            })
+            .collect::<Result<_>>()?;
+
+        let global_names = (0..reader.read_u32()?)
+            .map(|_| read_string(&mut reader))
+            .collect::<Result<_>>()?;
+
+        let field_names: Vec<String> = (0..reader.read_u32()?)
+            .map(|_| read_string(&mut reader))
+            .collect::<Result<_>>()?;
+
+        let structs = (0..reader.read_u32()?)
+            .map(|_| {
+                let name = read_string(&mut reader)?;
+                let fields = (0..reader.read_u32()?)
+                    .map(|_| {
+                        let field = reader.read_u32()?;
+                        if field as usize >= field_names.len() {
+                            return Err(BytecodeError::Malformed {
+                                message: format!(
+                                    "struct {} has field {}, but there are only {} field names",
+                                    name,
+                                    field,
+                                    field_names.len()
+                                ),
+                            });
+                        }
+                        Ok(field)
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(StructLayout { name, fields })
+            })
+            .collect::<Result<_>>()?;
+
+        let functions: Vec<Rc<CahnFunction>> = (0..reader.read_u32()?)
+            .map(|_| {
+                let len = reader.read_u32()? as usize;
+                Ok(Rc::new(CahnFunction::from_bytes(reader.read_bytes(len)?)?))
+            })
+            .collect::<Result<_>>()?;
+
+        if reader.i < bytes.len() {
+            return Err(BytecodeError::TrailingBytes {
+                count: bytes.len() - reader.i,
+            });
+        }
+
+        let mut exec = Executable::new(
+            num_consts,
+            string_data,
+            source_file,
+            functions,
+            native_functions,
+            global_names,
+        )
+        .with_structs(structs, field_names)
+        .with_module_files(module_files);
+        exec.compiler_version = compiler_version;
+
+        // the VM starts at the last function, the top level
+        let (main, functions) = match exec.functions.split_last() {
+            Some(split) => split,
+            None => {
+                return Err(BytecodeError::Malformed {
+                    message: "the executable doesn't have any functions".into(),
+                })
+            }
+        };
+        for func in functions {
+            exec.check_function(func)?;
+        }
+        exec.check_main_function(main)?;
+
+        Ok(exec)
+    }
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize) {
+    bytes.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+fn write_str(bytes: &mut Vec<u8>, string: &str) {
+    write_len(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn read_string(reader: &mut ByteReader) -> Result<String> {
+    let len = reader.read_u32()? as usize;
+    String::from_utf8(reader.read_bytes(len)?.to_vec()).map_err(|err| BytecodeError::Malformed {
+        message: format!("a string isn't valid utf8: {}", err),
+    })
+}
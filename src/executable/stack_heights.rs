@@ -0,0 +1,141 @@
+use std::{convert::TryInto, rc::Rc};
+
+use crate::executable::{
+    error::{BytecodeError, Result},
+    CahnFunction, Instruction,
+};
+
+/// Follows every path through the function, counting the values on its part of the stack,
+/// which starts with the callee and the arguments, or empty for the top level function.
+/// Checks that no instruction pops more values than there are, that locals are only used
+/// once they're on the stack, and that every path reaching an instruction agrees on the
+/// height there. Without this, bytecode could read past the top of the stack, which the VM
+/// doesn't check.
+pub(super) fn check_stack_heights(
+    func: &CahnFunction,
+    instructions: &[(usize, Instruction, &[u8])],
+    functions: &[Rc<CahnFunction>],
+    is_main: bool,
+) -> Result<()> {
+    // the height before each instruction, None until a path reaches it
+    let mut heights: Vec<Option<usize>> = vec![None; instructions.len()];
+    let position = |code_index: usize| {
+        instructions
+            .binary_search_by_key(&code_index, |&(start, _, _)| start)
+            .ok()
+    };
+
+    // a call with every argument starts at the beginning, the others at the default entries
+    // the top level function has to push itself, check_main_function made sure it has no parameters
+    let full_height = if is_main {
+        0
+    } else {
+        1 + func.param_count as usize
+    };
+    let mut work_list = vec![(0, full_height)];
+    let min_height = full_height - func.default_entries.len() - func.is_variadic as usize;
+    for (i, &entry) in func.default_entries.iter().enumerate() {
+        // check_function made sure the entries are the starts of instructions
+        work_list.push((entry as usize, min_height + i));
+    }
+
+    while let Some((code_index, height)) = work_list.pop() {
+        let position = match position(code_index) {
+            Some(position) => position,
+            None => continue,
+        };
+
+        match heights[position] {
+            Some(known) if known == height => continue,
+            Some(known) => {
+                return Err(BytecodeError::Malformed {
+                    message: format!(
+                        "paths to {} disagree on the stack, one has {} values and one has {}",
+                        code_index, known, height
+                    ),
+                })
+            }
+            None => heights[position] = Some(height),
+        }
+
+        let (index, instruction, operands) = instructions[position];
+        let operand = match operands.len() {
+            0 => 0,
+            1 => operands[0] as usize,
+            2 => u16::from_le_bytes(operands.try_into().unwrap()) as usize,
+            _ => u32::from_le_bytes(operands[..4].try_into().unwrap()) as usize,
+        };
+
+        let bad_reference = |message: String| BytecodeError::BadReference {
+            instruction,
+            index,
+            message,
+        };
+
+        let (pops, pushes) = match instruction {
+            Instruction::Invoke => (operand + 1, 1),
+            // its operands are two single bytes, the arguments and the results
+            Instruction::InvokeMany => (operands[0] as usize + 1, operands[1] as usize),
+            Instruction::ReturnMany | Instruction::PrintN => (operand, 0),
+            Instruction::Unpack => (1, operand),
+            _ => instruction
+                .stack_effect()
+                .expect("only instructions with counts have variable effects"),
+        };
+        if pops > height {
+            return Err(bad_reference(format!(
+                "pops {} values, but there are only {}",
+                pops, height
+            )));
+        }
+
+        // SetLocal pops its value before storing it
+        let local_slots = match instruction {
+            Instruction::GetLocal | Instruction::GetLocalW | Instruction::CloseUpvalue => height,
+            Instruction::SetLocal | Instruction::SetLocalW => height - pops,
+            _ => usize::MAX,
+        };
+        if operand >= local_slots {
+            return Err(bad_reference(format!(
+                "uses the local in slot {}, but there are only {} values on the stack",
+                operand, local_slots
+            )));
+        }
+
+        // the locals a closure captures have to be on the stack when it's created,
+        // check_function made sure the function exists
+        if instruction == Instruction::Closure {
+            let captures = &functions[operand].captures;
+            if let Some(capture) = captures
+                .iter()
+                .find(|capture| capture.is_local && capture.index as usize >= height)
+            {
+                return Err(bad_reference(format!(
+                    "the function captures the local in slot {}, but there are only {} values on the stack",
+                    capture.index, height
+                )));
+            }
+        }
+
+        let next_height = height - pops + pushes;
+        let next_index = index + 1 + operands.len();
+        match instruction {
+            Instruction::Return | Instruction::ReturnMany | Instruction::Halt => {}
+            Instruction::Jump => work_list.push((operand, next_height)),
+            Instruction::JumpIfFalse
+            | Instruction::JumpIfFalsePeek
+            | Instruction::JumpIfTruePeek => {
+                work_list.push((operand, next_height));
+                work_list.push((next_index, next_height));
+            }
+            // the element is only pushed when the iterator isn't exhausted
+            Instruction::IterNext => {
+                work_list.push((operand, height));
+                work_list.push((next_index, next_height));
+            }
+            _ => work_list.push((next_index, next_height)),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{convert::TryInto, rc::Rc};
+
+use super::{error::Result, function, CahnFunction, Executable, Instruction};
+
+// a LoadFunction or Closure of a function
+struct Reference {
+    index: usize,
+    function: usize,
+    // the global it's stored in right away, for function declarations
+    defined_global: Option<usize>,
+}
+
+impl Reference {
+    fn is_live(&self, read_globals: &[bool]) -> bool {
+        self.defined_global
+            .is_none_or(|global| read_globals[global])
+    }
+}
+
+// the functions and globals a function refers to
+struct Summary {
+    references: Vec<Reference>,
+    read_globals: Vec<usize>,
+}
+
+fn read_u32(operands: &[u8]) -> usize {
+    u32::from_le_bytes(operands[..4].try_into().unwrap()) as usize
+}
+
+fn summarize(code: &[u8]) -> Result<Summary> {
+    let instructions = function::decode(code)?;
+    let mut summary = Summary {
+        references: vec![],
+        read_globals: vec![],
+    };
+
+    for (i, &(index, instruction, operands)) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::LoadFunction | Instruction::Closure => {
+                let defined_global = match instructions.get(i + 1) {
+                    Some(&(_, Instruction::DefineGlobal, operands)) => Some(read_u32(operands)),
+                    _ => None,
+                };
+                summary.references.push(Reference {
+                    index,
+                    function: read_u32(operands),
+                    defined_global,
+                });
+            }
+            Instruction::GetGlobal => summary.read_globals.push(read_u32(operands)),
+            _ => {}
+        }
+    }
+    Ok(summary)
+}
+
+impl Executable {
+    /// Drops the functions the program can never call, returning how many were dropped.
+    /// Starting from the top level function, every function a kept function loads is kept,
+    /// unless it's only loaded to declare a global that no kept function reads.
+    /// Such a global is declared as nil instead, padded with Nops so no code moves.
+    pub fn tree_shake(&mut self) -> Result<usize> {
+        let summaries = self
+            .functions
+            .iter()
+            .map(|function| summarize(&function.code))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut kept = vec![false; self.functions.len()];
+        let mut read_globals = vec![false; self.global_names.len()];
+        *kept.last_mut().unwrap() = true;
+
+        // keeping a function can make a global read, which can keep more functions
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (summary, _) in summaries.iter().zip(&kept).filter(|&(_, &kept)| kept) {
+                for &global in &summary.read_globals {
+                    changed |= !read_globals[global];
+                    read_globals[global] = true;
+                }
+            }
+            for (summary, _) in summaries.iter().zip(kept.clone()).filter(|&(_, kept)| kept) {
+                for reference in &summary.references {
+                    if reference.is_live(&read_globals) && !kept[reference.function] {
+                        kept[reference.function] = true;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut new_indices = vec![None; kept.len()];
+        for (new_index, old_index) in (0..kept.len()).filter(|&i| kept[i]).enumerate() {
+            new_indices[old_index] = Some(new_index as u32);
+        }
+
+        let mut functions = vec![];
+        for (old_index, function) in self.functions.iter().enumerate() {
+            if !kept[old_index] {
+                continue;
+            }
+
+            let mut function = CahnFunction::clone(function);
+            for reference in &summaries[old_index].references {
+                let operands = reference.index + 1..reference.index + 5;
+                match new_indices[reference.function] {
+                    Some(new_index) if reference.is_live(&read_globals) => {
+                        function.code[operands].copy_from_slice(&new_index.to_le_bytes());
+                    }
+                    _ => {
+                        function.code[reference.index] = Instruction::LoadNil as u8;
+                        for byte in &mut function.code[operands] {
+                            *byte = Instruction::Nop as u8;
+                        }
+                    }
+                }
+            }
+            functions.push(Rc::new(function));
+        }
+
+        let dropped = self.functions.len() - functions.len();
+        self.functions = functions;
+        Ok(dropped)
+    }
+}
@@ -1,20 +1,94 @@
 pub mod compiler;
+pub mod error;
 pub mod executable;
 pub mod runtime;
 pub mod utils;
 
-use compiler::{string_handling::StringInterner, CodeGenerator, Parser};
-use runtime::VM;
+use compiler::{
+    codegen::{CodeGenOptions, Warning},
+    string_handling::StringInterner,
+    syntactical_analysis::{ModuleLoader, ModuleParser},
+    CodeGenerator,
+};
+use error::Result;
+use executable::Executable;
+use runtime::{NativeFunction, VM};
 
-pub fn execute_source_to_string(source: &str, file_name: String) -> String {
+/// Compiles cahn source code into an executable.
+/// Never panics, no matter the source text, every failure is returned as an error.
+pub fn compile(source: &str, file_name: String) -> Result<Executable> {
+    compile_with_natives(source, file_name, &[])
+}
+
+/// Like `compile`, but the given natives can be called by name from the source.
+pub fn compile_with_natives(
+    source: &str,
+    file_name: String,
+    natives: &[NativeFunction],
+) -> Result<Executable> {
+    compile_with_options(source, file_name, natives, CodeGenOptions::default())
+        .map(|(exec, _warnings)| exec)
+}
+
+/// Like `compile_with_natives`, but with options for the code generator,
+/// like the custom operators the source can use. The warnings found while compiling are returned along with the executable.
+pub fn compile_with_options(
+    source: &str,
+    file_name: String,
+    natives: &[NativeFunction],
+    options: CodeGenOptions,
+) -> Result<(Executable, Vec<Warning>)> {
+    compile_with_loader(source, file_name, natives, options, &|_| None)
+}
+
+/// Like `compile_with_options`, but the modules the source imports are loaded with `loader`.
+pub fn compile_with_loader(
+    source: &str,
+    file_name: String,
+    natives: &[NativeFunction],
+    options: CodeGenOptions,
+    loader: &ModuleLoader,
+) -> Result<(Executable, Vec<Warning>)> {
+    compile_program(source, file_name, natives, options, loader)
+}
+
+fn compile_program(
+    source: &str,
+    file_name: String,
+    natives: &[NativeFunction],
+    options: CodeGenOptions,
+    loader: &ModuleLoader,
+) -> Result<(Executable, Vec<Warning>)> {
+    // the parser borrows them until the program is compiled, which takes the options
+    let custom_operators = options.custom_operators.clone();
     let interner = StringInterner::new();
     let arena = bumpalo::Bump::new();
 
-    let ast = Parser::from_str(source, &arena, interner)
-        .parse_program()
-        .unwrap();
+    let (ast, module_files) = ModuleParser::new(&arena, interner, loader)
+        .strict(options.strict)
+        .custom_operators(&custom_operators)
+        .parse_program_with_modules(source, &file_name)?;
+
+    let (exec, warnings) =
+        CodeGenerator::gen_executable_with_options(file_name, &ast, natives, options)?;
+    Ok((exec.with_module_files(module_files), warnings))
+}
+
+/// Compiles and runs the source, giving what it printed.
+pub fn execute_source_to_string(source: &str, file_name: String) -> Result<String> {
+    let exec = compile(source, file_name)?;
+
+    Ok(VM::run_to_string(&exec)?)
+}
 
-    let exec = CodeGenerator::gen_executable(file_name, &ast).unwrap();
+/// Like `execute_source_to_string`, but the modules the source imports are loaded with `loader`.
+pub fn execute_source_to_string_with_loader(
+    source: &str,
+    file_name: String,
+    loader: &ModuleLoader,
+) -> Result<String> {
+    let (exec, _warnings) =
+        compile_with_loader(source, file_name, &[], CodeGenOptions::default(), loader)?;
 
-    VM::run_to_string(&exec).unwrap()
+    Ok(VM::run_to_string(&exec)?)
 }
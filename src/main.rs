@@ -1,31 +1,119 @@
 #![deny(missing_debug_implementations)]
 
-use std::{env, fs, process::exit};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    panic,
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
 
 use cahn_lang::{
     compiler::{
+        codegen::{CodeGenOptions, Warning},
         lexical_analysis::{Lexer, TokenType},
         string_handling::StringInterner,
-        CodeGenerator, Parser,
+        syntactical_analysis::ModuleParser,
+        CodeGenerator,
     },
-    runtime::VM,
+    error::internal_error_message,
+    executable::{Executable, Instruction},
+    runtime::{error::RuntimeError, NumberFormat, VMOptions, VM},
 };
 
+// the exit code of a program stopped with Ctrl-C, like shells use for SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+// the exit code of a bug in cahn itself, EX_SOFTWARE of sysexits.h.
+// not 101, which is what any Rust program exits with when it panics.
+const INTERNAL_ERROR_EXIT_CODE: i32 = 70;
+
+// the flag the VM looks at, set by the SIGINT handler
+static INTERRUPT_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+// makes Ctrl-C set the flag instead of killing the process, so the VM stops on its own
+// and the output is flushed. a second Ctrl-C before the VM noticed the first, like when
+// it's waiting for input, ends the process right away.
+#[cfg(unix)]
+fn install_interrupt_handler(flag: Arc<AtomicBool>) {
+    use std::os::raw::c_int;
+
+    const SIGINT: c_int = 2;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn _exit(status: c_int) -> !;
+    }
+
+    extern "C" fn on_interrupt(_signum: c_int) {
+        if let Some(flag) = INTERRUPT_FLAG.get() {
+            if flag.swap(true, Ordering::SeqCst) {
+                unsafe { _exit(INTERRUPTED_EXIT_CODE) };
+            }
+        }
+    }
+
+    if INTERRUPT_FLAG.set(flag).is_ok() {
+        unsafe { signal(SIGINT, on_interrupt) };
+    }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler(_flag: Arc<AtomicBool>) {}
+
 fn print_help() {
     eprintln!(
         "Cahn lang
 
 USAGE:
     cahn [FLAGS] <INPUT FILE>
+    cahn [FLAGS] -e <SOURCE>
 
 EXAMPLE:
     cahn ./hello_world.cahn
+    cahn -o ./hello_world.cbc ./hello_world.cahn
+    cahn -r ./hello_world.cbc
+    cahn -e 'print 1 + 2'
+    cat ./hello_world.cahn | cahn -
+
+An input file of '-' reads the program from stdin, input() gets nothing then.
 
 FLAGS:
     -s   --print-source        Prints Cahn source code to console
     -l   --print-tokens        Prints Lexer output
     -p   --print-ast           Prints the AST, the parser's output
     -c   --print-bytecode      Prints the compiled byte code
+    -k   --print-peak-stack    Prints the highest number of values on the stack during the run
+         --profile             Prints how many times each instruction ran, and how long
+                               the run took
+         --heap-report-at-exit Prints what's left on the heap once the program is done,
+                               like heap_report() does
+    -O   --opt                 Leaves out code that can never run
+         --strict              Requires ';' between statements on one line, and turns
+                               unused variables and values into errors
+         --warn-empty-blocks   Warns about if, else and while bodies without statements
+         --warn-infinite-loops Warns about while loops whose condition is always true,
+                               and whose body can't stop them or have any effect
+         --strict-concat       Makes concatenating nil a runtime error
+         --runtime-warnings    Warns about suspicious things the program does while it runs,
+                               like concatenating nil, once for every place they happen
+         --tree-shake          Drops the functions the program never uses
+         --allow <CODE>        Doesn't report warnings with the given code,
+                               like shadowed-variable
+         --deny-warnings       Doesn't run the program if compiling it gave any warnings
+         --number-format <SPEC>
+                               Changes how numbers are printed and turned into strings,
+                               like \"thousands=, decimals=2\", the settings are
+                               thousands=<char>|none, decimals=<count>|shortest, point=<char>
+    -o   --emit-bytecode <FILE>
+                               Writes the compiled program to the file instead of running it
+    -r   --run-bytecode        Runs the input file as bytecode written by --emit-bytecode,
+                               without compiling it
+    -e   --eval <SOURCE>       Runs the given source code instead of an input file
 "
     );
 }
@@ -36,9 +124,39 @@ struct Config {
     print_tokens: bool,
     print_ast: bool,
     print_bytecode: bool,
+    print_peak_stack: bool,
+    heap_report_at_exit: bool,
+    profile: bool,
+    optimize: bool,
+    strict: bool,
+    allowed_lints: Vec<String>,
+    deny_warnings: bool,
+    warn_empty_blocks: bool,
+    warn_infinite_loops: bool,
+    strict_concat: bool,
+    runtime_warnings: bool,
+    number_format: NumberFormat,
+    tree_shake: bool,
+    emit_bytecode: Option<String>,
+    run_bytecode: bool,
+    eval: Option<String>,
+    // the input file was '-', so the program comes from stdin
+    from_stdin: bool,
     cahn_file: String,
 }
 
+// the file name errors show for source given with --eval
+const EVAL_FILE_NAME: &str = "<eval>";
+// the file name errors show for source read from stdin
+const STDIN_FILE_NAME: &str = "<stdin>";
+
+fn parse_number_format(spec: &str) -> NumberFormat {
+    NumberFormat::parse(spec).unwrap_or_else(|err| {
+        eprintln!("--number-format '{}' is invalid: {}", spec, err);
+        exit(1);
+    })
+}
+
 fn get_config() -> Config {
     let mut args = env::args().peekable();
 
@@ -51,31 +169,119 @@ fn get_config() -> Config {
 
     let mut config = Config::default();
 
-    for arg in args {
+    while let Some(arg) = args.next() {
         match &arg[..] {
             "-s" | "--print-source" => config.print_source = true,
             "-l" | "--print-tokens" => config.print_tokens = true,
             "-p" | "--print-ast" => config.print_ast = true,
             "-c" | "--print-bytecode" => config.print_bytecode = true,
+            "-k" | "--print-peak-stack" => config.print_peak_stack = true,
+            "--heap-report-at-exit" => config.heap_report_at_exit = true,
+            "--profile" => config.profile = true,
+            "-O" | "--opt" => config.optimize = true,
+            "--strict" => config.strict = true,
+            "--deny-warnings" => config.deny_warnings = true,
+            "--warn-empty-blocks" => config.warn_empty_blocks = true,
+            "--warn-infinite-loops" => config.warn_infinite_loops = true,
+            "--strict-concat" => config.strict_concat = true,
+            "--runtime-warnings" => config.runtime_warnings = true,
+            "--tree-shake" => config.tree_shake = true,
+            "-r" | "--run-bytecode" => config.run_bytecode = true,
+            "-o" | "--emit-bytecode" => match args.next() {
+                Some(path) => config.emit_bytecode = Some(path),
+                None => {
+                    eprintln!("{} needs the file to write the bytecode to", arg);
+                    exit(1);
+                }
+            },
+            "-e" | "--eval" => match args.next() {
+                Some(source) => config.eval = Some(source),
+                None => {
+                    eprintln!("{} needs the source code to run", arg);
+                    exit(1);
+                }
+            },
+            "--number-format" => match args.next() {
+                Some(spec) => config.number_format = parse_number_format(&spec),
+                None => {
+                    eprintln!("--number-format needs the format, like \"thousands=, decimals=2\"");
+                    exit(1);
+                }
+            },
+            arg if arg.starts_with("--number-format=") => {
+                config.number_format = parse_number_format(&arg["--number-format=".len()..])
+            }
+            "--allow" => match args.next() {
+                Some(code) if Warning::CODES.contains(&code.as_str()) => {
+                    config.allowed_lints.push(code)
+                }
+                Some(code) => {
+                    eprintln!(
+                        "--allow got '{}', which isn't the code of a warning, the codes are {}",
+                        code,
+                        Warning::CODES.join(", ")
+                    );
+                    exit(1);
+                }
+                None => {
+                    eprintln!("--allow needs the code of a warning");
+                    exit(1);
+                }
+            },
             _ => config.cahn_file = arg,
         }
     }
+
+    if config.eval.is_some() {
+        if !config.cahn_file.is_empty() || config.run_bytecode {
+            eprintln!("--eval runs its own source, so it can't be given an input file");
+            exit(1);
+        }
+        config.cahn_file = EVAL_FILE_NAME.to_string();
+    }
+
+    if config.cahn_file == "-" {
+        config.from_stdin = true;
+        config.cahn_file = STDIN_FILE_NAME.to_string();
+    }
     config
 }
 
 fn main() {
+    // a panic is a bug in cahn, not in the program it runs, so no backtrace is shown
+    panic::set_hook(Box::new(|info| {
+        eprintln!("{}", internal_error_message(info.payload()));
+        exit(INTERNAL_ERROR_EXIT_CODE);
+    }));
+
     let config = get_config();
 
+    if config.run_bytecode {
+        run_bytecode_file(&config);
+        return;
+    }
+
     // READ SOURCE CODE
-    let source_code = match fs::read_to_string(&config.cahn_file) {
-        Ok(content) => content,
+    let source_code = match &config.eval {
+        Some(source) => source.clone(),
 
-        Err(err) => {
-            eprintln!(
-                "Couldn't read '{}' due to error: {}.",
-                config.cahn_file, err
-            );
-            exit(1);
+        None => {
+            let content = if config.from_stdin {
+                io::read_to_string(io::stdin())
+            } else {
+                fs::read_to_string(&config.cahn_file)
+            };
+            match content {
+                Ok(content) => content,
+
+                Err(err) => {
+                    eprintln!(
+                        "Couldn't read '{}' due to error: {}.",
+                        config.cahn_file, err
+                    );
+                    exit(1);
+                }
+            }
         }
     };
 
@@ -104,8 +310,12 @@ fn main() {
     }
 
     // PARSE PROGRAM
-    let ast = match Parser::from_str(&source_code, &arena, interner).parse_program() {
-        Ok(ast) => ast,
+    let load_module = |path: &str| fs::read_to_string(path).ok();
+    let (ast, module_files) = match ModuleParser::new(&arena, interner, &load_module)
+        .strict(config.strict)
+        .parse_program_with_modules(&source_code, &config.cahn_file)
+    {
+        Ok(parsed) => parsed,
         Err(err) => {
             eprintln!("An error occurred during parsing: {}.", err);
             exit(2);
@@ -118,22 +328,204 @@ fn main() {
     }
 
     // COMPILE PROGRAM
-    let executable = match CodeGenerator::gen_executable(config.cahn_file, &ast) {
-        Ok(exec) => exec,
+    let options = CodeGenOptions {
+        optimize: config.optimize,
+        strict: config.strict,
+        allowed_lints: config.allowed_lints.clone(),
+        warn_empty_blocks: config.warn_empty_blocks,
+        warn_infinite_loops: config.warn_infinite_loops,
+        ..CodeGenOptions::default()
+    };
+    let mut executable = match CodeGenerator::gen_executable_with_options(
+        config.cahn_file.clone(),
+        &ast,
+        &[],
+        options,
+    ) {
+        Ok((exec, warnings)) => {
+            let exec = exec.with_module_files(module_files);
+            for warning in &warnings {
+                let (pos, _) = warning.range();
+                eprintln!("warning[{}]: {}", warning.code(), warning);
+                eprintln!(" --> {}:{}", exec.source_file_of(pos), pos);
+            }
+            if config.deny_warnings && !warnings.is_empty() {
+                eprintln!(
+                    "Compiling gave {} warning(s), which --deny-warnings doesn't allow.",
+                    warnings.len()
+                );
+                exit(3);
+            }
+            exec
+        }
         Err(err) => {
             eprintln!("An error occurred during compilation: {}.", err);
+            if let Some((pos, _)) = err.range() {
+                let file = match pos.file.checked_sub(1) {
+                    Some(module) => &module_files[module],
+                    None => &config.cahn_file,
+                };
+                eprintln!(" --> {}:{}", file, pos);
+            }
             exit(3);
         }
     };
 
+    // DROP UNUSED FUNCTIONS
+    if config.tree_shake {
+        if let Err(err) = executable.tree_shake() {
+            eprintln!("An error occurred during tree shaking: {}.", err);
+            exit(3);
+        }
+    }
+
     // PRINT BYTECODE
     if config.print_bytecode {
         println!("<BYTECODE>\n{:?}\n</BYTECODE>\n", executable);
     }
 
-    // RUN PROGRAM
-    if let Err(err) = VM::run_to_stdout(&executable) {
-        eprintln!("A runtime error occurred: {}", err);
-        exit(4);
+    // WRITE BYTECODE
+    if let Some(path) = &config.emit_bytecode {
+        if let Err(err) = fs::write(path, executable.serialize()) {
+            eprintln!("Couldn't write '{}' due to error: {}.", path, err);
+            exit(1);
+        }
+        return;
+    }
+
+    run(&executable, Some(&source_code), &config);
+}
+
+// loads an executable written by --emit-bytecode, and runs it without compiling anything
+fn run_bytecode_file(config: &Config) {
+    let bytes = if config.from_stdin {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+    } else {
+        fs::read(&config.cahn_file)
+    };
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!(
+                "Couldn't read '{}' due to error: {}.",
+                config.cahn_file, err
+            );
+            exit(1);
+        }
+    };
+
+    let executable = match Executable::deserialize(&bytes) {
+        Ok(exec) => exec,
+        Err(err) => {
+            eprintln!("Couldn't load '{}': {}.", config.cahn_file, err);
+            exit(3);
+        }
+    };
+
+    if config.print_bytecode {
+        println!("<BYTECODE>\n{:?}\n</BYTECODE>\n", executable);
+    }
+
+    // errors are shown with their line if the source is still around
+    let source_code = fs::read_to_string(&executable.source_file).ok();
+    run(&executable, source_code.as_deref(), config);
+}
+
+fn run(executable: &Executable, source_code: Option<&str>, config: &Config) {
+    let mut stdout = io::stdout();
+    let options = VMOptions {
+        track_peak_stack: config.print_peak_stack,
+        strict_concat: config.strict_concat,
+        runtime_warnings: config.runtime_warnings,
+        count_instructions: config.profile,
+        number_format: config.number_format,
+        ..VMOptions::default()
+    };
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let interrupt_flag = Arc::new(AtomicBool::new(false));
+    install_interrupt_handler(Arc::clone(&interrupt_flag));
+    let mut vm = VM::with_options(executable, &mut stdout, options)
+        .with_stdin(&mut stdin)
+        .with_interrupt_flag(interrupt_flag);
+
+    let start = Instant::now();
+    loop {
+        let step = vm.step();
+        for warning in vm.take_warnings() {
+            eprintln!("warning[{}]: {}", warning.code(), warning);
+            eprintln!(
+                " --> {}:{}",
+                executable.source_file_of(warning.pos()),
+                warning.pos()
+            );
+        }
+        match step {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(RuntimeError::Interrupted { .. }) => {
+                // the output so far is all the program gets to write
+                let _ = io::stdout().flush();
+                eprintln!("interrupted");
+                exit(INTERRUPTED_EXIT_CODE);
+            }
+            Err(err) => {
+                let file = vm
+                    .error_pos()
+                    .map(|pos| (pos, executable.source_file_of(pos)));
+                // the sources of imported modules are only read when an error is in them
+                let source = match file {
+                    Some((pos, _)) if pos.file == 0 => source_code.map(str::to_owned),
+                    Some((_, file)) => fs::read_to_string(file).ok(),
+                    None => None,
+                };
+                match (file, source) {
+                    (Some((pos, file)), Some(source)) => {
+                        eprint!("{}", err.render(&source, file, pos))
+                    }
+                    _ => eprintln!("A runtime error occurred: {}", err),
+                }
+                exit(4);
+            }
+        }
+    }
+
+    // PRINT PEAK STACK
+    if let Some(peak_stack) = vm.peak_stack() {
+        println!("<PEAK STACK>\n{}\n</PEAK STACK>", peak_stack);
+    }
+
+    // PRINT PROFILE
+    if let Some(counts) = vm.instruction_counts() {
+        println!("<PROFILE>");
+        print_profile(&counts, start.elapsed());
+        println!("</PROFILE>");
+    }
+
+    // PRINT HEAP REPORT
+    if config.heap_report_at_exit {
+        println!("<HEAP REPORT>\n{}\n</HEAP REPORT>", vm.heap_report());
+    }
+}
+
+// the instructions that ran, with a bar as long as their share of the most frequent one
+fn print_profile(counts: &[(Instruction, usize)], elapsed: Duration) {
+    const BAR_WIDTH: usize = 40;
+
+    let total: usize = counts.iter().map(|&(_, count)| count).sum();
+    println!("total time: {:.3?}", elapsed);
+    println!("instructions run: {}", total);
+
+    let most = counts.first().map_or(1, |&(_, count)| count);
+    for &(instruction, count) in counts {
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most));
+        println!(
+            "{:<20}{:>12} {:>6.2}% {}",
+            format!("{:?}", instruction),
+            count,
+            count as f64 * 100.0 / total as f64,
+            bar
+        );
     }
 }
@@ -0,0 +1,768 @@
+use std::{cmp::Ordering, hash::Hasher};
+
+use ahash::{AHashSet, AHasher};
+
+use crate::utils::hash_string;
+
+use super::{
+    error::{Result, RuntimeError},
+    mem_manager::{HeapValue, HeapValueHeader},
+    NativeFunction, Value, VmContext,
+};
+
+/// Natives that every script can call. Natives given by the host take precedence over these.
+pub const BUILTINS: &[NativeFunction] = &[
+    NativeFunction {
+        name: "hash",
+        arity: 1,
+        optional_args: 0,
+        func: hash,
+    },
+    NativeFunction {
+        name: "str",
+        arity: 1,
+        optional_args: 0,
+        func: str,
+    },
+    NativeFunction {
+        name: "repr",
+        arity: 1,
+        optional_args: 0,
+        func: repr,
+    },
+    NativeFunction {
+        name: "num",
+        arity: 1,
+        optional_args: 0,
+        func: num,
+    },
+    NativeFunction {
+        name: "write",
+        arity: 1,
+        optional_args: 0,
+        func: write,
+    },
+    NativeFunction {
+        name: "split_n",
+        arity: 3,
+        optional_args: 0,
+        func: split_n,
+    },
+    NativeFunction {
+        name: "compare",
+        arity: 2,
+        optional_args: 0,
+        func: compare,
+    },
+    NativeFunction {
+        name: "input",
+        arity: 0,
+        optional_args: 0,
+        func: input,
+    },
+    NativeFunction {
+        name: "starts_with",
+        arity: 2,
+        optional_args: 0,
+        func: starts_with,
+    },
+    NativeFunction {
+        name: "ends_with",
+        arity: 2,
+        optional_args: 0,
+        func: ends_with,
+    },
+    NativeFunction {
+        name: "type",
+        arity: 1,
+        optional_args: 0,
+        func: type_,
+    },
+    NativeFunction {
+        name: "len",
+        arity: 1,
+        optional_args: 0,
+        func: len,
+    },
+    NativeFunction {
+        name: "byte_len",
+        arity: 1,
+        optional_args: 0,
+        func: byte_len,
+    },
+    NativeFunction {
+        name: "resize",
+        arity: 3,
+        optional_args: 0,
+        func: resize,
+    },
+    NativeFunction {
+        name: "slice",
+        arity: 3,
+        optional_args: 1,
+        func: slice,
+    },
+    NativeFunction {
+        name: "random",
+        arity: 0,
+        optional_args: 1,
+        func: random,
+    },
+    NativeFunction {
+        name: "clock",
+        arity: 0,
+        optional_args: 0,
+        func: clock,
+    },
+    NativeFunction {
+        name: "sqrt",
+        arity: 1,
+        optional_args: 0,
+        func: sqrt,
+    },
+    NativeFunction {
+        name: "abs",
+        arity: 1,
+        optional_args: 0,
+        func: abs,
+    },
+    NativeFunction {
+        name: "floor",
+        arity: 1,
+        optional_args: 0,
+        func: floor,
+    },
+    NativeFunction {
+        name: "ceil",
+        arity: 1,
+        optional_args: 0,
+        func: ceil,
+    },
+    NativeFunction {
+        name: "round",
+        arity: 1,
+        optional_args: 0,
+        func: round,
+    },
+    NativeFunction {
+        name: "min",
+        arity: 2,
+        optional_args: 0,
+        func: min,
+    },
+    NativeFunction {
+        name: "max",
+        arity: 2,
+        optional_args: 0,
+        func: max,
+    },
+    NativeFunction {
+        name: "heap_report",
+        arity: 0,
+        optional_args: 0,
+        func: heap_report,
+    },
+    NativeFunction {
+        name: "to_base",
+        arity: 2,
+        optional_args: 0,
+        func: to_base,
+    },
+    NativeFunction {
+        name: "from_base",
+        arity: 2,
+        optional_args: 0,
+        func: from_base,
+    },
+];
+
+// numbers are floats, so whole numbers past this can't all be told apart
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+fn hash(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let hash = hash_value(args[0], &ctx.vm.exec.string_data)?;
+    // numbers are floats, so only 53 bits of the hash fit without rounding
+    Ok(Value::Number((hash >> 11) as f64))
+}
+
+// the value as a string, formatted the same way print shows it
+fn str(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let string = ctx.display_string(args[0]);
+    Ok(ctx.alloc_string(string))
+}
+
+// the value formatted so its type can be told apart, strings are quoted and escaped
+fn repr(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let string = ctx.repr_string(args[0]);
+    Ok(ctx.alloc_string(string))
+}
+
+// the number the string spells, or nil when it isn't one, so scripts can validate input.
+// surrounding whitespace is ignored, and numbers are returned as they are.
+fn num(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    if let Value::Number(_) = args[0] {
+        return Ok(args[0]);
+    }
+    let string = ctx.as_str(args[0]).ok_or_else(|| RuntimeError::TypeError {
+        message: format!(
+            "num expects a string or a number, but got '{}'",
+            args[0].type_name()
+        ),
+    })?;
+    // rust also parses words like "inf" and "NaN", which aren't numbers in cahn
+    Ok(match string.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Value::Number(number),
+        _ => Value::Nil,
+    })
+}
+
+// the heap report as text, for finding out what keeps a long running script's memory growing
+fn heap_report(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    let report = ctx.heap_report().to_string();
+    Ok(ctx.alloc_string(report))
+}
+
+// the whole number written in the base, with lowercase letters for digits past 9,
+// like to_base(255, 16) gives "ff".
+fn to_base(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let number = number_arg(ctx, args[0], "to_base")?;
+    let base = base_arg(ctx, args[1], "to_base")?;
+    if number.fract() != 0.0 || number.abs() > MAX_EXACT_INTEGER {
+        return Err(RuntimeError::TypeError {
+            message: format!(
+                "to_base expects a whole number of at most 2^53, but got '{}'",
+                ctx.display_string(args[0])
+            ),
+        });
+    }
+
+    let mut magnitude = number.abs() as u64;
+    let mut digits = vec![];
+    loop {
+        let digit = std::char::from_digit((magnitude % base as u64) as u32, base)
+            .expect("the digit is smaller than the base");
+        digits.push(digit);
+        magnitude /= base as u64;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if number < 0.0 {
+        digits.push('-');
+    }
+
+    let string = digits.into_iter().rev().collect();
+    Ok(ctx.alloc_string(string))
+}
+
+// the number the string spells in the base, or nil when it isn't one, like num.
+// letters can be upper or lower case, and surrounding whitespace is ignored.
+fn from_base(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let base = base_arg(ctx, args[1], "from_base")?;
+    let string = ctx.as_str(args[0]).ok_or_else(|| RuntimeError::TypeError {
+        message: format!(
+            "from_base expects a string, but got '{}'",
+            args[0].type_name()
+        ),
+    })?;
+    Ok(match i64::from_str_radix(string.trim(), base) {
+        Ok(number) if (number as f64).abs() <= MAX_EXACT_INTEGER => Value::Number(number as f64),
+        _ => Value::Nil,
+    })
+}
+
+fn base_arg(ctx: &VmContext, value: Value, name: &str) -> Result<u32> {
+    match value {
+        Value::Number(base) if (2.0..=36.0).contains(&base) && base.fract() == 0.0 => {
+            Ok(base as u32)
+        }
+        other => Err(RuntimeError::TypeError {
+            message: format!(
+                "{} expects a whole number from 2 to 36 as its base, but got '{}'",
+                name,
+                ctx.display_string(other)
+            ),
+        }),
+    }
+}
+
+// prints the value without a newline, returns nil
+fn write(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let string = ctx.display_string(args[0]);
+    ctx.write(&string)?;
+    Ok(Value::Nil)
+}
+
+// splits the string at the separator into at most n parts,
+// the last part keeps the rest of the string, separators and all.
+fn split_n(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let type_error = |message: String| RuntimeError::TypeError { message };
+
+    let string = ctx.as_str(args[0]).ok_or_else(|| {
+        type_error(format!(
+            "split_n expects a string to split, but got '{}'",
+            args[0].type_name()
+        ))
+    })?;
+    let separator = ctx.as_str(args[1]).ok_or_else(|| {
+        type_error(format!(
+            "split_n expects a string separator, but got '{}'",
+            args[1].type_name()
+        ))
+    })?;
+    if separator.is_empty() {
+        return Err(type_error("split_n's separator can't be empty".into()));
+    }
+    let count = match args[2] {
+        Value::Number(n) if n >= 1.0 && n.fract() == 0.0 => n as usize,
+        other => {
+            return Err(type_error(format!(
+                "split_n expects a whole number of parts of at least 1, but got '{}'",
+                ctx.display_string(other)
+            )))
+        }
+    };
+
+    let parts: Vec<String> = string.splitn(count, separator).map(str::to_owned).collect();
+    let parts = parts
+        .into_iter()
+        .map(|part| ctx.alloc_string(part))
+        .collect();
+    Ok(ctx.alloc_list(parts))
+}
+
+// -1, 0 or 1 when the first value is smaller, equal or larger, so scripts can sort with it.
+fn compare(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let ordering =
+        args[0]
+            .compare(&args[1], &ctx.vm.exec)
+            .ok_or_else(|| RuntimeError::TypeError {
+                message: format!(
+                    "can't compare '{}' and '{}'",
+                    ctx.display_string(args[0]),
+                    ctx.display_string(args[1])
+                ),
+            })?;
+
+    Ok(Value::Number(match ordering {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    }))
+}
+
+// the next line of the input, or nil at the end of the input
+fn input(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    Ok(match ctx.read_line()? {
+        Some(line) => ctx.alloc_string(line),
+        None => Value::Nil,
+    })
+}
+
+fn starts_with(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    has_affix(
+        ctx,
+        args,
+        "starts_with",
+        |string, prefix| string.starts_with(prefix),
+        |list, len| &list[..len],
+    )
+}
+
+fn ends_with(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    has_affix(
+        ctx,
+        args,
+        "ends_with",
+        |string, suffix| string.ends_with(suffix),
+        |list, len| &list[list.len() - len..],
+    )
+}
+
+// the name of the value's type, like typeof
+fn type_(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(match ctx.vm.type_name_literal(args[0]) {
+        Some(literal) => literal,
+        None => ctx.alloc_string(args[0].type_name()),
+    })
+}
+
+// the number of characters in a string, or elements in a list
+fn len(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let len = match (ctx.as_str(args[0]), ctx.as_list(args[0])) {
+        (Some(string), _) => string.chars().count(),
+        (_, Some(list)) => list.len(),
+        _ => {
+            return Err(RuntimeError::TypeError {
+                message: format!(
+                    "len expects a string or a list, but got '{}'",
+                    args[0].type_name()
+                ),
+            })
+        }
+    };
+    Ok(Value::Number(len as f64))
+}
+
+// the number of bytes the string takes up in utf-8
+fn byte_len(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    match ctx.as_str(args[0]) {
+        Some(string) => Ok(Value::Number(string.len() as f64)),
+        None => Err(RuntimeError::TypeError {
+            message: format!(
+                "byte_len expects a string, but got '{}'",
+                args[0].type_name()
+            ),
+        }),
+    }
+}
+
+// lists can't be resized to more elements than this, a larger length is most likely a bug
+// and would take gigabytes of memory.
+const MAX_RESIZE_LEN: usize = 1 << 24;
+
+// truncates the list to n elements, or extends it to n elements with the fill value.
+// the list is changed in place, and nil is returned.
+fn resize(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let len = match args[1] {
+        Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= MAX_RESIZE_LEN as f64 => {
+            n as usize
+        }
+        other => {
+            return Err(RuntimeError::TypeError {
+                message: format!(
+                    "resize expects a whole number of elements between 0 and {}, but got '{}'",
+                    MAX_RESIZE_LEN,
+                    ctx.display_string(other)
+                ),
+            })
+        }
+    };
+    let fill = args[2];
+    let type_name = args[0].type_name();
+    // the fill value is an argument, so it stays rooted until it's in the list
+    let list = ctx
+        .as_list_mut(args[0])
+        .ok_or_else(|| RuntimeError::TypeError {
+            message: format!("resize expects a list, but got '{}'", type_name),
+        })?;
+    list.resize(len, fill);
+    Ok(Value::Nil)
+}
+
+// a new list of every step-th element from start up to, but not including, end.
+// negative indices count from the end, and indices past either end are clamped, like python.
+// a negative step goes backwards, so slice(list, nil, nil, -1) is the list reversed.
+// nil for start or end means the end of the list the step starts or stops at.
+fn slice(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let step = match args.get(3) {
+        None => 1,
+        Some(&step) => match slice_index(ctx, step, "step")? {
+            Some(0) => {
+                return Err(RuntimeError::TypeError {
+                    message: "slice expects a step other than 0".into(),
+                })
+            }
+            Some(step) => step,
+            None => 1,
+        },
+    };
+    let start = slice_index(ctx, args[1], "start")?;
+    let end = slice_index(ctx, args[2], "end")?;
+
+    let list = ctx
+        .as_list(args[0])
+        .ok_or_else(|| RuntimeError::TypeError {
+            message: format!("slice expects a list, but got '{}'", args[0].type_name()),
+        })?;
+    let len = list.len() as i64;
+
+    // indices are clamped to the elements, or one past them in the direction of the step
+    let (lowest, highest) = if step > 0 { (0, len) } else { (-1, len - 1) };
+    let resolve = |index: Option<i64>, default: i64| match index {
+        None => default,
+        Some(index) if index < 0 => (index + len).clamp(lowest, highest),
+        Some(index) => index.clamp(lowest, highest),
+    };
+    let (start, end) = if step > 0 {
+        (resolve(start, 0), resolve(end, len))
+    } else {
+        (resolve(start, len - 1), resolve(end, -1))
+    };
+
+    let mut elements = vec![];
+    let mut index = start;
+    while (step > 0 && index < end) || (step < 0 && index > end) {
+        elements.push(list[index as usize]);
+        index += step;
+    }
+    Ok(ctx.alloc_list(elements))
+}
+
+// an index or step of slice, which is a whole number or nil
+fn slice_index(ctx: &VmContext, value: Value, name: &str) -> Result<Option<i64>> {
+    match value {
+        Value::Nil => Ok(None),
+        // past this, the index is clamped to the list anyway
+        Value::Number(n) if n.fract() == 0.0 => {
+            Ok(Some(n.clamp(-MAX_EXACT_INTEGER, MAX_EXACT_INTEGER) as i64))
+        }
+        other => Err(RuntimeError::TypeError {
+            message: format!(
+                "slice expects a whole number or nil as its {}, but got '{}'",
+                name,
+                ctx.display_string(other)
+            ),
+        }),
+    }
+}
+
+// a float in [0, 1), or with a bound n, a whole number in [0, n)
+fn random(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let bound = match args.first() {
+        None => return Ok(Value::Number(ctx.random())),
+        Some(Value::Number(n)) if *n > 0.0 && n.is_finite() => *n,
+        Some(other) => {
+            return Err(RuntimeError::TypeError {
+                message: format!(
+                    "random expects a positive number as its bound, but got '{}'",
+                    ctx.display_string(*other)
+                ),
+            })
+        }
+    };
+    Ok(Value::Number((ctx.random() * bound).floor()))
+}
+
+fn clock(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(ctx.clock()))
+}
+
+fn sqrt(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number_arg(ctx, args[0], "sqrt")?.sqrt()))
+}
+
+fn abs(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number_arg(ctx, args[0], "abs")?.abs()))
+}
+
+fn floor(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number_arg(ctx, args[0], "floor")?.floor()))
+}
+
+fn ceil(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number_arg(ctx, args[0], "ceil")?.ceil()))
+}
+
+// halfway cases are rounded away from zero
+fn round(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number_arg(ctx, args[0], "round")?.round()))
+}
+
+fn min(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let a = number_arg(ctx, args[0], "min")?;
+    let b = number_arg(ctx, args[1], "min")?;
+    Ok(Value::Number(a.min(b)))
+}
+
+fn max(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let a = number_arg(ctx, args[0], "max")?;
+    let b = number_arg(ctx, args[1], "max")?;
+    Ok(Value::Number(a.max(b)))
+}
+
+fn number_arg(ctx: &VmContext, value: Value, name: &str) -> Result<f64> {
+    match value {
+        Value::Number(n) => Ok(n),
+        other => Err(RuntimeError::TypeError {
+            message: format!(
+                "{} expects a number, but got '{}'",
+                name,
+                ctx.display_string(other)
+            ),
+        }),
+    }
+}
+
+// whether the string or list has the other one as its prefix or suffix,
+// list elements are compared like '==' compares them.
+// `lists` gives the part of the list that has to equal an affix of the given length.
+fn has_affix(
+    ctx: &VmContext,
+    args: &[Value],
+    name: &str,
+    strings: fn(&str, &str) -> bool,
+    lists: fn(&[Value], usize) -> &[Value],
+) -> Result<Value> {
+    if let (Some(string), Some(affix)) = (ctx.as_str(args[0]), ctx.as_str(args[1])) {
+        return Ok(Value::Bool(strings(string, affix)));
+    }
+    if let (Some(list), Some(affix)) = (ctx.as_list(args[0]), ctx.as_list(args[1])) {
+        let has_affix = list.len() >= affix.len()
+            && lists(list, affix.len())
+                .iter()
+                .zip(affix)
+                .all(|(elem, affix_elem)| elem.cahn_eq(affix_elem, &ctx.vm.exec));
+        return Ok(Value::Bool(has_affix));
+    }
+    Err(RuntimeError::TypeError {
+        message: format!(
+            "{} expects two strings or two lists, but got '{}' and '{}'",
+            name,
+            args[0].type_name(),
+            args[1].type_name()
+        ),
+    })
+}
+
+enum Work {
+    Hash(Value),
+    // the list is done, so meeting it again is no longer a cycle
+    LeaveList(*mut HeapValueHeader),
+}
+
+// each type gets its own tag, so values of different types that contain the same bytes
+// don't hash the same.
+const TAG_NIL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_NUMBER: u8 = 2;
+const TAG_STRING: u8 = 3;
+const TAG_LIST: u8 = 4;
+const TAG_CYCLE: u8 = 5;
+
+/// Hashes a value by its contents, equal strings hash the same no matter where they live.
+/// Lists are hashed with an explicit work list, so deeply nested lists can't overflow the stack.
+pub(super) fn hash_value(value: Value, string_data: &str) -> Result<u64> {
+    let mut hasher = AHasher::default();
+    let mut work = vec![Work::Hash(value)];
+    let mut open_lists = AHashSet::new();
+
+    while let Some(item) = work.pop() {
+        let value = match item {
+            Work::Hash(value) => value,
+            Work::LeaveList(ptr) => {
+                open_lists.remove(&ptr);
+                continue;
+            }
+        };
+
+        match value {
+            Value::Nil => hasher.write_u8(TAG_NIL),
+
+            Value::Bool(b) => {
+                hasher.write_u8(TAG_BOOL);
+                hasher.write_u8(b as u8);
+            }
+
+            Value::Number(num) => {
+                // 0 and -0 are equal, and all NaNs should hash the same
+                let num = if num == 0.0 {
+                    0.0
+                } else if num.is_nan() {
+                    f64::NAN
+                } else {
+                    num
+                };
+                hasher.write_u8(TAG_NUMBER);
+                hasher.write_u64(num.to_bits());
+            }
+
+            Value::StringLiteral {
+                start_index,
+                end_index,
+            } => {
+                hasher.write_u8(TAG_STRING);
+                hasher.write_u64(hash_string(
+                    &string_data[start_index as usize..end_index as usize],
+                ));
+            }
+
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::String(string) => {
+                    hasher.write_u8(TAG_STRING);
+                    hasher.write_u64(hash_string(string));
+                }
+
+                HeapValue::List(list) => {
+                    // a list containing itself would otherwise be hashed forever
+                    if !open_lists.insert(ptr) {
+                        hasher.write_u8(TAG_CYCLE);
+                        continue;
+                    }
+
+                    hasher.write_u8(TAG_LIST);
+                    hasher.write_usize(list.len());
+
+                    work.push(Work::LeaveList(ptr));
+                    work.extend(list.iter().rev().map(|val| Work::Hash(*val)));
+                }
+
+                HeapValue::Opaque { .. }
+                | HeapValue::Closure { .. }
+                | HeapValue::Upvalue(_)
+                | HeapValue::Iterator { .. }
+                | HeapValue::Record { .. } => return Err(unhashable(value)),
+            },
+
+            Value::Function { .. } | Value::NativeFunction { .. } | Value::ReturnAdress { .. } => {
+                return Err(unhashable(value))
+            }
+        }
+    }
+
+    Ok(hasher.finish())
+}
+
+fn unhashable(value: Value) -> RuntimeError {
+    RuntimeError::TypeError {
+        message: format!("can't hash a value of type '{}'", value.type_name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    fn alloc_list(list: Vec<Value>) -> *mut HeapValueHeader {
+        Box::into_raw(Box::new(HeapValueHeader {
+            is_marked: false,
+            next_heap_val: ptr::null_mut(),
+            alloc_site: None,
+            payload: HeapValue::List(list),
+        }))
+    }
+
+    #[test]
+    fn cyclic_list_can_be_hashed() {
+        let list = alloc_list(vec![Value::Number(1.0)]);
+        if let HeapValue::List(elements) = unsafe { &mut (*list).payload } {
+            elements.push(Value::Heap(list));
+        }
+
+        let first = hash_value(Value::Heap(list), "").unwrap();
+        let second = hash_value(Value::Heap(list), "").unwrap();
+        assert_eq!(first, second);
+
+        unsafe { drop(Box::from_raw(list)) };
+    }
+
+    #[test]
+    fn shared_list_is_not_a_cycle() {
+        let inner = alloc_list(vec![Value::Number(1.0)]);
+        let shared = alloc_list(vec![Value::Heap(inner), Value::Heap(inner)]);
+
+        let inner_copy = alloc_list(vec![Value::Number(1.0)]);
+        let copied = alloc_list(vec![Value::Heap(inner), Value::Heap(inner_copy)]);
+
+        assert_eq!(
+            hash_value(Value::Heap(shared), "").unwrap(),
+            hash_value(Value::Heap(copied), "").unwrap()
+        );
+
+        for list in [inner, shared, inner_copy, copied] {
+            unsafe { drop(Box::from_raw(list)) };
+        }
+    }
+}
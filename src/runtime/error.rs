@@ -2,6 +2,8 @@ use std::io;
 
 use thiserror::Error;
 
+use crate::{compiler::lexical_analysis::TokenPos, error::render_snippet};
+
 #[derive(Debug, Error)]
 pub enum RuntimeError {
     #[error("TypeError: {}", .message)]
@@ -10,8 +12,53 @@ pub enum RuntimeError {
     #[error("IndexOufOfBounds: attempted to element at index {}, but list only has length {}", .index, .len)]
     IndexOutOfBounds { index: f64, len: usize },
 
+    #[error("ArityMismatch: {} expected {} arguments, but got {}", .name, .expected, .got)]
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+
+    #[error("UnpackMismatch: expected {} values, but got {}", .expected, .got)]
+    UnpackMismatch { expected: usize, got: usize },
+
+    #[error("UndefinedVariable: '{}' is not defined", .name)]
+    UndefinedVariable { name: String },
+
+    #[error("UndefinedField: {} has no field '{}'", .record, .field)]
+    UndefinedField { record: String, field: String },
+
+    #[error("AssertionFailed at {}: {}", .pos, .message)]
+    AssertionFailed { message: String, pos: TokenPos },
+
+    #[error("Breakpoint at {}", .pos)]
+    Breakpoint { pos: TokenPos },
+
+    /// Bytecode that got past the verifier did something the VM can't carry out.
+    #[error("InvalidBytecode: {}", .message)]
+    InvalidBytecode { message: String },
+
+    #[error("StackOverflow: more than {} nested function calls", .max)]
+    StackOverflow { max: usize },
+
+    /// The interrupt flag was set, see `VM::with_interrupt_flag`.
+    /// What the program printed before is kept, the program just doesn't go on.
+    #[error("Interrupted after {} instructions", .instructions_run)]
+    Interrupted { instructions_run: usize },
+
     #[error("couldn't write to stdout: {:?}", .0)]
     StdoutWriteError(#[from] io::Error),
+
+    #[error("couldn't read from stdin: {:?}", .0)]
+    StdinReadError(io::Error),
+}
+
+impl RuntimeError {
+    /// The error along with the line of the source it happened on, and a caret under where
+    /// on the line, like `error::render_snippet`. The position is the one `VM::error_pos` gives.
+    pub fn render(&self, source: &str, source_file: &str, pos: TokenPos) -> String {
+        render_snippet(&self.to_string(), source, source_file, pos)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, RuntimeError>;
@@ -1,4 +1,6 @@
 use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
     fmt::{self, Write},
     iter, ptr,
 };
@@ -6,24 +8,76 @@ use std::{
 #[cfg(feature = "string_interning")]
 use {crate::utils::hash_string, intmap::IntMap};
 
-use super::{Value, VM};
+use super::{repr::MAX_DEPTH, OpaqueValue, Value, VM};
+use crate::compiler::lexical_analysis::TokenPos;
+
+// how many of the largest lists and strings a heap report lists
+const LARGEST_IN_REPORT: usize = 10;
 
 #[derive(Debug)]
 pub enum HeapValue {
     String(String),
     List(Vec<Value>),
+    Opaque {
+        id: u32,
+        value: Box<dyn OpaqueValue>,
+    },
+    /// A function along with the variables it captured, which are all upvalues.
+    Closure {
+        function_index: u32,
+        upvalues: Vec<*mut HeapValueHeader>,
+    },
+    Upvalue(Upvalue),
+    /// The fields of a struct's record, in the order of the struct's layout.
+    Record {
+        struct_index: u32,
+        fields: Vec<Value>,
+    },
+    /// The state of a for-in loop, the container it goes through and the position it's at.
+    /// For strings, the index is a byte offset, so it always lands on a char boundary.
+    Iterator {
+        container: Value,
+        index: usize,
+    },
+}
+
+impl HeapValue {
+    /// The kind of heap value, like "list", which heap reports group them by.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            HeapValue::String(_) => "string",
+            HeapValue::List(_) => "list",
+            HeapValue::Opaque { .. } => "opaque",
+            HeapValue::Closure { .. } => "closure",
+            HeapValue::Upvalue(_) => "upvalue",
+            HeapValue::Record { .. } => "record",
+            HeapValue::Iterator { .. } => "iterator",
+        }
+    }
+}
+
+/// A captured variable, shared by every closure that captured it.
+#[derive(Debug, Clone, Copy)]
+pub enum Upvalue {
+    /// The variable is still on the stack, at the given index.
+    Open(usize),
+    /// The variable went out of scope, so it's kept here instead.
+    Closed(Value),
 }
 
 #[derive(Debug)]
 pub struct HeapValueHeader {
     pub is_marked: bool,
     pub next_heap_val: *mut HeapValueHeader,
+    /// Where in the source the instruction that allocated the value is,
+    /// None if it was allocated before the program started.
+    pub alloc_site: Option<TokenPos>,
     pub payload: HeapValue,
 }
 
 impl HeapValueHeader {
     pub fn fmt<'a, 'b>(&'a self, vm: &'a VM<'b>) -> FormatableHeapValue<'a, 'b> {
-        FormatableHeapValue { value: &self, vm }
+        FormatableHeapValue { value: self, vm }
     }
 }
 
@@ -34,19 +88,120 @@ pub struct FormatableHeapValue<'a, 'b> {
 
 impl<'a, 'b> fmt::Display for FormatableHeapValue<'a, 'b> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(match self.value.payload {
-            HeapValue::String(ref string) => f.write_str(&string)?,
+        self.value.write(self.vm, f, &mut vec![])
+    }
+}
+
+impl HeapValueHeader {
+    // a list or record that contains itself, or is nested too deep, is shown as '...',
+    // like repr does. open holds the lists and records that are being written.
+    pub(super) fn write(
+        &self,
+        vm: &VM,
+        f: &mut fmt::Formatter<'_>,
+        open: &mut Vec<*const HeapValueHeader>,
+    ) -> fmt::Result {
+        match self.payload {
+            HeapValue::String(ref string) => return f.write_str(string),
+            HeapValue::Opaque { id, ref value } => {
+                return f.write_fmt(format_args!("<{} #{}>", value.display_name(), id))
+            }
+            HeapValue::Closure { function_index, .. } => {
+                return Value::Function { function_index }.write(vm, f, open)
+            }
+            HeapValue::Upvalue(_) => return f.write_str("<upvalue>"),
+            HeapValue::Iterator { .. } => return f.write_str("<iterator>"),
+            HeapValue::List(_) | HeapValue::Record { .. } => {}
+        }
+
+        let ptr: *const HeapValueHeader = self;
+        if open.contains(&ptr) || open.len() >= MAX_DEPTH {
+            return f.write_str("...");
+        }
+        open.push(ptr);
+
+        match self.payload {
             HeapValue::List(ref list) => {
                 f.write_char('[')?;
                 for (index, val) in list.iter().enumerate() {
-                    fmt::Display::fmt(&val.fmt(self.vm), f)?;
+                    val.write(vm, f, open)?;
                     if index + 1 < list.len() {
                         f.write_str(", ")?;
                     }
                 }
                 f.write_char(']')?;
             }
-        })
+            HeapValue::Record {
+                struct_index,
+                ref fields,
+            } => {
+                let exec = &vm.exec;
+                let layout = &exec.structs[struct_index as usize];
+                f.write_fmt(format_args!("{}(", layout.name))?;
+                for (index, (&field, val)) in layout.fields.iter().zip(fields).enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_fmt(format_args!("{}: ", exec.field_names[field as usize]))?;
+                    val.write(vm, f, open)?;
+                }
+                f.write_char(')')?;
+            }
+            _ => unreachable!("only lists and records are left"),
+        }
+
+        open.pop();
+        Ok(())
+    }
+}
+
+/// What's on the heap at one point of a run, see `VM::heap_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeapReport {
+    /// How many heap values of each kind the program can still reach.
+    pub live_by_kind: BTreeMap<&'static str, usize>,
+    /// The reachable lists and strings with the most elements or bytes, the largest first.
+    pub largest: Vec<HeapEntry>,
+    /// How many heap values nothing reaches anymore, but weren't collected yet.
+    pub unreachable: usize,
+}
+
+/// A list or string in a heap report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapEntry {
+    pub kind: &'static str,
+    /// The elements of a list, or the bytes of a string.
+    pub size: usize,
+    pub alloc_site: Option<TokenPos>,
+}
+
+impl HeapReport {
+    /// How many reachable heap values there are of the kind.
+    pub fn live(&self, kind: &str) -> usize {
+        self.live_by_kind.get(kind).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for HeapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "live:")?;
+        for (kind, count) in &self.live_by_kind {
+            writeln!(f, "    {}: {}", kind, count)?;
+        }
+        writeln!(f, "largest:")?;
+        for entry in &self.largest {
+            let unit = if entry.kind == "list" {
+                "elements"
+            } else {
+                "bytes"
+            };
+            write!(f, "    {} of {} {}", entry.kind, entry.size, unit)?;
+            match entry.alloc_site {
+                Some(pos) => writeln!(f, ", allocated at {}", pos)?,
+                None => writeln!(f)?,
+            }
+        }
+        write!(f, "unreachable: {}", self.unreachable)
     }
 }
 
@@ -59,6 +214,7 @@ pub struct MemoryManager {
 
     total_allocs: u32,
     total_deallocs: u32,
+    total_opaques: u32,
 }
 
 impl MemoryManager {
@@ -67,6 +223,7 @@ impl MemoryManager {
             heap_vals: ptr::null_mut(),
             total_allocs: 0,
             total_deallocs: 0,
+            total_opaques: 0,
             #[cfg(feature = "string_interning")]
             intern_string_map: IntMap::new(),
         }
@@ -102,16 +259,96 @@ impl MemoryManager {
         val
     }
 
+    /// The number of heap values allocated so far.
+    pub fn total_allocs(&self) -> u32 {
+        self.total_allocs
+    }
+
     pub fn alloc_list<'a, 'b, 'c>(&'a mut self, vm: &'b VM<'c>, init_cap: usize) -> Value {
         let backing_vec = Vec::with_capacity(init_cap);
         let ptr = self.alloc(vm, HeapValue::List(backing_vec));
         Value::Heap(ptr)
     }
 
+    pub fn alloc_closure<'a, 'b, 'c>(&'a mut self, vm: &'b VM<'c>, function_index: u32) -> Value {
+        let ptr = self.alloc(
+            vm,
+            HeapValue::Closure {
+                function_index,
+                upvalues: vec![],
+            },
+        );
+        Value::Heap(ptr)
+    }
+
+    pub fn alloc_record<'a, 'b, 'c>(
+        &'a mut self,
+        vm: &'b VM<'c>,
+        struct_index: u32,
+        field_count: usize,
+    ) -> Value {
+        let ptr = self.alloc(
+            vm,
+            HeapValue::Record {
+                struct_index,
+                fields: Vec::with_capacity(field_count),
+            },
+        );
+        Value::Heap(ptr)
+    }
+
+    pub fn alloc_iterator<'a, 'b, 'c>(&'a mut self, vm: &'b VM<'c>, container: Value) -> Value {
+        let ptr = self.alloc(
+            vm,
+            HeapValue::Iterator {
+                container,
+                index: 0,
+            },
+        );
+        Value::Heap(ptr)
+    }
+
+    pub fn alloc_upvalue<'a, 'b, 'c>(
+        &'a mut self,
+        vm: &'b VM<'c>,
+        slot: usize,
+    ) -> *mut HeapValueHeader {
+        self.alloc(vm, HeapValue::Upvalue(Upvalue::Open(slot)))
+    }
+
+    pub fn alloc_opaque<'a, 'b, 'c>(
+        &'a mut self,
+        vm: &'b VM<'c>,
+        value: Box<dyn OpaqueValue>,
+    ) -> Value {
+        let id = self.total_opaques;
+        self.total_opaques += 1;
+        let ptr = self.alloc(vm, HeapValue::Opaque { id, value });
+        Value::Heap(ptr)
+    }
+
     fn alloc<'a, 'b, 'c>(&'a mut self, vm: &'b VM<'c>, val: HeapValue) -> *mut HeapValueHeader {
+        let val_pointer = self.link(val, vm.instruction_site());
+
+        if self.should_gc() {
+            // println!("=============GC START==========");
+            // println!("Stack:");
+            // vm.stack
+            //     .iter()
+            //     .for_each(|val| println!("    {}: {:?}", val.fmt(&vm), val));
+
+            let roots = roots(vm).chain(iter::once(val_pointer));
+            self.gc(roots);
+        }
+        val_pointer
+    }
+
+    // moves the value to the heap and adds it to the list of heap values, without collecting.
+    fn link(&mut self, val: HeapValue, alloc_site: Option<TokenPos>) -> *mut HeapValueHeader {
         let heap_val = HeapValueHeader {
             is_marked: false,
             next_heap_val: self.heap_vals,
+            alloc_site,
             payload: val,
         };
         // move to heap
@@ -123,25 +360,6 @@ impl MemoryManager {
 
         // println!("MemoryManager allocated: {:?}", unsafe { &*val_pointer });
 
-        if self.should_gc() {
-            // println!("=============GC START==========");
-            // println!("Stack:");
-            // vm.stack
-            //     .iter()
-            //     .for_each(|val| println!("    {}: {:?}", val.fmt(&vm), val));
-
-            let roots = vm
-                .stack
-                .iter()
-                .map(|val| match val {
-                    Value::Heap(ptr) => Some(*ptr),
-                    _ => None,
-                })
-                .flatten()
-                .chain(iter::once(val_pointer));
-
-            self.gc(roots);
-        }
         val_pointer
     }
 
@@ -161,12 +379,7 @@ impl MemoryManager {
         // }
 
         // println!("Marking...");
-        // let mut mark_count = 0;
-        roots.for_each(|root| {
-            self.mark(root);
-            // mark_count += 1;
-        });
-        // println!("Total marked: {}", mark_count);
+        self.mark(roots);
         // println!("Sweeping...");
         // let tdallocs = self.total_deallocs;
         self.sweep();
@@ -174,31 +387,92 @@ impl MemoryManager {
         // println!("=============GC DONE==========");
     }
 
-    fn mark(&mut self, ptr: *mut HeapValueHeader) {
-        unsafe {
-            // return if we're already marked, so we don't get
-            // infinite recursion in case of reference cycles
-            if (*ptr).is_marked {
-                return;
+    /// Marks what the VM can reach to count the heap values, like a collection would,
+    /// but without freeing anything. Every mark is cleared again afterwards,
+    /// so the next collection starts from a clean heap.
+    pub fn heap_report(&mut self, vm: &VM) -> HeapReport {
+        self.mark(roots(vm));
+
+        let mut report = HeapReport {
+            live_by_kind: BTreeMap::new(),
+            largest: vec![],
+            unreachable: 0,
+        };
+        let mut ptr = self.heap_vals;
+        while !ptr.is_null() {
+            let heap_val = unsafe { &mut *ptr };
+            if heap_val.is_marked {
+                heap_val.is_marked = false;
+                let kind = heap_val.payload.kind_name();
+                *report.live_by_kind.entry(kind).or_insert(0) += 1;
+
+                let size = match heap_val.payload {
+                    HeapValue::List(ref list) => Some(list.len()),
+                    HeapValue::String(ref string) => Some(string.len()),
+                    _ => None,
+                };
+                if let Some(size) = size {
+                    report.largest.push(HeapEntry {
+                        kind,
+                        size,
+                        alloc_site: heap_val.alloc_site,
+                    });
+                }
+            } else {
+                report.unreachable += 1;
             }
-            (*ptr).is_marked = true;
-            // println!("MemoryManager marked: {:?}", (*ptr).payload);
-
-            // mark children
-            match (*ptr).payload {
-                // strings don't have any children
-                HeapValue::String(_) => {}
-
-                // mark heap all values a list contains
-                HeapValue::List(ref list) => list
-                    .iter()
-                    .map(|val| match val {
-                        Value::Heap(ptr) => Some(ptr),
+            ptr = heap_val.next_heap_val;
+        }
+
+        // the heap list starts with the newest value, the sort keeps that order for equal sizes
+        report.largest.sort_by_key(|entry| Reverse(entry.size));
+        report.largest.truncate(LARGEST_IN_REPORT);
+        report
+    }
+
+    // marks every heap value reachable from the roots.
+    // values that still need to be marked go on a work list instead of the call stack,
+    // so deeply nested values can't overflow the stack.
+    fn mark<T: Iterator<Item = *mut HeapValueHeader>>(&mut self, roots: T) {
+        let mut work_list: Vec<*mut HeapValueHeader> = roots.collect();
+
+        while let Some(ptr) = work_list.pop() {
+            unsafe {
+                // skip the value if we've already marked it, so we don't
+                // loop forever in case of reference cycles
+                if (*ptr).is_marked {
+                    continue;
+                }
+                (*ptr).is_marked = true;
+                // println!("MemoryManager marked: {:?}", (*ptr).payload);
+
+                // queue the children, every kind of heap value has to add its children here
+                match (*ptr).payload {
+                    // strings and opaque values don't have any children
+                    HeapValue::String(_) | HeapValue::Opaque { .. } => {}
+
+                    // queue all heap values a list or record contains
+                    HeapValue::List(ref list)
+                    | HeapValue::Record {
+                        fields: ref list, ..
+                    } => work_list.extend(list.iter().filter_map(|val| match val {
+                        Value::Heap(ptr) => Some(*ptr),
                         _ => None,
-                    })
-                    .flatten()
-                    .for_each(|ptr| self.mark(*ptr)),
-            };
+                    })),
+
+                    HeapValue::Closure { ref upvalues, .. } => work_list.extend(upvalues),
+
+                    // open upvalues point into the stack, which is a root already
+                    HeapValue::Upvalue(Upvalue::Closed(Value::Heap(ptr))) => work_list.push(ptr),
+                    HeapValue::Upvalue(_) => {}
+
+                    HeapValue::Iterator {
+                        container: Value::Heap(ptr),
+                        ..
+                    } => work_list.push(ptr),
+                    HeapValue::Iterator { .. } => {}
+                };
+            }
         }
     }
 
@@ -210,7 +484,7 @@ impl MemoryManager {
         #[cfg(feature = "string_interning")]
         if let HeapValue::String(ref str) = bbox.payload {
             // println!("deallocing: {}", str);
-            let hash = hash_string(&str);
+            let hash = hash_string(str);
             let removed_value = self.intern_string_map.remove(hash);
             assert!(
                     removed_value.is_some(),
@@ -290,6 +564,18 @@ impl MemoryManager {
     }
 }
 
+// the values the VM can use directly, everything reachable from them is alive
+fn roots<'v>(vm: &'v VM) -> impl Iterator<Item = *mut HeapValueHeader> + 'v {
+    vm.stack
+        .iter()
+        .chain(vm.globals.iter().flatten())
+        .filter_map(|val| match val {
+            Value::Heap(ptr) => Some(*ptr),
+            _ => None,
+        })
+        .chain(vm.open_upvalues.iter().copied())
+}
+
 impl Drop for MemoryManager {
     fn drop(&mut self) {
         println!(
@@ -317,3 +603,35 @@ impl Drop for MemoryManager {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use super::{HeapValue, MemoryManager};
+    use crate::runtime::Value;
+
+    #[test]
+    fn gc_stress_deeply_nested_list() {
+        let depth = 100_000;
+        let mut mem_manager = MemoryManager::new();
+
+        // [[[...[]...]]], linked directly so it isn't collected while it's being built
+        let mut list = mem_manager.link(HeapValue::List(vec![]), None);
+        for _ in 0..depth {
+            list = mem_manager.link(HeapValue::List(vec![Value::Heap(list)]), None);
+        }
+
+        // a cycle at the outermost list, marking has to stop at already marked values
+        let cycle = mem_manager.link(HeapValue::List(vec![Value::Heap(list)]), None);
+        if let HeapValue::List(ref mut first) = unsafe { &mut (*list).payload } {
+            first.push(Value::Heap(cycle));
+        }
+
+        mem_manager.gc(iter::once(list));
+        assert_eq!(mem_manager.total_deallocs, 0);
+
+        mem_manager.gc(iter::empty());
+        assert_eq!(mem_manager.total_deallocs, depth + 2);
+    }
+}
@@ -1,7 +1,20 @@
+pub mod builtins;
 pub mod error;
 mod mem_manager;
+pub mod native;
+mod number_format;
+pub mod opaque;
+pub mod owned_value;
+pub mod repr;
 pub mod value;
 pub mod vm;
+pub mod warning;
 
+pub use mem_manager::{HeapEntry, HeapReport};
+pub use native::{NativeFunction, VmContext};
+pub use number_format::NumberFormat;
+pub use opaque::OpaqueValue;
+pub use owned_value::OwnedValue;
 pub use value::Value;
-pub use vm::VM;
+pub use vm::{RunOutput, RunStats, VMOptions, VM};
+pub use warning::RuntimeWarning;
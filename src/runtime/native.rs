@@ -0,0 +1,183 @@
+use std::{
+    any::{self, Any},
+    fmt,
+};
+
+use super::{
+    error::{Result, RuntimeError},
+    mem_manager::HeapValue,
+    HeapReport, OpaqueValue, Value, VM,
+};
+
+pub type NativeFn = fn(&mut VmContext, &[Value]) -> Result<Value>;
+
+/// A function implemented by the host, that scripts can call by name.
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    /// How many arguments calls can give on top of `arity`,
+    /// the native can tell which were given by the length of its arguments.
+    pub optional_args: usize,
+    pub func: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: usize, func: NativeFn) -> Self {
+        NativeFunction {
+            name,
+            arity,
+            optional_args: 0,
+            func,
+        }
+    }
+
+    pub fn with_optional_args(mut self, optional_args: usize) -> Self {
+        self.optional_args = optional_args;
+        self
+    }
+
+    /// Whether calls can give the native that many arguments.
+    pub fn accepts(&self, arg_count: usize) -> bool {
+        arg_count >= self.arity && arg_count <= self.arity + self.optional_args
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "NativeFunction(name: {}, arity: {})",
+            self.name, self.arity
+        ))
+    }
+}
+
+/// The part of the VM natives have access to.
+pub struct VmContext<'v, 'a> {
+    pub(super) vm: &'v mut VM<'a>,
+}
+
+impl<'v, 'a> fmt::Debug for VmContext<'v, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("VmContext({:?})", self.vm))
+    }
+}
+
+impl<'v, 'a> VmContext<'v, 'a> {
+    pub(super) fn new(vm: &'v mut VM<'a>) -> Self {
+        VmContext { vm }
+    }
+
+    pub fn alloc_opaque<T: OpaqueValue>(&mut self, value: T) -> Value {
+        self.vm.alloc_opaque(Box::new(value))
+    }
+
+    pub fn alloc_string(&mut self, string: String) -> Value {
+        self.vm.alloc_string(string)
+    }
+
+    /// Allocates a list of the given values. Values allocated by the native are kept alive
+    /// until it returns, so they can be put in the list.
+    pub fn alloc_list(&mut self, elements: Vec<Value>) -> Value {
+        self.vm.alloc_list(elements)
+    }
+
+    /// The text of a string value, or None if the value isn't a string.
+    pub fn as_str(&self, value: Value) -> Option<&str> {
+        match value {
+            Value::StringLiteral {
+                start_index,
+                end_index,
+            } => Some(&self.vm.exec.string_data[start_index as usize..end_index as usize]),
+            // the value is an argument, or was allocated by this native,
+            // so it's rooted for as long as the native runs.
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::String(string) => Some(string),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The elements of a list value, or None if the value isn't a list.
+    pub fn as_list(&self, value: Value) -> Option<&[Value]> {
+        match value {
+            // rooted for as long as the native runs, like the strings of as_str
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::List(list) => Some(list),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The elements of a list value, which the native can change, or None if the value isn't a list.
+    /// Values put in the list have to be rooted, like the native's arguments.
+    pub fn as_list_mut(&mut self, value: Value) -> Option<&mut Vec<Value>> {
+        match value {
+            Value::Heap(ptr) => match unsafe { &mut (*ptr).payload } {
+                HeapValue::List(list) => Some(list),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The value as `print` would show it.
+    pub fn display_string(&self, value: Value) -> String {
+        value.fmt(self.vm).to_string()
+    }
+
+    /// The value as the `repr` builtin shows it, with strings quoted.
+    pub fn repr_string(&self, value: Value) -> String {
+        value.repr(self.vm).to_string()
+    }
+
+    /// Writes to the VM's output, without adding a newline.
+    pub fn write(&mut self, text: &str) -> Result<()> {
+        self.vm.write_output(text)
+    }
+
+    /// Reads a line from the VM's input, without its line ending.
+    /// Returns None once the input is at its end.
+    pub fn read_line(&mut self) -> Result<Option<String>> {
+        self.vm.read_input_line()
+    }
+
+    /// A random number in [0, 1), from the VM's generator, see `VMOptions::random_seed`.
+    pub fn random(&mut self) -> f64 {
+        self.vm.rng.next_f64()
+    }
+
+    /// What the program has on the heap right now, see `VM::heap_report`.
+    pub fn heap_report(&self) -> HeapReport {
+        self.vm.heap_report()
+    }
+
+    /// The seconds since the VM started, see `VM::with_clock`.
+    pub fn clock(&mut self) -> f64 {
+        (self.vm.clock)()
+    }
+
+    /// Gets the concrete type back from an opaque value,
+    /// returns a TypeError if the value isn't an opaque value of type T.
+    pub fn downcast_opaque<T: OpaqueValue>(&mut self, value: Value) -> Result<&mut T> {
+        if let Value::Heap(ptr) = value {
+            // the value is an argument, or was allocated by this native,
+            // so it's rooted for as long as the native runs.
+            if let HeapValue::Opaque { value, .. } = unsafe { &mut (*ptr).payload } {
+                let value: &mut dyn Any = value.as_mut();
+                if let Some(value) = value.downcast_mut() {
+                    return Ok(value);
+                }
+            }
+        }
+        Err(RuntimeError::TypeError {
+            message: format!(
+                "expected an opaque value of type '{}', but got '{}'",
+                any::type_name::<T>(),
+                value.fmt(self.vm)
+            ),
+        })
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+// the most digits numbers are rounded to after the decimal point,
+// more than an f64 can tell apart would just print noise.
+const MAX_DECIMAL_PLACES: usize = 17;
+
+/// How print, concatenation and `str()` turn numbers into text, for programs that write reports.
+/// Only the text changes, numbers compare and calculate the same whatever the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// put between every three digits of the whole part, like the ',' in 1,234,567.
+    pub thousands_separator: Option<char>,
+    /// round to this many digits after the decimal point, at most 17,
+    /// without it numbers get the fewest digits that still tell them apart.
+    pub decimal_places: Option<usize>,
+    pub decimal_point: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            thousands_separator: None,
+            decimal_places: None,
+            decimal_point: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Reads a format from space separated settings, which all have defaults:
+    /// `thousands=<char>` or `thousands=none`, `decimals=<count>` or `decimals=shortest`,
+    /// and `point=<char>`. "thousands=, decimals=2" prints 1234567.891 as 1,234,567.89.
+    pub fn parse(spec: &str) -> Result<NumberFormat, String> {
+        let mut format = NumberFormat::default();
+
+        for setting in spec.split_whitespace() {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("'{}' should look like key=value", setting))?;
+
+            let single_char = || {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(c),
+                    _ => Err(format!(
+                        "{} should be a single character, not '{}'",
+                        key, value
+                    )),
+                }
+            };
+
+            match key {
+                "thousands" if value == "none" => format.thousands_separator = None,
+                "thousands" => format.thousands_separator = Some(single_char()?),
+                "decimals" if value == "shortest" => format.decimal_places = None,
+                "decimals" => {
+                    let places = value
+                        .parse()
+                        .ok()
+                        .filter(|&places| places <= MAX_DECIMAL_PLACES)
+                        .ok_or_else(|| {
+                            format!(
+                                "decimals should be a count up to {} or 'shortest', not '{}'",
+                                MAX_DECIMAL_PLACES, value
+                            )
+                        })?;
+                    format.decimal_places = Some(places);
+                }
+                "point" => format.decimal_point = single_char()?,
+                _ => {
+                    return Err(format!(
+                        "unknown setting '{}', the settings are thousands, decimals and point",
+                        key
+                    ))
+                }
+            }
+        }
+
+        if format.thousands_separator == Some(format.decimal_point) {
+            return Err(format!(
+                "thousands and point can't both be '{}'",
+                format.decimal_point
+            ));
+        }
+        Ok(format)
+    }
+
+    pub fn write(&self, num: f64, f: &mut dyn fmt::Write) -> fmt::Result {
+        // the default is how rust shows floats, which doesn't need taking apart
+        if *self == NumberFormat::default() || !num.is_finite() {
+            return f.write_fmt(format_args!("{}", num));
+        }
+
+        let text = match self.decimal_places {
+            Some(places) => format!("{:.*}", places.min(MAX_DECIMAL_PLACES), num),
+            None => num.to_string(),
+        };
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+
+        f.write_str(sign)?;
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(separator) = self.thousands_separator {
+                if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                    f.write_char(separator)?;
+                }
+            }
+            f.write_char(digit)?;
+        }
+        if let Some(fraction) = fraction {
+            f.write_char(self.decimal_point)?;
+            f.write_str(fraction)?;
+        }
+        Ok(())
+    }
+
+    pub fn format(&self, num: f64) -> String {
+        let mut text = String::new();
+        self.write(num, &mut text)
+            .expect("writing to a string doesn't fail");
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberFormat;
+
+    fn format(thousands: Option<char>, places: Option<usize>, point: char, num: f64) -> String {
+        NumberFormat {
+            thousands_separator: thousands,
+            decimal_places: places,
+            decimal_point: point,
+        }
+        .format(num)
+    }
+
+    #[test]
+    fn every_combination_of_options() {
+        let num = 1234567.891;
+        let cases = [
+            (None, None, '.', "1234567.891"),
+            (Some(','), None, '.', "1,234,567.891"),
+            (None, Some(2), '.', "1234567.89"),
+            (Some(','), Some(2), '.', "1,234,567.89"),
+            (None, None, ',', "1234567,891"),
+            (Some('.'), None, ',', "1.234.567,891"),
+            (None, Some(0), ',', "1234568"),
+            (Some(' '), Some(1), ',', "1 234 567,9"),
+        ];
+        for (thousands, places, point, expected) in cases {
+            assert_eq!(format(thousands, places, point, num), expected);
+            assert_eq!(
+                format(thousands, places, point, -num),
+                format!("-{}", expected)
+            );
+        }
+    }
+
+    #[test]
+    fn separators_only_go_between_groups_of_three() {
+        let grouped = |num| format(Some(','), None, '.', num);
+        assert_eq!(grouped(0.5), "0.5");
+        assert_eq!(grouped(999.0), "999");
+        assert_eq!(grouped(1000.0), "1,000");
+        assert_eq!(grouped(-100000.25), "-100,000.25");
+        assert_eq!(grouped(1e21), "1,000,000,000,000,000,000,000");
+    }
+
+    #[test]
+    fn decimal_places_pad_and_round() {
+        assert_eq!(format(None, Some(3), '.', 2.0), "2.000");
+        assert_eq!(format(None, Some(1), '.', 0.25), "0.2");
+        assert_eq!(format(None, Some(1), '.', 0.35), "0.3");
+        assert_eq!(
+            format(None, Some(usize::MAX), '.', 0.5),
+            "0.50000000000000000"
+        );
+    }
+
+    #[test]
+    fn odd_numbers_are_left_alone() {
+        let european = |num| format(Some('.'), Some(2), ',', num);
+        assert_eq!(european(f64::NAN), "NaN");
+        assert_eq!(european(f64::INFINITY), "inf");
+        assert_eq!(european(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn specs_are_parsed() {
+        assert_eq!(NumberFormat::parse(""), Ok(NumberFormat::default()));
+        assert_eq!(
+            NumberFormat::parse("thousands=, decimals=2"),
+            Ok(NumberFormat {
+                thousands_separator: Some(','),
+                decimal_places: Some(2),
+                decimal_point: '.',
+            })
+        );
+        assert_eq!(
+            NumberFormat::parse("point=, thousands=. decimals=shortest"),
+            Ok(NumberFormat {
+                thousands_separator: Some('.'),
+                decimal_places: None,
+                decimal_point: ',',
+            })
+        );
+        assert_eq!(
+            NumberFormat::parse("thousands=_ thousands=none"),
+            Ok(NumberFormat::default())
+        );
+
+        let bad_specs = [
+            "decimals",
+            "decimals=two",
+            "decimals=18",
+            "decimals=4294967295",
+            "point=",
+            "point=ab",
+            "width=3",
+            "thousands=. point=.",
+            "thousands=, point=,",
+        ];
+        for bad in bad_specs {
+            assert!(NumberFormat::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
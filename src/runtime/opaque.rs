@@ -0,0 +1,9 @@
+use std::{any::Any, fmt::Debug};
+
+/// A resource the host hands to scripts, like a database handle or a sprite.
+/// Scripts can only pass opaque values around, natives get the concrete type back
+/// with `VmContext::downcast_opaque`.
+pub trait OpaqueValue: Any + Debug {
+    /// The name used when printing the value, and in its type, "opaque:<name>".
+    fn display_name(&self) -> &str;
+}
@@ -0,0 +1,106 @@
+use super::{
+    mem_manager::{HeapValue, HeapValueHeader},
+    Value, VM,
+};
+
+/// A copy of a value that doesn't depend on the VM, so it can outlive it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<OwnedValue>),
+    /// A function, as print shows it.
+    Function(String),
+    /// A record, as print shows it.
+    Record(String),
+    /// A host value, with its type name.
+    Opaque(String),
+    /// A list inside itself, which can't be copied.
+    Cycle,
+}
+
+// a list that is being copied, along with its elements copied so far
+struct OpenList {
+    ptr: *mut HeapValueHeader,
+    elements: Vec<OwnedValue>,
+}
+
+fn list_elements<'v>(ptr: *mut HeapValueHeader) -> Option<&'v [Value]> {
+    match unsafe { &(*ptr).payload } {
+        HeapValue::List(list) => Some(list),
+        _ => None,
+    }
+}
+
+impl OwnedValue {
+    /// Copies the value out of the VM.
+    /// Lists are copied with an explicit work list, so deeply nested lists can't overflow the stack.
+    pub fn from_value(value: Value, vm: &VM) -> OwnedValue {
+        let mut open_lists: Vec<OpenList> = vec![];
+        let mut next = Some(value);
+
+        loop {
+            let owned = match next.take() {
+                // every element of the innermost open list has been copied
+                None => {
+                    let list = open_lists.pop().expect("there should be an open list");
+                    OwnedValue::List(list.elements)
+                }
+
+                Some(Value::Heap(ptr)) if list_elements(ptr).is_some() => {
+                    if open_lists.iter().any(|list| list.ptr == ptr) {
+                        OwnedValue::Cycle
+                    } else {
+                        let elements = list_elements(ptr).unwrap();
+                        next = elements.first().copied();
+                        open_lists.push(OpenList {
+                            ptr,
+                            elements: Vec::with_capacity(elements.len()),
+                        });
+                        continue;
+                    }
+                }
+
+                Some(value) => OwnedValue::from_non_list(value, vm),
+            };
+
+            match open_lists.last_mut() {
+                None => return owned,
+                Some(list) => {
+                    list.elements.push(owned);
+                    next = list_elements(list.ptr)
+                        .unwrap()
+                        .get(list.elements.len())
+                        .copied();
+                }
+            }
+        }
+    }
+
+    fn from_non_list(value: Value, vm: &VM) -> OwnedValue {
+        match value {
+            Value::Nil => OwnedValue::Nil,
+            Value::Bool(b) => OwnedValue::Bool(b),
+            Value::Number(num) => OwnedValue::Number(num),
+            Value::Function { .. } | Value::NativeFunction { .. } => {
+                OwnedValue::Function(value.fmt(vm).to_string())
+            }
+            Value::ReturnAdress { .. } => OwnedValue::Opaque(value.type_name()),
+            Value::StringLiteral { .. } => OwnedValue::String(value.fmt(vm).to_string()),
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::String(string) => OwnedValue::String(string.clone()),
+                HeapValue::Opaque { value, .. } => {
+                    OwnedValue::Opaque(value.display_name().to_owned())
+                }
+                HeapValue::Closure { .. } => OwnedValue::Function(value.fmt(vm).to_string()),
+                HeapValue::Upvalue(_) | HeapValue::Iterator { .. } => {
+                    OwnedValue::Opaque(value.type_name())
+                }
+                HeapValue::Record { .. } => OwnedValue::Record(value.fmt(vm).to_string()),
+                HeapValue::List(_) => unreachable!("lists are copied by from_value"),
+            },
+        }
+    }
+}
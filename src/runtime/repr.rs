@@ -0,0 +1,148 @@
+use std::fmt::{self, Write};
+
+use crate::executable::FunctionName;
+
+use super::{
+    mem_manager::{HeapValue, HeapValueHeader},
+    Value, VM,
+};
+
+// lists and records nested deeper than this are shown as '...',
+// which also keeps the recursion from overflowing the stack.
+// printed values are cut off at the same depth.
+pub(super) const MAX_DEPTH: usize = 64;
+
+/// A value formatted so its type can be told apart, unlike how `print` shows it.
+/// Strings are quoted and escaped, lists and records show the repr of their elements,
+/// and functions show their name and number of parameters.
+/// A list or record that contains itself shows '...' where it appears again.
+pub struct ReprValue<'a, 'b> {
+    value: Value,
+    vm: &'a VM<'b>,
+}
+
+impl<'a, 'b> ReprValue<'a, 'b> {
+    pub fn new(value: Value, vm: &'a VM<'b>) -> Self {
+        ReprValue { value, vm }
+    }
+}
+
+impl<'a, 'b> fmt::Display for ReprValue<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Formatter {
+            f,
+            vm: self.vm,
+            open: vec![],
+        }
+        .value(self.value)
+    }
+}
+
+struct Formatter<'f, 'g, 'a, 'b> {
+    f: &'f mut fmt::Formatter<'g>,
+    vm: &'a VM<'b>,
+    // the lists and records that are being formatted, meeting one of them again is a cycle
+    open: Vec<*mut HeapValueHeader>,
+}
+
+impl<'f, 'g, 'a, 'b> Formatter<'f, 'g, 'a, 'b> {
+    fn value(&mut self, value: Value) -> fmt::Result {
+        let exec = &self.vm.exec;
+        match value {
+            Value::Bool(b) => write!(self.f, "{}", b),
+            Value::Nil => self.f.write_str("nil"),
+            // rust prints the shortest digits that parse back to the same number
+            Value::Number(num) => write!(self.f, "{}", num),
+            Value::StringLiteral { .. } => self.string(value.as_str(exec).unwrap_or_default()),
+            Value::Function { function_index } => self.function(function_index),
+            Value::NativeFunction { native_index } => {
+                let native = &exec.natives[native_index as usize];
+                write!(self.f, "<native fn {}/{}>", native.name, native.arity)
+            }
+            Value::ReturnAdress { ip } => write!(self.f, "<returnaddr {}>", ip),
+            Value::Heap(ptr) => self.heap_value(ptr),
+        }
+    }
+
+    fn heap_value(&mut self, ptr: *mut HeapValueHeader) -> fmt::Result {
+        let payload = unsafe { &(*ptr).payload };
+        match payload {
+            HeapValue::String(string) => return self.string(string),
+            HeapValue::Closure { function_index, .. } => return self.function(*function_index),
+            HeapValue::Opaque { id, value } => {
+                return write!(self.f, "<{} #{}>", value.display_name(), id)
+            }
+            HeapValue::Upvalue(_) => return self.f.write_str("<upvalue>"),
+            HeapValue::Iterator { .. } => return self.f.write_str("<iterator>"),
+            HeapValue::List(_) | HeapValue::Record { .. } => {}
+        }
+
+        if self.open.contains(&ptr) || self.open.len() >= MAX_DEPTH {
+            return self.f.write_str("...");
+        }
+        self.open.push(ptr);
+
+        match payload {
+            HeapValue::List(list) => {
+                self.f.write_char('[')?;
+                for (index, &val) in list.iter().enumerate() {
+                    if index > 0 {
+                        self.f.write_str(", ")?;
+                    }
+                    self.value(val)?;
+                }
+                self.f.write_char(']')?;
+            }
+            HeapValue::Record {
+                struct_index,
+                fields,
+            } => {
+                let exec = &self.vm.exec;
+                let layout = &exec.structs[*struct_index as usize];
+                write!(self.f, "{} {{", layout.name)?;
+                for (index, (&field, &val)) in layout.fields.iter().zip(fields).enumerate() {
+                    if index > 0 {
+                        self.f.write_char(',')?;
+                    }
+                    write!(self.f, " {}: ", exec.field_names[field as usize])?;
+                    self.value(val)?;
+                }
+                self.f.write_str(" }")?;
+            }
+            _ => unreachable!("only lists and records are left"),
+        }
+
+        self.open.pop();
+        Ok(())
+    }
+
+    fn function(&mut self, function_index: u32) -> fmt::Result {
+        let exec = &self.vm.exec;
+        let function = &exec.functions[function_index as usize];
+        match function.name {
+            FunctionName::Anonymous => write!(self.f, "<fn/{}>", function.param_count),
+            name => write!(
+                self.f,
+                "<fn {}/{}>",
+                name.fmt(&exec.string_data),
+                function.param_count
+            ),
+        }
+    }
+
+    fn string(&mut self, string: &str) -> fmt::Result {
+        self.f.write_char('"')?;
+        for c in string.chars() {
+            match c {
+                '"' => self.f.write_str("\\\"")?,
+                '\\' => self.f.write_str("\\\\")?,
+                '\n' => self.f.write_str("\\n")?,
+                '\r' => self.f.write_str("\\r")?,
+                '\t' => self.f.write_str("\\t")?,
+                c if c.is_control() => write!(self.f, "\\u{{{:x}}}", c as u32)?,
+                c => self.f.write_char(c)?,
+            }
+        }
+        self.f.write_char('"')
+    }
+}
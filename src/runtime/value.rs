@@ -1,6 +1,14 @@
-use std::fmt::{self, Write};
+use std::{cmp::Ordering, fmt};
 
-use super::{mem_manager::HeapValueHeader, VM};
+use ahash::AHashSet;
+
+use crate::executable::Executable;
+
+use super::{
+    mem_manager::{HeapValue, HeapValueHeader},
+    repr::ReprValue,
+    VM,
+};
 
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
 pub enum Value {
@@ -10,12 +18,13 @@ pub enum Value {
     StringLiteral { start_index: u32, end_index: u32 },
     Heap(*mut HeapValueHeader),
     Function { function_index: u32 },
+    NativeFunction { native_index: u32 },
     ReturnAdress { ip: usize },
 }
 
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(match self {
+        match self {
             Value::Nil => f.write_str("Nil")?,
             Value::Bool(b) => f.write_fmt(format_args!("Bool({})", b))?,
             Value::Number(num) => f.write_fmt(format_args!("Number({})", num))?,
@@ -32,24 +41,163 @@ impl fmt::Debug for Value {
                 f.write_fmt(format_args!("Format(index: {})", function_index))?
             }
 
+            Value::NativeFunction { native_index } => {
+                f.write_fmt(format_args!("NativeFunction(index: {})", native_index))?
+            }
+
             Value::ReturnAdress { ip } => f.write_fmt(format_args!("ReturnAdress({})", ip))?,
 
             Value::Heap(ptr) => f.write_fmt(format_args!("HeapPtr({:?})", *ptr))?,
-        })
+        };
+        Ok(())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(num: f64) -> Self {
+        Value::Number(num)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
     }
 }
 
 impl Value {
     pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Bool(false) | Value::Nil)
+    }
+
+    /// The name of the value's type, host values are called "opaque:<name>".
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Bool(_) => "bool".into(),
+            Value::Nil => "nil".into(),
+            Value::Number(_) => "number".into(),
+            Value::StringLiteral { .. } => "string".into(),
+            Value::Function { .. } | Value::NativeFunction { .. } => "function".into(),
+            Value::ReturnAdress { .. } => "returnaddr".into(),
+            Value::Heap(ptr) => match unsafe { &(**ptr).payload } {
+                HeapValue::String(_) => "string".into(),
+                HeapValue::List(_) => "list".into(),
+                HeapValue::Opaque { value, .. } => format!("opaque:{}", value.display_name()),
+                HeapValue::Closure { .. } => "function".into(),
+                HeapValue::Upvalue(_) => "upvalue".into(),
+                HeapValue::Iterator { .. } => "iterator".into(),
+                HeapValue::Record { .. } => "record".into(),
+            },
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(num) => Some(*num),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The text of a string value, string literals are stored in the executable.
+    /// Strings created at runtime live on the VM's heap, so they can only be read
+    /// while the VM that created them is alive and the value is still reachable.
+    pub fn as_str<'a>(&'a self, exec: &'a Executable) -> Option<&'a str> {
         match self {
-            Value::Bool(false) | Value::Nil => false,
-            _ => true,
+            Value::StringLiteral {
+                start_index,
+                end_index,
+            } => Some(&exec.string_data[*start_index as usize..*end_index as usize]),
+            Value::Heap(ptr) => match unsafe { &(**ptr).payload } {
+                HeapValue::String(string) => Some(string),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Orders numbers numerically and strings lexicographically, wherever they live.
+    /// Other values, values of different types and NaN can't be compared, which gives None.
+    pub fn compare(&self, other: &Value, exec: &Executable) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left.partial_cmp(right),
+            _ => Some(self.as_str(exec)?.cmp(other.as_str(exec)?)),
+        }
+    }
+
+    /// Whether the values are equal, the way '==' compares them.
+    /// Strings are equal when their text is, wherever they live, and lists when their elements are.
+    /// Functions are equal when they're the same function, and nil only equals nil.
+    /// Closures, records and opaque values are only equal to themselves.
+    pub fn cahn_eq(&self, other: &Value, exec: &Executable) -> bool {
+        let mut work = vec![(*self, *other)];
+        // a list containing itself would otherwise be compared forever,
+        // so a pair of lists that's already being compared counts as equal
+        let mut compared_lists = AHashSet::new();
+
+        while let Some((left, right)) = work.pop() {
+            let equal = match (left, right) {
+                (Value::Nil, Value::Nil) => true,
+                (Value::Bool(left), Value::Bool(right)) => left == right,
+                (Value::Number(left), Value::Number(right)) => left == right,
+                (
+                    Value::Function {
+                        function_index: left,
+                    },
+                    Value::Function {
+                        function_index: right,
+                    },
+                ) => left == right,
+                (
+                    Value::NativeFunction { native_index: left },
+                    Value::NativeFunction {
+                        native_index: right,
+                    },
+                ) => left == right,
+                _ => match (left.as_str(exec), right.as_str(exec)) {
+                    (Some(left), Some(right)) => left == right,
+                    (Some(_), None) | (None, Some(_)) => false,
+                    (None, None) => match (left, right) {
+                        (Value::Heap(left_ptr), Value::Heap(right_ptr)) => {
+                            match unsafe { (&(*left_ptr).payload, &(*right_ptr).payload) } {
+                                (HeapValue::List(left), HeapValue::List(right)) => {
+                                    if left_ptr != right_ptr
+                                        && compared_lists.insert((left_ptr, right_ptr))
+                                    {
+                                        work.extend(
+                                            left.iter().copied().zip(right.iter().copied()),
+                                        );
+                                    }
+                                    left.len() == right.len()
+                                }
+                                _ => left_ptr == right_ptr,
+                            }
+                        }
+                        _ => false,
+                    },
+                },
+            };
+            if !equal {
+                return false;
+            }
         }
+        true
     }
 
     pub fn fmt<'a, 'b>(self, vm: &'a VM<'b>) -> FormatableValue<'a, 'b> {
         FormatableValue { value: self, vm }
     }
+
+    /// The value formatted the way the `repr` builtin shows it.
+    pub fn repr<'a, 'b>(self, vm: &'a VM<'b>) -> ReprValue<'a, 'b> {
+        ReprValue::new(self, vm)
+    }
 }
 
 pub struct FormatableValue<'a, 'b> {
@@ -59,25 +207,138 @@ pub struct FormatableValue<'a, 'b> {
 
 impl<'a, 'b> fmt::Display for FormatableValue<'a, 'b> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.value {
+        self.value.write(self.vm, f, &mut vec![])
+    }
+}
+
+impl Value {
+    // open holds the lists and records the value is inside of, see HeapValueHeader::write
+    pub(super) fn write(
+        self,
+        vm: &VM,
+        f: &mut fmt::Formatter<'_>,
+        open: &mut Vec<*const HeapValueHeader>,
+    ) -> fmt::Result {
+        match self {
             Value::Bool(b) => f.write_fmt(format_args!("{}", b)),
             Value::Nil => f.write_str("nil"),
-            Value::Number(num) => f.write_fmt(format_args!("{}", num)),
+            Value::Number(num) => vm.options.number_format.write(num, f),
 
             Value::Function { function_index } => {
-                let cahn_function = &self.vm.exec.functions[function_index as usize];
-                let cahn_function = cahn_function.fmt(&self.vm.exec);
+                let cahn_function = &vm.exec.functions[function_index as usize];
+                let cahn_function = cahn_function.fmt(&vm.exec);
                 fmt::Display::fmt(&cahn_function, f)
             }
 
+            Value::NativeFunction { native_index } => {
+                let native = &vm.exec.natives[native_index as usize];
+                f.write_fmt(format_args!("<native fn {}>", native.name))
+            }
+
             Value::ReturnAdress { ip } => f.write_fmt(format_args!("<returnaddr {}>", ip)),
 
             Value::StringLiteral {
                 start_index,
                 end_index,
-            } => f.write_str(&self.vm.exec.string_data[start_index as usize..end_index as usize]),
+            } => f.write_str(&vm.exec.string_data[start_index as usize..end_index as usize]),
+
+            Value::Heap(heap_val) => unsafe { (*heap_val).write(vm, f, open) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use crate::executable::Executable;
+
+    #[test]
+    fn numbers_round_trip() {
+        for num in &[0.0, -1.5, 1e300, f64::INFINITY] {
+            assert_eq!(Value::from(*num).as_f64(), Some(*num));
+        }
+        assert_eq!(Value::from(true).as_f64(), None);
+    }
+
+    #[test]
+    fn bools_round_trip() {
+        assert_eq!(Value::from(true).as_bool(), Some(true));
+        assert_eq!(Value::from(false).as_bool(), Some(false));
+        assert_eq!(Value::Nil.as_bool(), None);
+        assert_eq!(Value::from(0.0).as_bool(), None);
+    }
+
+    #[test]
+    fn string_literal_reads_from_executable() {
+        let exec = Executable::new(
+            vec![],
+            "helloworld".into(),
+            "test".into(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let world = Value::StringLiteral {
+            start_index: 5,
+            end_index: 10,
+        };
+        assert_eq!(world.as_str(&exec), Some("world"));
+        assert_eq!(Value::from(1.0).as_str(&exec), None);
+    }
+
+    #[test]
+    fn compare_needs_two_numbers_or_two_strings() {
+        use std::cmp::Ordering;
+
+        let exec = Executable::new(vec![], "ab".into(), "test".into(), vec![], vec![], vec![]);
+        let a = Value::StringLiteral {
+            start_index: 0,
+            end_index: 1,
+        };
+        let b = Value::StringLiteral {
+            start_index: 1,
+            end_index: 2,
+        };
+        assert_eq!(a.compare(&b, &exec), Some(Ordering::Less));
+        assert_eq!(
+            Value::from(2.0).compare(&Value::from(1.0), &exec),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(Value::from(1.0).compare(&a, &exec), None);
+        assert_eq!(
+            Value::from(f64::NAN).compare(&Value::from(1.0), &exec),
+            None
+        );
+        assert_eq!(Value::Nil.compare(&Value::Nil, &exec), None);
+    }
+
+    #[test]
+    fn lists_containing_themselves_can_be_compared() {
+        use crate::runtime::mem_manager::{HeapValue, HeapValueHeader};
+        use std::ptr;
+
+        // [n, <the list itself>]
+        let cyclic_list = |n: f64| {
+            let list = Box::into_raw(Box::new(HeapValueHeader {
+                is_marked: false,
+                next_heap_val: ptr::null_mut(),
+                alloc_site: None,
+                payload: HeapValue::List(vec![Value::Number(n)]),
+            }));
+            if let HeapValue::List(elements) = unsafe { &mut (*list).payload } {
+                elements.push(Value::Heap(list));
+            }
+            list
+        };
+
+        let exec = Executable::new(vec![], "".into(), "test".into(), vec![], vec![], vec![]);
+        let (a, b, c) = (cyclic_list(1.0), cyclic_list(1.0), cyclic_list(2.0));
+        assert!(Value::Heap(a).cahn_eq(&Value::Heap(a), &exec));
+        assert!(Value::Heap(a).cahn_eq(&Value::Heap(b), &exec));
+        assert!(!Value::Heap(a).cahn_eq(&Value::Heap(c), &exec));
 
-            Value::Heap(heap_val) => unsafe { fmt::Display::fmt(&(*heap_val).fmt(self.vm), f) },
+        for list in [a, b, c] {
+            unsafe { drop(Box::from_raw(list)) };
         }
     }
 }
@@ -1,32 +1,161 @@
 use crate::{
-    executable::{CahnFunction, Executable, Instruction},
+    compiler::lexical_analysis::TokenPos,
+    executable::{self, BytecodeError, CahnFunction, Executable, Instruction},
     runtime::{
         error::{Result, RuntimeError},
         mem_manager::MemoryManager,
-        Value,
+        NumberFormat, OpaqueValue, OwnedValue, RuntimeWarning, Value, VmContext,
     },
+    utils::Rng,
 };
 
+use ahash::AHashSet;
+
 use std::{
+    borrow::Cow,
     cell::RefCell,
+    cmp::Reverse,
+    collections::hash_map::RandomState,
     fmt::{self, Debug},
-    io::{self, Write},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufRead, Write},
     mem,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
 
-use super::mem_manager::HeapValue;
+use super::mem_manager::{HeapReport, HeapValue, HeapValueHeader, Upvalue};
+
+// how many instructions run between looking at the interrupt flag
+const INTERRUPT_CHECK_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub struct VMOptions {
+    /// record the highest number of values on the stack during the run, see `VM::peak_stack`.
+    pub track_peak_stack: bool,
+    /// calls nested deeper than this are a StackOverflow error.
+    pub max_call_depth: usize,
+    /// concatenating nil is a TypeError, rather than giving "nil", as it's most likely
+    /// a missing value.
+    pub strict_concat: bool,
+    /// collect warnings about suspicious things the program does, see `VM::take_warnings`.
+    pub runtime_warnings: bool,
+    /// the seed of the numbers `random()` gives, the same seed gives the same numbers.
+    /// without one, every run gets different numbers.
+    pub random_seed: Option<u64>,
+    /// count how many times each instruction runs, see `VM::instruction_counts`.
+    pub count_instructions: bool,
+    /// how numbers are shown by print, concatenation and `str()`.
+    pub number_format: NumberFormat,
+}
+
+impl Default for VMOptions {
+    fn default() -> Self {
+        VMOptions {
+            track_peak_stack: false,
+            // deeper recursion than this is most likely a bug in the script
+            max_call_depth: 10_000,
+            strict_concat: false,
+            runtime_warnings: false,
+            random_seed: None,
+            count_instructions: false,
+            number_format: NumberFormat::default(),
+        }
+    }
+}
+
+/// Everything a run produced, see `VM::run_full`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutput {
+    /// What the program printed.
+    pub output: String,
+    /// The value of the last statement of the program, if that's an expression statement,
+    /// otherwise nil.
+    pub value: OwnedValue,
+    pub stats: RunStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunStats {
+    pub instructions_run: usize,
+    pub peak_stack: usize,
+    pub heap_allocations: u32,
+}
+
+// the state of a function that called another function, restored once the callee returns.
+// frames hold on to the function they were running, so they finish the old code
+// if the function is replaced in the meantime.
+struct CallFrame {
+    function: Rc<CahnFunction>,
+    function_index: u32,
+    // how many results the caller takes from the call
+    results: usize,
+    ip: usize,
+    fp: usize,
+}
 
 pub struct VM<'a> {
-    pub exec: &'a Executable,
+    // only cloned once a function is replaced
+    pub exec: Cow<'a, Executable>,
     mem_manager: RefCell<MemoryManager>,
 
     pub stack: Vec<Value>,
-
-    pub curr_func: &'a CahnFunction,
+    pub(super) globals: Vec<Option<Value>>,
+    // the upvalues of variables that are still on the stack, sorted by their stack index
+    pub(super) open_upvalues: Vec<*mut HeapValueHeader>,
+
+    frames: Vec<CallFrame>,
+    pub curr_func: Rc<CahnFunction>,
+    // the index of curr_func in the executable's functions
+    curr_func_index: u32,
     ip: usize,
     fp: usize,
 
     stdout: RefCell<&'a mut dyn Write>,
+    // where input() reads lines from, without it there is no input
+    stdin: Option<&'a mut dyn BufRead>,
+
+    // set by the Halt at the end of the top level function
+    halted: bool,
+    // where the instruction that failed is in the source
+    error_pos: Option<TokenPos>,
+
+    pub(super) options: VMOptions,
+    peak_stack: usize,
+    instructions_run: usize,
+    // indexed by the byte of the instruction, empty unless the instructions are counted
+    instruction_counts: Vec<usize>,
+
+    warnings: Vec<RuntimeWarning>,
+    // the kinds of warnings each instruction already reported, by its function and code offset
+    warned_at: AHashSet<(&'static str, u32, u32)>,
+
+    pub(super) rng: Rng,
+    // the seconds since the VM started, which clock() returns
+    pub(super) clock: Box<dyn Fn() -> f64 + 'a>,
+    // set from elsewhere, like a Ctrl-C handler, to stop the program
+    interrupt_flag: Option<Arc<AtomicBool>>,
+}
+
+// appends written bytes to a string, each write has to be valid utf8 on its own,
+// which holds for the VM, as it only writes formatted strings.
+struct StringWriter<'s>(&'s mut String);
+
+impl<'s> Write for StringWriter<'s> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = std::str::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.0.push_str(text);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 impl<'a> Debug for VM<'a> {
@@ -40,35 +169,178 @@ impl<'a> Debug for VM<'a> {
 
 impl<'a> VM<'a> {
     pub fn new(exec: &'a Executable, stdout: &'a mut dyn Write) -> Self {
-        VM {
+        VM::with_options(exec, stdout, VMOptions::default())
+    }
+
+    pub fn with_options(
+        exec: &'a Executable,
+        stdout: &'a mut dyn Write,
+        options: VMOptions,
+    ) -> Self {
+        let mut vm = VM {
             mem_manager: RefCell::new(MemoryManager::new()),
-            exec,
+            exec: Cow::Borrowed(exec),
 
             stack: Vec::new(),
+            globals: vec![None; exec.global_names.len()],
+            open_upvalues: Vec::new(),
+
+            frames: Vec::new(),
 
+            // without any functions there's no code to run, which step reports
             curr_func: exec
                 .functions
                 .last()
-                .expect("CodeGenerator didn't create any functions ¯\\_(ツ)_/¯"),
+                .cloned()
+                .unwrap_or_else(|| Rc::new(CahnFunction::new_anonymous(0, vec![], vec![]))),
+            curr_func_index: exec.functions.len().saturating_sub(1) as u32,
 
             ip: 0,
             fp: 0,
 
             stdout: RefCell::new(stdout),
+            stdin: None,
+
+            halted: false,
+            error_pos: None,
+
+            options,
+            peak_stack: 0,
+            instructions_run: 0,
+            instruction_counts: if options.count_instructions {
+                vec![0; Instruction::ALL.len()]
+            } else {
+                vec![]
+            },
+
+            warnings: Vec::new(),
+            warned_at: AHashSet::new(),
+
+            // std's hash maps are keyed randomly, which makes for a random seed
+            rng: Rng::new(
+                options
+                    .random_seed
+                    .unwrap_or_else(|| RandomState::new().build_hasher().finish()),
+            ),
+
+            clock: {
+                let start = Instant::now();
+                Box::new(move || start.elapsed().as_secs_f64())
+            },
+            interrupt_flag: None,
+        };
+
+        // code for the same instruction set runs fine, but a bug might be the other version's
+        if exec.compiled_by_other_version() {
+            vm.warn(RuntimeWarning::OtherCompilerVersion {
+                compiled_with: exec.compiler_version().to_string(),
+            });
         }
+        vm
+    }
+
+    /// Lets `input()` read lines from `stdin`.
+    /// Without it, the input is empty, so `input()` returns nil.
+    pub fn with_stdin(mut self, stdin: &'a mut dyn BufRead) -> Self {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Replaces where `clock()` gets the time from, which is the seconds since the VM
+    /// was created otherwise. Lets tests see the same times on every run.
+    pub fn with_clock(mut self, clock: impl Fn() -> f64 + 'a) -> Self {
+        self.clock = Box::new(clock);
+        self
     }
 
+    /// Stops the program with `RuntimeError::Interrupted` once the flag is set, which can be
+    /// done from another thread or a signal handler. The VM only looks at the flag every few
+    /// hundred instructions, and clears it when it stops, so it can be used for the next run.
+    pub fn with_interrupt_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt_flag = Some(flag);
+        self
+    }
+
+    /// Runs the executable like a script run from a terminal,
+    /// `input()` reads from stdin and the output goes to stdout.
     pub fn run_to_stdout(exec: &'a Executable) -> Result<()> {
-        let mut stdout = io::stdout();
-        let vm = VM::new(exec, &mut stdout);
-        vm.run()
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        VM::new(exec, &mut io::stdout())
+            .with_stdin(&mut stdin)
+            .run()
+    }
+
+    /// Runs the executable, writing its output to `writer`, like a log file or a socket.
+    pub fn run_with_writer(exec: &'a Executable, writer: &'a mut dyn Write) -> Result<()> {
+        VM::new(exec, writer).run()
     }
 
     pub fn run_to_string(exec: &'a Executable) -> Result<String> {
-        let mut bytes: Vec<u8> = vec![];
-        let vm = VM::new(exec, &mut bytes);
-        vm.run()?;
-        Ok(String::from_utf8(bytes).expect("VM shouldn't be able to produce invalid utf8"))
+        let mut output = String::new();
+        VM::run_into(exec, &mut output)?;
+        Ok(output)
+    }
+
+    /// Like `run_to_string`, but `input()` reads its lines from `input`.
+    pub fn run_to_string_with_input(exec: &'a Executable, input: &str) -> Result<String> {
+        let mut output = String::new();
+        let mut writer = StringWriter(&mut output);
+        let mut stdin = input.as_bytes();
+        VM::new(exec, &mut writer).with_stdin(&mut stdin).run()?;
+        Ok(output)
+    }
+
+    /// Runs the executable, appending its output to `buf`.
+    /// Reusing one buffer for many runs saves allocating a new one every time.
+    pub fn run_into(exec: &'a Executable, buf: &mut String) -> Result<()> {
+        let mut writer = StringWriter(buf);
+        let vm = VM::new(exec, &mut writer);
+        vm.run()
+    }
+
+    /// Runs the executable, capturing its output along with the value it ends with.
+    /// The value is copied out of the VM, so it can be used after the run.
+    pub fn run_full(exec: &'a Executable) -> Result<RunOutput> {
+        let mut output = String::new();
+        let mut writer = StringWriter(&mut output);
+        let options = VMOptions {
+            track_peak_stack: true,
+            ..VMOptions::default()
+        };
+        let mut vm = VM::with_options(exec, &mut writer, options);
+        while vm.step()? {}
+
+        let value = OwnedValue::from_value(vm.result(), &vm);
+        let stats = RunStats {
+            instructions_run: vm.instructions_run,
+            peak_stack: vm.peak_stack,
+            heap_allocations: vm.mem_manager.borrow().total_allocs(),
+        };
+        drop(vm);
+
+        Ok(RunOutput {
+            output,
+            value,
+            stats,
+        })
+    }
+
+    /// Where in the source the instruction that returned an error is,
+    /// None if no instruction failed.
+    pub fn error_pos(&self) -> Option<TokenPos> {
+        self.error_pos
+    }
+
+    /// The warnings reported since the last call, if `VMOptions::runtime_warnings` is set.
+    pub fn take_warnings(&mut self) -> Vec<RuntimeWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// The value the program ended with, once it has finished.
+    /// Programs leave it on the stack, right above the script function.
+    pub fn result(&self) -> Value {
+        self.stack.get(1).copied().unwrap_or(Value::Nil)
     }
 
     #[inline]
@@ -84,6 +356,228 @@ impl<'a> VM<'a> {
     #[inline]
     fn push(&mut self, val: Value) {
         self.stack.push(val);
+        if self.options.track_peak_stack {
+            self.peak_stack = self.peak_stack.max(self.stack.len());
+        }
+    }
+
+    /// The highest number of values that has been on the stack so far,
+    /// or None if the VM wasn't created with `track_peak_stack`.
+    pub fn peak_stack(&self) -> Option<usize> {
+        if self.options.track_peak_stack {
+            Some(self.peak_stack)
+        } else {
+            None
+        }
+    }
+
+    /// How many times each instruction that ran so far ran, the most frequent first,
+    /// or None if the VM wasn't created with `count_instructions`.
+    pub fn instruction_counts(&self) -> Option<Vec<(Instruction, usize)>> {
+        if !self.options.count_instructions {
+            return None;
+        }
+        let mut counts: Vec<(Instruction, usize)> = Instruction::ALL
+            .iter()
+            .copied()
+            .zip(self.instruction_counts.iter().copied())
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+        Some(counts)
+    }
+
+    // the value is left on the stack, so it isn't collected while the native that allocated it
+    // is still running. the stack is cut back to the callee once the native returns.
+    pub(super) fn alloc_opaque(&mut self, value: Box<dyn OpaqueValue>) -> Value {
+        let val = self.mem_manager.borrow_mut().alloc_opaque(self, value);
+        self.push(val);
+        val
+    }
+
+    // like alloc_opaque, the string is left on the stack until the native returns.
+    pub(super) fn alloc_string(&mut self, string: String) -> Value {
+        let val = self.mem_manager.borrow_mut().alloc_string(self, string);
+        self.push(val);
+        val
+    }
+
+    // the list is pushed before it's filled, the elements have to be rooted by the caller.
+    pub(super) fn alloc_list(&mut self, elements: Vec<Value>) -> Value {
+        let val = self.mem_manager.borrow_mut().alloc_list(self, 0);
+        self.push(val);
+        if let Value::Heap(ptr) = val {
+            if let HeapValue::List(list) = unsafe { &mut (*ptr).payload } {
+                *list = elements;
+            }
+        }
+        val
+    }
+
+    // replaces the operands of the instruction on top of the stack with a new string.
+    // the operands are only popped once the string is allocated, so the gc it may run
+    // can't free them while the instruction still holds on to them.
+    fn replace_operands_with_string(&mut self, operand_count: usize, string: String) {
+        let val = self.mem_manager.borrow_mut().alloc_string(self, string);
+        self.stack.truncate(self.stack.len() - operand_count);
+        self.push(val);
+    }
+
+    // moves the iterator on top of the stack to its next element, and returns it.
+    // every kind of container the for-in loop supports has to be handled here.
+    // compiled code only uses IterNext on the iterator of an IterInit, but the verifier
+    // doesn't know the types of values, so anything else is a TypeError.
+    fn advance_iterator(&mut self) -> Result<Option<Value>> {
+        let value = self.peek();
+        let not_an_iterator = || RuntimeError::TypeError {
+            message: format!("IterNext expected an iterator, got {}", value.fmt(self)),
+        };
+        let Value::Heap(ptr) = value else {
+            return Err(not_an_iterator());
+        };
+        let HeapValue::Iterator { container, index } = (unsafe { &mut (*ptr).payload }) else {
+            return Err(not_an_iterator());
+        };
+        let container = *container;
+        let not_iterable = || RuntimeError::TypeError {
+            message: format!(
+                "IterNext expected an iterator over a list or string, got one over {}",
+                container.fmt(self)
+            ),
+        };
+
+        match container {
+            // chars of a literal are slices of the string data, so they don't need an allocation
+            Value::StringLiteral {
+                start_index,
+                end_index,
+            } => {
+                let string = &self.exec.string_data[start_index as usize..end_index as usize];
+                let Some(char) = string[*index..].chars().next() else {
+                    return Ok(None);
+                };
+                let char_len = char.len_utf8();
+                let start = start_index + *index as u32;
+                *index += char_len;
+                Ok(Some(Value::StringLiteral {
+                    start_index: start,
+                    end_index: start + char_len as u32,
+                }))
+            }
+
+            Value::Heap(container_ptr) => match unsafe { &(*container_ptr).payload } {
+                HeapValue::List(list) => {
+                    let Some(&element) = list.get(*index) else {
+                        return Ok(None);
+                    };
+                    *index += 1;
+                    Ok(Some(element))
+                }
+                HeapValue::String(string) => {
+                    let Some(char) = string[*index..].chars().next() else {
+                        return Ok(None);
+                    };
+                    *index += char.len_utf8();
+                    // the iterator is still on the stack, so the gc keeps the string alive
+                    let string = char.to_string();
+                    Ok(Some(
+                        self.mem_manager.borrow_mut().alloc_string(self, string),
+                    ))
+                }
+                _ => Err(not_iterable()),
+            },
+
+            _ => Err(not_iterable()),
+        }
+    }
+
+    // replaces the values on top of the stack with a list of them.
+    // the list is allocated while the values are still on the stack, so a gc can't free them.
+    fn collect_rest_arguments(&mut self, count: usize) {
+        let list = self.mem_manager.borrow_mut().alloc_list(self, count);
+        let first = self.stack.len() - count;
+        if let Value::Heap(ptr) = list {
+            if let HeapValue::List(elements) = unsafe { &mut (*ptr).payload } {
+                elements.extend(self.stack.drain(first..));
+            }
+        }
+        self.push(list);
+    }
+
+    // the upvalue of the variable at the stack index, closures capturing the same variable share it.
+    fn capture_upvalue(&mut self, slot: usize) -> *mut HeapValueHeader {
+        match self
+            .open_upvalues
+            .binary_search_by_key(&slot, |&upvalue| open_slot(upvalue))
+        {
+            Ok(index) => self.open_upvalues[index],
+            Err(index) => {
+                let upvalue = self.mem_manager.borrow_mut().alloc_upvalue(self, slot);
+                self.open_upvalues.insert(index, upvalue);
+                upvalue
+            }
+        }
+    }
+
+    // moves the variables from the stack index up out of the stack, into their upvalues.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(&upvalue) = self.open_upvalues.last() {
+            let slot = open_slot(upvalue);
+            if slot < from {
+                break;
+            }
+            *upvalue_mut(upvalue) = Upvalue::Closed(self.stack[slot]);
+            self.open_upvalues.pop();
+        }
+    }
+
+    // the running function is a closure if it uses upvalues, which is in the callee slot.
+    // the verifier checks the index against the function's captures, but a function with
+    // captures can still end up running without them, e.g. when it's loaded with LoadFunction.
+    fn current_upvalue(&self, index: usize) -> Result<*mut HeapValueHeader> {
+        if let Value::Heap(ptr) = self.stack[self.fp] {
+            if let HeapValue::Closure { upvalues, .. } = unsafe { &(*ptr).payload } {
+                if let Some(&upvalue) = upvalues.get(index) {
+                    return Ok(upvalue);
+                }
+            }
+        }
+        Err(RuntimeError::InvalidBytecode {
+            message: format!(
+                "{} uses capture {}, but isn't a closure capturing it",
+                self.curr_func.name.fmt(&self.exec.string_data),
+                index
+            ),
+        })
+    }
+
+    pub(super) fn write_output(&mut self, text: &str) -> Result<()> {
+        self.stdout.borrow_mut().write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    // the next line of the input without its line ending, None at the end of the input.
+    pub(super) fn read_input_line(&mut self) -> Result<Option<String>> {
+        let stdin = match &mut self.stdin {
+            Some(stdin) => stdin,
+            None => return Ok(None),
+        };
+
+        let mut line = String::new();
+        if stdin
+            .read_line(&mut line)
+            .map_err(RuntimeError::StdinReadError)?
+            == 0
+        {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
     }
 
     #[inline]
@@ -120,50 +614,17 @@ impl<'a> VM<'a> {
         val
     }
 
-    #[inline]
-    fn read_u64(&mut self) -> u64 {
-        let code = &self.curr_func.code;
-        let val = u64::from_le_bytes([
-            code[self.ip],
-            code[self.ip + 1],
-            code[self.ip + 2],
-            code[self.ip + 3],
-            code[self.ip + 4],
-            code[self.ip + 5],
-            code[self.ip + 6],
-            code[self.ip + 7],
-        ]);
-        self.ip += 8;
-        val
-    }
-
     #[inline]
     fn get_local(&self, stack_offset: usize) -> Value {
         self.stack[self.fp + stack_offset]
     }
 
-    fn assert_function<'b>(&'b self, val: Value) -> &'a CahnFunction {
-        match val {
-            Value::Function { function_index } => &self.exec.functions[function_index as usize],
-            other => panic!(
-                "invalid bytecode, expected value to be a function, got: {}",
-                other.fmt(&self)
-            ),
-        }
-    }
-
-    fn assert_return_address(&self, val: Value) -> usize {
-        match val {
-            Value::ReturnAdress { ip } => ip,
-            other => panic!(
-                "invalid bytecode, expected value to be a return adress, got: {}",
-                other.fmt(&self)
-            ),
-        }
-    }
-
     #[inline]
     fn exec_instruction(&mut self, instruction: Instruction) -> Result<()> {
+        if self.options.count_instructions {
+            self.instruction_counts[instruction as usize] += 1;
+        }
+
         match instruction {
             Instruction::LoadStringLiteral => {
                 let start_index = self.read_u32();
@@ -175,16 +636,12 @@ impl<'a> VM<'a> {
             }
 
             Instruction::Concat => {
-                let right_val = self.pop();
-                let left_val = self.pop();
-                let new_string = format!("{}{}", left_val.fmt(&self), right_val.fmt(&self));
-
-                let new_val = self
-                    .mem_manager
-                    .borrow_mut()
-                    .alloc_string(&self, new_string);
+                let right_val = self.stack[self.stack.len() - 1];
+                let left_val = self.stack[self.stack.len() - 2];
+                self.check_concat_operands(left_val, right_val)?;
+                let new_string = format!("{}{}", left_val.fmt(self), right_val.fmt(self));
 
-                self.push(new_val);
+                self.replace_operands_with_string(2, new_string);
             }
 
             Instruction::LoadConstNum => {
@@ -227,6 +684,50 @@ impl<'a> VM<'a> {
                 self.push(self.get_local(stack_offset as usize))
             }
 
+            Instruction::GetUpvalue => {
+                let index = self.read_u8() as usize;
+                let value = match *upvalue_mut(self.current_upvalue(index)?) {
+                    Upvalue::Open(slot) => self.stack[slot],
+                    Upvalue::Closed(value) => value,
+                };
+                self.push(value);
+            }
+
+            Instruction::SetUpvalue => {
+                let index = self.read_u8() as usize;
+                let value = self.pop();
+                match upvalue_mut(self.current_upvalue(index)?) {
+                    Upvalue::Open(slot) => self.stack[*slot] = value,
+                    Upvalue::Closed(closed) => *closed = value,
+                }
+            }
+
+            Instruction::CloseUpvalue => {
+                let slot = self.read_u16() as usize;
+                self.close_upvalues(self.fp + slot);
+            }
+
+            Instruction::DefineGlobal => {
+                let index = self.read_u32() as usize;
+                self.globals[index] = Some(self.pop());
+            }
+
+            Instruction::SetGlobal => {
+                let index = self.read_u32() as usize;
+                if self.globals[index].is_none() {
+                    return Err(self.undefined_global(index));
+                }
+                self.globals[index] = Some(self.pop());
+            }
+
+            Instruction::GetGlobal => {
+                let index = self.read_u32() as usize;
+                match self.globals[index] {
+                    Some(val) => self.push(val),
+                    None => return Err(self.undefined_global(index)),
+                }
+            }
+
             Instruction::LoadTrue => self.push(Value::Bool(true)),
             Instruction::LoadFalse => self.push(Value::Bool(false)),
             Instruction::LoadNil => self.push(Value::Nil),
@@ -301,6 +802,46 @@ impl<'a> VM<'a> {
                 }
             }
 
+            Instruction::FloorDiv => {
+                let right = self.pop();
+                let left = self.pop();
+
+                match (left, right) {
+                    (Value::Number(left_num), Value::Number(right_val)) => {
+                        self.push(Value::Number((left_num / right_val).floor()))
+                    }
+                    _ => {
+                        return Err(RuntimeError::TypeError {
+                            message: format!(
+                                "floor-division-instruction expected two numbers, but got '{}' and '{}'",
+                                left.fmt(self),
+                                right.fmt(self)
+                            ),
+                        })
+                    }
+                }
+            }
+
+            Instruction::Pow => {
+                let right = self.pop();
+                let left = self.pop();
+
+                match (left, right) {
+                    (Value::Number(left_num), Value::Number(right_val)) => {
+                        self.push(Value::Number(left_num.powf(right_val)))
+                    }
+                    _ => {
+                        return Err(RuntimeError::TypeError {
+                            message: format!(
+                                "power-instruction expected two numbers, but got '{}' and '{}'",
+                                left.fmt(self),
+                                right.fmt(self)
+                            ),
+                        })
+                    }
+                }
+            }
+
             Instruction::Modulo => {
                 let right = self.pop();
                 let left = self.pop();
@@ -321,6 +862,44 @@ impl<'a> VM<'a> {
                 }
             }
 
+            Instruction::BitAnd => {
+                let (left, right) = self.pop_whole_numbers("bitwise-and")?;
+                self.push(Value::Number((left & right) as f64));
+            }
+
+            Instruction::BitOr => {
+                let (left, right) = self.pop_whole_numbers("bitwise-or")?;
+                self.push(Value::Number((left | right) as f64));
+            }
+
+            Instruction::BitXor => {
+                let (left, right) = self.pop_whole_numbers("bitwise-xor")?;
+                self.push(Value::Number((left ^ right) as f64));
+            }
+
+            Instruction::Shl => {
+                let (left, right) = self.pop_whole_numbers("shift-left")?;
+                let shifted = left << Self::shift_amount(right)?;
+                self.push(Value::Number(shifted as f64));
+            }
+
+            Instruction::Shr => {
+                let (left, right) = self.pop_whole_numbers("shift-right")?;
+                let shifted = left >> Self::shift_amount(right)?;
+                self.push(Value::Number(shifted as f64));
+            }
+
+            Instruction::TypeOf => {
+                let val = self.peek();
+                match self.type_name_literal(val) {
+                    Some(literal) => {
+                        self.pop();
+                        self.push(literal);
+                    }
+                    None => self.replace_operands_with_string(1, val.type_name()),
+                }
+            }
+
             Instruction::Negate => {
                 let val = self.pop();
 
@@ -426,7 +1005,55 @@ impl<'a> VM<'a> {
                 let right = self.pop();
                 let left = self.pop();
 
-                self.push(Value::Bool(left == right));
+                self.push(Value::Bool(left.cahn_eq(&right, &self.exec)));
+            }
+
+            Instruction::Contains => {
+                let container = self.pop();
+                let value = self.pop();
+
+                let list = match container {
+                    Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                        HeapValue::List(list) => Some(list),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                // elements are compared like '==' compares them
+                let contains = match list {
+                    Some(list) => list.iter().any(|elem| elem.cahn_eq(&value, &self.exec)),
+                    None => match (container.as_str(&self.exec), value.as_str(&self.exec)) {
+                        (Some(string), Some(substring)) => string.contains(substring),
+                        (Some(_), None) => {
+                            return Err(RuntimeError::TypeError {
+                                message: format!(
+                                    "only strings can be in a string, got {}",
+                                    value.fmt(self)
+                                ),
+                            })
+                        }
+                        (None, _) => {
+                            return Err(RuntimeError::TypeError {
+                                message: format!(
+                                    "'in' expected a list or string, got {}",
+                                    container.fmt(self)
+                                ),
+                            })
+                        }
+                    },
+                };
+                self.push(Value::Bool(contains));
+            }
+
+            Instruction::ConstTrue => {
+                let val = self.pop();
+                self.push(Value::Bool(val == Value::Bool(true)));
+            }
+
+            Instruction::ConstFalse => {
+                let val = self.pop();
+                self.push(Value::Bool(val == Value::Bool(false)));
             }
 
             Instruction::Dup => {
@@ -438,10 +1065,43 @@ impl<'a> VM<'a> {
                 self.pop();
             }
 
+            Instruction::Swap => {
+                let len = self.stack.len();
+                self.stack.swap(len - 1, len - 2);
+            }
+
+            Instruction::DupUnder => {
+                let val = self.stack[self.stack.len() - 2];
+                self.push(val);
+            }
+
+            Instruction::Rot => {
+                let len = self.stack.len();
+                self.stack[len - 3..].rotate_left(1);
+            }
+
             Instruction::Print => {
                 let val = self.pop();
                 // let out = mem::replace(self.stdout);
-                write!(self.stdout.borrow_mut(), "{}\n", val.fmt(self))?;
+                writeln!(self.stdout.borrow_mut(), "{}", val.fmt(self))?;
+            }
+
+            Instruction::PrintN => {
+                let count = self.read_u8() as usize;
+                let first = self.stack.len() - count;
+                let line = self.stack[first..]
+                    .iter()
+                    .map(|val| Value::fmt(*val, self).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(self.stdout.borrow_mut(), "{}", line)?;
+                self.stack.truncate(first);
+            }
+
+            Instruction::Unpack => {
+                let count = self.read_u8() as usize;
+                let value = self.pop();
+                self.unpack(value, count)?;
             }
 
             Instruction::Jump => {
@@ -455,6 +1115,56 @@ impl<'a> VM<'a> {
                     self.ip = jump_location;
                 }
             }
+
+            Instruction::JumpIfFalsePeek => {
+                let jump_location = self.read_u32() as usize;
+                if !self.peek().is_truthy() {
+                    self.ip = jump_location;
+                }
+            }
+
+            Instruction::JumpIfTruePeek => {
+                let jump_location = self.read_u32() as usize;
+                if self.peek().is_truthy() {
+                    self.ip = jump_location;
+                }
+            }
+            Instruction::IterInit => {
+                let container = self.peek();
+                let is_iterable = match container {
+                    Value::StringLiteral { .. } => true,
+                    Value::Heap(ptr) => matches!(
+                        unsafe { &(*ptr).payload },
+                        HeapValue::List(_) | HeapValue::String(_)
+                    ),
+                    _ => false,
+                };
+                if !is_iterable {
+                    return Err(RuntimeError::TypeError {
+                        message: format!(
+                            "for-in expected a list or string, got {}",
+                            container.fmt(self)
+                        ),
+                    });
+                }
+
+                // the container stays on the stack until the iterator holding it is allocated
+                let iterator = self
+                    .mem_manager
+                    .borrow_mut()
+                    .alloc_iterator(self, container);
+                self.pop();
+                self.push(iterator);
+            }
+
+            Instruction::IterNext => {
+                let jump_location = self.read_u32() as usize;
+                match self.advance_iterator()? {
+                    Some(element) => self.push(element),
+                    None => self.ip = jump_location,
+                }
+            }
+
             Instruction::CreateList => {
                 let list = self.mem_manager.borrow_mut().alloc_list(self, 0);
                 self.push(list)
@@ -480,15 +1190,109 @@ impl<'a> VM<'a> {
                             return Ok(());
                         }
                     }
-                    return Err(RuntimeError::TypeError {
+                    Err(RuntimeError::TypeError {
                         message: format!(
                             "tried to push an element to a non-list type: '{}'",
                             right.fmt(self)
                         ),
-                    });
+                    })
                 })()?;
             }
 
+            Instruction::CreateRecord => {
+                let struct_index = self.read_u32();
+                let field_count = self.exec.structs[struct_index as usize].fields.len();
+                let record =
+                    self.mem_manager
+                        .borrow_mut()
+                        .alloc_record(self, struct_index, field_count);
+                self.push(record)
+            }
+
+            Instruction::RecordPush => {
+                let value = self.pop();
+                let record = self.peek();
+
+                (|| unsafe {
+                    if let Value::Heap(ptr) = record {
+                        if let HeapValue::Record { fields, .. } = &mut (*ptr).payload {
+                            fields.push(value);
+                            return Ok(());
+                        }
+                    }
+                    Err(RuntimeError::TypeError {
+                        message: format!(
+                            "tried to set a field of a non-record type: '{}'",
+                            record.fmt(self)
+                        ),
+                    })
+                })()?;
+            }
+
+            Instruction::GetField => {
+                let field = self.read_u32();
+                let slot = self.read_u32();
+                let record = self.pop();
+
+                let (struct_index, fields) = match record {
+                    Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                        HeapValue::Record {
+                            struct_index,
+                            fields,
+                        } => (*struct_index, fields),
+                        _ => return Err(self.not_a_record(record, field, "get")),
+                    },
+                    _ => return Err(self.not_a_record(record, field, "get")),
+                };
+
+                match self
+                    .exec
+                    .field_index(struct_index, field, slot)
+                    .and_then(|index| fields.get(index))
+                {
+                    Some(&value) => self.push(value),
+                    None => {
+                        return Err(RuntimeError::UndefinedField {
+                            record: self.exec.structs[struct_index as usize].name.clone(),
+                            field: self.exec.field_names[field as usize].clone(),
+                        })
+                    }
+                }
+            }
+
+            Instruction::SetField => {
+                let field = self.read_u32();
+                let slot = self.read_u32();
+                let record = self.pop();
+                let value = self.pop();
+
+                let (struct_index, fields) = match record {
+                    Value::Heap(ptr) => match unsafe { &mut (*ptr).payload } {
+                        HeapValue::Record {
+                            struct_index,
+                            fields,
+                        } => (*struct_index, fields),
+                        _ => return Err(self.not_a_record(record, field, "set")),
+                    },
+                    _ => return Err(self.not_a_record(record, field, "set")),
+                };
+
+                match self
+                    .exec
+                    .field_index(struct_index, field, slot)
+                    .and_then(|index| fields.get_mut(index))
+                {
+                    Some(slot) => *slot = value,
+                    None => {
+                        return Err(RuntimeError::UndefinedField {
+                            record: self.exec.structs[struct_index as usize].name.clone(),
+                            field: self.exec.field_names[field as usize].clone(),
+                        })
+                    }
+                }
+                self.push(value);
+            }
+
             Instruction::ListGetIndex => {
                 let index = self.pop();
                 let list = self.pop();
@@ -512,6 +1316,10 @@ impl<'a> VM<'a> {
                                 len: list.len(),
                             });
                         }
+                        if num.fract() != 0.0 {
+                            let pos = self.instruction_pos();
+                            self.warn(RuntimeWarning::FractionalIndex { index: num, pos });
+                        }
                         num as usize
                     }
 
@@ -532,46 +1340,571 @@ impl<'a> VM<'a> {
                 let function_index = self.read_u32();
                 self.push(Value::Function { function_index })
             }
+
+            Instruction::Closure => {
+                let function_index = self.read_u32();
+                let function = Rc::clone(&self.exec.functions[function_index as usize]);
+
+                // pushed before the upvalues are allocated, so the ones it holds survive a gc
+                let closure = self
+                    .mem_manager
+                    .borrow_mut()
+                    .alloc_closure(self, function_index);
+                self.push(closure);
+
+                for capture in &function.captures {
+                    let upvalue = if capture.is_local {
+                        self.capture_upvalue(self.fp + capture.index as usize)
+                    } else {
+                        self.current_upvalue(capture.index as usize)?
+                    };
+                    if let Value::Heap(ptr) = closure {
+                        if let HeapValue::Closure { upvalues, .. } = unsafe { &mut (*ptr).payload }
+                        {
+                            upvalues.push(upvalue);
+                        }
+                    }
+                }
+            }
+
+            Instruction::Return => {
+                let result = self.pop();
+                let results = self.return_to_caller()?;
+                if results == 1 {
+                    self.push(result);
+                } else {
+                    self.unpack(result, results)?;
+                }
+            }
+
+            Instruction::ReturnMany => {
+                let count = self.read_u8() as usize;
+                let results = match self.frames.last() {
+                    Some(frame) => frame.results,
+                    None => 1,
+                };
+
+                if results == 1 {
+                    // the caller takes a single value, so it gets them as a list
+                    self.collect_rest_arguments(count);
+                    let result = self.pop();
+                    self.return_to_caller()?;
+                    self.push(result);
+                } else if results == count {
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    self.return_to_caller()?;
+                    self.stack.extend(values);
+                } else {
+                    return Err(RuntimeError::UnpackMismatch {
+                        expected: results,
+                        got: count,
+                    });
+                }
+            }
+
+            Instruction::Assert => {
+                let message = self.pop();
+                let condition = self.pop();
+                if !condition.is_truthy() {
+                    return Err(RuntimeError::AssertionFailed {
+                        message: match message {
+                            Value::Nil => "the condition is falsy".into(),
+                            message => message.fmt(self).to_string(),
+                        },
+                        // Assert doesn't have operands, so the instruction is the byte before ip
+                        pos: self.curr_func.code_map[self.ip - 1],
+                    });
+                }
+            }
+
+            Instruction::Nop => {}
+
+            Instruction::Breakpoint => {
+                // stepping again after the debugger restored the instruction runs it
+                self.ip -= 1;
+                return Err(RuntimeError::Breakpoint {
+                    pos: self.curr_func.code_map[self.ip],
+                });
+            }
+
+            Instruction::Halt => self.halted = true,
+
+            Instruction::LoadNative => {
+                let native_index = self.read_u32();
+                self.push(Value::NativeFunction { native_index })
+            }
+
+            Instruction::Invoke => {
+                let arg_count = self.read_u8() as usize;
+                self.invoke(arg_count, 1)?;
+            }
+
+            Instruction::InvokeMany => {
+                let arg_count = self.read_u8() as usize;
+                let results = self.read_u8() as usize;
+                self.invoke(arg_count, results)?;
+            }
+        };
+        Ok(())
+    }
+
+    /// Replaces the function at the given index, so the next calls to it run the new code.
+    /// Calls to it that are already running finish with the old code.
+    pub fn replace_function(
+        &mut self,
+        index: usize,
+        new_func: CahnFunction,
+    ) -> executable::error::Result<()> {
+        let count = self.exec.functions.len();
+        if index >= count {
+            return Err(BytecodeError::NoSuchFunction { index, count });
+        }
+
+        if index == count - 1 {
+            self.exec.check_main_function(&new_func)?;
+        } else {
+            self.exec.check_function(&new_func)?;
+            // the code loading and calling the function was verified against the old one
+            let old_func = &self.exec.functions[index];
+            let incompatible = |what: &str, old: usize, new: usize| {
+                Err(BytecodeError::IncompatibleReplacement {
+                    index,
+                    message: format!("it has {} {}, the old one has {}", new, what, old),
+                })
+            };
+            if new_func.param_count != old_func.param_count {
+                return incompatible(
+                    "parameters",
+                    old_func.param_count as usize,
+                    new_func.param_count as usize,
+                );
+            }
+            if new_func.default_entries.len() != old_func.default_entries.len() {
+                return incompatible(
+                    "default values",
+                    old_func.default_entries.len(),
+                    new_func.default_entries.len(),
+                );
+            }
+            if new_func.is_variadic != old_func.is_variadic {
+                return incompatible(
+                    "rest parameters",
+                    old_func.is_variadic as usize,
+                    new_func.is_variadic as usize,
+                );
+            }
+            // Closure instructions creating it were checked against the old captures
+            if new_func.captures != old_func.captures {
+                return Err(BytecodeError::IncompatibleReplacement {
+                    index,
+                    message: "it captures different variables than the old one".into(),
+                });
+            }
+        }
+        self.exec.to_mut().functions[index] = Rc::new(new_func);
+        Ok(())
+    }
+
+    // the value's type name as a string literal, None for the names that aren't in the
+    // string data, which have to be allocated.
+    pub(super) fn type_name_literal(&self, value: Value) -> Option<Value> {
+        let (start_index, end_index) = self.exec.type_name_slice(&value.type_name())?;
+        Some(Value::StringLiteral {
+            start_index,
+            end_index,
+        })
+    }
+
+    // concatenating nil is an error in strict concat mode, and a warning otherwise
+    fn check_concat_operands(&mut self, left: Value, right: Value) -> Result<()> {
+        let side = match (left, right) {
+            (Value::Nil, Value::Nil) => "both sides",
+            (Value::Nil, _) => "the left side",
+            (_, Value::Nil) => "the right side",
+            _ => return Ok(()),
+        };
+        let pos = self.instruction_pos();
+        if !self.options.strict_concat {
+            self.warn(RuntimeWarning::NilConcat { side, pos });
+            return Ok(());
+        }
+        Err(RuntimeError::TypeError {
+            message: format!(
+                "{} of the concatenation at {} is nil, which strict concatenation doesn't allow",
+                side, pos
+            ),
+        })
+    }
+
+    /// How many heap values of each kind the program can reach right now, along with its
+    /// largest lists and strings, and how much garbage is waiting to be collected.
+    /// Nothing is collected, and the heap is left like it was.
+    pub fn heap_report(&self) -> HeapReport {
+        self.mem_manager.borrow_mut().heap_report(self)
+    }
+
+    // where the instruction allocating a heap value is, None before the program started
+    pub(super) fn instruction_site(&self) -> Option<TokenPos> {
+        self.ip
+            .checked_sub(1)
+            .and_then(|index| self.curr_func.code_map.get(index))
+            .copied()
+    }
+
+    // where the running instruction is in the source. the code map has a position for every
+    // byte of an instruction, so the byte before ip works whether it has operands or not.
+    fn instruction_pos(&self) -> TokenPos {
+        self.curr_func.code_map[self.ip - 1]
+    }
+
+    // whether the interrupt flag was set, clearing it
+    fn take_interrupt(&self) -> bool {
+        match &self.interrupt_flag {
+            Some(flag) => flag.swap(false, Ordering::SeqCst),
+            None => false,
+        }
+    }
+
+    // reports the warning, unless the running instruction already reported that kind of warning
+    // this run. ip is past the start of the instruction, but not past its end,
+    // so it tells the instruction apart from the others in its function.
+    fn warn(&mut self, warning: RuntimeWarning) {
+        let site = (warning.code(), self.curr_func_index, self.ip as u32);
+        if self.options.runtime_warnings && self.warned_at.insert(site) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn not_a_record(&self, value: Value, field: u32, action: &str) -> RuntimeError {
+        RuntimeError::TypeError {
+            message: format!(
+                "can't {} field '{}' of {}, it isn't a record",
+                action,
+                self.exec.field_names[field as usize],
+                value.fmt(self)
+            ),
+        }
+    }
+
+    // calls the function below the arguments, the caller takes the given number of results
+    fn invoke(&mut self, arg_count: usize, results: usize) -> Result<()> {
+        let callee_index = self.stack.len() - 1 - arg_count;
+
+        // closures are called like their function, which finds the upvalues in the callee slot
+        let callee = match self.stack[callee_index] {
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::Closure { function_index, .. } => Value::Function {
+                    function_index: *function_index,
+                },
+                _ => Value::Heap(ptr),
+            },
+            callee => callee,
+        };
+
+        match callee {
+            Value::NativeFunction { native_index } => {
+                let native = self.exec.natives[native_index as usize];
+                if !native.accepts(arg_count) {
+                    let expected = if arg_count < native.arity {
+                        native.arity
+                    } else {
+                        native.arity + native.optional_args
+                    };
+                    return Err(RuntimeError::ArityMismatch {
+                        name: native.name.into(),
+                        expected,
+                        got: arg_count,
+                    });
+                }
+
+                let args = self.stack[callee_index + 1..].to_vec();
+                let result = (native.func)(&mut VmContext::new(self), &args)?;
+
+                self.stack.truncate(callee_index);
+                if results == 1 {
+                    self.push(result);
+                } else {
+                    self.unpack(result, results)?;
+                }
+            }
+
+            Value::Function { function_index } => {
+                let function = Rc::clone(&self.exec.functions[function_index as usize]);
+
+                // the arguments after the fixed parameters become the rest parameter
+                let mut arg_count = arg_count;
+                let fixed_count = function.param_count as usize - function.is_variadic as usize;
+                if function.is_variadic && arg_count >= fixed_count {
+                    self.collect_rest_arguments(arg_count - fixed_count);
+                    arg_count = fixed_count + 1;
+                }
+
+                let entry_point = match function.entry_point(arg_count) {
+                    Some(entry_point) => entry_point,
+                    None => {
+                        let expected = if arg_count < function.param_count as usize {
+                            function.min_param_count()
+                        } else {
+                            function.param_count
+                        };
+                        return Err(RuntimeError::ArityMismatch {
+                            name: function.name.fmt(&self.exec.string_data).to_string(),
+                            expected: expected as usize,
+                            got: arg_count,
+                        });
+                    }
+                };
+
+                if self.frames.len() >= self.options.max_call_depth {
+                    return Err(RuntimeError::StackOverflow {
+                        max: self.options.max_call_depth,
+                    });
+                }
+
+                // the callee and the arguments become the first locals of the function
+                let caller = mem::replace(&mut self.curr_func, function);
+                self.frames.push(CallFrame {
+                    function: caller,
+                    function_index: mem::replace(&mut self.curr_func_index, function_index),
+                    results,
+                    ip: self.ip,
+                    fp: self.fp,
+                });
+
+                // missing arguments are pushed by the code that computes their defaults
+                self.ip = entry_point;
+                self.fp = callee_index;
+            }
+
+            callee => {
+                return Err(RuntimeError::TypeError {
+                    message: format!(
+                        "tried to call '{}', which isn't a function",
+                        callee.fmt(self)
+                    ),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    // leaves the running function, returning the number of results its caller takes
+    fn return_to_caller(&mut self) -> Result<usize> {
+        let frame = self
+            .frames
+            .pop()
+            .ok_or_else(|| RuntimeError::InvalidBytecode {
+                message: "the top level function returned, there's no caller to return to".into(),
+            })?;
+
+        self.close_upvalues(self.fp);
+        self.stack.truncate(self.fp);
+        self.curr_func = frame.function;
+        self.curr_func_index = frame.function_index;
+        self.ip = frame.ip;
+        self.fp = frame.fp;
+        Ok(frame.results)
+    }
+
+    // pushes the elements of the value, which has to be a list of count elements
+    fn unpack(&mut self, value: Value, count: usize) -> Result<()> {
+        let elements = match value {
+            Value::Heap(ptr) => match unsafe { &(*ptr).payload } {
+                HeapValue::List(list) => Some(list),
+                _ => None,
+            },
+            _ => None,
         };
+        match elements {
+            Some(elements) if elements.len() == count => {
+                for &element in elements.iter() {
+                    self.push(element);
+                }
+            }
+            Some(elements) => {
+                return Err(RuntimeError::UnpackMismatch {
+                    expected: count,
+                    got: elements.len(),
+                })
+            }
+            None => {
+                return Err(RuntimeError::TypeError {
+                    message: format!(
+                        "can't unpack {} values from '{}', it isn't a list",
+                        count,
+                        value.fmt(self)
+                    ),
+                })
+            }
+        }
         Ok(())
     }
 
+    // pops the operands of a bitwise instruction, which have to be whole numbers.
+    // they're converted to 64 bit integers, numbers out of that range saturate.
+    fn pop_whole_numbers(&mut self, instruction: &str) -> Result<(i64, i64)> {
+        let right = self.pop();
+        let left = self.pop();
+
+        match (left, right) {
+            (Value::Number(left_num), Value::Number(right_num))
+                if left_num.fract() == 0.0 && right_num.fract() == 0.0 =>
+            {
+                Ok((left_num as i64, right_num as i64))
+            }
+            (Value::Number(_), Value::Number(_)) => Err(RuntimeError::TypeError {
+                message: format!(
+                    "{}-instruction expected two whole numbers, but got '{}' and '{}'",
+                    instruction,
+                    left.fmt(self),
+                    right.fmt(self)
+                ),
+            }),
+            _ => Err(RuntimeError::TypeError {
+                message: format!(
+                    "{}-instruction expected two numbers, but got '{}' and '{}'",
+                    instruction,
+                    left.fmt(self),
+                    right.fmt(self)
+                ),
+            }),
+        }
+    }
+
+    // shifting by 64 bits or more would shift every bit out, which i64 doesn't allow
+    fn shift_amount(amount: i64) -> Result<u32> {
+        if (0..64).contains(&amount) {
+            Ok(amount as u32)
+        } else {
+            Err(RuntimeError::TypeError {
+                message: format!("can only shift by 0 to 63 bits, but got {}", amount),
+            })
+        }
+    }
+
+    fn undefined_global(&self, index: usize) -> RuntimeError {
+        RuntimeError::UndefinedVariable {
+            name: self.exec.global_names[index].clone(),
+        }
+    }
+
     fn print_stack(&self) {
         for (index, val) in self.stack.iter().enumerate() {
             if index == self.fp {
                 print!("<fp>");
             }
-            print!("{}   ", (*val).fmt(&self));
+            print!("{}   ", (*val).repr(self));
         }
         println!();
     }
 
-    pub fn run(mut self) -> Result<()> {
-        while self.ip < self.curr_func.code.len() {
-            let code_pos = self.curr_func.code_map[self.ip];
+    // where in the source the next instruction is from.
+    // verified code always ends with Return or Halt, so only other code can run out of it.
+    fn current_code_pos(&self) -> Result<TokenPos> {
+        match self.curr_func.code_map.get(self.ip) {
+            Some(&code_pos) => Ok(code_pos),
+            None => Err(RuntimeError::InvalidBytecode {
+                message: format!(
+                    "{} has no instruction at {}",
+                    self.curr_func.name.fmt(&self.exec.string_data),
+                    self.ip
+                ),
+            }),
+        }
+    }
+
+    /// Executes a single instruction.
+    /// Returns false once the program has finished, true otherwise.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.halted {
+            return Ok(false);
+        }
 
-            let instruction = self.read_instruction();
-            // println!("about to run: {:?}", instruction);
+        let code_pos = self.current_code_pos()?;
+
+        if self
+            .instructions_run
+            .is_multiple_of(INTERRUPT_CHECK_INTERVAL)
+            && self.take_interrupt()
+        {
+            self.error_pos = Some(code_pos);
+            return Err(RuntimeError::Interrupted {
+                instructions_run: self.instructions_run,
+            });
+        }
 
-            // let mut string = String::new();
-            // std::io::stdin().read_line(&mut string).unwrap();
+        let instruction = self.read_instruction();
+        self.instructions_run += 1;
+        // println!("about to run: {:?}", instruction);
 
-            self.exec_instruction(instruction)?;
+        // let mut string = String::new();
+        // std::io::stdin().read_line(&mut string).unwrap();
 
-            let mut padding = String::new();
-            let ins_str = format!("{:?}", instruction);
+        if let Err(err) = self.exec_instruction(instruction) {
+            self.error_pos = Some(code_pos);
+            return Err(err);
+        }
 
-            for _ in 0..(20 - ins_str.len()) {
-                padding.push('-');
-            }
+        let ins_str = format!("{:?}", instruction);
 
-            print!(
-                "{}:{}\t{:?}{}-->   ",
-                self.exec.source_file, code_pos, instruction, padding,
-            );
+        print!(
+            "{}:{}\t{:-<20}-->   ",
+            self.exec.source_file_of(code_pos),
+            code_pos,
+            ins_str,
+        );
 
-            self.print_stack();
+        self.print_stack();
+
+        Ok(!self.halted)
+    }
+
+    /// Like `step`, but also returns the instruction it executed along with its operands,
+    /// like "GetGlobal    0 'x'", so a debugger can show what ran.
+    /// Returns None once the program has finished, without executing anything.
+    pub fn step_disassembled(&mut self) -> Result<Option<String>> {
+        if self.halted {
+            return Ok(None);
         }
+
+        self.current_code_pos()?;
+        let disassembly = self
+            .curr_func
+            .fmt_instruction(&self.exec, self.ip)
+            .to_string();
+        self.step()?;
+        Ok(Some(disassembly))
+    }
+
+    /// Runs the program to its end.
+    /// The VM is used up, as its globals and heap belong to this one run,
+    /// so running the executable again takes a new VM.
+    ///
+    /// ```compile_fail
+    /// # use cahn_lang::{compile, runtime::VM};
+    /// let exec = compile("print 1", "example".into()).unwrap();
+    /// let mut out = std::io::sink();
+    /// let vm = VM::new(&exec, &mut out);
+    /// vm.run().unwrap();
+    /// vm.run().unwrap(); // the first run moved the VM
+    /// ```
+    pub fn run(mut self) -> Result<()> {
+        while self.step()? {}
         Ok(())
     }
 }
+
+// the state of the upvalue the pointer points to.
+fn upvalue_mut<'u>(upvalue: *mut HeapValueHeader) -> &'u mut Upvalue {
+    match unsafe { &mut (*upvalue).payload } {
+        HeapValue::Upvalue(upvalue) => upvalue,
+        _ => unreachable!("closures only hold upvalues"),
+    }
+}
+
+fn open_slot(upvalue: *mut HeapValueHeader) -> usize {
+    match upvalue_mut(upvalue) {
+        Upvalue::Open(slot) => *slot,
+        Upvalue::Closed(_) => unreachable!("closed upvalues aren't in the open list"),
+    }
+}
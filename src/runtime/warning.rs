@@ -0,0 +1,64 @@
+use std::fmt;
+
+use crate::{compiler::lexical_analysis::TokenPos, executable::COMPILER_VERSION};
+
+/// Things a running program did that are allowed, but are most likely mistakes.
+/// Each place in the source reports a kind of warning only once per run,
+/// so a warning in a loop doesn't repeat for every iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeWarning {
+    /// A concatenation with nil, which gives "nil".
+    /// `side` says which operand was nil, like "the left side".
+    NilConcat { side: &'static str, pos: TokenPos },
+
+    /// A list index with a fractional part, which is cut off.
+    FractionalIndex { index: f64, pos: TokenPos },
+
+    /// The executable was compiled by another version of cahn_lang, with the same instruction set.
+    /// It's about the whole program, so its position is the start.
+    OtherCompilerVersion { compiled_with: String },
+}
+
+impl RuntimeWarning {
+    /// A short name for the kind of warning, that stays the same between versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeWarning::NilConcat { .. } => "nil-concat",
+            RuntimeWarning::FractionalIndex { .. } => "fractional-index",
+            RuntimeWarning::OtherCompilerVersion { .. } => "other-compiler-version",
+        }
+    }
+
+    /// Where in the source the warning happened.
+    pub fn pos(&self) -> TokenPos {
+        match self {
+            RuntimeWarning::NilConcat { pos, .. } | RuntimeWarning::FractionalIndex { pos, .. } => {
+                *pos
+            }
+            RuntimeWarning::OtherCompilerVersion { .. } => TokenPos::default(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeWarning::NilConcat { side, pos } => f.write_fmt(format_args!(
+                "{} of the concatenation at {} is nil",
+                side, pos
+            )),
+
+            RuntimeWarning::FractionalIndex { index, pos } => f.write_fmt(format_args!(
+                "the list index {} at {} isn't a whole number, so it's cut off to {}",
+                index,
+                pos,
+                index.trunc()
+            )),
+
+            RuntimeWarning::OtherCompilerVersion { compiled_with } => f.write_fmt(format_args!(
+                "the program was compiled by cahn_lang {}, but is run by {}",
+                compiled_with, COMPILER_VERSION
+            )),
+        }
+    }
+}
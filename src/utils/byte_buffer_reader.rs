@@ -6,7 +6,11 @@ pub struct PanickingByteBufferReader<'a> {
 
 impl<'a> PanickingByteBufferReader<'a> {
     pub fn new(bytes: &'a [u8]) -> Self {
-        Self { bytes, i: 0 }
+        Self::starting_at(bytes, 0)
+    }
+
+    pub fn starting_at(bytes: &'a [u8], i: usize) -> Self {
+        Self { bytes, i }
     }
 
     pub fn current_index(&self) -> usize {
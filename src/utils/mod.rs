@@ -1,8 +1,10 @@
 mod byte_buffer_reader;
+mod rng;
 
 use {ahash::AHasher, std::hash::Hasher};
 
 pub use byte_buffer_reader::PanickingByteBufferReader;
+pub use rng::Rng;
 
 pub fn hash_string(string: &str) -> u64 {
     let mut hasher = AHasher::default();
@@ -0,0 +1,67 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    runtime::{error::RuntimeError, VM},
+};
+use common::run;
+
+#[test]
+fn strings_have_prefixes_and_suffixes() {
+    assert_eq!(
+        run(r#"print starts_with("hello", "he"), starts_with("hello", "lo")"#),
+        "true false\n"
+    );
+    assert_eq!(
+        run(r#"print ends_with("hello", "lo"), ends_with("hello", "he")"#),
+        "true false\n"
+    );
+    assert_eq!(
+        run(r#"print starts_with("hi", ""), ends_with("hi", "hi"), starts_with("hi", "hi!")"#),
+        "true true false\n"
+    );
+}
+
+#[test]
+fn strings_built_at_runtime_are_compared_by_content() {
+    let source = r#"
+        let greeting := "hel" .. "lo"
+        print starts_with(greeting, "h" .. "e"), ends_with("say " .. greeting, greeting)
+    "#;
+    assert_eq!(run(source), "true true\n");
+}
+
+#[test]
+fn lists_have_prefixes_and_suffixes() {
+    assert_eq!(
+        run("print ends_with([1, 2, 3], [2, 3]), ends_with([1, 2, 3], [1, 2])"),
+        "true false\n"
+    );
+    assert_eq!(
+        run("print starts_with([1, 2, 3], [1, 2]), starts_with([1, 2, 3], [2])"),
+        "true false\n"
+    );
+    assert_eq!(
+        run("print starts_with([], []), ends_with([1], [1, 1]), starts_with([true], [true])"),
+        "true false true\n"
+    );
+}
+
+#[test]
+fn operands_have_to_be_alike() {
+    for source in &[
+        r#"starts_with("abc", ["a"])"#,
+        r#"ends_with(["a"], "a")"#,
+        "starts_with(1, 1)",
+    ] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use cahn_lang::{
+    compiler::{string_handling::StringInterner, syntactical_analysis::Parser},
+    execute_source_to_string,
+};
+
+#[test]
+fn assigned_to_a_variable_and_called() {
+    let source = "
+        let f := fn(x) { return x + 1 }
+        print f(41)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "42\n"
+    );
+}
+
+#[test]
+fn passed_to_and_returned_from_functions() {
+    let source = "
+        fn apply(f, x) {
+            return f(x)
+        }
+        fn make_doubler() {
+            return fn(x) { return x * 2 }
+        }
+        print apply(fn(x) { return x - 1 }, 10)
+        print apply(make_doubler(), 10)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "9\n20\n"
+    );
+}
+
+#[test]
+fn locals_and_globals() {
+    let source = r#"
+        let greeting := "hi"
+        {
+            let greet := fn(name, punctuation := "!") {
+                print greeting .. " " .. name .. punctuation
+            }
+            let count := fn(rest...) { return rest }
+            greet("ann")
+            greet("bob", "?")
+            print count(1, 2)
+        }
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "hi ann!\nhi bob?\n[1, 2]\n"
+    );
+}
+
+#[test]
+fn anonymous_functions_are_printed_without_a_name() {
+    assert_eq!(
+        execute_source_to_string("print fn(a, b) {}", "inline-test".into()).unwrap(),
+        "<fn:2>\n"
+    );
+}
+
+#[test]
+fn anonymous_functions_are_displayed_in_the_ast() {
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str("let f := fn(a, b := 1) {}", &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    assert_eq!(
+        &ast.to_string(),
+        "(program (let f (fn (a, b := 1) (block )))\n)"
+    );
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::{lexical_analysis::TokenPos, syntactical_analysis::ParseError},
+    error::CahnError,
+    execute_source_to_string,
+    runtime::error::RuntimeError,
+};
+use common::try_run;
+
+#[test]
+fn passing_assertions_do_nothing() {
+    let source = r#"
+        assert 1 == 1
+        assert true, "never shown"
+        assert [], "empty lists are truthy"
+        print "done"
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "done\n"
+    );
+}
+
+#[test]
+fn failing_assertion_has_its_message_and_position() {
+    match try_run("print 1\n  assert 1 == 2, \"oops\"\nprint 2") {
+        Err(RuntimeError::AssertionFailed { message, pos }) => {
+            assert_eq!(message, "oops");
+            assert_eq!(pos, TokenPos::new(2, 3));
+        }
+        other => panic!("expected an AssertionFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn failing_assertion_without_message() {
+    let err = try_run("let nil_var\nassert nil_var").unwrap_err();
+    assert!(matches!(
+        err,
+        RuntimeError::AssertionFailed { pos, .. } if pos == TokenPos::new(2, 1)
+    ));
+    assert_eq!(
+        err.to_string(),
+        "AssertionFailed at 2:1: the condition is falsy"
+    );
+}
+
+#[test]
+fn message_can_be_any_expression() {
+    let source = "let x := 3\nassert x < 2, \"x is \" .. x";
+    assert_eq!(
+        try_run(source).unwrap_err().to_string(),
+        "AssertionFailed at 2:1: x is 3"
+    );
+}
+
+#[test]
+fn assertions_work_inside_functions() {
+    let source = r#"
+        fn check(n) {
+            assert n > 0, "n has to be positive"
+            return n
+        }
+        print check(1)
+        print check(-1)
+    "#;
+    assert!(matches!(
+        try_run(source),
+        Err(RuntimeError::AssertionFailed { message, .. }) if message == "n has to be positive"
+    ));
+}
+
+#[test]
+fn assert_needs_a_condition() {
+    assert!(matches!(
+        compile("assert", "inline-test".into()),
+        Err(CahnError::Parse(ParseError::BadToken { .. }))
+    ));
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use cahn_lang::runtime::error::RuntimeError;
+use common::{run, runtime_error};
+
+#[test]
+fn numbers_convert_to_bases() {
+    assert_eq!(run("print to_base(255, 16)"), "ff\n");
+    assert_eq!(
+        run("print to_base(5, 2), to_base(0, 2), to_base(35, 36)"),
+        "101 0 z\n"
+    );
+    assert_eq!(run("print to_base(-255, 16), to_base(-0, 10)"), "-ff 0\n");
+    assert_eq!(run("print to_base(9007199254740992, 36)"), "2gosa7pa2gw\n");
+}
+
+#[test]
+fn strings_convert_from_bases() {
+    assert_eq!(run("print from_base(\"ff\", 16)"), "255\n");
+    assert_eq!(
+        run("print from_base(\"FF\", 16), from_base(\" 101 \", 2)"),
+        "255 5\n"
+    );
+    assert_eq!(
+        run("print from_base(\"-z\", 36), from_base(\"+7\", 8)"),
+        "-35 7\n"
+    );
+}
+
+#[test]
+fn conversions_round_trip() {
+    let source = "
+        for base in [2, 3, 8, 10, 16, 36] {
+            for n in [0, 1, -1, 42, 1000, -123456789] {
+                if from_base(to_base(n, base), base) != n {
+                    print n, base
+                }
+            }
+        }
+        print \"done\"
+    ";
+    assert_eq!(run(source), "done\n");
+}
+
+#[test]
+fn strings_that_are_not_numbers_give_nil() {
+    assert_eq!(
+        run("print from_base(\"12\", 2), from_base(\"\", 10), from_base(\"1 0\", 10)"),
+        "nil nil nil\n"
+    );
+    assert_eq!(
+        run("print from_base(\"zzzzzzzzzzzzzzzzzzzz\", 36)"),
+        "nil\n"
+    );
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+    for source in &[
+        "print to_base(1.5, 2)",
+        "print to_base(\"1\", 2)",
+        "print to_base(1e300, 10)",
+        "print to_base(10, 1)",
+        "print to_base(10, 37)",
+        "print to_base(10, 2.5)",
+        "print from_base(10, 10)",
+        "print from_base(\"10\", 0)",
+        "print from_base(\"10\", true)",
+    ] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::TypeError { .. }),
+            "{}",
+            source
+        );
+    }
+}
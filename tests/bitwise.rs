@@ -0,0 +1,74 @@
+mod common;
+
+use cahn_lang::runtime::error::RuntimeError;
+use common::{disassemble, run, runtime_error};
+
+#[test]
+fn bitwise_operators() {
+    assert_eq!(run("print 6 & 3"), "2\n");
+    assert_eq!(run("print 6 | 3"), "7\n");
+    assert_eq!(run("print 6 ^ 3"), "5\n");
+    assert_eq!(run("print 1 << 10"), "1024\n");
+    assert_eq!(run("print 1024 >> 3"), "128\n");
+}
+
+#[test]
+fn shifting_right_keeps_the_sign() {
+    assert_eq!(run("print -16 >> 2, -1 >> 63"), "-4 -1\n");
+}
+
+#[test]
+fn bitwise_operators_bind_looser_than_arithmetic() {
+    assert_eq!(run("print 1 + 2 << 3"), "24\n");
+    assert_eq!(run("print 1 << 2 + 3"), "32\n");
+    assert_eq!(run("print 2 * 3 & 4 - 1"), "2\n");
+    // they all bind the same, from left to right
+    assert_eq!(run("print 1 | 2 & 3"), "3\n");
+    assert_eq!(run("print 1 << 4 >> 2"), "4\n");
+}
+
+#[test]
+fn bitwise_operators_bind_tighter_than_comparisons() {
+    assert_eq!(run("print 6 & 3 == 2, 4 | 1 > 4"), "true true\n");
+}
+
+#[test]
+fn flags_can_be_set_and_tested() {
+    let source = "
+        const READ := 1 << 0
+        const WRITE := 1 << 1
+        const EXEC := 1 << 2
+        let flags := READ | EXEC
+        print flags & WRITE == 0, flags & EXEC != 0
+        flags := flags ^ READ
+        print flags
+    ";
+    assert_eq!(run(source), "true true\n4\n");
+}
+
+#[test]
+fn operands_have_to_be_whole_numbers() {
+    for source in &[
+        "print 1 & true",
+        "print \"1\" | 2",
+        "let nothing\nprint nothing ^ 1",
+        "print 1.5 & 1",
+        "print 1 << 0.5",
+        "print 1 << 64",
+        "print 1 >> -1",
+    ] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::TypeError { .. }),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn bitwise_operators_have_their_own_instructions() {
+    let code = disassemble("let a := 1\nprint a & 1, a | 1, a ^ 1, a << 1, a >> 1");
+    for name in &["BitAnd", "BitOr", "BitXor", "Shl", "Shr"] {
+        assert!(code.contains(&format!("\t{}", name)), "{}\n{}", name, code);
+    }
+}
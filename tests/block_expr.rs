@@ -0,0 +1,65 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn block_yields_last_expression() {
+    let source = "
+        fn compute() { return 21 }
+        let x := do { let a := compute(); a * 2 }
+        print x
+    ";
+    assert_eq!(run(source), "42\n");
+}
+
+#[test]
+fn block_locals_are_popped_under_the_result() {
+    let source = "
+        fn f() {
+            let before := 1
+            let x := do { let a := 10 let b := 20 a + b }
+            let after := 3
+            return before + x + after
+        }
+        print f()
+    ";
+    assert_eq!(run(source), "34\n");
+}
+
+#[test]
+fn block_inside_expression_with_temporaries() {
+    let source = "
+        fn f(n) {
+            return n + do { let a := n * 2; a + 1 } * 10
+        }
+        print f(1)
+        print [1, do { let a := 2; let b := 3; a * b }, 4]
+    ";
+    assert_eq!(run(source), "31\n[1, 6, 4]\n");
+}
+
+#[test]
+fn nested_blocks() {
+    let source = "
+        let x := do {
+            let a := 1
+            let b := do {
+                let c := a + 1
+                do { let d := c * 10; d + a }
+            }
+            a + b
+        }
+        print x
+    ";
+    assert_eq!(run(source), "22\n");
+}
+
+#[test]
+fn block_without_trailing_expression_yields_nil() {
+    let source = "
+        print do { let a := 1 }
+        print do { }
+        print do { print 5 }
+    ";
+    assert_eq!(run(source), "nil\nnil\n5\nnil\n");
+}
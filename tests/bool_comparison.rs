@@ -0,0 +1,38 @@
+mod common;
+
+use common::{disassemble, run};
+
+#[test]
+fn comparing_to_bool_literal_emits_fast_path() {
+    let code = disassemble("let flag := 1 < 2\nprint flag == true");
+    assert!(code.contains("\tConstTrue"), "{}", code);
+    assert!(!code.contains("\tEqual"), "{}", code);
+    assert!(!code.contains("\tLoadTrue"), "{}", code);
+
+    let code = disassemble("let flag := 1 < 2\nprint false != flag");
+    assert!(code.contains("\tConstFalse"), "{}", code);
+    assert!(!code.contains("\tEqual"), "{}", code);
+    assert!(!code.contains("\tLoadFalse"), "{}", code);
+}
+
+#[test]
+fn fast_path_matches_generic_equality() {
+    let values = ["true", "false", "0", "1", "\"true\"", "[]"];
+
+    for value in &values {
+        for constant in &["true", "false"] {
+            for operator in &["==", "!="] {
+                // the group keeps the codegen from using the fast path
+                let fast = format!(
+                    "let x := {}\nprint x {} {}\nprint {} {} x",
+                    value, operator, constant, constant, operator
+                );
+                let generic = format!(
+                    "let x := {}\nprint x {} ({})\nprint ({}) {} x",
+                    value, operator, constant, constant, operator
+                );
+                assert_eq!(run(&fast), run(&generic), "{}", fast);
+            }
+        }
+    }
+}
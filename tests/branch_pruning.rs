@@ -0,0 +1,195 @@
+use cahn_lang::{
+    compile_with_options,
+    compiler::codegen::{CodeGenError, CodeGenOptions, Warning},
+    error::CahnError,
+    executable::Executable,
+    runtime::VM,
+};
+
+fn opt() -> CodeGenOptions {
+    CodeGenOptions {
+        optimize: true,
+        ..CodeGenOptions::default()
+    }
+}
+
+fn compile_opt(source: &str) -> (Executable, Vec<Warning>) {
+    compile_with_options(source, "inline-test".into(), &[], opt()).unwrap()
+}
+
+fn main_code_len(exec: &Executable) -> usize {
+    exec.functions.last().unwrap().code.len()
+}
+
+#[test]
+fn dead_then_branch_is_left_out() {
+    let (pruned, _) = compile_opt("if false { print 1 } print 2");
+    let (expected, _) = compile_opt("print 2");
+    assert_eq!(main_code_len(&pruned), main_code_len(&expected));
+    assert_eq!(VM::run_to_string(&pruned).unwrap(), "2\n");
+}
+
+#[test]
+fn dead_else_branch_is_left_out() {
+    let (pruned, _) = compile_opt("if not false { print 1 } else { print 2 }");
+    let (expected, _) = compile_opt("{ print 1 }");
+    assert_eq!(main_code_len(&pruned), main_code_len(&expected));
+    assert_eq!(VM::run_to_string(&pruned).unwrap(), "1\n");
+}
+
+#[test]
+fn const_flag_prunes_branch() {
+    let source = "
+        const DEBUG := false
+        fn log(message) {
+            if DEBUG { print message }
+        }
+        log(1)
+        print 2
+    ";
+    let (pruned, warnings) = compile_opt(source);
+    let (unpruned, _) =
+        compile_with_options(source, "inline-test".into(), &[], CodeGenOptions::default()).unwrap();
+
+    assert!(pruned.functions[0].code.len() < unpruned.functions[0].code.len());
+    assert_eq!(VM::run_to_string(&pruned).unwrap(), "2\n");
+    assert!(matches!(
+        warnings.as_slice(),
+        [Warning::ConstantCondition {
+            value: false,
+            pruned: true,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn while_false_body_is_left_out() {
+    let (pruned, _) = compile_opt("while false { print 1 } print 2");
+    let (expected, _) = compile_opt("print 2");
+    assert_eq!(main_code_len(&pruned), main_code_len(&expected));
+}
+
+#[test]
+fn errors_in_dead_branch_are_reported() {
+    let result = compile_with_options(
+        "if false { print missing }",
+        "inline-test".into(),
+        &[],
+        opt(),
+    );
+    assert!(matches!(
+        result,
+        Err(CahnError::CodeGen(CodeGenError::UnresolvedVariable { .. }))
+    ));
+}
+
+#[test]
+fn locals_in_dead_branch_dont_take_slots() {
+    let source = "
+        fn f() {
+            if false { let a := 1 let b := 2 }
+            let c := 3
+            return c
+        }
+        print f()
+    ";
+    let (exec, _) = compile_opt(source);
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "3\n");
+}
+
+#[test]
+fn constant_condition_warns_without_opt() {
+    let (_, warnings) = compile_with_options(
+        "if true { print 1 }",
+        "inline-test".into(),
+        &[],
+        CodeGenOptions::default(),
+    )
+    .unwrap();
+    assert!(matches!(
+        warnings.as_slice(),
+        [Warning::ConstantCondition {
+            value: true,
+            pruned: false,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn let_is_not_folded() {
+    // only constants are folded, a let global could be assigned anywhere
+    let (_, warnings) = compile_opt("let DEBUG := false if DEBUG { print 1 }");
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn const_global_cant_be_redeclared() {
+    // log was compiled for the first value, so the second one would be ignored
+    let source = "
+        const DEBUG := false
+        fn log(message) {
+            if DEBUG { print message }
+        }
+        let DEBUG := true
+        log(1)
+    ";
+    assert!(matches!(
+        compile_with_options(source, "inline-test".into(), &[], opt()),
+        Err(CahnError::CodeGen(
+            CodeGenError::DuplicateDeclaration { .. }
+        ))
+    ));
+}
+
+#[test]
+fn const_assigned_before_its_declaration_is_not_folded() {
+    let source = "
+        fn disable() {
+            DEBUG := false
+        }
+        const DEBUG := true
+        disable()
+        if DEBUG { print 1 } else { print 2 }
+    ";
+    let (exec, warnings) = compile_opt(source);
+    assert!(warnings.is_empty());
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "2\n");
+}
+
+#[test]
+fn captured_locals_shadow_const_globals() {
+    let source = "
+        const flag := false
+        fn outer() {
+            let flag := true
+            fn inner() {
+                if flag { print \"t\" } else { print \"f\" }
+            }
+            inner()
+        }
+        outer()
+
+        fn make() {
+            let flag := true
+            return fn() {
+                if flag { print \"t\" } else { print \"f\" }
+            }
+        }
+        make()()
+    ";
+    let is_folded = |warning: &Warning| {
+        matches!(
+            warning,
+            Warning::ConstantCondition { .. } | Warning::UnusedVariable { .. }
+        )
+    };
+    let (exec, warnings) = compile_opt(source);
+    assert!(!warnings.iter().any(is_folded), "{:?}", warnings);
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "t\nt\n");
+
+    let (_, warnings) =
+        compile_with_options(source, "inline-test".into(), &[], CodeGenOptions::default()).unwrap();
+    assert!(!warnings.iter().any(is_folded), "{:?}", warnings);
+}
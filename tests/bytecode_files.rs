@@ -0,0 +1,189 @@
+use std::{env, fs, process::Command};
+
+use cahn_lang::{
+    compile, compile_with_natives,
+    executable::{BytecodeError, Executable, COMPILER_VERSION, INSTRUCTION_SET_VERSION},
+    runtime::{NativeFunction, RuntimeWarning, VMOptions, Value, VM},
+};
+
+const SOURCE: &str = r#"
+    struct Point { x, y }
+    let origin := Point(0, 1)
+    fn shifted(p, by := 2) {
+        return Point(p.x + by, p.y)
+    }
+    let add := fn(a) { return fn(b) { return a + b } }
+    print shifted(origin), add(1)(1.5), len("three"), "a" .. origin.y
+"#;
+
+fn run(exec: &Executable) -> String {
+    VM::run_to_string(exec).unwrap()
+}
+
+#[test]
+fn deserialized_executables_run_like_the_original() {
+    let exec = compile(SOURCE, "inline-test".into()).unwrap();
+    let loaded = Executable::deserialize(&exec.serialize()).unwrap();
+
+    assert_eq!(run(&loaded), run(&exec));
+    assert_eq!(loaded.source_file, "inline-test");
+    assert_eq!(loaded.serialize(), exec.serialize());
+}
+
+#[test]
+fn host_natives_are_looked_up_by_name() {
+    let natives = [NativeFunction::new("answer", 0, |_, _| {
+        Ok(Value::Number(42.0))
+    })];
+    let exec = compile_with_natives("print answer()", "inline-test".into(), &natives).unwrap();
+    let bytes = exec.serialize();
+
+    let loaded = Executable::deserialize_with_natives(&bytes, &natives).unwrap();
+    assert_eq!(run(&loaded), "42\n");
+
+    assert!(matches!(
+        Executable::deserialize(&bytes),
+        Err(BytecodeError::UnknownNative { name }) if name == "answer"
+    ));
+}
+
+#[test]
+fn broken_files_are_rejected() {
+    let bytes = compile(SOURCE, "inline-test".into()).unwrap().serialize();
+
+    assert!(matches!(
+        Executable::deserialize(b"print 1"),
+        Err(BytecodeError::NotBytecode)
+    ));
+
+    let mut newer = bytes.clone();
+    newer[6] += 1;
+    assert!(matches!(
+        Executable::deserialize(&newer),
+        Err(BytecodeError::UnsupportedVersion { .. })
+    ));
+
+    // cutting the file anywhere must give an error, not a panic
+    for len in 0..bytes.len() {
+        assert!(Executable::deserialize(&bytes[..len]).is_err(), "{}", len);
+    }
+
+    let mut trailing = bytes;
+    trailing.push(0);
+    assert!(matches!(
+        Executable::deserialize(&trailing),
+        Err(BytecodeError::TrailingBytes { count: 1 })
+    ));
+}
+
+// the compiler version comes right after the magic and the format version
+const VERSION_START: usize = 7;
+
+fn with_compiler_version(bytes: &[u8], version: &str) -> Vec<u8> {
+    let rest = VERSION_START + 4 + COMPILER_VERSION.len();
+    let mut changed = bytes[..VERSION_START].to_vec();
+    changed.extend_from_slice(&(version.len() as u32).to_le_bytes());
+    changed.extend_from_slice(version.as_bytes());
+    changed.extend_from_slice(&bytes[rest..]);
+    changed
+}
+
+#[test]
+fn the_compiler_version_is_remembered() {
+    let exec = compile(SOURCE, "inline-test".into()).unwrap();
+    assert_eq!(exec.compiler_version(), COMPILER_VERSION);
+    assert_eq!(exec.instruction_set_version(), INSTRUCTION_SET_VERSION);
+    assert!(!exec.compiled_by_other_version());
+    assert!(format!("{:?}", exec).contains(&format!(
+        "COMPILER: cahn_lang {} (instruction set {})",
+        COMPILER_VERSION, INSTRUCTION_SET_VERSION
+    )));
+
+    let loaded = Executable::deserialize(&exec.serialize()).unwrap();
+    assert_eq!(loaded.compiler_version(), COMPILER_VERSION);
+    assert_eq!(loaded.instruction_set_version(), INSTRUCTION_SET_VERSION);
+}
+
+#[test]
+fn other_compiler_versions_only_warn() {
+    let bytes = compile(SOURCE, "inline-test".into()).unwrap().serialize();
+    let loaded = Executable::deserialize(&with_compiler_version(&bytes, "0.0.1-old")).unwrap();
+
+    assert_eq!(loaded.compiler_version(), "0.0.1-old");
+    assert!(loaded.compiled_by_other_version());
+    // it's kept when the executable is written again
+    assert_eq!(
+        Executable::deserialize(&loaded.serialize())
+            .unwrap()
+            .compiler_version(),
+        "0.0.1-old"
+    );
+
+    let options = VMOptions {
+        runtime_warnings: true,
+        ..VMOptions::default()
+    };
+    let mut output = vec![];
+    let mut vm = VM::with_options(&loaded, &mut output, options);
+    assert_eq!(
+        vm.take_warnings(),
+        vec![RuntimeWarning::OtherCompilerVersion {
+            compiled_with: "0.0.1-old".into()
+        }]
+    );
+    while vm.step().unwrap() {}
+    assert!(vm.take_warnings().is_empty());
+
+    let mut output = vec![];
+    let mut vm = VM::new(&loaded, &mut output);
+    assert!(vm.take_warnings().is_empty());
+}
+
+#[test]
+fn other_instruction_sets_are_rejected() {
+    let mut bytes = compile(SOURCE, "inline-test".into()).unwrap().serialize();
+    bytes[VERSION_START + 4 + COMPILER_VERSION.len()] += 1;
+
+    assert!(matches!(
+        Executable::deserialize(&bytes),
+        Err(BytecodeError::UnsupportedInstructionSet { found, compiler_version, .. })
+            if found == INSTRUCTION_SET_VERSION + 1 && compiler_version == COMPILER_VERSION
+    ));
+}
+
+#[test]
+fn the_binary_runs_the_bytecode_it_emitted() {
+    let dir = env::temp_dir().join(format!("cahn-bytecode-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source_path = dir.join("program.cahn");
+    let bytecode_path = dir.join("program.cbc");
+    fs::write(&source_path, "print \"from bytecode\", 1 + 2").unwrap();
+
+    let emit = Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .arg("--emit-bytecode")
+        .arg(&bytecode_path)
+        .arg(&source_path)
+        .output()
+        .unwrap();
+    assert!(emit.status.success(), "{:?}", emit);
+    // emitting doesn't run the program
+    assert!(!String::from_utf8_lossy(&emit.stdout).contains("from bytecode"));
+
+    // the source is gone, so the program can only come from the bytecode
+    fs::remove_file(&source_path).unwrap();
+
+    let run = Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .arg("-r")
+        .arg(&bytecode_path)
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{:?}", run);
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(
+        stdout.lines().any(|line| line == "from bytecode 3"),
+        "{}",
+        stdout
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,57 @@
+use std::cell::Cell;
+
+use cahn_lang::{compile, execute_source_to_string, runtime::VM};
+
+// runs the source with a clock that moves forward by `step` seconds every time it's read
+fn run_with_ticking_clock(source: &str, step: f64) -> String {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let now = Cell::new(0.0);
+    VM::new(&exec, &mut stdout)
+        .with_clock(|| {
+            now.set(now.get() + step);
+            now.get()
+        })
+        .run()
+        .unwrap();
+    String::from_utf8(stdout).unwrap()
+}
+
+#[test]
+fn clock_reads_the_injected_time() {
+    let source = "
+        let start := clock()
+        let i := 0
+        while i < 100 {
+            i := i + 1
+        }
+        print start, clock() - start
+    ";
+    assert_eq!(run_with_ticking_clock(source, 0.5), "0.5 0.5\n");
+}
+
+#[test]
+fn timing_a_loop_gives_a_small_positive_delta() {
+    let source = "
+        let start := clock()
+        let i := 0
+        while i < 1000 {
+            i := i + 1
+        }
+        let elapsed := clock() - start
+        print elapsed >= 0, elapsed < 60, typeof elapsed
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true true number\n"
+    );
+}
+
+#[test]
+fn the_default_clock_counts_from_the_start_of_the_vm() {
+    // a fresh VM has been running for way less than a minute
+    assert_eq!(
+        execute_source_to_string("print clock() < 60", "inline-test".into()).unwrap(),
+        "true\n"
+    );
+}
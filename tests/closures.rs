@@ -0,0 +1,165 @@
+mod common;
+
+use cahn_lang::{compile, compiler::codegen::CodeGenError, error::CahnError};
+use common::run;
+
+#[test]
+fn counter_closure_increments_across_calls() {
+    let source = "
+        fn make_counter() {
+            let count := 0
+            return fn() {
+                count := count + 1
+                return count
+            }
+        }
+        let counter := make_counter()
+        print counter()
+        print counter()
+        print counter()
+    ";
+    assert_eq!(run(source), "1\n2\n3\n");
+}
+
+#[test]
+fn counters_dont_share_their_count() {
+    let source = "
+        fn make_counter() {
+            let count := 0
+            return fn() {
+                count := count + 1
+                return count
+            }
+        }
+        let a := make_counter()
+        let b := make_counter()
+        print a()
+        print a()
+        print b()
+    ";
+    assert_eq!(run(source), "1\n2\n1\n");
+}
+
+#[test]
+fn closure_reads_parameter_of_enclosing_function() {
+    let source = "
+        fn make_adder(n) {
+            return fn(x) { return x + n }
+        }
+        let add_two := make_adder(2)
+        print add_two(3)
+        print make_adder(10)(5)
+    ";
+    assert_eq!(run(source), "5\n15\n");
+}
+
+#[test]
+fn closures_share_a_captured_variable() {
+    let source = "
+        fn make_pair() {
+            let value := 0
+            let set := fn(v) { value := v }
+            let get := fn() { return value }
+            return [set, get]
+        }
+        let pair := make_pair()
+        let set := pair[0]
+        let get := pair[1]
+        set(7)
+        print get()
+    ";
+    assert_eq!(run(source), "7\n");
+}
+
+#[test]
+fn closure_sees_changes_while_variable_is_on_the_stack() {
+    let source = "
+        fn run() {
+            let x := 1
+            let get := fn() { return x }
+            x := 2
+            return get()
+        }
+        print run()
+    ";
+    assert_eq!(run(source), "2\n");
+}
+
+#[test]
+fn closure_captures_through_several_functions() {
+    let source = "
+        fn outer() {
+            let x := 1
+            fn middle() {
+                return fn() {
+                    x := x + 1
+                    return x
+                }
+            }
+            return middle()
+        }
+        let inner := outer()
+        print inner()
+        print inner()
+    ";
+    assert_eq!(run(source), "2\n3\n");
+}
+
+#[test]
+fn loop_body_variable_is_captured_fresh_every_iteration() {
+    let source = "
+        fn collect() {
+            let first
+            let second
+            let i := 0
+            while i < 2 {
+                let j := i * 10
+                if i == 0 { first := fn() { return j } } else { second := fn() { return j } }
+                i := i + 1
+            }
+            return first() + second()
+        }
+        print collect()
+    ";
+    assert_eq!(run(source), "10\n");
+}
+
+#[test]
+fn block_local_in_script_can_be_captured() {
+    let source = "
+        let get
+        {
+            let secret := 42
+            get := fn() { return secret }
+        }
+        print get()
+    ";
+    assert_eq!(run(source), "42\n");
+}
+
+#[test]
+fn block_expression_can_return_closure_over_its_local() {
+    let source = "
+        let get := do {
+            let hidden := 5
+            let double := fn() { return hidden * 2 }
+            double
+        }
+        print get()
+    ";
+    assert_eq!(run(source), "10\n");
+}
+
+#[test]
+fn captured_constant_cant_be_assigned() {
+    let source = "
+        fn f() {
+            const x := 1
+            return fn() { x := 2 }
+        }
+    ";
+    assert!(matches!(
+        compile(source, "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::AssignmentToConst { .. }))
+    ));
+}
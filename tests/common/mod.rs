@@ -0,0 +1,52 @@
+// shared by several test files, each of which only uses some of it
+#![allow(dead_code)]
+
+pub mod program_gen;
+
+use cahn_lang::{
+    compile,
+    compiler::{codegen::CodeGenError, syntactical_analysis::ParseError},
+    error::CahnError,
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VM},
+};
+
+pub fn run(source: &str) -> String {
+    execute_source_to_string(source, "inline-test".into()).unwrap()
+}
+
+// for programs that may fail at runtime, but must compile
+pub fn try_run(source: &str) -> Result<String, RuntimeError> {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    VM::run_to_string(&exec)
+}
+
+pub fn runtime_error(source: &str) -> RuntimeError {
+    try_run(source).expect_err("the program should fail")
+}
+
+// the disassembled executable, one instruction per line
+pub fn disassemble(source: &str) -> String {
+    format!("{:?}", compile(source, "inline-test".into()).unwrap())
+}
+
+pub fn parse_error(source: &str) -> ParseError {
+    expect_parse_error(source, compile(source, "inline-test".into()))
+}
+
+// for sources that are compiled some other way, like with a module loader
+pub fn expect_parse_error<T>(source: &str, result: Result<T, CahnError>) -> ParseError {
+    match result {
+        Err(CahnError::Parse(err)) => err,
+        Err(other) => panic!("expected a parse error, got {}", other),
+        Ok(_) => panic!("{} should not compile", source),
+    }
+}
+
+pub fn compile_error(source: &str) -> CodeGenError {
+    match compile(source, "inline-test".into()) {
+        Err(CahnError::CodeGen(err)) => err,
+        Err(other) => panic!("expected a code generation error, got {}", other),
+        Ok(_) => panic!("{} should not compile", source),
+    }
+}
@@ -0,0 +1,162 @@
+//! Generators for big cahn programs, for tests that need to push the
+//! compiler or the vm further than a hand-written program would.
+//! The same seed always gives the same program.
+
+use cahn_lang::utils::Rng;
+
+/// The source of a generated program, and what it prints when it's run.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedProgram {
+    pub source: String,
+    pub expected_output: String,
+}
+
+impl GeneratedProgram {
+    /// Runs `other` after this program, they mustn't declare the same globals.
+    pub fn then(mut self, other: GeneratedProgram) -> GeneratedProgram {
+        self.source.push('\n');
+        self.source.push_str(&other.source);
+        self.expected_output.push_str(&other.expected_output);
+        self
+    }
+}
+
+pub struct ProgramGen {
+    rng: Rng,
+    // the generated globals are numbered, so programs from one generator can be chained
+    next_id: usize,
+}
+
+impl ProgramGen {
+    pub fn new(seed: u64) -> Self {
+        ProgramGen {
+            rng: Rng::new(seed),
+            next_id: 0,
+        }
+    }
+
+    fn small_number(&mut self) -> u64 {
+        self.next_u64() % 100
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fresh_name(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    /// A sum nested `depth` parentheses deep, `((1 + 2) + 3) + 4` and so on.
+    pub fn deep_parens(&mut self, depth: usize) -> GeneratedProgram {
+        let mut sum = self.small_number();
+        let mut source = "print ".to_string() + &"(".repeat(depth) + &sum.to_string();
+
+        for _ in 0..depth {
+            let term = self.small_number();
+            sum += term;
+            source += &format!(" + {})", term);
+        }
+
+        GeneratedProgram {
+            source,
+            expected_output: format!("{}\n", sum),
+        }
+    }
+
+    /// A block that declares `count` locals and sums them up.
+    pub fn many_locals(&mut self, count: usize) -> GeneratedProgram {
+        let mut source = String::from("{\n");
+        let mut sum = 0;
+
+        for i in 0..count {
+            let value = self.small_number();
+            sum += value;
+            source += &format!("let local{} := {}\n", i, value);
+        }
+
+        source += "let sum := 0\n";
+        for i in 0..count {
+            source += &format!("sum := sum + local{}\n", i);
+        }
+        source += "print sum\n}";
+
+        GeneratedProgram {
+            source,
+            expected_output: format!("{}\n", sum),
+        }
+    }
+
+    /// `count` top-level statements that update a global, printing it now and then.
+    pub fn long_stmt_list(&mut self, count: usize) -> GeneratedProgram {
+        let name = self.fresh_name("counter");
+        let mut source = format!("let {} := 0\n", name);
+        let mut expected_output = String::new();
+        let mut counter = 0;
+
+        for _ in 0..count {
+            if self.next_u64().is_multiple_of(10) {
+                source += &format!("print {}\n", name);
+                expected_output += &format!("{}\n", counter);
+            } else {
+                let term = self.small_number();
+                counter += term;
+                source += &format!("{0} := {0} + {1}\n", name, term);
+            }
+        }
+
+        GeneratedProgram {
+            source,
+            expected_output,
+        }
+    }
+
+    /// `count` functions, each adding its own number to what the one before it returns.
+    pub fn many_functions(&mut self, count: usize) -> GeneratedProgram {
+        let prefix = self.fresh_name("function") + "_";
+        let mut source = String::new();
+        let mut result = 0;
+
+        for i in 0..count {
+            let term = self.small_number();
+            result += term;
+            source += &format!("fn {}{}(x) {{ return x + {} }}\n", prefix, i, term);
+        }
+
+        // the calls are made one after another rather than nested, to not hit the frame limit
+        let total = self.fresh_name("total");
+        source += &format!("let {} := 0\n", total);
+        for i in 0..count {
+            source += &format!("{0} := {1}{2}({0})\n", total, prefix, i);
+        }
+        source += &format!("print {}", total);
+
+        GeneratedProgram {
+            source,
+            expected_output: format!("{}\n", result),
+        }
+    }
+
+    /// Concatenates about `kilobytes` of string literals and prints how long the result is.
+    pub fn string_heavy(&mut self, kilobytes: usize) -> GeneratedProgram {
+        const CHUNK_LEN: usize = 64;
+
+        let name = self.fresh_name("text");
+        let mut source = format!("let {} := \"\"\n", name);
+        let chunks = kilobytes * 1024 / CHUNK_LEN;
+
+        for _ in 0..chunks {
+            let chunk: String = (0..CHUNK_LEN)
+                .map(|_| (b'a' + (self.next_u64() % 26) as u8) as char)
+                .collect();
+            source += &format!("{0} := {0} .. \"{1}\"\n", name, chunk);
+        }
+        source += &format!("print len({})", name);
+
+        GeneratedProgram {
+            source,
+            expected_output: format!("{}\n", chunks * CHUNK_LEN),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use cahn_lang::{
+    compile,
+    compiler::{codegen::CodeGenError, lexical_analysis::TokenPos},
+    error::CahnError,
+    execute_source_to_string,
+};
+
+#[test]
+fn const_can_be_read() {
+    let source = "
+        const PI := 3.14159
+        print PI * 2
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "6.28318\n"
+    );
+}
+
+#[test]
+fn assigning_to_const_is_an_error() {
+    let source = "const PI := 3.14159
+let x := 2
+PI := 3";
+
+    match compile(source, "inline-test".into()) {
+        Err(CahnError::CodeGen(CodeGenError::AssignmentToConst { token, declared_at })) => {
+            assert_eq!(token.pos, TokenPos::new(3, 1));
+            assert_eq!(declared_at, TokenPos::new(1, 7));
+        }
+        other => panic!("expected an AssignmentToConst error, got {:?}", other),
+    }
+}
+
+#[test]
+fn assigning_to_const_in_inner_scope_is_an_error() {
+    let source = "
+        const limit := 10
+        if true {
+            limit := 20
+        }
+    ";
+    assert!(matches!(
+        compile(source, "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::AssignmentToConst { .. }))
+    ));
+}
+
+#[test]
+fn const_can_be_shadowed_by_let() {
+    let source = "
+        const x := 1
+        {
+            let x := 2
+            x := 3
+            print x
+        }
+        print x
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "3\n1\n"
+    );
+}
@@ -0,0 +1,190 @@
+use cahn_lang::{
+    compile, compile_with_loader, compile_with_options,
+    compiler::{
+        codegen::{CodeGenError, CodeGenOptions},
+        lexical_analysis::{CustomOperator, OperatorPrecedence},
+    },
+    error::CahnError,
+    runtime::{
+        error::{Result, RuntimeError},
+        NativeFunction, Value, VmContext, VM,
+    },
+};
+
+fn number(value: Value) -> Result<f64> {
+    value.as_f64().ok_or_else(|| RuntimeError::TypeError {
+        message: format!("expected a number, got {}", value.type_name()),
+    })
+}
+
+// the sum of the digits, modulo 10
+fn digit_sum(_ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number((number(args[0])? + number(args[1])?) % 10.0))
+}
+
+fn difference(_ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(number(args[0])? - number(args[1])?))
+}
+
+fn dot_product(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    let (a, b) = match (ctx.as_list(args[0]), ctx.as_list(args[1])) {
+        (Some(a), Some(b)) => (a.to_vec(), b.to_vec()),
+        _ => {
+            return Err(RuntimeError::TypeError {
+                message: "dot expects two lists".into(),
+            })
+        }
+    };
+    let mut sum = 0.0;
+    for (x, y) in a.into_iter().zip(b) {
+        sum += number(x)? * number(y)?;
+    }
+    Ok(Value::Number(sum))
+}
+
+fn natives() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("digit_sum", 2, digit_sum),
+        NativeFunction::new("difference", 2, difference),
+        NativeFunction::new("dot_product", 2, dot_product),
+    ]
+}
+
+fn operators() -> Vec<CustomOperator> {
+    vec![
+        CustomOperator::new("⊕", "digit_sum", OperatorPrecedence::Addition),
+        CustomOperator::new("<->", "difference", OperatorPrecedence::Multiplication),
+        CustomOperator::new("dot", "dot_product", OperatorPrecedence::Bitwise),
+    ]
+}
+
+fn dsl_options() -> CodeGenOptions {
+    CodeGenOptions {
+        custom_operators: operators(),
+        ..CodeGenOptions::default()
+    }
+}
+
+fn compile_dsl(source: &str) -> std::result::Result<cahn_lang::executable::Executable, CahnError> {
+    compile_with_options(source, "inline-test".into(), &natives(), dsl_options())
+        .map(|(exec, _warnings)| exec)
+}
+
+fn run(source: &str) -> String {
+    VM::run_to_string(&compile_dsl(source).unwrap()).unwrap()
+}
+
+#[test]
+fn custom_operators_call_their_native() {
+    assert_eq!(run("print 7 ⊕ 5"), "2\n");
+    assert_eq!(run("let a := 9\nprint a ⊕ a ⊕ a"), "7\n");
+}
+
+#[test]
+fn custom_operators_bind_at_their_precedence() {
+    // like +, so looser than * and tighter than ==
+    assert_eq!(run("print 7 ⊕ 5 * 2, 1 + 7 ⊕ 5"), "7 3\n");
+    assert_eq!(run("print 7 ⊕ 5 == 2"), "true\n");
+    // like *, from left to right
+    assert_eq!(run("print 10 <-> 3 <-> 2, 1 + 10 <-> 3"), "5 8\n");
+    // like &, so tighter than == and ..
+    assert_eq!(run("print [1, 2] dot [3, 4] == 11"), "true\n");
+    assert_eq!(run("print [1, 2] dot [3, 4] .. \"!\""), "11!\n");
+}
+
+#[test]
+fn custom_operators_chain_with_the_operators_they_bind_like() {
+    // (((1 + 7) ⊕ 5) - 2) ⊕ 9 and ((10 <-> 3) * 2) <-> 1
+    assert_eq!(run("print 1 + 7 ⊕ 5 - 2 ⊕ 9"), "0\n");
+    assert_eq!(run("print 10 <-> 3 * 2 <-> 1"), "13\n");
+    assert_eq!(run(&("print 0".to_string() + &" ⊕ 1".repeat(1000))), "0\n");
+}
+
+#[test]
+fn symbols_win_over_the_operators_they_start_with() {
+    assert_eq!(run("print 5 <-> 1, 5 < 1, 5 <- 1"), "4 false false\n");
+}
+
+#[test]
+fn words_are_lexed_like_keywords() {
+    assert_eq!(
+        run("let v := [1, 2, 3]\nprint v dot v, v dot [1, 0, 0]"),
+        "14 1\n"
+    );
+    // but only whole words
+    assert_eq!(run("let dotted := 1\nprint dotted"), "1\n");
+}
+
+#[test]
+fn custom_operators_work_in_interpolated_strings() {
+    assert_eq!(run(r#"print "{7 ⊕ 5}""#), "2\n");
+}
+
+#[test]
+fn errors_in_the_native_point_at_the_operator() {
+    let exec = compile_dsl("let nothing\nprint 1 *  1 ⊕ nothing").unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    let err = loop {
+        match vm.step() {
+            Ok(true) => {}
+            Ok(false) => panic!("the program should fail"),
+            Err(err) => break err,
+        }
+    };
+    assert!(matches!(err, RuntimeError::TypeError { .. }));
+    let pos = vm.error_pos().unwrap();
+    assert_eq!((pos.line, pos.column), (2, 14));
+}
+
+#[test]
+fn operators_are_only_there_when_registered() {
+    assert!(compile("print 7 ⊕ 5", "inline-test".into()).is_err());
+    assert!(compile("print [1] dot [1]", "inline-test".into()).is_err());
+}
+
+#[test]
+fn variables_named_like_the_native_dont_change_the_operator() {
+    let source = "
+        fn digit_sum(a, b) {
+            return 0
+        }
+        print 7 ⊕ 5, digit_sum(7, 5)
+        {
+            let digit_sum := 3
+            print 7 ⊕ 5
+        }
+    ";
+    assert_eq!(run(source), "2 0\n2\n");
+}
+
+#[test]
+fn operators_have_to_be_bound_to_a_native() {
+    let options = CodeGenOptions {
+        custom_operators: vec![CustomOperator::new(
+            "⊕",
+            "missing",
+            OperatorPrecedence::Addition,
+        )],
+        ..CodeGenOptions::default()
+    };
+    let result = compile_with_options("print 7 ⊕ 5", "inline-test".into(), &natives(), options);
+    assert!(matches!(
+        result,
+        Err(CahnError::CodeGen(CodeGenError::UnboundOperator { .. }))
+    ));
+}
+
+#[test]
+fn modules_can_use_the_operators() {
+    let loader = |path: &str| (path == "m.cahn").then(|| "let sum := 7 ⊕ 5".to_string());
+    let (exec, _) = compile_with_loader(
+        "import \"m.cahn\"\nprint sum <-> 1",
+        "main.cahn".into(),
+        &natives(),
+        dsl_options(),
+        &loader,
+    )
+    .unwrap();
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "1\n");
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::{string_handling::StringInterner, syntactical_analysis::Parser},
+    error::CahnError,
+    executable::CahnFunction,
+    execute_source_to_string,
+    runtime::error::RuntimeError,
+};
+use common::try_run;
+
+const GREET: &str = r#"
+    fn greet(name, greeting := "hello", punctuation := "!") {
+        print greeting .. " " .. name .. punctuation
+    }
+"#;
+
+#[test]
+fn no_defaults_supplied() {
+    let source = format!("{}\ngreet(\"ann\")", GREET);
+    assert_eq!(
+        execute_source_to_string(&source, "inline-test".into()).unwrap(),
+        "hello ann!\n"
+    );
+}
+
+#[test]
+fn some_defaults_supplied() {
+    let source = format!("{}\ngreet(\"bob\", \"hi\")", GREET);
+    assert_eq!(
+        execute_source_to_string(&source, "inline-test".into()).unwrap(),
+        "hi bob!\n"
+    );
+}
+
+#[test]
+fn all_defaults_supplied() {
+    let source = format!("{}\ngreet(\"cy\", \"hey\", \"?\")", GREET);
+    assert_eq!(
+        execute_source_to_string(&source, "inline-test".into()).unwrap(),
+        "hey cy?\n"
+    );
+}
+
+#[test]
+fn defaults_can_use_earlier_parameters_and_locals_after_them() {
+    let source = "
+        fn area(width, height := width) {
+            let result := width * height
+            return result
+        }
+        print area(3)
+        print area(3, 4)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "9\n12\n"
+    );
+}
+
+#[test]
+fn defaults_are_computed_on_every_call() {
+    let source = "
+        let calls := 0
+        fn next() {
+            calls := calls + 1
+            return calls
+        }
+        fn show(x := next()) {
+            print x
+        }
+        show()
+        show(10)
+        show()
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1\n10\n2\n"
+    );
+}
+
+#[test]
+fn local_and_anonymous_functions_take_defaults() {
+    let source = r#"
+        fn outer() {
+            fn inner(a, b := a * 2) {
+                return a + b
+            }
+            return inner(1) + inner(1, 1)
+        }
+        print outer()
+        let join := fn(x, y := "d") { return x .. y }
+        print join("a")
+        print join("a", "b")
+    "#;
+    assert_eq!(try_run(source).unwrap(), "5\nad\nab\n");
+}
+
+#[test]
+fn too_few_or_too_many_arguments_are_arity_errors() {
+    let source = format!("{}\ngreet()", GREET);
+    assert!(matches!(
+        try_run(&source),
+        Err(RuntimeError::ArityMismatch {
+            expected: 1,
+            got: 0,
+            ..
+        })
+    ));
+
+    let source = format!("{}\ngreet(1, 2, 3, 4)", GREET);
+    assert!(matches!(
+        try_run(&source),
+        Err(RuntimeError::ArityMismatch {
+            expected: 3,
+            got: 4,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn defaults_must_be_trailing() {
+    assert!(matches!(
+        compile("fn f(a := 1, b) {}", "inline-test".into()),
+        Err(CahnError::Parse(_))
+    ));
+}
+
+#[test]
+fn defaults_are_displayed_in_the_ast() {
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str("fn f(a, b := 1 + 2) {}", &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    assert_eq!(
+        &ast.to_string(),
+        "(program (fn f (a, b := (+ 1 2)) (block ))\n)"
+    );
+}
+
+#[test]
+fn default_entries_survive_serialization() {
+    let exec = compile(GREET, "inline-test".into()).unwrap();
+    let greet = &exec.functions[0];
+    assert_eq!(greet.min_param_count(), 1);
+
+    let loaded = CahnFunction::from_bytes(&greet.to_bytes()).unwrap();
+    assert_eq!(loaded.default_entries, greet.default_entries);
+    assert!(exec.check_function(&loaded).is_ok());
+}
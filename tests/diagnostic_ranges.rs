@@ -0,0 +1,73 @@
+mod common;
+
+use cahn_lang::{
+    compile_with_options,
+    compiler::{
+        codegen::{CodeGenError, CodeGenOptions},
+        lexical_analysis::TokenPos,
+        syntactical_analysis::ParseError,
+    },
+};
+use common::{compile_error, parse_error};
+
+fn pos(line: usize, column: usize) -> TokenPos {
+    TokenPos::new(line, column)
+}
+
+#[test]
+fn identifier_error_spans_the_whole_name() {
+    let err = compile_error("let x := 1\nprint x + missing_name");
+    assert_eq!(err.range(), Some((pos(2, 11), pos(2, 23))));
+}
+
+#[test]
+fn unclosed_brace_spans_to_the_end_of_the_source() {
+    let err = parse_error("let x := 1\nif x > 0 {\n    print x\n");
+    assert!(matches!(err, ParseError::UnclosedBrace { .. }), "{}", err);
+    assert_eq!(err.range(), (pos(2, 10), pos(4, 1)));
+
+    let err = parse_error("fn f() {\n    return do { 1 }");
+    assert_eq!(err.range(), (pos(1, 8), pos(2, 20)));
+}
+
+#[test]
+fn operator_error_spans_one_character() {
+    let err = parse_error("print 1 < 2 < 3");
+    assert_eq!(err.range(), (pos(1, 13), pos(1, 14)));
+}
+
+#[test]
+fn multi_line_tokens_end_on_their_last_line() {
+    let err = parse_error("let \"a\nbc\" := 1");
+    assert_eq!(err.range(), (pos(1, 5), pos(2, 4)));
+}
+
+#[test]
+fn errors_about_the_whole_program_have_no_range() {
+    let params: Vec<_> = (0..300).map(|i| format!("p{}", i)).collect();
+    let err = compile_error(&format!("fn f({}) {{}}", params.join(", ")));
+    assert!(matches!(err, CodeGenError::TooManyParameters { .. }));
+    assert_eq!(err.range(), None);
+}
+
+#[test]
+fn warnings_have_ranges() {
+    let options = CodeGenOptions {
+        warn_empty_blocks: true,
+        ..CodeGenOptions::default()
+    };
+    let source = "fn f() {\n    let unused := 1\n}\nif f() {\n}";
+    let (_, warnings) = compile_with_options(source, "inline-test".into(), &[], options).unwrap();
+
+    let ranges: Vec<_> = warnings
+        .iter()
+        .map(|warning| (warning.code(), warning.range()))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            ("unused-variable", (pos(2, 9), pos(2, 15))),
+            ("empty-block", (pos(4, 8), pos(5, 2))),
+        ]
+    );
+}
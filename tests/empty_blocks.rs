@@ -0,0 +1,61 @@
+use cahn_lang::{
+    compile_with_options,
+    compiler::codegen::{CodeGenOptions, Warning},
+};
+
+fn empty_block_lines(source: &str, warn_empty_blocks: bool) -> Vec<usize> {
+    let options = CodeGenOptions {
+        warn_empty_blocks,
+        ..CodeGenOptions::default()
+    };
+    let (_, warnings) = compile_with_options(source, "inline-test".into(), &[], options)
+        .unwrap_or_else(|err| panic!("{} failed to compile: {}", source, err));
+    warnings
+        .into_iter()
+        .filter_map(|warning| match warning {
+            Warning::EmptyBlock { pos, .. } => Some(pos.line),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn empty_if_body_warns() {
+    let source = "
+        let x := 1
+        if x > 0 { }
+        print x
+    ";
+    assert_eq!(empty_block_lines(source, true), vec![3]);
+}
+
+#[test]
+fn non_empty_if_body_doesnt_warn() {
+    let source = "
+        let x := 1
+        if x > 0 { print x }
+    ";
+    assert!(empty_block_lines(source, true).is_empty());
+}
+
+#[test]
+fn empty_else_and_while_bodies_warn() {
+    let source = "
+        let x := 1
+        if x > 0 {
+            print x
+        } else {
+        }
+        while x > 5 { }
+    ";
+    assert_eq!(empty_block_lines(source, true), vec![5, 7]);
+}
+
+#[test]
+fn empty_blocks_arent_reported_by_default() {
+    let source = "
+        let x := 1
+        if x > 0 { }
+    ";
+    assert!(empty_block_lines(source, false).is_empty());
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn lists_are_equal_when_their_elements_are() {
+    let source = r#"
+        print [1, "a", [true]] == [1, "a", [true]]
+        print [1, 2] == [2, 1], [1] == [1, 1], [] == []
+        print ["a" .. "b"] == ["ab"], [[]] != [[]]
+    "#;
+    assert_eq!(run(source), "true\nfalse false true\ntrue false\n");
+}
+
+#[test]
+fn nil_in_lists() {
+    let source = "
+        let nothing
+        print [nothing] == [nothing], [nothing, 1] == [nothing, 1]
+        print [nothing] == [false], [nothing] == [[]], [nothing] == []
+    ";
+    assert_eq!(run(source), "true true\nfalse false false\n");
+}
+
+#[test]
+fn functions_in_lists() {
+    let source = "
+        fn f() {}
+        fn g() {}
+        let nothing
+        print [nothing, f] == [nothing, f], [f] == [g], [f] == [nothing]
+        print [f] == [0], [str] == [str], [str] == [len], [f] == [str]
+    ";
+    assert_eq!(run(source), "true false false\nfalse true false false\n");
+}
+
+#[test]
+fn closures_and_records_are_only_equal_to_themselves() {
+    let source = "
+        struct Point { x, y }
+        fn counter() {
+            let count := 0
+            return fn() { count := count + 1 }
+        }
+        let a := counter()
+        let b := counter()
+        let p := Point { x: 1, y: 2 }
+        print [a] == [a], [a] == [b]
+        print [p] == [p], p == Point { x: 1, y: 2 }
+    ";
+    assert_eq!(run(source), "true false\ntrue false\n");
+}
+
+#[test]
+fn membership_and_affixes_compare_deeply() {
+    let source = "
+        let nothing
+        print [1, 2] in [[1, 2]], [nothing] in [[nothing]], [3] in [[1, 2]]
+        print starts_with([[1], nothing, 2], [[1], nothing]), ends_with([1, [2]], [[2]])
+    ";
+    assert_eq!(run(source), "true true false\ntrue true\n");
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use cahn_lang::compiler::syntactical_analysis::ParseError;
+use common::{parse_error, run};
+
+fn bad_token(source: &str) -> String {
+    match parse_error(source) {
+        ParseError::BadToken { message, .. } => message,
+        other => panic!("expected a bad token, got {}", other),
+    }
+}
+
+#[test]
+fn strings_decode_escapes() {
+    assert_eq!(run(r#"print "a\tb\\c\"d\'e""#), "a\tb\\c\"d'e\n");
+    assert_eq!(run(r#"print "two\nlines""#), "two\nlines\n");
+    assert_eq!(
+        run(r#"print "\x41\x62" .. "\u{e9}\u{1F600}""#),
+        "Ab\u{e9}\u{1F600}\n"
+    );
+    assert_eq!(
+        run(r#"print len("\u{1F600}"), byte_len("\u{1F600}")"#),
+        "1 4\n"
+    );
+}
+
+#[test]
+fn raw_strings_still_keep_their_backslashes() {
+    assert_eq!(run(r#"print r"\x41\n""#), "\\x41\\n\n");
+}
+
+#[test]
+fn escapes_work_in_interpolated_strings() {
+    let source = r#"
+        let n := 3
+        print "\u{2192} {n}\t{n + 1}\x21 {{\u{7d}"
+    "#;
+    assert_eq!(run(source), "\u{2192} 3\t4! {}\n");
+}
+
+#[test]
+fn chars_are_strings_of_one_char() {
+    assert_eq!(run("print 'a', typeof 'a', len('a')"), "a string 1\n");
+    assert_eq!(run("print 'é' == \"é\", 'a' .. 'b'"), "true ab\n");
+    assert_eq!(run("print '\"', '\\''"), "\" '\n");
+}
+
+#[test]
+fn escapes_decode_the_same_in_strings_and_chars() {
+    let escapes = [
+        r"\n",
+        r"\r",
+        r"\t",
+        r"\0",
+        r"\\",
+        r#"\""#,
+        r"\'",
+        r"\x41",
+        r"\x7f",
+        r"\u{e9}",
+        r"\u{1F600}",
+        r"\u{0}",
+    ];
+    for escape in &escapes {
+        let source = format!("print '{0}' == \"{0}\", len('{0}')", escape);
+        assert_eq!(run(&source), "true 1\n", "{}", escape);
+    }
+}
+
+#[test]
+fn bad_escapes_are_errors_in_strings_and_chars() {
+    for escape in &[r"\q", r"\x4g", r"\x80", r"\u41", r"\u{}", r"\u{110000}"] {
+        let in_string = bad_token(&format!("print \"{}\"", escape));
+        let in_char = bad_token(&format!("print '{}'", escape));
+        assert_eq!(in_string, in_char, "{}", escape);
+    }
+    assert_eq!(bad_token(r#"print "\q""#), r"unknown escape '\q'");
+}
+
+#[test]
+fn chars_hold_exactly_one_char() {
+    for source in &["print ''", "print 'ab'", r"print '\n\n'"] {
+        assert_eq!(
+            bad_token(source),
+            "a char literal has to contain exactly one char",
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn unterminated_chars_are_errors() {
+    assert_eq!(bad_token("print 'a"), "string is never terminated");
+}
@@ -0,0 +1,38 @@
+use std::process::Command;
+
+fn cahn(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn eval_runs_the_given_source() {
+    let output = cahn(&["-e", "print 1 + 2"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|line| line == "3"), "{}", stdout);
+
+    let output = cahn(&["--eval", "let a := \"x\"\nprint a .. a"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|line| line == "xx"), "{}", stdout);
+}
+
+#[test]
+fn eval_errors_name_the_source_eval() {
+    let output = cahn(&["-e", "print 1 + true"]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("<eval>"), "{}", stderr);
+}
+
+#[test]
+fn eval_takes_no_input_file() {
+    let output = cahn(&["-e", "print 1", "program.cahn"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+
+    let output = cahn(&["-e"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+}
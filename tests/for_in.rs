@@ -0,0 +1,170 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::{lexical_analysis::TokenPos, syntactical_analysis::ParseError},
+    executable::{CahnFunction, Executable, Instruction},
+    runtime::{error::RuntimeError, VM},
+};
+use common::{disassemble, parse_error, run};
+
+#[test]
+fn for_in_goes_through_list_elements() {
+    let source = r#"
+        for x in [1, "two", [3]] {
+            print x
+        }
+    "#;
+    assert_eq!(run(source), "1\ntwo\n[3]\n");
+}
+
+#[test]
+fn for_in_goes_through_string_chars() {
+    let source = r#"
+        for c in "hé!" {
+            print c, len(c)
+        }
+        let built := "ab" .. "c"
+        for c in built {
+            print c
+        }
+    "#;
+    assert_eq!(run(source), "h 1\né 1\n! 1\na\nb\nc\n");
+}
+
+#[test]
+fn empty_containers_skip_the_body() {
+    let source = r#"
+        for x in [] {
+            print "list", x
+        }
+        for c in "" {
+            print "string", c
+        }
+        print "done"
+    "#;
+    assert_eq!(run(source), "done\n");
+}
+
+#[test]
+fn loops_nest_and_run_in_functions() {
+    let source = "
+        fn pairs(left, right) {
+            let count := 0
+            for l in left {
+                for r in right {
+                    print l .. r
+                    count := count + 1
+                }
+            }
+            return count
+        }
+        print pairs([1, 2], \"ab\")
+    ";
+    assert_eq!(run(source), "1a\n1b\n2a\n2b\n4\n");
+}
+
+#[test]
+fn the_loop_variable_doesnt_leak_into_the_enclosing_scope() {
+    let source = "
+        let x := \"outer\"
+        for x in [1, 2] {
+            print x
+        }
+        print x
+    ";
+    assert_eq!(run(source), "1\n2\nouter\n");
+}
+
+#[test]
+fn closures_capture_the_element_of_their_iteration() {
+    let source = "
+        struct Fns { first, second }
+        let nothing
+        let fns := Fns(nothing, nothing)
+        for n in [10, 20] {
+            if fns.first == nothing {
+                fns.first := fn() { return n }
+            } else {
+                fns.second := fn() { return n }
+            }
+        }
+        print fns.first(), fns.second()
+    ";
+    assert_eq!(run(source), "10 20\n");
+}
+
+#[test]
+fn the_iterator_keeps_its_container_alive() {
+    // the gc runs on every allocation, so the strings built in the body would free
+    // the list if the iterator didn't mark it
+    let source = "
+        fn make() {
+            return [\"a\" .. 1, \"b\" .. 2, \"c\" .. 3]
+        }
+        for s in make() {
+            let garbage := s .. s .. s
+            print s
+        }
+    ";
+    assert_eq!(run(source), "a1\nb2\nc3\n");
+}
+
+#[test]
+fn for_in_compiles_to_the_iterator_instructions() {
+    let code = disassemble("for x in [1, 2] {\nprint x\n}");
+    assert!(code.contains("\tIterInit"), "{}", code);
+    assert!(code.contains("\tIterNext"), "{}", code);
+}
+
+#[test]
+fn only_lists_and_strings_can_be_iterated() {
+    for source in &["for x in 1 { print x }", "for x in nothing { print x }"] {
+        let source = format!("let nothing\n{}", source);
+        let exec = compile(&source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn for_needs_a_variable_and_in() {
+    for source in &["for in [1] { }", "for x [1] { }", "for x in [1] print x"] {
+        assert!(
+            matches!(parse_error(source), ParseError::BadToken { .. }),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn iter_next_without_an_iterator_is_a_type_error() {
+    // the verifier only counts values, so it can't tell that nil isn't an iterator
+    let code = vec![
+        Instruction::LoadNil as u8,
+        Instruction::IterNext as u8,
+        8,
+        0,
+        0,
+        0,
+        Instruction::Pop as u8,
+        Instruction::Halt as u8,
+        Instruction::Halt as u8,
+    ];
+    let code_map = vec![TokenPos::default(); code.len()];
+    let mut exec = compile("print 1", "inline-test".into()).unwrap();
+    *exec.functions.last_mut().unwrap() = CahnFunction::new_anonymous(0, code, code_map).into();
+    let exec = Executable::deserialize(&exec.serialize()).unwrap();
+
+    assert!(matches!(
+        VM::run_to_string(&exec),
+        Err(RuntimeError::TypeError { .. })
+    ));
+}
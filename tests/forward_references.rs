@@ -0,0 +1,75 @@
+mod common;
+
+use cahn_lang::{
+    compile, compile_with_options,
+    compiler::codegen::{CodeGenError, CodeGenOptions},
+    error::CahnError,
+};
+use common::run;
+
+#[test]
+fn top_level_functions_can_be_mutually_recursive() {
+    let source = "
+        print is_even(10)
+        print is_odd(7)
+
+        fn is_even(n) {
+            if n == 0 { return true }
+            return is_odd(n - 1)
+        }
+
+        fn is_odd(n) {
+            if n == 0 { return false }
+            return is_even(n - 1)
+        }
+    ";
+    assert_eq!(run(source), "true\ntrue\n");
+}
+
+#[test]
+fn function_can_be_called_before_its_declaration() {
+    let source = "
+        print square(4)
+        fn square(x) { return x * x }
+    ";
+    assert_eq!(run(source), "16\n");
+}
+
+#[test]
+fn hoisted_function_still_sees_constants_declared_before_it() {
+    let source = "
+        const DEBUG := false
+        fn log(message) {
+            if DEBUG { print message }
+        }
+        log(1)
+    ";
+    let (_, warnings) =
+        compile_with_options(source, "inline-test".into(), &[], CodeGenOptions::default()).unwrap();
+    assert!(warnings
+        .iter()
+        .any(|warning| warning.code() == "constant-condition"));
+}
+
+#[test]
+fn forward_reference_to_variable_is_unresolved() {
+    let source = "
+        print x
+        let x := 1
+    ";
+    assert!(matches!(
+        compile(source, "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::UnresolvedVariable { .. }))
+    ));
+}
+
+#[test]
+fn function_redeclared_with_let_isnt_hoisted() {
+    let source = "
+        fn f() { return 1 }
+        print f()
+        let f := 2
+        print f
+    ";
+    assert_eq!(run(source), "1\n2\n");
+}
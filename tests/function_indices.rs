@@ -0,0 +1,53 @@
+use cahn_lang::{compile, executable::Executable};
+
+const SOURCE: &str = "
+    fn first() {
+        fn nested() { return 1 }
+        let inner := fn() { return nested() }
+        return inner()
+    }
+    let lambda := fn(x) { return x * 2 }
+    print first() + lambda(3) + later()
+    fn later() {
+        return do {
+            fn in_block() { return 4 }
+            in_block()
+        }
+    }
+";
+
+fn function_names(exec: &Executable) -> Vec<String> {
+    exec.functions
+        .iter()
+        .map(|function| function.name.fmt(&exec.string_data).to_string())
+        .collect()
+}
+
+#[test]
+fn functions_are_numbered_in_source_order() {
+    let exec = compile(SOURCE, "inline-test".into()).unwrap();
+    assert_eq!(
+        function_names(&exec),
+        [
+            "first",
+            "nested",
+            "Anonymous",
+            "Anonymous",
+            "later",
+            "in_block",
+            "CahnMain"
+        ]
+    );
+}
+
+#[test]
+fn recompiling_gives_the_same_function_order() {
+    let first = compile(SOURCE, "inline-test".into()).unwrap();
+    let second = compile(SOURCE, "inline-test".into()).unwrap();
+
+    assert_eq!(function_names(&first), function_names(&second));
+    for (a, b) in first.functions.iter().zip(&second.functions) {
+        assert_eq!(a.to_bytes(), b.to_bytes());
+    }
+    assert_eq!(first.serialize(), second.serialize());
+}
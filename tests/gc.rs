@@ -0,0 +1,95 @@
+mod common;
+
+use common::run;
+use std::cell::Cell;
+
+use cahn_lang::{
+    compile_with_natives,
+    runtime::{error::Result, NativeFunction, OpaqueValue, Value, VmContext, VM},
+};
+
+// the gc runs on every allocation, so a value that's only held by the instruction
+// allocating would be freed and read afterwards.
+#[test]
+fn values_reassigned_in_loops_survive_concatenation() {
+    let source = r#"
+        let list := ["start"]
+        let i := 0
+        while i < 2000 {
+            let word := "w" .. i
+            list := [list[0] .. "", word .. word, typeof list]
+            i := i + 1
+        }
+        print list
+    "#;
+    assert_eq!(run(source), "[start, w1999w1999, list]\n");
+}
+
+#[test]
+fn concatenated_heap_strings_are_read_before_they_can_be_freed() {
+    let source = r#"
+        let text := ""
+        let i := 0
+        while i < 500 {
+            text := ("a" .. i) .. (typeof i .. text)
+            i := i + 1
+        }
+        print len(text), starts_with(text, "a499number")
+    "#;
+    let len: usize = (0..500).map(|i| 1 + i.to_string().len() + 6).sum();
+    assert_eq!(run(source), format!("{} true\n", len));
+}
+
+thread_local! {
+    static TRACKED_DROPS: Cell<usize> = const { Cell::new(0) };
+}
+
+// an opaque value that counts how often values of it were freed
+#[derive(Debug)]
+struct Tracked;
+
+impl OpaqueValue for Tracked {
+    fn display_name(&self) -> &str {
+        "tracked"
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        TRACKED_DROPS.with(|drops| drops.set(drops.get() + 1));
+    }
+}
+
+fn tracked(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    Ok(ctx.alloc_opaque(Tracked))
+}
+
+#[test]
+fn concat_operands_are_alive_while_the_result_is_allocated() {
+    let natives = [NativeFunction::new("tracked", 0, tracked)];
+    let exec = compile_with_natives(
+        "print tracked() .. (\"heap\" .. 1)",
+        "inline-test".into(),
+        &natives,
+    )
+    .unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+
+    // allocating the result of the outer Concat runs a gc, which can't free its operands
+    let mut concats = 0;
+    while let Some(step) = vm.step_disassembled().unwrap() {
+        if step == "Concat" {
+            concats += 1;
+            if concats == 2 {
+                break;
+            }
+        }
+    }
+    assert_eq!(TRACKED_DROPS.with(Cell::get), 0);
+
+    while vm.step().unwrap() {}
+    drop(vm);
+    assert_eq!(String::from_utf8(stdout).unwrap(), "<tracked #0>heap1\n");
+    assert_eq!(TRACKED_DROPS.with(Cell::get), 1);
+}
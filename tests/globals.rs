@@ -0,0 +1,196 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::codegen::CodeGenError,
+    error::CahnError,
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VMOptions, VM},
+};
+use common::try_run;
+
+#[test]
+fn function_mutates_top_level_counter() {
+    let source = "
+        let counter := 0
+
+        fn increment() {
+            counter := counter + 1
+        }
+
+        increment()
+        increment()
+        increment()
+        print counter
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "3\n"
+    );
+}
+
+#[test]
+fn function_arguments_and_return_values() {
+    let source = "
+        let offset := 10
+
+        fn add(a, b) {
+            let sum := a + b
+            return sum + offset
+        }
+
+        fn nothing() {}
+
+        print add(1, 2) * 2
+        print nothing()
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "26\nnil\n"
+    );
+}
+
+#[test]
+fn recursive_global_function() {
+    let source = "
+        fn fib(n) {
+            if n < 2 {
+                return n
+            }
+            return fib(n - 1) + fib(n - 2)
+        }
+        print fib(15)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "610\n"
+    );
+}
+
+#[test]
+fn functions_can_use_globals_declared_after_them() {
+    let source = "
+        fn get() {
+            return later
+        }
+        let later := 5
+        print get()
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "5\n"
+    );
+}
+
+#[test]
+fn globals_survive_gc() {
+    let source = r#"
+        let xs := ["a" .. "b"]
+        let s := "c" .. "d"
+        let t := s .. "e"
+        print xs
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "[ab]\n"
+    );
+}
+
+#[test]
+fn block_declarations_are_locals() {
+    let source = "
+        let x := 1
+        {
+            let x := 2
+            print x
+        }
+        print x
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "2\n1\n"
+    );
+}
+
+#[test]
+fn reading_undefined_global_is_a_runtime_error() {
+    let source = "
+        fn get() {
+            return missing
+        }
+        get()
+    ";
+    match try_run(source) {
+        Err(RuntimeError::UndefinedVariable { name }) => assert_eq!(name, "missing"),
+        other => panic!("expected an UndefinedVariable error, got {:?}", other),
+    }
+
+    let source = "
+        fn set() {
+            missing := 1
+        }
+        set()
+    ";
+    assert!(matches!(
+        try_run(source),
+        Err(RuntimeError::UndefinedVariable { .. })
+    ));
+}
+
+#[test]
+fn unknown_variable_at_top_level_is_a_compile_error() {
+    assert!(matches!(
+        compile("print missing", "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::UnresolvedVariable { .. }))
+    ));
+}
+
+#[test]
+fn assigning_to_const_global_in_function_is_a_compile_error() {
+    let source = "
+        const limit := 10
+        fn raise() {
+            limit := 20
+        }
+    ";
+    assert!(matches!(
+        compile(source, "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::AssignmentToConst { .. }))
+    ));
+}
+
+#[test]
+fn calling_with_wrong_argument_count_is_a_runtime_error() {
+    let source = "
+        fn add(a, b) {
+            return a + b
+        }
+        add(1)
+    ";
+    assert!(matches!(
+        try_run(source),
+        Err(RuntimeError::ArityMismatch {
+            expected: 2,
+            got: 1,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow() {
+    let exec = compile("fn f() { return f() }\nf()", "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            max_call_depth: 100,
+            ..VMOptions::default()
+        },
+    );
+    assert!(matches!(
+        vm.run(),
+        Err(RuntimeError::StackOverflow { max: 100 })
+    ));
+}
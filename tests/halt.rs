@@ -0,0 +1,66 @@
+use cahn_lang::{
+    compile,
+    executable::{BytecodeError, CahnFunction, Instruction},
+    runtime::{OwnedValue, VM},
+};
+
+#[test]
+fn top_level_function_ends_with_halt() {
+    for source in &["", "print 1", "let x := 2\nx * 3", "fn f() { return 1 }"] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        let main = exec.functions.last().unwrap();
+        assert_eq!(
+            main.code.last(),
+            Some(&(Instruction::Halt as u8)),
+            "{:?}",
+            source
+        );
+        // functions return instead
+        for function in &exec.functions[..exec.functions.len() - 1] {
+            assert_ne!(function.code.last(), Some(&(Instruction::Halt as u8)));
+        }
+    }
+}
+
+#[test]
+fn halting_stops_the_vm() {
+    let exec = compile("let x := 2\nprint x\nx * 3", "inline-test".into()).unwrap();
+    let mut out = vec![];
+    let mut vm = VM::new(&exec, &mut out);
+
+    let mut steps = 1;
+    while vm.step().unwrap() {
+        steps += 1;
+    }
+    // the Halt was the last instruction run, and nothing runs after it
+    assert_eq!(steps, 9);
+    assert!(!vm.step().unwrap());
+    assert!(matches!(vm.step_disassembled(), Ok(None)));
+    drop(vm);
+    assert_eq!(String::from_utf8(out).unwrap(), "2\n");
+}
+
+#[test]
+fn halted_program_keeps_its_value() {
+    let exec = compile("let x := 2\nx * 3", "inline-test".into()).unwrap();
+    let run = VM::run_full(&exec).unwrap();
+    assert_eq!(run.value, OwnedValue::Number(6.0));
+    // loading the script function, defining x, computing x * 3 and halting
+    assert_eq!(run.stats.instructions_run, 7);
+}
+
+#[test]
+fn replacements_have_to_end_with_return_or_halt() {
+    let exec = compile("fn f() { return 1 }\nprint f()", "inline-test".into()).unwrap();
+    for function in &exec.functions {
+        assert!(exec.check_function(function).is_ok());
+
+        let mut truncated = CahnFunction::clone(function);
+        truncated.code.pop();
+        truncated.code_map.pop();
+        assert!(matches!(
+            exec.check_function(&truncated),
+            Err(BytecodeError::Malformed { .. })
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+use cahn_lang::{compile, execute_source_to_string, runtime::VM};
+
+#[test]
+fn string_hash_is_stable() {
+    let source = "
+        print hash(\"abc\") == hash(\"abc\")
+        print hash(\"ab\" .. \"c\") == hash(\"abc\")
+        print hash(\"abc\") == hash(\"abd\")
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn different_numbers_hash_differently() {
+    let source = "
+        print hash(1) == hash(2)
+        print hash(1) == hash(1)
+        print hash(0) == hash(-0)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "false\ntrue\ntrue\n"
+    );
+}
+
+#[test]
+fn lists_hash_over_their_elements() {
+    let source = "
+        print hash([1, \"a\", [true]]) == hash([1, \"a\", [true]])
+        print hash([1, 2]) == hash([2, 1])
+        let nothing
+        print hash([]) == hash(nothing)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true\nfalse\nfalse\n"
+    );
+}
+
+#[test]
+fn functions_cant_be_hashed() {
+    let exec = compile("fn f() {} print hash(f)", "inline-test".into()).unwrap();
+    assert!(VM::run_to_string(&exec).is_err());
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::lexical_analysis::TokenPos,
+    runtime::{HeapEntry, HeapReport, VM},
+};
+use common::run;
+
+// the report of what's left on the heap once the program is done
+fn report_at_exit(source: &str) -> HeapReport {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {}
+    vm.heap_report()
+}
+
+#[test]
+fn live_values_are_counted_by_kind() {
+    let source = "let rows := [[1, 2], [3], []]
+let name := \"a\" .. \"b\"
+fn counter() {
+    let n := 0
+    return fn() { n := n + 1; return n }
+}
+let next := counter()";
+    let report = report_at_exit(source);
+    assert_eq!(report.live("list"), 4);
+    assert_eq!(report.live("string"), 1);
+    assert_eq!(report.live("closure"), 1);
+    assert_eq!(report.live("upvalue"), 1);
+    assert_eq!(report.live("record"), 0);
+    assert_eq!(report.unreachable, 0);
+}
+
+#[test]
+fn the_largest_lists_and_strings_come_with_where_they_were_allocated() {
+    let source = "let small := [1]
+let big := []
+resize(big, 100, 0)
+let text := \"abc\" .. \"def\"";
+    let report = report_at_exit(source);
+    assert_eq!(
+        report.largest[0],
+        HeapEntry {
+            kind: "list",
+            size: 100,
+            alloc_site: Some(TokenPos::new(2, 12)),
+        }
+    );
+    assert_eq!(report.largest[1].kind, "string");
+    assert_eq!(report.largest[1].size, 6);
+    assert_eq!(report.largest[1].alloc_site.unwrap().line, 4);
+    assert_eq!(report.largest.last().unwrap().size, 1);
+}
+
+#[test]
+fn only_the_ten_largest_are_listed() {
+    let source = "
+        fn sized(n) {
+            let list := []
+            resize(list, n, 0)
+            return list
+        }
+        let lists := [sized(1), sized(2), sized(3), sized(4), sized(5), sized(6),
+            sized(7), sized(8), sized(9), sized(10), sized(11), sized(12)]
+    ";
+    let report = report_at_exit(source);
+    assert_eq!(report.live("list"), 13);
+    let sizes: Vec<usize> = report.largest.iter().map(|entry| entry.size).collect();
+    assert_eq!(sizes, [12, 12, 11, 10, 9, 8, 7, 6, 5, 4]);
+}
+
+#[test]
+fn garbage_is_counted_until_it_is_collected() {
+    // the list is popped right away, and nothing allocates afterwards to collect it
+    let report = report_at_exit("let keep := [1]\nprint len([2, 3])");
+    assert_eq!(report.live("list"), 1);
+    assert_eq!(report.unreachable, 1);
+}
+
+#[test]
+fn reporting_leaves_the_heap_alone() {
+    let exec = compile(
+        "let keep := [[1], \"a\" .. \"b\"]\nprint len([2, 3])",
+        "inline-test".into(),
+    )
+    .unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {}
+    let first = vm.heap_report();
+    assert_eq!(vm.heap_report(), first);
+}
+
+#[test]
+fn heap_report_builtin_describes_the_heap() {
+    let source = "
+        let accumulator := []
+        resize(accumulator, 3, 0)
+        let label := \"size \" .. len(accumulator)
+        print heap_report()
+    ";
+    assert_eq!(
+        run(source),
+        "live:
+    list: 1
+    string: 1
+largest:
+    string of 6 bytes, allocated at 4:30
+    list of 3 elements, allocated at 2:28
+unreachable: 0
+"
+    );
+}
+
+#[test]
+fn values_a_report_saw_alive_are_still_collected() {
+    // reports run between every instruction, so if they left their marks behind, the list
+    // would survive the collection the concatenation triggers after it became garbage
+    let source = "
+        let keep := [1, 2]
+        keep := 0
+        let text := \"a\" .. keep
+    ";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {
+        vm.heap_report();
+    }
+    let report = vm.heap_report();
+    assert_eq!(report.live("list"), 0);
+    assert_eq!(report.live("string"), 1);
+    assert_eq!(report.unreachable, 0);
+}
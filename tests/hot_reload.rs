@@ -0,0 +1,173 @@
+use std::{cell::RefCell, io, rc::Rc};
+
+use cahn_lang::{
+    compile,
+    executable::{BytecodeError, CahnFunction, Capture, Executable},
+    runtime::VM,
+};
+
+// stdout that can still be read while the VM holds on to it
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl SharedOutput {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl io::Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const ORIGINAL: &str = "
+    fn combine(a, b) {
+        return a + b
+    }
+    print combine(3, 4)
+    print combine(3, 4)
+";
+
+const RELOADED: &str = "
+    fn combine(a, b) {
+        return a * b
+    }
+    print combine(3, 4)
+    print combine(3, 4)
+";
+
+fn reloaded_combine() -> CahnFunction {
+    let exec = compile(RELOADED, "reloaded".into()).unwrap();
+    CahnFunction::from_bytes(&exec.functions[0].to_bytes()).unwrap()
+}
+
+fn finish(vm: &mut VM) {
+    while vm.step().unwrap() {}
+}
+
+#[test]
+fn replaced_function_is_used_by_later_calls() {
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    let output = SharedOutput::default();
+    let mut stdout = output.clone();
+    let mut vm = VM::new(&exec, &mut stdout);
+
+    while output.text().is_empty() {
+        assert!(vm.step().unwrap());
+    }
+    vm.replace_function(0, reloaded_combine()).unwrap();
+    finish(&mut vm);
+
+    assert_eq!(output.text(), "7\n12\n");
+}
+
+#[test]
+fn running_call_finishes_with_old_code() {
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    let output = SharedOutput::default();
+    let mut stdout = output.clone();
+    let mut vm = VM::new(&exec, &mut stdout);
+
+    while !Rc::ptr_eq(&vm.curr_func, &exec.functions[0]) {
+        assert!(vm.step().unwrap());
+    }
+    vm.replace_function(0, reloaded_combine()).unwrap();
+    finish(&mut vm);
+
+    assert_eq!(output.text(), "7\n12\n");
+}
+
+#[test]
+fn bytes_round_trip() {
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    for func in &exec.functions {
+        let loaded = CahnFunction::from_bytes(&func.to_bytes()).unwrap();
+        assert_eq!(loaded.to_bytes(), func.to_bytes());
+        exec.check_function(&loaded).unwrap();
+    }
+}
+
+#[test]
+fn truncated_bytes_are_rejected() {
+    let bytes = reloaded_combine().to_bytes();
+    for len in 0..bytes.len() {
+        assert!(CahnFunction::from_bytes(&bytes[..len]).is_err());
+    }
+}
+
+#[test]
+fn invalid_instruction_is_rejected() {
+    let mut func = reloaded_combine();
+    func.code[0] = u8::MAX;
+    assert!(matches!(
+        CahnFunction::from_bytes(&func.to_bytes()),
+        Err(BytecodeError::InvalidInstruction {
+            byte: u8::MAX,
+            index: 0
+        })
+    ));
+}
+
+fn replace_in(exec: &Executable, index: usize, func: CahnFunction) -> Result<(), BytecodeError> {
+    let mut stdout = vec![];
+    let mut vm = VM::new(exec, &mut stdout);
+    vm.replace_function(index, func)
+}
+
+#[test]
+fn function_from_unrelated_executable_is_rejected() {
+    let exec = compile("print 1", "original".into()).unwrap();
+    let other = compile(
+        "let s := \"a long string\" print s print 12345.5",
+        "other".into(),
+    )
+    .unwrap();
+    let func = CahnFunction::from_bytes(&other.functions[0].to_bytes()).unwrap();
+
+    assert!(matches!(
+        replace_in(&exec, 0, func),
+        Err(BytecodeError::BadReference { .. })
+    ));
+}
+
+#[test]
+fn replacement_has_to_take_the_same_arguments() {
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    let takes_one = compile("fn combine(a) { return a }", "reloaded".into()).unwrap();
+    let func = (*takes_one.functions[0]).clone();
+
+    assert!(matches!(
+        replace_in(&exec, 0, func),
+        Err(BytecodeError::IncompatibleReplacement { index: 0, .. })
+    ));
+}
+
+#[test]
+fn replacement_has_to_capture_the_same_variables() {
+    // combine is created without a closure, so it would have nothing to capture from
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    let capturing = reloaded_combine().with_captures(vec![Capture {
+        is_local: true,
+        index: 1,
+    }]);
+
+    assert!(matches!(
+        replace_in(&exec, 0, capturing),
+        Err(BytecodeError::IncompatibleReplacement { index: 0, .. })
+    ));
+}
+
+#[test]
+fn missing_function_index_is_rejected() {
+    let exec = compile(ORIGINAL, "original".into()).unwrap();
+    assert!(matches!(
+        replace_in(&exec, 5, reloaded_combine()),
+        Err(BytecodeError::NoSuchFunction { index: 5, count: 2 })
+    ));
+}
@@ -17,7 +17,7 @@ fn basic_if_test() {
         print 2000
     ";
 
-    let output = execute_source_to_string(source, "inline-test".into());
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
     assert_eq!(output, "1000\n2000\n");
 }
 
@@ -44,6 +44,36 @@ fn if_else_test() {
 
 print 9000";
 
-    let output = execute_source_to_string(source, "inline-test".into());
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
     assert_eq!(output, "1000\n3000\n4000\n9000\n");
 }
+
+#[test]
+fn declare_then_assign_in_branches() {
+    let source = "
+        fn pick(cond) {
+            let x
+            if cond { x := 1 } else { x := 2 }
+            return x
+        }
+
+        let y
+        if false { y := 1 } else { y := 2 }
+        print y
+        print pick(true)
+    ";
+
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "2\n1\n");
+}
+
+#[test]
+fn uninitialized_variable_is_nil() {
+    let source = "
+        let x
+        print x
+    ";
+
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "nil\n");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn if_expression_yields_then_value() {
+    let source = "let y := if 1 < 2 { 10 } else { 20 }; print y";
+    assert_eq!(run(source), "10\n");
+}
+
+#[test]
+fn if_expression_yields_else_value() {
+    let source = "let y := if 2 < 1 { 10 } else { 20 }; print y";
+    assert_eq!(run(source), "20\n");
+}
+
+#[test]
+fn if_expression_without_else_is_nil_when_false() {
+    let source = "
+        let x := 5
+        print if x > 10 { \"big\" }
+        print if x > 1 { \"big\" }
+    ";
+    assert_eq!(run(source), "nil\nbig\n");
+}
+
+#[test]
+fn else_if_chain_picks_first_true_branch() {
+    let source = "
+        fn size(n) {
+            return if n < 10 { \"small\" } else if n < 100 { \"medium\" } else { \"large\" }
+        }
+        print size(5), size(50), size(500)
+    ";
+    assert_eq!(run(source), "small medium large\n");
+}
+
+#[test]
+fn branches_can_declare_locals() {
+    let source = "
+        let a := 3
+        let b := if a > 1 {
+            let doubled := a * 2
+            doubled + 1
+        } else {
+            0
+        }
+        print b, a
+    ";
+    assert_eq!(run(source), "7 3\n");
+}
+
+#[test]
+fn branch_without_trailing_expression_is_nil() {
+    let source = "
+        let v := if true { let unused_ := 1 } else { 2 }
+        print v
+    ";
+    assert_eq!(run(source), "nil\n");
+}
+
+#[test]
+fn if_expression_in_arithmetic() {
+    let source = "
+        let n := 4
+        print 1 + if n > 3 { n } else { 0 } * 2
+    ";
+    assert_eq!(run(source), "9\n");
+}
@@ -0,0 +1,77 @@
+use cahn_lang::{
+    compile_with_options,
+    compiler::codegen::{CodeGenOptions, Warning},
+};
+
+fn infinite_loop_lines(source: &str, warn_infinite_loops: bool) -> Vec<usize> {
+    let options = CodeGenOptions {
+        warn_infinite_loops,
+        ..CodeGenOptions::default()
+    };
+    let (_, warnings) = compile_with_options(source, "inline-test".into(), &[], options)
+        .unwrap_or_else(|err| panic!("{} failed to compile: {}", source, err));
+    warnings
+        .into_iter()
+        .filter_map(|warning| match warning {
+            Warning::LikelyInfiniteLoop { pos } => Some(pos.line),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn loop_without_effects_warns() {
+    let source = "
+        while true { 1 + 1 }
+        while not false {
+            let x := [1, 2]
+            x[0] + 1
+        }
+    ";
+    assert_eq!(infinite_loop_lines(source, true), vec![2, 3]);
+}
+
+#[test]
+fn loops_that_can_stop_or_do_something_dont_warn() {
+    let source = "
+        fn f() {
+            while true { return 1 }
+        }
+        let n := 0
+        while true { print n }
+        while true { n := n + 1 }
+        while true { f() }
+        while true {
+            if n > 10 {
+                assert false
+            }
+        }
+        while true { let _x := f() }
+    ";
+    assert!(infinite_loop_lines(source, true).is_empty());
+}
+
+#[test]
+fn loops_with_a_condition_that_isnt_constant_dont_warn() {
+    let source = "
+        let go := true
+        while go { 1 + 1 }
+        while false { 1 + 1 }
+    ";
+    assert!(infinite_loop_lines(source, true).is_empty());
+}
+
+#[test]
+fn declaring_a_function_isnt_an_effect() {
+    let source = "
+        while true {
+            fn g() { print 1 }
+        }
+    ";
+    assert_eq!(infinite_loop_lines(source, true), vec![2]);
+}
+
+#[test]
+fn infinite_loops_arent_reported_by_default() {
+    assert!(infinite_loop_lines("while true { 1 + 1 }", false).is_empty());
+}
@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use cahn_lang::{compile, runtime::VM};
+
+fn run_with_input(source: &str, input: &str) -> String {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    VM::run_to_string_with_input(&exec, input).unwrap()
+}
+
+#[test]
+fn input_echoes_lines_back() {
+    let source = r#"
+        let nil_var
+        let line := input()
+        while line != nil_var {
+            print "> " .. line
+            line := input()
+        }
+    "#;
+    assert_eq!(
+        run_with_input(source, "first\nsecond line\n\nlast"),
+        "> first\n> second line\n> \n> last\n"
+    );
+}
+
+#[test]
+fn line_endings_are_trimmed() {
+    let source = "print [input(), input(), input()]";
+    assert_eq!(run_with_input(source, "a\r\nb \n c"), "[a, b ,  c]\n");
+}
+
+#[test]
+fn input_is_nil_at_the_end() {
+    let source = "let a := input(); let b := input()\nprint a, str(b)";
+    assert_eq!(run_with_input(source, "only\n"), "only nil\n");
+}
+
+#[test]
+fn without_stdin_there_is_no_input() {
+    let exec = compile("print str(input())", "inline-test".into()).unwrap();
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "nil\n");
+}
+
+#[test]
+fn input_reads_a_number_line() {
+    let exec = compile("print input()", "inline-test".into()).unwrap();
+    let mut stdin = Cursor::new("42\n");
+    let mut out = vec![];
+    VM::new(&exec, &mut out)
+        .with_stdin(&mut stdin)
+        .run()
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "42\n");
+}
+
+#[test]
+fn stdin_can_be_any_reader() {
+    let exec = compile("print input() .. input()", "inline-test".into()).unwrap();
+    let mut stdin = Cursor::new(b"ab\ncd\n".to_vec());
+    let mut out = vec![];
+    VM::new(&exec, &mut out)
+        .with_stdin(&mut stdin)
+        .run()
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "abcd\n");
+}
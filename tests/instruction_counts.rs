@@ -0,0 +1,81 @@
+use std::process::Command;
+
+use cahn_lang::{
+    compile,
+    executable::Instruction,
+    runtime::{VMOptions, VM},
+};
+
+fn instruction_counts(source: &str, count_instructions: bool) -> Option<Vec<(Instruction, usize)>> {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let options = VMOptions {
+        count_instructions,
+        ..VMOptions::default()
+    };
+    let mut vm = VM::with_options(&exec, &mut stdout, options);
+    while vm.step().unwrap() {}
+    vm.instruction_counts()
+}
+
+fn count_of(counts: &[(Instruction, usize)], instruction: Instruction) -> usize {
+    counts
+        .iter()
+        .find(|&&(counted, _)| counted == instruction)
+        .map_or(0, |&(_, count)| count)
+}
+
+const LOOP: &str = "
+    let i := 0
+    while i < 10 {
+        i := i + 1
+    }
+";
+
+#[test]
+fn loops_count_their_jumps() {
+    let counts = instruction_counts(LOOP, true).unwrap();
+    // one jump back to the condition per iteration
+    assert_eq!(count_of(&counts, Instruction::Jump), 10);
+    assert_eq!(count_of(&counts, Instruction::JumpIfFalse), 11);
+    assert_eq!(count_of(&counts, Instruction::Halt), 1);
+}
+
+#[test]
+fn counts_are_sorted_and_add_up_to_every_instruction_run() {
+    let counts = instruction_counts(LOOP, true).unwrap();
+    assert!(counts.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    assert!(counts.iter().all(|&(_, count)| count > 0));
+
+    let exec = compile(LOOP, "inline-test".into()).unwrap();
+    let total: usize = counts.iter().map(|&(_, count)| count).sum();
+    assert_eq!(total, VM::run_full(&exec).unwrap().stats.instructions_run);
+}
+
+#[test]
+fn instructions_are_only_counted_when_asked() {
+    assert_eq!(instruction_counts(LOOP, false), None);
+}
+
+#[test]
+fn profile_flag_prints_the_counts() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .args(["--profile", "-e", "let i := 0\nwhile i < 3 { i := i + 1 }"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let profile: Vec<&str> = stdout
+        .lines()
+        .skip_while(|line| *line != "<PROFILE>")
+        .take_while(|line| *line != "</PROFILE>")
+        .collect();
+    assert!(profile[1].starts_with("total time: "), "{}", stdout);
+    assert!(profile[2].starts_with("instructions run: "), "{}", stdout);
+    let jump = profile
+        .iter()
+        .find(|line| line.starts_with("Jump "))
+        .expect("a line for Jump");
+    assert_eq!(jump.split_whitespace().nth(1), Some("3"), "{}", jump);
+}
@@ -0,0 +1,58 @@
+use std::{
+    panic,
+    sync::{Arc, Mutex},
+};
+
+use cahn_lang::{
+    compile,
+    error::{internal_error_message, CahnError},
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VM},
+};
+
+#[test]
+fn panics_are_reported_as_internal_errors() {
+    let reported = Arc::new(Mutex::new(vec![]));
+    let hook_reported = Arc::clone(&reported);
+    panic::set_hook(Box::new(move |info| {
+        let message = internal_error_message(info.payload());
+        hook_reported.lock().unwrap().push(message);
+    }));
+
+    let index = 3;
+    assert!(panic::catch_unwind(|| panic!("forced")).is_err());
+    assert!(panic::catch_unwind(|| panic!("no element at {}", index)).is_err());
+    assert!(panic::catch_unwind(|| panic::panic_any(7)).is_err());
+    let _ = panic::take_hook();
+
+    assert_eq!(
+        *reported.lock().unwrap(),
+        vec![
+            "internal error in cahn, please report: forced",
+            "internal error in cahn, please report: no element at 3",
+            "internal error in cahn, please report: unknown error",
+        ]
+    );
+}
+
+#[test]
+fn executable_without_functions_is_an_error() {
+    let mut exec = compile("print 1", "inline-test".into()).unwrap();
+    exec.functions.clear();
+    assert!(matches!(
+        VM::run_to_string(&exec),
+        Err(RuntimeError::InvalidBytecode { .. })
+    ));
+}
+
+#[test]
+fn failing_programs_give_errors() {
+    assert!(matches!(
+        execute_source_to_string("print missing", "inline-test".into()),
+        Err(CahnError::CodeGen(_))
+    ));
+    assert!(matches!(
+        execute_source_to_string("print 1 + \"a\"", "inline-test".into()),
+        Err(CahnError::Runtime(RuntimeError::TypeError { .. }))
+    ));
+}
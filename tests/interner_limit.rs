@@ -0,0 +1,51 @@
+use cahn_lang::compiler::{
+    string_handling::StringInterner,
+    syntactical_analysis::{ParseError, Parser},
+};
+
+fn parse_with_max_bytes(source: &str, max_bytes: usize) -> Result<String, ParseError> {
+    let arena = bumpalo::Bump::new();
+    let parser = Parser::from_str(source, &arena, StringInterner::with_max_bytes(max_bytes));
+    parser.parse_program().map(|ast| ast.to_string())
+}
+
+#[test]
+fn sources_within_the_limit_parse() {
+    assert_eq!(
+        parse_with_max_bytes("print a + a + a", 100).unwrap(),
+        "(program (print (+ (+ a a) a))\n)"
+    );
+}
+
+#[test]
+fn exceeding_the_limit_is_a_parse_error() {
+    // every name is new, so each one takes up room in the interner
+    let source: String = (0..100).map(|i| format!("let name{}\n", i)).collect();
+    match parse_with_max_bytes(&source, 200) {
+        Err(ParseError::InternerFull { token, max_bytes }) => {
+            assert_eq!(max_bytes, 200);
+            assert!(token.pos.line > 1, "{}", token.pos);
+        }
+        other => panic!("expected the interner to be full, got {:?}", other),
+    }
+}
+
+#[test]
+fn exceeding_the_limit_inside_a_string_is_a_parse_error() {
+    let source = format!("print \"{{{}}}\"", "x".repeat(300));
+    assert!(matches!(
+        parse_with_max_bytes(&source, 400),
+        Err(ParseError::InternerFull { .. })
+    ));
+}
+
+#[test]
+fn interner_reports_its_size() {
+    let interner = StringInterner::with_max_bytes(1000);
+    let arena = bumpalo::Bump::new();
+    let parser = Parser::from_str("print abc", &arena, interner.clone());
+    let before = interner.bytes_used();
+    parser.parse_program().unwrap();
+    assert!(interner.bytes_used() >= before + "abc".len());
+    assert_eq!(interner.max_bytes(), Some(1000));
+}
@@ -0,0 +1,89 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use cahn_lang::{
+    compile,
+    runtime::{error::RuntimeError, VM},
+};
+
+const ENDLESS: &str = "
+print \"started\"
+let i := 0
+while i >= 0 {
+    i := i + 1
+}
+";
+
+#[test]
+fn setting_the_flag_from_another_thread_stops_the_vm() {
+    let exec = compile(ENDLESS, "inline-test".into()).unwrap();
+    let flag = Arc::new(AtomicBool::new(false));
+
+    let setter = {
+        let flag = Arc::clone(&flag);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            flag.store(true, Ordering::SeqCst);
+        })
+    };
+
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout).with_interrupt_flag(Arc::clone(&flag));
+    let err = loop {
+        if let Err(err) = vm.step() {
+            break err;
+        }
+    };
+    setter.join().unwrap();
+
+    assert!(
+        matches!(err, RuntimeError::Interrupted { instructions_run } if instructions_run > 0),
+        "{:?}",
+        err
+    );
+    // it stopped inside the loop
+    assert!(vm.error_pos().unwrap().line >= 4);
+    // the flag is cleared, so it can stop the next run too
+    assert!(!flag.load(Ordering::SeqCst));
+
+    drop(vm);
+    // what was printed before the interrupt is kept
+    assert_eq!(String::from_utf8(stdout).unwrap(), "started\n");
+}
+
+#[test]
+fn a_flag_set_before_the_run_stops_it_right_away() {
+    let exec = compile(ENDLESS, "inline-test".into()).unwrap();
+    let flag = Arc::new(AtomicBool::new(true));
+
+    let mut stdout = vec![];
+    let result = VM::new(&exec, &mut stdout).with_interrupt_flag(flag).run();
+    assert!(matches!(
+        result,
+        Err(RuntimeError::Interrupted {
+            instructions_run: 0
+        })
+    ));
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn programs_finish_while_the_flag_is_unset() {
+    let exec = compile(
+        "let i := 0\nwhile i < 1000 { i := i + 1 }\nprint i",
+        "inline-test".into(),
+    )
+    .unwrap();
+    let mut stdout = vec![];
+    VM::new(&exec, &mut stdout)
+        .with_interrupt_flag(Arc::new(AtomicBool::new(false)))
+        .run()
+        .unwrap();
+    assert_eq!(String::from_utf8(stdout).unwrap(), "1000\n");
+}
@@ -0,0 +1,103 @@
+use cahn_lang::{
+    compile, compiler::codegen::CodeGenError, error::CahnError, execute_source_to_string,
+};
+
+#[test]
+fn function_in_if_block_isnt_visible_outside() {
+    let source = "
+        if true {
+            fn helper() { return 1 }
+            print helper()
+        }
+        print helper()
+    ";
+    assert!(matches!(
+        compile(source, "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::UnresolvedVariable { .. }))
+    ));
+}
+
+#[test]
+fn function_in_block_can_be_called() {
+    let source = "
+        {
+            fn double(x) { return x * 2 }
+            print double(4)
+        }
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "8\n"
+    );
+}
+
+#[test]
+fn local_function_can_recurse() {
+    let source = "
+        {
+            fn fact(n) {
+                if n < 2 { return 1 }
+                return n * fact(n - 1)
+            }
+            print fact(5)
+        }
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "120\n"
+    );
+}
+
+#[test]
+fn local_function_shadows_global() {
+    let source = "
+        fn name() { return 1 }
+        {
+            fn name() { return 2 }
+            print name()
+        }
+        print name()
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "2\n1\n"
+    );
+}
+
+#[test]
+fn function_nested_in_function() {
+    let source = "
+        fn outer(x) {
+            fn inner(y) { return y + 1 }
+            return inner(x) * 2
+        }
+        print outer(2)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "6\n"
+    );
+}
+
+#[test]
+fn naive_fib_recurses_at_every_level() {
+    let fib = "
+        fn fib(n) {
+            if n < 2 { return n }
+            return fib(n - 1) + fib(n - 2)
+        }
+    ";
+    let sources = [
+        format!("{}\nprint fib(20)", fib),
+        format!("{{\n{}\nprint fib(20)\n}}", fib),
+        format!("fn run() {{\n{}\nreturn fib(20)\n}}\nprint run()", fib),
+    ];
+    for source in &sources {
+        assert_eq!(
+            execute_source_to_string(source, "inline-test".into()).unwrap(),
+            "6765\n",
+            "{}",
+            source
+        );
+    }
+}
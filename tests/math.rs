@@ -0,0 +1,59 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    runtime::{error::RuntimeError, VM},
+};
+use common::run;
+
+#[test]
+fn sqrt_and_abs() {
+    assert_eq!(run("print sqrt(16), sqrt(2.25), sqrt(0)"), "4 1.5 0\n");
+    assert_eq!(run("print abs(-3), abs(3), abs(-0.5)"), "3 3 0.5\n");
+}
+
+#[test]
+fn floor_ceil_and_round() {
+    assert_eq!(run("print floor(2.7), floor(-2.2), floor(5)"), "2 -3 5\n");
+    assert_eq!(run("print ceil(2.2), ceil(-2.7), ceil(5)"), "3 -2 5\n");
+    assert_eq!(run("print round(2.4), round(2.5), round(-2.5)"), "2 3 -3\n");
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(run("print min(1, 2), min(2, -1), min(3, 3)"), "1 -1 3\n");
+    assert_eq!(
+        run("print max(1, 2), max(2, -1), max(0.5, 0.25)"),
+        "2 2 0.5\n"
+    );
+    assert_eq!(run("print max(min(7, 10), 0) + abs(-1)"), "8\n");
+}
+
+#[test]
+fn math_functions_reject_other_values() {
+    for source in &[
+        "sqrt(\"4\")",
+        "abs(sqrt)",
+        "floor([1])",
+        "ceil(true)",
+        "round(\"1.5\")",
+        "min(1, \"2\")",
+        "max(false, 1)",
+    ] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{} should be a TypeError",
+            source
+        );
+    }
+
+    let exec = compile("abs([1, \"a\"])", "inline-test".into()).unwrap();
+    assert_eq!(
+        VM::run_to_string(&exec).unwrap_err().to_string(),
+        "TypeError: abs expects a number, but got '[1, a]'"
+    );
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    compiler::syntactical_analysis::ParseError,
+    error::CahnError,
+    runtime::{error::RuntimeError, VM},
+};
+use common::run;
+
+#[test]
+fn in_finds_list_elements() {
+    assert_eq!(run("print 2 in [1, 2, 3]"), "true\n");
+    assert_eq!(run("print 4 in [1, 2, 3]"), "false\n");
+    assert_eq!(
+        run(r#"print "b" in ["a", "b"], true in [], [] in [[]]"#),
+        "true false true\n"
+    );
+}
+
+#[test]
+fn in_compares_list_elements_like_equal() {
+    let source = r#"
+        let pairs := [[1, 2], [3, 4]]
+        for needle in [[3, 4], [4, 3], "1", 1] {
+            print (needle in pairs) == (needle == pairs[0] or needle == pairs[1])
+        }
+        print 'b' in ["a" .. "b", "b"], 'b' in "abc"
+    "#;
+    assert_eq!(run(source), "true\ntrue\ntrue\ntrue\ntrue true\n");
+}
+
+#[test]
+fn in_finds_substrings() {
+    assert_eq!(run(r#"print "bc" in "abcd""#), "true\n");
+    assert_eq!(run(r#"print "cb" in "abcd", "" in "abcd""#), "false true\n");
+    // strings built at runtime are searched the same way
+    assert_eq!(
+        run(r#"let s := "a" .. "b"
+print s in "xaby", "by" in "x" .. s .. "y""#),
+        "true true\n"
+    );
+}
+
+#[test]
+fn not_in_negates() {
+    assert_eq!(run("print 2 not in [1, 2, 3]"), "false\n");
+    assert_eq!(run(r#"print "z" not in "abc""#), "true\n");
+}
+
+#[test]
+fn in_binds_like_a_comparison() {
+    let source = "
+        let list := [2, 4]
+        print 1 + 1 in list and 3 not in list
+        if 2 * 2 in list { print \"four\" }
+    ";
+    assert_eq!(run(source), "true\nfour\n");
+}
+
+#[test]
+fn not_on_the_next_line_starts_a_new_statement() {
+    let source = "
+        let flag := 1
+        not flag
+        print flag
+    ";
+    assert_eq!(run(source), "1\n");
+}
+
+#[test]
+fn membership_can_not_be_chained() {
+    for source in &["print 1 in [1] in [true]", "print 1 in [1] not in [true]"] {
+        match compile(source, "inline-test".into()) {
+            Err(CahnError::Parse(ParseError::ChainingComparisonOperator { .. })) => {}
+            other => panic!(
+                "expected a chaining error for {:?}, got {:?}",
+                source,
+                other.err()
+            ),
+        }
+    }
+}
+
+#[test]
+fn in_needs_a_list_or_a_string() {
+    for source in &[
+        "print 1 in 2",
+        "print 3 in 5",
+        "print 1 in \"123\"",
+        "print [] in nil_var",
+        "print 1 not in 1",
+    ] {
+        let source = format!("let nil_var\n{}", source);
+        let exec = compile(&source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use std::collections::HashMap;
+
+use cahn_lang::{
+    compile, compile_with_loader,
+    compiler::{codegen::CodeGenOptions, syntactical_analysis::ParseError},
+    error::CahnError,
+    executable::Executable,
+    execute_source_to_string_with_loader,
+    runtime::{OwnedValue, VM},
+};
+use common::expect_parse_error;
+
+fn loader<'m>(modules: &'m [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'm {
+    let modules: HashMap<_, _> = modules.iter().copied().collect();
+    move |path| modules.get(path).map(|source| source.to_string())
+}
+
+fn run(source: &str, modules: &[(&str, &str)]) -> String {
+    execute_source_to_string_with_loader(source, "main.cahn".into(), &loader(modules)).unwrap()
+}
+
+fn parse_error(source: &str, modules: &[(&str, &str)]) -> ParseError {
+    let result = compile_with_loader(
+        source,
+        "main.cahn".into(),
+        &[],
+        CodeGenOptions::default(),
+        &loader(modules),
+    );
+    expect_parse_error(source, result)
+}
+
+#[test]
+fn functions_and_globals_of_modules_can_be_used() {
+    let utils = r#"
+        let greeting := "hello"
+        fn double(n) { return n * 2 }
+    "#;
+    let source = r#"
+        import "utils.cahn"
+        print greeting, double(21)
+    "#;
+    assert_eq!(run(source, &[("utils.cahn", utils)]), "hello 42\n");
+}
+
+#[test]
+fn modules_run_where_they_are_imported() {
+    let source = "
+        print 1
+        import \"two.cahn\"
+        print 3
+    ";
+    assert_eq!(run(source, &[("two.cahn", "print 2")]), "1\n2\n3\n");
+}
+
+#[test]
+fn paths_are_relative_to_the_importing_file() {
+    let modules = [
+        (
+            "lib/math.cahn",
+            "import \"./consts.cahn\"\nfn area(r) { return pi * r * r }",
+        ),
+        (
+            "lib/consts.cahn",
+            "import \"../shared/one.cahn\"\nconst pi := 3 * one",
+        ),
+        ("shared/one.cahn", "const one := 1"),
+    ];
+    assert_eq!(
+        run("import \"lib/math.cahn\"\nprint area(2)", &modules),
+        "12\n"
+    );
+}
+
+#[test]
+fn modules_are_only_included_once() {
+    let modules = [
+        ("a.cahn", "import \"counter.cahn\"\ncount := count + 1"),
+        ("b.cahn", "import \"counter.cahn\"\ncount := count + 1"),
+        ("counter.cahn", "let count := 0\nprint \"counter loaded\""),
+    ];
+    let source = "
+        import \"a.cahn\"
+        import \"b.cahn\"
+        import \"a.cahn\"
+        print count
+    ";
+    assert_eq!(run(source, &modules), "counter loaded\n2\n");
+}
+
+#[test]
+fn circular_imports_report_the_chain() {
+    let modules = [
+        ("a.cahn", "import \"b.cahn\""),
+        ("b.cahn", "print 1\nimport \"a.cahn\""),
+    ];
+    match parse_error("import \"a.cahn\"", &modules) {
+        ParseError::CircularImport { token, chain } => {
+            assert_eq!(chain, ["a.cahn", "b.cahn", "a.cahn"]);
+            assert_eq!(token.pos.line, 2);
+        }
+        other => panic!("expected a circular import, got {}", other),
+    }
+
+    match parse_error("import \"main.cahn\"", &[]) {
+        ParseError::CircularImport { chain, .. } => assert_eq!(chain, ["main.cahn", "main.cahn"]),
+        other => panic!("expected a circular import, got {}", other),
+    }
+}
+
+#[test]
+fn missing_modules_point_at_the_import() {
+    match parse_error("print 1\nimport \"dir/missing.cahn\"", &[]) {
+        ParseError::ModuleNotFound { token, path } => {
+            assert_eq!(path, "dir/missing.cahn");
+            assert_eq!((token.pos.line, token.pos.column), (2, 8));
+        }
+        other => panic!("expected a missing module, got {}", other),
+    }
+
+    // without a loader there are no modules
+    assert!(matches!(
+        compile("import \"utils.cahn\"", "main.cahn".into()),
+        Err(CahnError::Parse(ParseError::ModuleNotFound { .. }))
+    ));
+}
+
+#[test]
+fn errors_in_modules_name_the_module() {
+    let modules = [
+        ("outer.cahn", "import \"inner.cahn\""),
+        ("inner.cahn", "let := 1"),
+    ];
+    match parse_error("import \"outer.cahn\"", &modules) {
+        ParseError::InModule { path, error } => {
+            assert_eq!(path, "inner.cahn");
+            assert!(matches!(*error, ParseError::BadToken { .. }), "{}", error);
+        }
+        other => panic!("expected an error in a module, got {}", other),
+    }
+}
+
+#[test]
+fn imports_have_to_be_at_the_top_level() {
+    let result = compile_with_loader(
+        "fn f() {\n    import \"utils.cahn\"\n}",
+        "main.cahn".into(),
+        &[],
+        CodeGenOptions::default(),
+        &loader(&[("utils.cahn", "")]),
+    );
+    assert!(matches!(result, Err(CahnError::CodeGen(_))));
+}
+
+#[test]
+fn the_program_ends_with_the_value_of_the_importer() {
+    let (exec, _) = compile_with_loader(
+        "import \"m.cahn\"\n1 + 1",
+        "main.cahn".into(),
+        &[],
+        CodeGenOptions::default(),
+        &loader(&[("m.cahn", "fn f() {}")]),
+    )
+    .unwrap();
+    assert_eq!(VM::run_full(&exec).unwrap().value, OwnedValue::Number(2.0));
+}
+
+fn compile_modules(source: &str, modules: &[(&str, &str)]) -> Executable {
+    let (exec, _) = compile_with_loader(
+        source,
+        "main.cahn".into(),
+        &[],
+        CodeGenOptions::default(),
+        &loader(modules),
+    )
+    .unwrap();
+    exec
+}
+
+// the file, line and column of the runtime error
+fn runtime_error_at(exec: &Executable) -> (String, usize, usize) {
+    let mut stdout = vec![];
+    let mut vm = VM::new(exec, &mut stdout);
+    while vm.step().is_ok_and(|running| {
+        assert!(running, "the program should fail");
+        running
+    }) {}
+    let pos = vm.error_pos().unwrap();
+    (exec.source_file_of(pos).to_owned(), pos.line, pos.column)
+}
+
+#[test]
+fn runtime_errors_in_modules_are_in_the_module() {
+    let modules = [
+        ("m.cahn", "import \"u.cahn\"\nprint 1"),
+        ("u.cahn", "fn fail() {\n    return 1 + \"a\"\n}"),
+    ];
+    let exec = compile_modules("import \"m.cahn\"\n\nfail()", &modules);
+    assert_eq!(exec.module_files, ["m.cahn", "u.cahn"]);
+    assert_eq!(runtime_error_at(&exec), ("u.cahn".to_owned(), 2, 14));
+
+    // and the top level code of a module too
+    let exec = compile_modules(
+        "import \"m.cahn\"",
+        &[("m.cahn", "let nothing\nprint -nothing")],
+    );
+    assert_eq!(runtime_error_at(&exec), ("m.cahn".to_owned(), 2, 7));
+}
+
+#[test]
+fn module_files_survive_serialization() {
+    let modules = [("u.cahn", "fn fail() {\n    return 1 + \"a\"\n}")];
+    let exec = compile_modules("import \"u.cahn\"\nfail()", &modules);
+    let loaded = Executable::deserialize(&exec.serialize()).unwrap();
+    assert_eq!(runtime_error_at(&loaded), ("u.cahn".to_owned(), 2, 14));
+}
+
+#[test]
+fn warnings_in_modules_are_in_the_module() {
+    let (exec, warnings) = compile_with_loader(
+        "import \"m.cahn\"",
+        "main.cahn".into(),
+        &[],
+        CodeGenOptions::default(),
+        &loader(&[("m.cahn", "fn f() {\n    let unused := 1\n}")]),
+    )
+    .unwrap();
+    let (pos, _) = warnings[0].range();
+    assert_eq!(exec.source_file_of(pos), "m.cahn");
+    assert_eq!((pos.line, pos.column), (2, 9));
+}
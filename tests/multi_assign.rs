@@ -0,0 +1,222 @@
+use cahn_lang::{
+    compile,
+    compiler::codegen::CodeGenError,
+    error::CahnError,
+    executable::Instruction,
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VMOptions, VM},
+};
+
+#[test]
+fn declare_multiple_globals() {
+    let source = "
+        let a, b := 1, 2
+        print a
+        print b
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1\n2\n"
+    );
+}
+
+#[test]
+fn declare_multiple_locals() {
+    let source = "
+        {
+            let a, b, c := 1, 2, 3
+            print a
+            print b
+            print c
+        }
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1\n2\n3\n"
+    );
+}
+
+#[test]
+fn swap_globals() {
+    let source = "
+        let a, b := 1, 2
+        a, b := b, a
+        print a
+        print b
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "2\n1\n"
+    );
+}
+
+#[test]
+fn swap_locals() {
+    let source = "
+        fn f(a, b) {
+            a, b := b, a
+            return a - b
+        }
+        print f(1, 3)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "2\n"
+    );
+}
+
+#[test]
+fn arity_mismatch_is_an_error() {
+    for source in &["let a, b := 1, 2, 3", "let a, b := 1, 2\na, b := 3"] {
+        match compile(source, "inline-test".into()) {
+            Err(CahnError::CodeGen(CodeGenError::AssignmentCountMismatch {
+                targets,
+                values,
+                ..
+            })) => assert_ne!(targets, values),
+            other => panic!("expected AssignmentCountMismatch, got {:?}", other.err()),
+        }
+    }
+}
+
+#[test]
+fn arity_mismatch_points_at_operator() {
+    match compile("let a, b := 1, 2\na, b := 3", "inline-test".into()) {
+        Err(CahnError::CodeGen(CodeGenError::AssignmentCountMismatch { token, .. })) => {
+            assert_eq!(token.pos.line, 2);
+            assert_eq!(token.pos.column, 6);
+        }
+        other => panic!("expected AssignmentCountMismatch, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn multi_assignment_isnt_an_expression() {
+    assert!(compile(
+        "let a, b := 1, 2\nprint (a, b := b, a)",
+        "inline-test".into()
+    )
+    .is_err());
+    assert!(compile("let a, b := 1, 2\na := 1, 2", "inline-test".into()).is_err());
+}
+
+#[test]
+fn assigning_const_is_an_error() {
+    assert!(matches!(
+        compile("const a, b := 1, 2\na, b := b, a", "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::AssignmentToConst { .. }))
+    ));
+}
+
+#[test]
+fn functions_return_multiple_values() {
+    let source = "
+        fn pair(x) {
+            return x, x * 2
+        }
+        let a, b := pair(3)
+        print a, b
+        fn sum_and_product() {
+            let s, p := pair(5)
+            return s + p, s * p
+        }
+        print sum_and_product()
+        a, b := pair(b)
+        print a, b
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "3 6\n[15, 50]\n6 12\n"
+    );
+}
+
+#[test]
+fn multiple_values_are_returned_on_the_stack() {
+    let source = "
+        fn fib_pair(n) {
+            if n == 0 { return 0, 1 }
+            let a, b := fib_pair(n - 1)
+            return b, a + b
+        }
+        let a, b := fib_pair(10)
+        print a, b
+    ";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let options = VMOptions {
+        count_instructions: true,
+        ..VMOptions::default()
+    };
+    let mut vm = VM::with_options(&exec, &mut stdout, options);
+    while vm.step().unwrap() {}
+    let counts = vm.instruction_counts().unwrap();
+    drop(vm);
+    assert_eq!(String::from_utf8(stdout).unwrap(), "55 89\n");
+
+    // the values are never put in a list and taken out of it again
+    let count_of = |instruction| {
+        counts
+            .iter()
+            .find(|&&(counted, _)| counted == instruction)
+            .map_or(0, |&(_, count)| count)
+    };
+    assert_eq!(count_of(Instruction::ReturnMany), 11);
+    assert_eq!(count_of(Instruction::InvokeMany), 11);
+    assert_eq!(count_of(Instruction::CreateListWithCap), 0);
+    assert_eq!(count_of(Instruction::Unpack), 0);
+}
+
+#[test]
+fn lists_returned_by_calls_are_unpacked() {
+    let source = "
+        fn list() { return [1, 2] }
+        let a, b := list()
+        let c, d := split_n(\"x,y\", \",\", 2)
+        print a, b, c, d
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1 2 x y\n"
+    );
+}
+
+#[test]
+fn lists_are_unpacked() {
+    let source = "
+        let list := [1, [2, 3]]
+        let a, b := list
+        let c, d := b
+        print a, c, d
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1 2 3\n"
+    );
+}
+
+#[test]
+fn unpacking_the_wrong_number_of_values_is_an_error() {
+    let run = |source: &str| VM::run_to_string(&compile(source, "inline-test".into()).unwrap());
+
+    assert!(matches!(
+        run("fn pair() { return 1, 2 }\nlet a, b, c := pair()"),
+        Err(RuntimeError::UnpackMismatch {
+            expected: 3,
+            got: 2
+        })
+    ));
+    assert!(matches!(
+        run("fn one() { return 1 }\nlet a, b := one()"),
+        Err(RuntimeError::TypeError { .. })
+    ));
+
+    // literals are checked while compiling
+    assert!(matches!(
+        compile("let a, b := [1, 2, 3]", "inline-test".into()),
+        Err(CahnError::CodeGen(CodeGenError::AssignmentCountMismatch {
+            targets: 2,
+            values: 3,
+            ..
+        }))
+    ));
+}
@@ -0,0 +1,114 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+};
+
+use cahn_lang::{compile, runtime::VM};
+
+// runs the source through the entire pipeline, returns whether it panicked.
+// compile and runtime errors are fine, panics are not.
+fn panics(name: &str, source: &str) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        if let Ok(exec) = compile(source, name.into()) {
+            let _ = VM::run_to_string(&exec);
+        }
+    }))
+    .is_err()
+}
+
+fn assert_no_panics(programs: Vec<(String, String)>) {
+    let panicking: Vec<String> = programs
+        .iter()
+        .filter(|(name, source)| panics(name, source))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    assert!(panicking.is_empty(), "cahn panicked on: {:?}", panicking);
+}
+
+#[test]
+fn corpus_doesnt_panic() {
+    let corpus_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+
+    let programs = fs::read_dir(corpus_dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let bytes = fs::read(&path).unwrap();
+            (
+                path.display().to_string(),
+                String::from_utf8_lossy(&bytes).into_owned(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert!(!programs.is_empty());
+    assert_no_panics(programs);
+}
+
+#[test]
+fn deeply_nested_programs_dont_panic() {
+    let depth = 100_000;
+
+    let programs = vec![
+        ("deep_parens", "(".repeat(depth) + "1" + &")".repeat(depth)),
+        ("unclosed_parens", "(".repeat(depth)),
+        ("deep_lists", "[".repeat(depth) + &"]".repeat(depth)),
+        ("deep_blocks", "{".repeat(depth) + &"}".repeat(depth)),
+        (
+            "deep_prefix",
+            "print ".to_string() + &"-".repeat(depth) + "1",
+        ),
+        (
+            "deep_not",
+            "print ".to_string() + &"not ".repeat(depth) + "1",
+        ),
+        ("deep_power", "print 1".to_string() + &" ** 1".repeat(depth)),
+        (
+            "long_addition",
+            "print 1".to_string() + &" + 1".repeat(depth),
+        ),
+        (
+            "long_and",
+            "print true".to_string() + &" and true".repeat(depth),
+        ),
+        // building the string would take quadratic time, so it's compiled but never run
+        (
+            "long_concat",
+            "fn long() { print \"\"".to_string() + &" .. 1".repeat(depth) + " }",
+        ),
+        (
+            "long_subscript",
+            "let x := [1]\nx".to_string() + &"[0]".repeat(depth),
+        ),
+        ("deep_ifs", "if true { ".repeat(depth) + &"}".repeat(depth)),
+        (
+            "deep_interpolation",
+            "print \"{".to_string() + &"(".repeat(depth) + "1" + &")".repeat(depth) + "}\"",
+        ),
+        (
+            "deep_string_braces",
+            "print \"".to_string() + &"{".repeat(depth) + "\"",
+        ),
+    ];
+
+    assert_no_panics(
+        programs
+            .into_iter()
+            .map(|(name, source)| (name.to_string(), source))
+            .collect(),
+    );
+}
+
+#[test]
+fn too_many_locals_doesnt_panic() {
+    // inside a block, so they're locals instead of globals
+    let source = "{\n".to_string()
+        + &(0..70_000)
+            .map(|i| format!("let x{} := {}\n", i, i))
+            .collect::<String>()
+        + "}";
+
+    assert!(!panics("too_many_locals", &source));
+    assert!(compile(&source, "too_many_locals".into()).is_err());
+}
@@ -0,0 +1,99 @@
+use std::rc::Rc;
+
+use cahn_lang::{
+    compile,
+    executable::{BytecodeError, CahnFunction, Executable, Instruction},
+    runtime::{error::RuntimeError, VM},
+};
+
+// puts a Nop in front of every instruction of the top level function, which can't have jumps.
+fn with_nops(exec: &Executable) -> Executable {
+    let main = exec.functions.last().unwrap();
+    let (mut code, mut code_map) = (vec![], vec![]);
+    let mut i = 0;
+    while i < main.code.len() {
+        let len = 1 + Instruction::from_byte(main.code[i]).unwrap().operand_len();
+        code.push(Instruction::Nop as u8);
+        code_map.push(main.code_map[i]);
+        code.extend_from_slice(&main.code[i..i + len]);
+        code_map.extend_from_slice(&main.code_map[i..i + len]);
+        i += len;
+    }
+
+    let mut exec = exec.clone();
+    let last = exec.functions.len() - 1;
+    exec.functions[last] = Rc::new(CahnFunction::new_anonymous(0, code, code_map));
+    exec
+}
+
+#[test]
+fn nops_change_nothing() {
+    let source = "let x := 2\nprint x * 3, \"a\" .. \"b\"\nprint [x, x + 1], str(x)\nx";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let padded = with_nops(&exec);
+    let main = padded.functions.last().unwrap();
+    assert!(padded.check_function(main).is_ok());
+
+    let original = VM::run_full(&exec).unwrap();
+    let run = VM::run_full(&padded).unwrap();
+    assert_eq!(run.output, original.output);
+    assert_eq!(run.value, original.value);
+}
+
+#[test]
+fn breakpoints_round_trip_the_original_byte() {
+    let exec = compile("fn f(a) { return a + 2 }\nprint f(1)", "inline-test".into()).unwrap();
+    let mut func = (*exec.functions[0]).clone();
+    let code = func.code.clone();
+
+    let original = func.set_breakpoint(0).unwrap();
+    assert_eq!(original, code[0]);
+    assert_eq!(func.code[0], Instruction::Breakpoint as u8);
+    assert_eq!(func.code[1..], code[1..]);
+
+    func.clear_breakpoint(0, original);
+    assert_eq!(func.code, code);
+}
+
+#[test]
+fn breakpoints_have_to_replace_an_instruction() {
+    let exec = compile("fn f(a) { return 1 + 2 }", "inline-test".into()).unwrap();
+    let mut func = (*exec.functions[0]).clone();
+    // the operand of the LoadConstNum
+    assert!(matches!(
+        func.set_breakpoint(1),
+        Err(BytecodeError::NotAnInstruction { index: 1 })
+    ));
+    assert!(matches!(
+        func.set_breakpoint(func.code.len()),
+        Err(BytecodeError::NotAnInstruction { .. })
+    ));
+}
+
+#[test]
+fn breakpoint_stops_the_vm_before_the_instruction() {
+    let exec = compile(
+        "fn f(a) {\n  return a + 2\n}\nprint f(1)",
+        "inline-test".into(),
+    )
+    .unwrap();
+    let mut func = (*exec.functions[0]).clone();
+    func.set_breakpoint(0).unwrap();
+    let pos = func.code_map[0];
+
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    vm.replace_function(0, func).unwrap();
+    let err = loop {
+        match vm.step() {
+            Ok(true) => {}
+            Ok(false) => panic!("the breakpoint wasn't hit"),
+            Err(err) => break err,
+        }
+    };
+    assert!(matches!(err, RuntimeError::Breakpoint { pos: at } if at == pos));
+    // nothing after the breakpoint ran
+    assert!(matches!(vm.step(), Err(RuntimeError::Breakpoint { .. })));
+    drop(vm);
+    assert!(stdout.is_empty());
+}
@@ -0,0 +1,97 @@
+use std::process::Command;
+
+use cahn_lang::{
+    compile,
+    runtime::{NumberFormat, VMOptions, VM},
+};
+
+fn run_with_format(source: &str, number_format: NumberFormat) -> String {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            number_format,
+            ..VMOptions::default()
+        },
+    );
+    while vm.step().unwrap() {}
+    drop(vm);
+    String::from_utf8(stdout).unwrap()
+}
+
+const REPORT: NumberFormat = NumberFormat {
+    thousands_separator: Some(','),
+    decimal_places: Some(2),
+    decimal_point: '.',
+};
+
+#[test]
+fn print_concat_and_str_use_the_format() {
+    let source = "
+        let total := 1234567.891
+        print total
+        print \"total: \" .. total
+        print str(total)
+        print [1000, 0.5]
+        print \"{total}\"
+    ";
+    assert_eq!(
+        run_with_format(source, REPORT),
+        "1,234,567.89\ntotal: 1,234,567.89\n1,234,567.89\n[1,000.00, 0.50]\n1,234,567.89\n"
+    );
+}
+
+#[test]
+fn the_default_format_is_unchanged() {
+    let source = "print 1234567.891, 1000, 0.1 + 0.2, -0.5";
+    assert_eq!(
+        run_with_format(source, NumberFormat::default()),
+        "1234567.891 1000 0.30000000000000004 -0.5\n"
+    );
+}
+
+#[test]
+fn only_the_text_changes() {
+    // the numbers themselves are compared and calculated with as they are
+    let source = "
+        let a := 0.001
+        print a == 0.001, a * 1000 == 1, 1000 in [1000]
+        print str(1000) == \"1,000.00\", repr(1234.5)
+    ";
+    assert_eq!(
+        run_with_format(source, REPORT),
+        "true true true\ntrue 1234.5\n"
+    );
+}
+
+fn cahn(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn the_cli_takes_a_number_format() {
+    for args in [
+        &[
+            "--number-format",
+            "thousands=. point=,",
+            "-e",
+            "print 1234.5",
+        ][..],
+        &["--number-format=thousands=. point=,", "-e", "print 1234.5"][..],
+    ] {
+        let output = cahn(args);
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.lines().any(|line| line == "1.234,5"), "{}", stdout);
+    }
+
+    let output = cahn(&["--number-format", "decimals=many", "-e", "print 1"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("decimals"), "{}", stderr);
+}
@@ -0,0 +1,57 @@
+use cahn_lang::{compile, error::CahnError, execute_source_to_string};
+
+#[test]
+fn scientific_notation() {
+    let source = "
+        print 1e3 == 1000
+        print 2.5e-1 == 0.25
+        print 1E+2
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true\ntrue\n100\n"
+    );
+}
+
+#[test]
+fn digit_separators() {
+    let source = "
+        print 1_000 == 1000
+        print 1_000.000_5
+        print 1_0e1_0 == 100000000000
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true\n1000.0005\ntrue\n"
+    );
+}
+
+#[test]
+fn malformed_numbers_are_errors() {
+    for source in &[
+        "print 1e",
+        "print 1e-",
+        "print 1_",
+        "print 1__0",
+        "print 2.5e+x",
+    ] {
+        assert!(
+            matches!(
+                compile(source, "inline-test".into()),
+                Err(CahnError::Parse(_))
+            ),
+            "{} should be a parse error",
+            source
+        );
+    }
+}
+
+#[test]
+fn equal_constants_share_one_slot() {
+    let exec = compile(
+        "print 1.5 print 1.50 print 15e-1 print 1e999 print 2e999",
+        "inline-test".into(),
+    )
+    .unwrap();
+    assert_eq!(exec.num_consts, [1.5, f64::INFINITY]);
+}
@@ -0,0 +1,132 @@
+use cahn_lang::{
+    compile_with_natives,
+    runtime::{error::Result, NativeFunction, OpaqueValue, Value, VmContext, VM},
+};
+
+#[derive(Debug)]
+struct Counter {
+    count: f64,
+}
+
+impl OpaqueValue for Counter {
+    fn display_name(&self) -> &str {
+        "counter"
+    }
+}
+
+#[derive(Debug)]
+struct Sprite;
+
+impl OpaqueValue for Sprite {
+    fn display_name(&self) -> &str {
+        "sprite"
+    }
+}
+
+fn new_counter(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    Ok(ctx.alloc_opaque(Counter { count: 0.0 }))
+}
+
+fn increment(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    ctx.downcast_opaque::<Counter>(args[0])?.count += 1.0;
+    Ok(Value::Nil)
+}
+
+fn read(ctx: &mut VmContext, args: &[Value]) -> Result<Value> {
+    Ok(Value::Number(
+        ctx.downcast_opaque::<Counter>(args[0])?.count,
+    ))
+}
+
+fn new_sprite(ctx: &mut VmContext, _args: &[Value]) -> Result<Value> {
+    Ok(ctx.alloc_opaque(Sprite))
+}
+
+fn run(source: &str) -> std::result::Result<String, String> {
+    let natives = [
+        NativeFunction::new("new_counter", 0, new_counter),
+        NativeFunction::new("increment", 1, increment),
+        NativeFunction::new("read", 1, read),
+        NativeFunction::new("new_sprite", 0, new_sprite),
+    ];
+    let exec = compile_with_natives(source, "opaque.cahn".into(), &natives).unwrap();
+    VM::run_to_string(&exec).map_err(|err| err.to_string())
+}
+
+#[test]
+fn natives_use_opaque_values() {
+    let source = "
+        let counter := new_counter()
+        increment(counter)
+        increment(counter)
+        print read(counter)
+        increment(counter)
+        print read(counter)
+    ";
+    assert_eq!(run(source).unwrap(), "2\n3\n");
+}
+
+#[test]
+fn opaque_values_print_with_name_and_id() {
+    let source = "
+        print new_counter()
+        print new_counter()
+        print new_sprite()
+    ";
+    assert_eq!(
+        run(source).unwrap(),
+        "<counter #0>\n<counter #1>\n<sprite #2>\n"
+    );
+}
+
+#[test]
+fn opaque_values_compare_by_identity() {
+    let source = "
+        let a := new_counter()
+        let b := new_counter()
+        print a == a
+        print a == b
+    ";
+    assert_eq!(run(source).unwrap(), "true\nfalse\n");
+}
+
+#[test]
+fn opaque_type_name() {
+    let source = "
+        let sprite := new_sprite()
+        print typeof new_counter()
+        print type(sprite)
+        print str(sprite) .. \"!\"
+    ";
+    assert_eq!(
+        run(source).unwrap(),
+        "opaque:counter\nopaque:sprite\n<sprite #0>!\n"
+    );
+}
+
+#[test]
+fn opaque_values_survive_gc() {
+    // every allocation triggers a gc, so the strings would collect an unrooted counter
+    let source = "
+        let counter := new_counter()
+        let s := \"a\" .. \"b\" .. \"c\"
+        increment(counter)
+        print read(counter)
+    ";
+    assert_eq!(run(source).unwrap(), "1\n");
+}
+
+#[test]
+fn downcasting_to_the_wrong_type_is_a_type_error() {
+    let err = run("increment(new_sprite())").unwrap_err();
+    assert!(err.starts_with("TypeError"), "{}", err);
+
+    let err = run("increment(1)").unwrap_err();
+    assert!(err.starts_with("TypeError"), "{}", err);
+}
+
+#[test]
+fn natives_check_their_arity() {
+    let err = run("increment()").unwrap_err();
+    assert!(err.starts_with("ArityMismatch"), "{}", err);
+}
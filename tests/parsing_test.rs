@@ -10,6 +10,56 @@ fn basic_precedence() {
     assert_eq!(&ast.to_string(), "(program (print (+ 2 (* 2 3)))\n)");
 }
 
+#[test]
+fn var_decl_without_initializer() {
+    let src = "let x let y := 2";
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str(src, &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    assert_eq!(&ast.to_string(), "(program (let x)\n(let y 2)\n)");
+}
+
+#[test]
+fn chains_nest_on_the_left() {
+    let src = "print 1 - 2 + 3 .. 4 and a or b";
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str(src, &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    assert_eq!(
+        &ast.to_string(),
+        "(program (print (and (.. (+ (- 1 2) 3) 4) (or a b)))\n)"
+    );
+}
+
+#[test]
+fn long_chains_arent_too_deep() {
+    // only parentheses, blocks and calls nest, a chain of operators is as deep as its operands
+    let src = "print 1".to_string() + &" + 1".repeat(10_000);
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str(&src, &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    let printed = ast.to_string();
+    assert_eq!(printed.matches("(+ ").count(), 10_000);
+    assert!(printed.contains("(+ 1 1) 1) 1)"));
+
+    let src = "print ".to_string() + &"(".repeat(10_000) + "1" + &")".repeat(10_000);
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str(&src, &arena, interner);
+    assert!(parser.parse_program().is_err());
+}
+
+#[test]
+fn const_requires_initializer() {
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str("const x", &arena, interner);
+    assert!(parser.parse_program().is_err());
+}
+
 // pub fn parse_test() {
 //     use crate::compiler::syntactical_analysis::Parser;
 //     use bumpalo::Bump;
@@ -0,0 +1,43 @@
+use cahn_lang::{
+    compile,
+    runtime::{VMOptions, VM},
+};
+
+fn peak_stack(source: &str) -> Option<usize> {
+    let exec = compile(source, "peak_stack.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            track_peak_stack: true,
+            ..VMOptions::default()
+        },
+    );
+    while vm.step().unwrap() {}
+    vm.peak_stack()
+}
+
+#[test]
+fn peak_stack_is_tracked() {
+    // the function itself, 1, 2 and 3
+    assert_eq!(peak_stack("print 1 + 2 * 3"), Some(4));
+
+    // the function itself, 1, 2, 3 and 4
+    assert_eq!(peak_stack("print 1 + (2 + (3 + 4))"), Some(5));
+
+    // the function itself, the local x, x again and 0
+    assert_eq!(peak_stack("{\nlet x := [1, 2]\nprint x[0]\n}"), Some(4));
+
+    // the function itself, the list and 1, globals don't live on the stack
+    assert_eq!(peak_stack("let x := [1, 2]\nprint x[0]"), Some(3));
+}
+
+#[test]
+fn peak_stack_is_not_tracked_by_default() {
+    let exec = compile("print 1", "peak_stack.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {}
+    assert_eq!(vm.peak_stack(), None);
+}
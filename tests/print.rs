@@ -0,0 +1,47 @@
+use cahn_lang::{compile, execute_source_to_string};
+
+#[test]
+fn print_separates_arguments_with_spaces() {
+    assert_eq!(
+        execute_source_to_string("print 1, 2, 3", "inline-test".into()).unwrap(),
+        "1 2 3\n"
+    );
+}
+
+#[test]
+fn print_mixes_value_types() {
+    let source = r#"
+        let name := "cahn"
+        let nothing
+        print "hello", name .. "!", [1, 2], true, nothing
+        print 1
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "hello cahn! [1, 2] true nil\n1\n"
+    );
+}
+
+#[test]
+fn print_leaves_locals_in_place() {
+    let source = "
+        {
+            let a, b := 1, 2
+            print a, b, a + b
+            print b
+        }
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1 2 3\n2\n"
+    );
+}
+
+#[test]
+fn print_takes_at_most_255_arguments() {
+    let args = vec!["1"; 256].join(", ");
+    assert!(compile(&format!("print {}", args), "inline-test".into()).is_err());
+
+    let args = vec!["1"; 255].join(", ");
+    assert!(compile(&format!("print {}", args), "inline-test".into()).is_ok());
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use cahn_lang::execute_source_to_string;
+use common::program_gen::{GeneratedProgram, ProgramGen};
+
+// the generated programs have to be valid for the tests built on them to mean anything
+fn assert_runs(program: &GeneratedProgram) {
+    assert_eq!(
+        execute_source_to_string(&program.source, "generated".into()).unwrap(),
+        program.expected_output
+    );
+}
+
+#[test]
+fn every_generator_makes_a_program_that_runs() {
+    let mut gen = ProgramGen::new(1);
+
+    assert_runs(&gen.deep_parens(50));
+    assert_runs(&gen.many_locals(300));
+    assert_runs(&gen.long_stmt_list(500));
+    assert_runs(&gen.many_functions(100));
+    assert_runs(&gen.string_heavy(4));
+}
+
+#[test]
+fn generators_handle_zero() {
+    let mut gen = ProgramGen::new(2);
+
+    assert_runs(&gen.deep_parens(0));
+    assert_runs(&gen.many_locals(0));
+    assert_runs(&gen.long_stmt_list(0));
+    assert_runs(&gen.many_functions(0));
+    assert_runs(&gen.string_heavy(0));
+}
+
+#[test]
+fn programs_can_be_chained() {
+    let mut gen = ProgramGen::new(3);
+
+    let program = gen
+        .long_stmt_list(50)
+        .then(gen.many_functions(10))
+        .then(gen.long_stmt_list(50))
+        .then(gen.many_functions(10))
+        .then(gen.many_locals(10));
+    assert_runs(&program);
+}
+
+#[test]
+fn the_same_seed_gives_the_same_program() {
+    let generate = |seed| {
+        let mut gen = ProgramGen::new(seed);
+        gen.deep_parens(20)
+            .then(gen.long_stmt_list(20))
+            .then(gen.string_heavy(1))
+            .source
+    };
+
+    assert_eq!(generate(4), generate(4));
+    assert_ne!(generate(4), generate(5));
+}
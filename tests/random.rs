@@ -0,0 +1,89 @@
+mod common;
+
+use cahn_lang::{
+    compile, execute_source_to_string,
+    runtime::{error::RuntimeError, VMOptions, VM},
+};
+use common::runtime_error;
+
+fn run_seeded(source: &str, seed: u64) -> String {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let options = VMOptions {
+        random_seed: Some(seed),
+        ..VMOptions::default()
+    };
+    VM::with_options(&exec, &mut stdout, options).run().unwrap();
+    String::from_utf8(stdout).unwrap()
+}
+
+#[test]
+fn random_floats_are_in_range() {
+    let source = "
+        let i := 0
+        let in_range := true
+        while i < 10000 {
+            let r := random()
+            in_range := in_range and r >= 0 and r < 1
+            i := i + 1
+        }
+        print in_range
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true\n"
+    );
+}
+
+#[test]
+fn bounded_random_numbers_are_whole_and_in_range() {
+    let source = "
+        let i := 0
+        let in_range := true
+        let seen := []
+        while i < 10000 {
+            let r := random(3)
+            in_range := in_range and r >= 0 and r < 3 and r == floor(r)
+            if not (r in seen) {
+                resize(seen, len(seen) + 1, r)
+            }
+            i := i + 1
+        }
+        print in_range, len(seen)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "true 3\n"
+    );
+}
+
+#[test]
+fn a_seed_gives_the_same_numbers_every_run() {
+    let source = "print random(), random(1000000), random()";
+    let first = run_seeded(source, 42);
+    assert_eq!(run_seeded(source, 42), first);
+    assert_ne!(run_seeded(source, 43), first);
+}
+
+#[test]
+fn bad_bounds_are_type_errors() {
+    for source in &["random(0)", "random(-1)", "random(\"3\")", "random(1 / 0)"] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::TypeError { .. }),
+            "{} should be a type error",
+            source
+        );
+    }
+}
+
+#[test]
+fn random_takes_at_most_one_argument() {
+    assert!(matches!(
+        runtime_error("random(1, 2)"),
+        RuntimeError::ArityMismatch {
+            expected: 1,
+            got: 2,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,347 @@
+mod common;
+
+use cahn_lang::{compiler::codegen::CodeGenError, runtime::error::RuntimeError};
+use common::{compile_error, run, runtime_error};
+
+#[test]
+fn records_are_constructed_by_position_or_by_name() {
+    let source = "
+        struct Point { x, y }
+        print Point(1, 2)
+        print Point { x: 3, y: 4 }
+        print Point { y: 6, x: 5 }
+    ";
+    assert_eq!(
+        run(source),
+        "Point(x: 1, y: 2)\nPoint(x: 3, y: 4)\nPoint(x: 5, y: 6)\n"
+    );
+}
+
+#[test]
+fn fields_are_read_by_name() {
+    let source = r#"
+        struct Person { name, age }
+        let p := Person("Ada", 36)
+        print p.name, p.age + 1
+        print Person { age: 1, name: "Bo" }.name
+    "#;
+    assert_eq!(run(source), "Ada 37\nBo\n");
+}
+
+#[test]
+fn records_can_be_stored_in_lists_and_hold_lists() {
+    let source = "
+        struct Pair { left, right }
+        let pairs := [Pair(1, [2, 3]), Pair(Pair(4, 5), false)]
+        print pairs
+        print pairs[0].right[1], pairs[1].left.right
+    ";
+    assert_eq!(
+        run(source),
+        "[Pair(left: 1, right: [2, 3]), Pair(left: Pair(left: 4, right: 5), right: false)]\n3 5\n"
+    );
+}
+
+#[test]
+fn records_survive_garbage_collection() {
+    // every allocation collects, so the records and the strings in them have to be traced
+    let source = r#"
+        struct Named { name }
+        let names := []
+        let i := 0
+        while i < 3 {
+            names := [Named("n" .. i), names]
+            i := i + 1
+        }
+        print names
+    "#;
+    assert_eq!(
+        run(source),
+        "[Named(name: n2), [Named(name: n1), [Named(name: n0), []]]]\n"
+    );
+}
+
+#[test]
+fn structs_can_be_used_before_their_declaration() {
+    let source = "
+        fn origin() { return Point(0, 0) }
+        print origin().x
+        struct Point { x, y }
+    ";
+    assert_eq!(run(source), "0\n");
+}
+
+#[test]
+fn different_structs_can_share_field_names() {
+    let source = r#"
+        struct Point { x, y }
+        struct Label { text, x }
+        fn x_of(value) { return value.x }
+        print x_of(Point(1, 2)), x_of(Label("three", 3))
+    "#;
+    assert_eq!(run(source), "1 3\n");
+}
+
+#[test]
+fn named_values_are_evaluated_in_the_order_they_are_given() {
+    let source = r#"
+        struct Point { x, y, z }
+        fn trace(name, value) {
+            print name
+            return value
+        }
+        fn make(base) {
+            let offset := 10
+            return Point { z: trace("z", base + offset), x: trace("x", base), y: trace("y", offset) }
+        }
+        print make(1)
+    "#;
+    assert_eq!(run(source), "z\nx\ny\nPoint(x: 1, y: 10, z: 11)\n");
+}
+
+#[test]
+fn fields_in_different_slots_are_assigned_by_name() {
+    let source = r#"
+        struct Point { x, y }
+        struct Label { text, x }
+        fn move(value) { value.x := value.x + 1 }
+        let p := Point(1, 2)
+        let l := Label("three", 3)
+        move(p)
+        move(l)
+        print p, l
+    "#;
+    assert_eq!(run(source), "Point(x: 2, y: 2) Label(text: three, x: 4)\n");
+}
+
+#[test]
+fn locals_shadow_struct_names() {
+    let source = "
+        struct Point { x, y }
+        fn f(Point) { return Point(5) }
+        print f(fn(n) { return n * 2 })
+    ";
+    assert_eq!(run(source), "10\n");
+}
+
+#[test]
+fn record_in_condition_needs_parentheses() {
+    let source = "
+        struct Flag { on }
+        let Flag2 := true
+        if Flag2 { print 1 }
+        if (Flag { on: true }).on { print 2 }
+    ";
+    assert_eq!(run(source), "1\n2\n");
+}
+
+#[test]
+fn field_count_is_checked_at_construction() {
+    let source = "struct Point { x, y }\nprint Point(1)";
+    assert!(matches!(
+        compile_error(source),
+        CodeGenError::FieldCountMismatch {
+            expected: 2,
+            got: 1,
+            ..
+        }
+    ));
+
+    let source = "struct Point { x, y }\nprint Point { x: 1 }";
+    match compile_error(source) {
+        CodeGenError::MissingField { field, .. } => assert_eq!(field, "y"),
+        other => panic!("expected a missing field, got {}", other),
+    }
+
+    let source = "struct Point { x, y }\nprint Point { x: 1, y: 2, z: 3 }";
+    assert!(matches!(
+        compile_error(source),
+        CodeGenError::NotAField { .. }
+    ));
+
+    let source = "struct Point { x, y }\nprint Point { x: 1, x: 2 }";
+    assert!(matches!(
+        compile_error(source),
+        CodeGenError::DuplicateField { .. }
+    ));
+}
+
+#[test]
+fn bad_declarations_and_uses_dont_compile() {
+    assert!(matches!(
+        compile_error("struct A { x }\nstruct A { y }"),
+        CodeGenError::StructRedeclared { .. }
+    ));
+    assert!(matches!(
+        compile_error("struct A { x, x }"),
+        CodeGenError::DuplicateField { .. }
+    ));
+    assert!(matches!(
+        compile_error("print Missing { x: 1 }"),
+        CodeGenError::UnknownStruct { .. }
+    ));
+    assert!(matches!(
+        compile_error("struct A { x }\nprint A(1).y"),
+        CodeGenError::UnknownField { .. }
+    ));
+    assert!(matches!(
+        compile_error("struct A { x }\nlet a := A"),
+        CodeGenError::Unsupported { .. }
+    ));
+    assert!(matches!(
+        compile_error("{ struct A { x } }"),
+        CodeGenError::Unsupported { .. }
+    ));
+    assert!(matches!(
+        compile_error("struct A { x }\nlet a := A(1)\na.y := 2"),
+        CodeGenError::UnknownField { .. }
+    ));
+}
+
+#[test]
+fn fields_can_be_assigned() {
+    let source = "
+        struct Point { x, y }
+        let p := Point(1, 2)
+        p.x := 10
+        print p, p.y := p.x + 1
+        print p
+    ";
+    assert_eq!(run(source), "Point(x: 10, y: 11) 11\nPoint(x: 10, y: 11)\n");
+}
+
+#[test]
+fn the_record_is_evaluated_before_the_assigned_value() {
+    let source = r#"
+        struct Box { value }
+        let b := Box(0)
+        fn record() {
+            print "record"
+            return b
+        }
+        fn value() {
+            print "value"
+            return 5
+        }
+        print record().value := value()
+        print b
+    "#;
+    assert_eq!(run(source), "record\nvalue\n5\nBox(value: 5)\n");
+}
+
+#[test]
+fn assigned_fields_are_seen_through_every_reference() {
+    let source = "
+        struct Counter { count }
+        fn bump(counter) { counter.count := counter.count + 1 }
+        let c := Counter(0)
+        let same := [c]
+        bump(c)
+        bump(same[0])
+        print c.count
+    ";
+    assert_eq!(run(source), "2\n");
+}
+
+#[test]
+fn nested_fields_are_read_and_assigned() {
+    let source = "
+        struct Line { start, end }
+        struct Point { x, y }
+        let line := Line(Point(0, 0), Point(3, 4))
+        line.end.y := line.start.x - 1
+        line.start.x, line.start.y := line.end.y, line.end.x
+        print line.start, line.end.y
+    ";
+    assert_eq!(run(source), "Point(x: -1, y: 3) -1\n");
+}
+
+#[test]
+fn assigned_fields_survive_garbage_collection() {
+    let source = r#"
+        struct Box { value }
+        let b := Box(0)
+        b.value := "a" .. "b"
+        let garbage := ["c" .. "d", "e" .. "f"]
+        print b.value
+    "#;
+    assert_eq!(run(source), "ab\n");
+}
+
+#[test]
+fn records_that_contain_themselves_print_dots() {
+    let source = "
+        struct Node { value, next }
+        let node := Node(1, 0)
+        node.next := node
+        print node
+        print str([node]) .. \"!\"
+    ";
+    assert_eq!(
+        run(source),
+        "Node(value: 1, next: ...)\n[Node(value: 1, next: ...)]!\n"
+    );
+
+    let source = "
+        struct Node { value, next }
+        let node := Node(1, 0)
+        node.next := node
+        print node + 1
+    ";
+    let message = runtime_error(source).to_string();
+    assert!(message.contains("Node(value: 1, next: ...)"), "{}", message);
+}
+
+#[test]
+fn missing_fields_are_runtime_errors() {
+    let source = "
+        struct Point { x, y }
+        struct Label { text }
+        fn text_of(value) { return value.text }
+        text_of(Point(1, 2))
+    ";
+    match runtime_error(source) {
+        RuntimeError::UndefinedField { record, field } => {
+            assert_eq!(record, "Point");
+            assert_eq!(field, "text");
+        }
+        other => panic!("expected an undefined field, got {}", other),
+    }
+
+    let source = "
+        struct Label { text }
+        fn text_of(value) { return value.text }
+        text_of([1])
+    ";
+    assert!(matches!(
+        runtime_error(source),
+        RuntimeError::TypeError { .. }
+    ));
+}
+
+#[test]
+fn assigning_a_missing_field_is_a_runtime_error() {
+    let source = "
+        struct Point { x, y }
+        struct Label { text }
+        fn set_text(value) { value.text := \"hi\" }
+        set_text(Point(1, 2))
+    ";
+    match runtime_error(source) {
+        RuntimeError::UndefinedField { record, field } => {
+            assert_eq!(record, "Point");
+            assert_eq!(field, "text");
+        }
+        other => panic!("expected an undefined field, got {}", other),
+    }
+
+    let source = "
+        struct Label { text }
+        fn set_text(value) { value.text := 1 }
+        set_text(\"not a record\")
+    ";
+    assert!(matches!(
+        runtime_error(source),
+        RuntimeError::TypeError { .. }
+    ));
+}
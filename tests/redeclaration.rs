@@ -0,0 +1,79 @@
+mod common;
+
+use cahn_lang::{compile, compiler::codegen::CodeGenError, error::CahnError};
+use common::run;
+
+// where the second declaration and the first one are
+fn redeclaration(source: &str) -> ((usize, usize), (usize, usize)) {
+    match compile(source, "inline-test".into()) {
+        Err(CahnError::CodeGen(CodeGenError::DuplicateDeclaration { token, declared_at })) => (
+            (token.pos.line, token.pos.column),
+            (declared_at.line, declared_at.column),
+        ),
+        Err(other) => panic!("expected a duplicate declaration, got {}", other),
+        Ok(_) => panic!("{} should not compile", source),
+    }
+}
+
+#[test]
+fn redeclaring_a_local_in_the_same_scope_is_an_error() {
+    let source = "{\n    let x := 1\n    let x := 2\n    print x\n}";
+    assert_eq!(redeclaration(source), ((3, 9), (2, 9)));
+}
+
+#[test]
+fn every_kind_of_local_is_checked() {
+    // constants, unpacked locals, parameters and local functions
+    assert_eq!(
+        redeclaration("{\nlet x := 1\nconst x := 2\n}"),
+        ((3, 7), (2, 5))
+    );
+    assert_eq!(redeclaration("{\nlet a, a := [1, 2]\n}"), ((2, 8), (2, 5)));
+    assert_eq!(redeclaration("fn f(a, a) { return a }"), ((1, 9), (1, 6)));
+    assert_eq!(
+        redeclaration("fn outer() {\nlet helper := 1\nfn helper() {}\n}"),
+        ((3, 4), (2, 5))
+    );
+    // the loop variable and the body are scopes of their own, but not the same one
+    assert_eq!(
+        redeclaration("for x in [1] {\nlet y := x\nlet y := 2\n}"),
+        ((3, 5), (2, 5))
+    );
+}
+
+#[test]
+fn an_inner_scope_in_between_doesnt_hide_the_first_declaration() {
+    let source = "{\nlet x := 1\n{\nlet x := 2\n}\nlet x := 3\n}";
+    assert_eq!(redeclaration(source), ((6, 5), (2, 5)));
+}
+
+#[test]
+fn shadowing_in_an_inner_scope_is_still_fine() {
+    let source = "
+        {
+            let x := 1
+            {
+                let x := 2
+                print x
+            }
+            for x in [3] {
+                let x := x + 1
+                print x
+            }
+            print x
+        }
+    ";
+    assert_eq!(run(source), "2\n4\n1\n");
+}
+
+#[test]
+fn locals_named_like_their_function_are_fine() {
+    let source = "
+        fn count(n) {
+            let count := n + 1
+            return count
+        }
+        print count(1)
+    ";
+    assert_eq!(run(source), "2\n");
+}
@@ -0,0 +1,69 @@
+use cahn_lang::{
+    compile,
+    compiler::lexical_analysis::TokenPos,
+    runtime::{error::RuntimeError, VM},
+};
+
+// runs the source until it fails, and renders the error
+fn render_error(source: &str) -> String {
+    let exec = compile(source, "main.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    let err = loop {
+        match vm.step() {
+            Ok(true) => {}
+            Ok(false) => panic!("{} should fail", source),
+            Err(err) => break err,
+        }
+    };
+    let pos = vm.error_pos().expect("a failed step has a position");
+    err.render(source, &exec.source_file, pos)
+}
+
+#[test]
+fn the_failing_line_is_shown_with_a_caret() {
+    let source = "let a := 1\nprint a + \"b\"\nprint a";
+    assert_eq!(
+        render_error(source),
+        "error: TypeError: add-instruction expected two numbers, but got '1' and 'b'
+ --> main.cahn:2:9
+  |
+2 | print a + \"b\"
+  |         ^
+"
+    );
+}
+
+#[test]
+fn errors_in_functions_point_into_the_function() {
+    let source = "fn f(list) {\n\treturn list[5]\n}\nf([1])\n";
+    assert_eq!(
+        render_error(source),
+        "error: IndexOufOfBounds: attempted to element at index 5, but list only has length 1
+ --> main.cahn:2:13
+  |
+2 | \treturn list[5]
+  | \t           ^
+"
+    );
+}
+
+#[test]
+fn positions_outside_of_the_source_leave_out_the_snippet() {
+    let err = RuntimeError::TypeError {
+        message: "bad".into(),
+    };
+    assert_eq!(
+        err.render("print 1", "main.cahn", TokenPos::new(7, 1)),
+        "error: TypeError: bad\n --> main.cahn:7:1\n"
+    );
+}
+
+#[test]
+fn finished_runs_have_no_error_position() {
+    let exec = compile("print 1", "main.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {}
+    assert_eq!(vm.error_pos(), None);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use cahn_lang::{compile, runtime::VM};
+use common::run;
+
+#[test]
+fn repr_tells_strings_apart_from_other_values() {
+    assert_eq!(
+        run(r#"let nothing print repr("1"), repr(1), repr(nothing), repr("nil"), repr(true)"#),
+        "\"1\" 1 nil \"nil\" true\n"
+    );
+    assert_eq!(
+        run(r#"print repr(["a,b", "c"]), repr(["a", "b,c"])"#),
+        "[\"a,b\", \"c\"] [\"a\", \"b,c\"]\n"
+    );
+}
+
+#[test]
+fn repr_escapes_quotes_and_newlines_in_nested_structures() {
+    // there's no escape for '"' in string literals, so the quote comes from the input
+    let source = "
+        struct Pair { left, right }
+        let line := input()
+        let nothing
+        print repr([1.5, [line, \"two\nlines\"], Pair { left: nothing, right: [] }])
+    ";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    assert_eq!(
+        VM::run_to_string_with_input(&exec, "say \"hi\"\\\n").unwrap(),
+        "[1.5, [\"say \\\"hi\\\"\\\\\", \"two\\nlines\"], Pair { left: nil, right: [] }]\n"
+    );
+}
+
+#[test]
+fn repr_shows_functions_with_their_arity() {
+    let source = "
+        fn add(a, b) { return a + b }
+        print repr(add), repr(fn(x) { return x }), repr(len)
+    ";
+    assert_eq!(run(source), "<fn add/2> <fn/1> <native fn len/1>\n");
+}
+
+#[test]
+fn repr_of_a_record_containing_itself() {
+    let source = "
+        struct Node { value, next }
+        let node := Node { value: 1, next: 2 }
+        node.next := [node]
+        print repr(node), repr([node, node])
+    ";
+    assert_eq!(
+        run(source),
+        "Node { value: 1, next: [...] } [Node { value: 1, next: [...] }, Node { value: 1, next: [...] }]\n"
+    );
+}
+
+#[test]
+fn deeply_nested_lists_are_cut_off() {
+    let source = "
+        let list := []
+        let i := 0
+        while i < 100 {
+            list := [list]
+            i := i + 1
+        }
+        print len(repr(list))
+    ";
+    // 64 levels are shown, the rest is '...'
+    assert_eq!(run(source), format!("{}\n", 64 * 2 + 3));
+}
@@ -0,0 +1,91 @@
+use cahn_lang::{compile, executable::Executable, runtime::VM};
+
+// allocates strings, lists and closures, and uses globals, so every part of a VM's state is used
+const PROGRAM: &str = r#"
+    let greeting := "hello" .. " " .. "world"
+    let list := [1, 2, [3, greeting]]
+    fn make_counter() {
+        let count := 0
+        return fn() {
+            count := count + 1
+            return count
+        }
+    }
+    let counter := make_counter()
+    counter()
+    print greeting, list, counter(), str(hash("x") > 0)
+"#;
+
+// everything that makes up the executable, including the code of every function
+fn fingerprint(exec: &Executable) -> String {
+    format!("{:?}", exec)
+}
+
+#[test]
+fn executable_can_be_run_many_times_in_a_row() {
+    let exec = compile(PROGRAM, "inline-test".into()).unwrap();
+    let first = VM::run_to_string(&exec).unwrap();
+    assert_eq!(first, "hello world [1, 2, [3, hello world]] 2 true\n");
+
+    for _ in 0..10 {
+        assert_eq!(VM::run_to_string(&exec).unwrap(), first);
+    }
+}
+
+#[test]
+fn interleaved_runs_dont_affect_each_other() {
+    let exec = compile(PROGRAM, "inline-test".into()).unwrap();
+    let expected = VM::run_to_string(&exec).unwrap();
+
+    let mut first_out = vec![];
+    let mut second_out = vec![];
+    let mut first = VM::new(&exec, &mut first_out);
+    let mut second = VM::new(&exec, &mut second_out);
+
+    // the second run starts a few instructions later, so the two are at different points
+    for _ in 0..5 {
+        first.step().unwrap();
+    }
+    let mut first_running = true;
+    let mut second_running = true;
+    while first_running || second_running {
+        if first_running {
+            first_running = first.step().unwrap();
+        }
+        if second_running {
+            second_running = second.step().unwrap();
+        }
+    }
+    drop(first);
+    drop(second);
+
+    assert_eq!(String::from_utf8(first_out).unwrap(), expected);
+    assert_eq!(String::from_utf8(second_out).unwrap(), expected);
+}
+
+#[test]
+fn running_doesnt_change_the_executable() {
+    let exec = compile(PROGRAM, "inline-test".into()).unwrap();
+    let before = fingerprint(&exec);
+
+    VM::run_to_string(&exec).unwrap();
+    VM::run_full(&exec).unwrap();
+    assert_eq!(fingerprint(&exec), before);
+}
+
+#[test]
+fn replacing_a_function_only_changes_the_vms_copy() {
+    let exec = compile("fn f() { return 1 }\nprint f()", "inline-test".into()).unwrap();
+    let before = fingerprint(&exec);
+
+    let replacement = compile("fn f() { return 2 }", "inline-test".into()).unwrap();
+    let mut out = vec![];
+    let mut vm = VM::new(&exec, &mut out);
+    vm.replace_function(0, (*replacement.functions[0]).clone())
+        .unwrap();
+    vm.run().unwrap();
+
+    assert_eq!(String::from_utf8(out).unwrap(), "2\n");
+    assert_eq!(fingerprint(&exec), before);
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "1\n");
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use cahn_lang::runtime::error::RuntimeError;
+use common::{run, runtime_error};
+
+#[test]
+fn resize_grows_with_the_fill_value() {
+    let source = "
+        let list := [1]
+        resize(list, 3, 0)
+        print list
+    ";
+    assert_eq!(run(source), "[1, 0, 0]\n");
+}
+
+#[test]
+fn resize_truncates() {
+    let source = "
+        let list := [1, 2, 3]
+        resize(list, 1, 0)
+        print list
+        resize(list, 0, 0)
+        print list, len(list)
+    ";
+    assert_eq!(run(source), "[1]\n[] 0\n");
+}
+
+#[test]
+fn heap_fill_values_survive_collections() {
+    let source = r#"
+        let list := []
+        resize(list, 2, "fi" .. "ll")
+        let i := 0
+        while i < 10000 {
+            let garbage := [i .. ""]
+            i := i + 1
+        }
+        print list
+    "#;
+    assert_eq!(run(source), "[fill, fill]\n");
+}
+
+#[test]
+fn bad_lengths_and_non_lists_are_type_errors() {
+    for source in &[
+        "resize([1], -1, 0)",
+        "resize([1], 1.5, 0)",
+        "resize([1], 1e300, 0)",
+        r#"resize([1], "2", 0)"#,
+        r#"resize("abc", 1, 0)"#,
+    ] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::TypeError { .. }),
+            "{} should be a type error",
+            source
+        );
+    }
+}
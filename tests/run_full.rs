@@ -0,0 +1,80 @@
+use cahn_lang::{
+    compile,
+    runtime::{OwnedValue, RunOutput, VM},
+};
+
+fn run_full(source: &str) -> RunOutput {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    VM::run_full(&exec).unwrap()
+}
+
+#[test]
+fn output_and_value_from_one_run() {
+    let result = run_full(
+        r#"
+        let name := "cahn"
+        print "hello " .. name
+        let result := [1, name .. "!", [true]]
+        result
+    "#,
+    );
+    assert_eq!(result.output, "hello cahn\n");
+    assert_eq!(
+        result.value,
+        OwnedValue::List(vec![
+            OwnedValue::Number(1.0),
+            OwnedValue::String("cahn!".into()),
+            OwnedValue::List(vec![OwnedValue::Bool(true)]),
+        ])
+    );
+    assert!(result.stats.instructions_run > 0);
+    assert!(result.stats.heap_allocations >= 3);
+}
+
+#[test]
+fn programs_not_ending_in_an_expression_are_nil() {
+    let result = run_full("print 1");
+    assert_eq!(result.output, "1\n");
+    assert_eq!(result.value, OwnedValue::Nil);
+
+    assert_eq!(run_full("").value, OwnedValue::Nil);
+    assert_eq!(run_full("let x := 2").value, OwnedValue::Nil);
+}
+
+#[test]
+fn value_of_calls_and_blocks() {
+    let source = "
+        fn double(x) {
+            return x * 2
+        }
+        do {
+            let y := double(21)
+            y
+        }
+    ";
+    assert_eq!(run_full(source).value, OwnedValue::Number(42.0));
+    assert_eq!(
+        run_full("fn f() {}\nf").value,
+        OwnedValue::Function("<fn f:0>".into())
+    );
+}
+
+#[test]
+fn nested_lists_are_copied() {
+    let source = "
+        let list := []
+        let i := 0
+        while i < 1000 {
+            list := [list]
+            i := i + 1
+        }
+        list
+    ";
+    let mut value = run_full(source).value;
+    let mut depth = 0;
+    while let OwnedValue::List(mut elements) = value {
+        value = elements.pop().unwrap_or(OwnedValue::Nil);
+        depth += 1;
+    }
+    assert_eq!(depth, 1001);
+}
@@ -0,0 +1,73 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use cahn_lang::{compile, runtime::VM};
+
+// counts allocations, so the test can check that reusing a buffer saves some
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const SOURCE: &str = "
+    let i := 0
+    while i < 3 {
+        print i
+        i := i + 1
+    }
+";
+
+const RUNS: usize = 100;
+
+// the only test in this file, so no other test allocates while it counts
+#[test]
+fn reusing_a_buffer_allocates_less_and_gives_the_same_output() {
+    let exec = compile(SOURCE, "inline-test".into()).unwrap();
+
+    let mut buf = String::from("before\n");
+    VM::run_into(&exec, &mut buf).unwrap();
+    assert_eq!(buf, "before\n0\n1\n2\n");
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "0\n1\n2\n");
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RUNS {
+        VM::run_to_string(&exec).unwrap();
+    }
+    let fresh = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RUNS {
+        buf.clear();
+        VM::run_into(&exec, &mut buf).unwrap();
+    }
+    let reused = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(buf, "0\n1\n2\n");
+    // a fresh string grows at least once per run
+    assert!(
+        reused + RUNS <= fresh,
+        "reusing allocated {}, fresh allocated {}",
+        reused,
+        fresh
+    );
+}
@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use cahn_lang::{
+    compile,
+    runtime::{error::RuntimeError, VM},
+};
+
+#[test]
+fn output_goes_to_the_writer() {
+    let exec = compile("print 1, \"two\"\nwrite([3])", "inline-test".into()).unwrap();
+    let mut output: Vec<u8> = vec![];
+    VM::run_with_writer(&exec, &mut output).unwrap();
+    assert_eq!(output, b"1 two\n[3]");
+}
+
+#[test]
+fn output_is_appended_to_what_the_writer_has() {
+    let exec = compile("print \"second\"", "inline-test".into()).unwrap();
+    let mut output = b"first\n".to_vec();
+    VM::run_with_writer(&exec, &mut output).unwrap();
+    assert_eq!(output, b"first\nsecond\n");
+}
+
+// a writer that can't be written to, like a closed socket
+struct ClosedWriter;
+
+impl Write for ClosedWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failing_writes_are_runtime_errors() {
+    let exec = compile("print 1", "inline-test".into()).unwrap();
+    assert!(matches!(
+        VM::run_with_writer(&exec, &mut ClosedWriter),
+        Err(RuntimeError::StdoutWriteError(_))
+    ));
+}
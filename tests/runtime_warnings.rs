@@ -0,0 +1,90 @@
+use cahn_lang::{
+    compile,
+    compiler::lexical_analysis::TokenPos,
+    runtime::{RuntimeWarning, VMOptions, VM},
+};
+
+// the program's output and the warnings it reported
+fn run_with_warnings(source: &str) -> (String, Vec<RuntimeWarning>) {
+    let exec = compile(source, "runtime_warnings.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            runtime_warnings: true,
+            ..VMOptions::default()
+        },
+    );
+    let mut warnings = vec![];
+    while vm.step().unwrap() {
+        warnings.extend(vm.take_warnings());
+    }
+    drop(vm);
+    (String::from_utf8(stdout).unwrap(), warnings)
+}
+
+// both warnings happen on every one of the 5000 iterations
+const LOOP: &str = "let nothing
+let list := [10, 20]
+let i := 0
+let text := \"\"
+while i < 5000 {
+    text := nothing .. list[0.5]
+    i := i + 1
+}
+print text
+";
+
+#[test]
+fn each_place_warns_once_per_run() {
+    let (output, warnings) = run_with_warnings(LOOP);
+    assert_eq!(output, "nil10\n");
+    assert_eq!(
+        warnings,
+        [
+            RuntimeWarning::FractionalIndex {
+                index: 0.5,
+                pos: TokenPos::new(6, 28)
+            },
+            RuntimeWarning::NilConcat {
+                side: "the left side",
+                pos: TokenPos::new(6, 21)
+            },
+        ]
+    );
+}
+
+#[test]
+fn different_places_warn_separately() {
+    let source = "let nothing\nprint nothing .. \"a\"\nprint \"b\" .. nothing\nprint [1][0.5]";
+    let (_, warnings) = run_with_warnings(source);
+    let codes: Vec<_> = warnings.iter().map(RuntimeWarning::code).collect();
+    assert_eq!(codes, ["nil-concat", "nil-concat", "fractional-index"]);
+    assert_eq!(
+        warnings[1].to_string(),
+        "the right side of the concatenation at 3:11 is nil"
+    );
+}
+
+#[test]
+fn instructions_at_the_same_place_warn_separately() {
+    // both concatenations of the interpolated string are at its position
+    let source = "let nothing\nlet i := 0\nwhile i < 3 {\n    print \"{nothing} and {nothing}\"\n    i := i + 1\n}";
+    let (output, warnings) = run_with_warnings(source);
+    assert_eq!(output, "nil and nil\n".repeat(3));
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].pos(), warnings[1].pos());
+}
+
+#[test]
+fn warnings_are_off_by_default_and_never_printed() {
+    let exec = compile(LOOP, "runtime_warnings.cahn".into()).unwrap();
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "nil10\n");
+
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    while vm.step().unwrap() {
+        assert!(vm.take_warnings().is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+use std::process::Command;
+
+use cahn_lang::{
+    compile_with_options,
+    compiler::codegen::{CodeGenOptions, Warning},
+};
+
+fn warnings(source: &str, allowed_lints: &[&str]) -> Vec<Warning> {
+    let options = CodeGenOptions {
+        allowed_lints: allowed_lints.iter().map(|&code| code.to_owned()).collect(),
+        ..CodeGenOptions::default()
+    };
+    let (_, warnings) = compile_with_options(source, "inline-test".into(), &[], options)
+        .unwrap_or_else(|err| panic!("{} failed to compile: {}", source, err));
+    warnings
+}
+
+fn shadowed_names(source: &str) -> Vec<String> {
+    warnings(source, &[])
+        .into_iter()
+        .filter_map(|warning| match warning {
+            Warning::ShadowedVariable { name, .. } => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn local_in_nested_block_shadows_outer_local() {
+    let source = "
+        {
+            let x := 1
+            {
+                let x := 2
+                print x
+            }
+            print x
+        }
+    ";
+    let warnings = warnings(source, &[]);
+    let shadowed: Vec<_> = warnings
+        .iter()
+        .filter(|warning| warning.code() == "shadowed-variable")
+        .collect();
+    assert_eq!(shadowed.len(), 1);
+    match shadowed[0] {
+        Warning::ShadowedVariable {
+            name,
+            declared_at,
+            shadowed_at,
+        } => {
+            assert_eq!(name, "x");
+            assert_eq!(shadowed_at.line, 3);
+            assert_eq!(declared_at.line, 5);
+        }
+        other => panic!("expected a shadowed variable, got {}", other),
+    }
+}
+
+#[test]
+fn local_in_loop_body_shadows_global() {
+    let source = "
+        let total := 0
+        let i := 0
+        while i < 3 {
+            let total := i
+            print total
+            i := i + 1
+        }
+        print total
+    ";
+    assert_eq!(shadowed_names(source), vec!["total"]);
+}
+
+#[test]
+fn parameter_shadows_global() {
+    let source = "
+        let x := 1
+        fn f(x) { return x }
+        print f(2)
+    ";
+    assert_eq!(shadowed_names(source), vec!["x"]);
+}
+
+#[test]
+fn sibling_blocks_dont_shadow() {
+    let source = "
+        {
+            let a := 1
+            print a
+        }
+        {
+            let a := 2
+            print a
+        }
+    ";
+    assert!(shadowed_names(source).is_empty());
+}
+
+#[test]
+fn underscore_prefix_suppresses_the_warning() {
+    let source = "
+        let _x := 1
+        {
+            let _x := 2
+            print _x
+        }
+    ";
+    assert!(shadowed_names(source).is_empty());
+}
+
+#[test]
+fn allowed_lint_isnt_reported() {
+    let source = "
+        let x := 1
+        {
+            let x := 2
+            print x
+        }
+    ";
+    assert!(warnings(source, &["shadowed-variable"])
+        .iter()
+        .all(|warning| warning.code() != "shadowed-variable"));
+}
+
+#[test]
+fn allowing_an_unknown_code_is_an_error() {
+    let allow = |code| {
+        Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+            .args(["--allow", code, "-e", "print 1"])
+            .output()
+            .unwrap()
+    };
+    for &code in Warning::CODES {
+        let output = allow(code);
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let output = allow("shadowed-varable");
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("shadowed-variable"), "{}", stderr);
+}
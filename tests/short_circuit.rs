@@ -0,0 +1,75 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    runtime::{OwnedValue, VMOptions, VM},
+};
+use common::run;
+
+fn peak_stack(source: &str) -> usize {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            track_peak_stack: true,
+            ..VMOptions::default()
+        },
+    );
+    while vm.step().unwrap() {}
+    vm.peak_stack().unwrap()
+}
+
+#[test]
+fn and_or_give_the_deciding_operand() {
+    let source = r#"
+        let nil_var
+        print 1 and 2, nil_var and 2, false and 2
+        print 1 or 2, nil_var or 2, false or nil_var
+        print (false or 3) and (nil_var or "x"), 1 and (false or 0) and 4
+    "#;
+    assert_eq!(run(source), "2 nil false\n1 2 nil\nx 4\n");
+}
+
+#[test]
+fn the_right_side_is_skipped() {
+    let source = r#"
+        fn side(name, value) {
+            print name
+            return value
+        }
+        let a := side("a", false) and side("b", true)
+        let c := side("c", 1) or side("d", 2)
+        let e := (side("e", false) or side("f", 0)) and side("g", 3)
+        print a, c, e
+    "#;
+    assert_eq!(run(source), "a\nc\ne\nf\ng\nfalse 1 3\n");
+}
+
+#[test]
+fn short_circuit_leaves_one_value() {
+    // the function itself and the left side, which is replaced by the right side
+    assert_eq!(peak_stack("{\nlet x := true and false\n}"), 2);
+    assert_eq!(peak_stack("{\nlet x := false or true\n}"), 2);
+    // the nested operators only ever add their own left side
+    assert_eq!(
+        peak_stack("{\nlet x := true and (false or (true and 1))\n}"),
+        2
+    );
+
+    let exec = compile("true and 5", "inline-test".into()).unwrap();
+    assert_eq!(VM::run_full(&exec).unwrap().value, OwnedValue::Number(5.0));
+}
+
+#[test]
+fn control_flow_leaves_nothing() {
+    let looping = |times: usize| {
+        format!(
+            "let i := 0\nwhile i < {} and true {{\nif i > 2 or false {{ i := i + 1 }}\ni := i + 1\n}}",
+            times
+        )
+    };
+    // every iteration cleans up after its conditions, so looping longer doesn't take more stack
+    assert_eq!(peak_stack(&looping(3)), peak_stack(&looping(300)));
+}
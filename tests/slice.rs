@@ -0,0 +1,76 @@
+mod common;
+
+use cahn_lang::runtime::error::RuntimeError;
+use common::{run, runtime_error};
+
+#[test]
+fn slices_take_every_step_th_element() {
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], 0, 5, 2)"), "[0, 2, 4]\n");
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], 1, 4)"), "[1, 2, 3]\n");
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], 1, 5, 3)"), "[1, 4]\n");
+}
+
+#[test]
+fn negative_steps_go_backwards() {
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], 3, 0, -1)"), "[3, 2, 1]\n");
+    assert_eq!(
+        run("let nothing\nprint slice([0, 1, 2, 3, 4], nothing, nothing, -1)"),
+        "[4, 3, 2, 1, 0]\n"
+    );
+    assert_eq!(
+        run("let nothing\nprint slice([0, 1, 2, 3, 4], 4, nothing, -2)"),
+        "[4, 2, 0]\n"
+    );
+}
+
+#[test]
+fn negative_indices_count_from_the_end() {
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], -2, 5)"), "[3, 4]\n");
+    assert_eq!(run("print slice([0, 1, 2, 3, 4], 0, -1)"), "[0, 1, 2, 3]\n");
+    assert_eq!(
+        run("print slice([0, 1, 2, 3, 4], -1, -4, -1)"),
+        "[4, 3, 2]\n"
+    );
+}
+
+#[test]
+fn indices_past_the_ends_are_clamped() {
+    assert_eq!(run("print slice([0, 1, 2], -10, 10)"), "[0, 1, 2]\n");
+    assert_eq!(run("print slice([0, 1, 2], 10, -10, -1)"), "[2, 1, 0]\n");
+    assert_eq!(
+        run("print slice([0, 1, 2], 2, 1), slice([], 0, 5)"),
+        "[] []\n"
+    );
+    assert_eq!(run("print slice([0, 1, 2], 0, 3, 1e300)"), "[0]\n");
+}
+
+#[test]
+fn slices_are_new_lists() {
+    let source = "
+        let list := [[1], [2], [3]]
+        let sliced := slice(list, 0, 3)
+        resize(sliced, 1, nothing)
+        print list, sliced, slice(list, 0, 3) == list
+    ";
+    assert_eq!(
+        run(&format!("let nothing\n{}", source)),
+        "[[1], [2], [3]] [[1]] true\n"
+    );
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+    for source in &[
+        "print slice([1], 0, 1, 0)",
+        "print slice([1], 0.5, 1)",
+        "print slice([1], 0, \"1\")",
+        "print slice(\"abc\", 0, 1)",
+        "print slice([1], 0, 1, true)",
+    ] {
+        assert!(
+            matches!(runtime_error(source), RuntimeError::TypeError { .. }),
+            "{}",
+            source
+        );
+    }
+}
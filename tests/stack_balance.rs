@@ -0,0 +1,100 @@
+// the code generator checks that every statement leaves the stack balanced in debug builds,
+// which is what the tests run in, so compiling these is the test.
+
+mod common;
+
+use cahn_lang::{compile, execute_source_to_string};
+use common::program_gen::ProgramGen;
+
+// a statement of every kind, which are put in every kind of body below
+const STATEMENTS: &str = "
+    let a := 1
+    const b := true
+    let c, d := [2, 3]
+    c, d := d, c
+    a := a + c
+    print a, d
+    assert b
+    if a > 1 { let e := a } else if b { let f := 2 } else { print 0 }
+    while a < 10 { let g := a
+        a := g + 1 }
+    for h in [a, c] { let i := h }
+    let p := Point { x: a, y: c }
+    p.x := 5
+    fn local_fn(j, k := 2) { let l := j + k
+        return l }
+    local_fn(a)
+    do { let m := 1
+        m }
+    if b { 1 } else { 2 }
+    { let n := local_fn(1)
+        print n }
+";
+
+// structs can only be declared at the top level
+fn assert_compiles(source: &str) {
+    let source = format!("struct Point {{ x, y }}\n{}", source);
+    if let Err(err) = compile(&source, "inline-test".into()) {
+        panic!("{}\nfailed to compile: {}", source, err);
+    }
+}
+
+#[test]
+fn statements_in_every_kind_of_body_are_balanced() {
+    let bodies = [
+        STATEMENTS.to_string(),
+        format!("{{ {} }}", STATEMENTS),
+        format!("fn f() {{ {} }}", STATEMENTS),
+        format!("fn f() {{ fn g() {{ {} }} }}", STATEMENTS),
+        format!("let f := fn() {{ {} }}", STATEMENTS),
+        format!("if true {{ {} }} else {{ {} }}", STATEMENTS, STATEMENTS),
+        format!(
+            "let once := true\nwhile once {{ once := false\n{} }}",
+            STATEMENTS
+        ),
+        format!("for x in [1] {{ {} }}", STATEMENTS),
+        format!("let v := do {{ {}\n1 }}", STATEMENTS),
+    ];
+
+    for body in &bodies {
+        assert_compiles(body);
+    }
+}
+
+#[test]
+fn balanced_code_still_runs() {
+    let source = "
+        fn f(n) {
+            let total := 0
+            for i in [1, 2, 3] {
+                let doubled := i * 2
+                if doubled > n {
+                    let extra := doubled - n
+                    total := total + extra
+                }
+            }
+            return total
+        }
+        print f(3)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "4\n"
+    );
+}
+
+#[test]
+fn generated_programs_are_balanced() {
+    let mut gen = ProgramGen::new(7);
+    let program = gen
+        .many_locals(200)
+        .then(gen.long_stmt_list(200))
+        .then(gen.many_functions(50))
+        .then(gen.deep_parens(50));
+
+    assert_compiles(&program.source);
+    assert_eq!(
+        execute_source_to_string(&program.source, "inline-test".into()).unwrap(),
+        program.expected_output
+    );
+}
@@ -0,0 +1,374 @@
+use cahn_lang::{
+    compile,
+    compiler::lexical_analysis::TokenPos,
+    executable::{BytecodeError, CahnFunction, Capture, Executable, Instruction},
+    runtime::{error::RuntimeError, VM},
+};
+
+const SOURCE: &str = "
+    fn combine(a, b) {
+        return a + b
+    }
+    print combine(3, 4)
+";
+
+enum Asm {
+    Ins(Instruction),
+    Byte(u8),
+}
+
+use Asm::*;
+
+fn assemble_with_params(param_count: u8, code: &[Asm]) -> CahnFunction {
+    let code: Vec<u8> = code
+        .iter()
+        .map(|asm| match asm {
+            Ins(ins) => *ins as u8,
+            Byte(byte) => *byte,
+        })
+        .collect();
+    let code_map = vec![TokenPos::new(1, 1); code.len()];
+    CahnFunction::new_anonymous(param_count, code, code_map)
+}
+
+// a hand written version of combine, which takes two parameters
+fn assemble(code: &[Asm]) -> CahnFunction {
+    assemble_with_params(2, code)
+}
+
+fn check(code: &[Asm]) -> Result<(), BytecodeError> {
+    let exec = compile(SOURCE, "inline-test".into()).unwrap();
+    exec.check_function(&assemble(code))
+}
+
+fn compiled() -> Executable {
+    compile(SOURCE, "inline-test".into()).unwrap()
+}
+
+#[test]
+fn locals_past_the_frame_are_rejected() {
+    // the frame only has the callee and the two arguments, slot 5 would be past the top
+    // of the stack, or whatever a later call left there
+    let reads_past_frame = [
+        Ins(Instruction::GetLocal),
+        Byte(5),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        check(&reads_past_frame),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::GetLocal,
+            index: 0,
+            ..
+        })
+    ));
+
+    // so a VM never gets to run it
+    let exec = compiled();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    assert!(vm.replace_function(0, assemble(&reads_past_frame)).is_err());
+}
+
+#[test]
+fn locals_on_the_frame_are_fine() {
+    let code = [
+        Ins(Instruction::GetLocal),
+        Byte(1),
+        Ins(Instruction::GetLocal),
+        Byte(2),
+        Ins(Instruction::Add),
+        // the sum is the local in slot 3 now
+        Ins(Instruction::GetLocal),
+        Byte(3),
+        Ins(Instruction::Return),
+    ];
+    check(&code).unwrap();
+}
+
+#[test]
+fn locals_have_to_be_pushed_before_they_are_set() {
+    // the value is popped first, so slot 3 doesn't exist anymore when it's stored
+    let code = [
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::SetLocal),
+        Byte(3),
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        check(&code),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::SetLocal,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn popping_more_than_the_frame_has_is_rejected() {
+    let code = [
+        Ins(Instruction::Pop),
+        Ins(Instruction::Pop),
+        Ins(Instruction::Pop),
+        Ins(Instruction::Pop),
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        check(&code),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::Pop,
+            index: 3,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn branches_have_to_agree_on_the_stack() {
+    // one branch pushes a value the other doesn't, so slot 3 only exists on one of them
+    let code = [
+        Ins(Instruction::GetLocal),
+        Byte(1),
+        Ins(Instruction::JumpIfFalse),
+        Byte(8),
+        Byte(0),
+        Byte(0),
+        Byte(0),
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::GetLocal),
+        Byte(3),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(check(&code), Err(BytecodeError::Malformed { .. })));
+}
+
+#[test]
+fn compiled_functions_pass() {
+    let source = "
+        fn f(a, b := 2) {
+            return a + b
+        }
+        fn g(a, rest...) {
+            let sum := a
+            for x in rest {
+                sum := sum + x
+            }
+            let first, second := [sum, len(rest)]
+            print first, second
+            return if sum > 10 { sum } else { nil_var }
+        }
+        let nil_var
+        print f(1), f(1, 2), g(1), g(1, 2, 3, 4), 1 and 2 or 3
+    ";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    for func in &exec.functions {
+        exec.check_function(func).unwrap();
+    }
+}
+
+#[test]
+fn stack_shuffles_change_the_height() {
+    // DupUnder leaves a fourth value above the callee and the two arguments
+    let code = [
+        Ins(Instruction::DupUnder),
+        Ins(Instruction::GetLocal),
+        Byte(3),
+        Ins(Instruction::Return),
+    ];
+    check(&code).unwrap();
+
+    // Swap and Rot only move values around
+    let reads_past_the_top = [
+        Ins(Instruction::Swap),
+        Ins(Instruction::Rot),
+        Ins(Instruction::GetLocal),
+        Byte(3),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        check(&reads_past_the_top),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::GetLocal,
+            index: 2,
+            ..
+        })
+    ));
+
+    // and need as many values as they move
+    let rotates_two_values = [
+        Ins(Instruction::Pop),
+        Ins(Instruction::Rot),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        check(&rotates_two_values),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::Rot,
+            index: 1,
+            ..
+        })
+    ));
+}
+
+// the executable with its top level function replaced, written and loaded again
+fn load_with_main(main: CahnFunction) -> Result<Executable, BytecodeError> {
+    let mut exec = compiled();
+    *exec.functions.last_mut().unwrap() = main.into();
+    Executable::deserialize(&exec.serialize())
+}
+
+#[test]
+fn the_top_level_function_starts_on_an_empty_stack() {
+    // the top level function isn't called, so there's no callee below its values
+    let swaps_with_nothing = [
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::Swap),
+        Ins(Instruction::Halt),
+    ];
+    let reads_callee_slot = [Ins(Instruction::GetLocal), Byte(0), Ins(Instruction::Halt)];
+    for code in [&swaps_with_nothing[..], &reads_callee_slot[..]] {
+        assert!(matches!(
+            load_with_main(assemble_with_params(0, code)),
+            Err(BytecodeError::BadReference { .. })
+        ));
+    }
+
+    // pushing itself first, like compiled code does, makes slot 0 exist
+    let code = [
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::GetLocal),
+        Byte(0),
+        Ins(Instruction::Halt),
+    ];
+    load_with_main(assemble_with_params(0, &code)).unwrap();
+}
+
+#[test]
+fn the_top_level_function_takes_nothing() {
+    let code = [
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::GetLocal),
+        Byte(0),
+        Ins(Instruction::Halt),
+    ];
+
+    let with_params = assemble_with_params(2, &code);
+    let variadic = assemble_with_params(1, &code).variadic(true);
+    let with_defaults = assemble_with_params(1, &code).with_default_entries(vec![0]);
+    let capturing = assemble_with_params(
+        0,
+        &[
+            Ins(Instruction::GetUpvalue),
+            Byte(0),
+            Ins(Instruction::Halt),
+        ],
+    )
+    .with_captures(vec![Capture {
+        is_local: true,
+        index: 0,
+    }]);
+
+    for main in [with_params, variadic, with_defaults, capturing] {
+        assert!(matches!(
+            load_with_main(main),
+            Err(BytecodeError::Malformed { .. })
+        ));
+    }
+}
+
+#[test]
+fn the_top_level_function_cant_return() {
+    let returns = [Ins(Instruction::LoadNil), Ins(Instruction::Return)];
+    assert!(matches!(
+        load_with_main(assemble_with_params(0, &returns)),
+        Err(BytecodeError::Malformed { .. })
+    ));
+
+    // an executable that was never checked stops with an error instead
+    let mut exec = compiled();
+    *exec.functions.last_mut().unwrap() = assemble_with_params(0, &returns).into();
+    assert!(matches!(
+        VM::run_to_string(&exec),
+        Err(RuntimeError::InvalidBytecode { .. })
+    ));
+}
+
+#[test]
+fn replacing_the_top_level_function_checks_it_as_one() {
+    let exec = compiled();
+    let mut stdout = vec![];
+    let mut vm = VM::new(&exec, &mut stdout);
+    let main_index = exec.functions.len() - 1;
+    let swaps_with_nothing = [
+        Ins(Instruction::LoadNil),
+        Ins(Instruction::Swap),
+        Ins(Instruction::Halt),
+    ];
+    assert!(vm
+        .replace_function(main_index, assemble_with_params(0, &swaps_with_nothing))
+        .is_err());
+}
+
+const CLOSURE_SOURCE: &str = "
+    fn outer(a, b) {
+        fn inner() {
+            return a
+        }
+        return inner
+    }
+";
+
+#[test]
+fn captured_locals_have_to_be_on_the_stack() {
+    let exec = compile(CLOSURE_SOURCE, "inline-test".into()).unwrap();
+    // inner captures a, the local in slot 1 of outer
+    assert_eq!(
+        exec.functions[1].captures,
+        [Capture {
+            is_local: true,
+            index: 1
+        }]
+    );
+    let code = [
+        Ins(Instruction::Closure),
+        Byte(1),
+        Byte(0),
+        Byte(0),
+        Byte(0),
+        Ins(Instruction::Return),
+    ];
+
+    // without parameters, there's only the callee on the stack
+    assert!(matches!(
+        exec.check_function(&assemble_with_params(0, &code)),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::Closure,
+            index: 0,
+            ..
+        })
+    ));
+    exec.check_function(&assemble_with_params(2, &code))
+        .unwrap();
+}
+
+#[test]
+fn capturing_functions_need_a_closure() {
+    let exec = compile(CLOSURE_SOURCE, "inline-test".into()).unwrap();
+    let code = [
+        Ins(Instruction::LoadFunction),
+        Byte(1),
+        Byte(0),
+        Byte(0),
+        Byte(0),
+        Ins(Instruction::Return),
+    ];
+    assert!(matches!(
+        exec.check_function(&assemble_with_params(2, &code)),
+        Err(BytecodeError::BadReference {
+            instruction: Instruction::LoadFunction,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+use cahn_lang::{
+    compiler::lexical_analysis::TokenPos,
+    executable::{CahnFunction, Executable, Instruction},
+    runtime::VM,
+};
+
+enum Asm {
+    Ins(Instruction),
+    Byte(u8),
+}
+
+// assembles a script function from instructions and operand bytes, runs it and returns the output.
+fn run_asm(code: &[Asm]) -> String {
+    // the top level function ends with a Halt
+    let code: Vec<u8> = code
+        .iter()
+        .map(|asm| match asm {
+            Asm::Ins(ins) => *ins as u8,
+            Asm::Byte(byte) => *byte,
+        })
+        .chain(Some(Instruction::Halt as u8))
+        .collect();
+    let code_map = vec![TokenPos::new(1, 1); code.len()];
+
+    let main = CahnFunction::new_anonymous(0, code, code_map);
+    let exec = Executable::new(
+        vec![],
+        String::new(),
+        "asm-test".into(),
+        vec![Rc::new(main)],
+        vec![],
+        vec![],
+    );
+
+    VM::run_to_string(&exec).unwrap()
+}
+
+use Asm::*;
+use Instruction::*;
+
+#[test]
+fn swap_exchanges_top_two() {
+    let output = run_asm(&[
+        Ins(LoadLitNum),
+        Byte(1),
+        Ins(LoadLitNum),
+        Byte(2),
+        Ins(Swap),
+        Ins(Print),
+        Ins(Print),
+    ]);
+    assert_eq!(output, "1\n2\n");
+}
+
+#[test]
+fn dup_under_copies_second_to_top() {
+    let output = run_asm(&[
+        Ins(LoadLitNum),
+        Byte(1),
+        Ins(LoadLitNum),
+        Byte(2),
+        Ins(DupUnder),
+        Ins(Print),
+        Ins(Print),
+        Ins(Print),
+    ]);
+    assert_eq!(output, "1\n2\n1\n");
+}
+
+#[test]
+fn rot_moves_third_to_top() {
+    let output = run_asm(&[
+        Ins(LoadLitNum),
+        Byte(1),
+        Ins(LoadLitNum),
+        Byte(2),
+        Ins(LoadLitNum),
+        Byte(3),
+        Ins(Rot),
+        Ins(Print),
+        Ins(Print),
+        Ins(Print),
+    ]);
+    assert_eq!(output, "1\n3\n2\n");
+}
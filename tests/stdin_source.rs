@@ -0,0 +1,57 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+// runs cahn with the given stdin
+fn cahn(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn a_dash_reads_the_program_from_stdin() {
+    let output = cahn(&["-"], b"let a := 20\nprint a + 22\n");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|line| line == "42"), "{}", stdout);
+}
+
+#[test]
+fn errors_in_stdin_programs_name_the_source_stdin() {
+    let output = cahn(&["-"], b"print 1\nprint 1 + true\n");
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("<stdin>:2:"), "{}", stderr);
+
+    let output = cahn(&["-"], b"print (1");
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
+
+#[test]
+fn bytecode_can_be_piped_in_too() {
+    let dir = std::env::temp_dir().join(format!("cahn-stdin-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let bytecode_path = dir.join("program.cbc");
+
+    let emit = cahn(
+        &["-o", bytecode_path.to_str().unwrap(), "-"],
+        b"print \"piped\"",
+    );
+    assert!(emit.status.success(), "{:?}", emit);
+
+    let bytecode = std::fs::read(&bytecode_path).unwrap();
+    let run = cahn(&["-r", "-"], &bytecode);
+    assert!(run.status.success(), "{:?}", run);
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(stdout.lines().any(|line| line == "piped"), "{}", stdout);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
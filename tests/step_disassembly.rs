@@ -0,0 +1,63 @@
+use cahn_lang::{compile, runtime::VM};
+
+// the disassembly of every instruction the program runs, in order
+fn disassembled_steps(source: &str) -> Vec<String> {
+    let exec = compile(source, "inline-test".into()).unwrap();
+    let mut out = vec![];
+    let mut vm = VM::new(&exec, &mut out);
+
+    let mut steps = vec![];
+    while let Some(step) = vm.step_disassembled().unwrap() {
+        steps.push(step);
+    }
+    steps
+}
+
+#[test]
+fn each_step_reports_the_instruction_it_ran() {
+    let steps = disassembled_steps("let x := 1.5\nprint x .. \"!\"");
+    assert_eq!(
+        steps,
+        [
+            "LoadFunction     0 '<fn CahnMain:0>'",
+            "LoadConstNum    0 '1.5'",
+            "DefineGlobal    0 'x'",
+            "GetGlobal    0 'x'",
+            "LoadStringLiteral    45..46 '!'",
+            "Concat",
+            "Print",
+            "LoadNil",
+            "Halt",
+        ]
+    );
+}
+
+#[test]
+fn steps_follow_calls_into_functions() {
+    let steps = disassembled_steps("fn f(a) { return a }\nprint f(7)");
+    let after_call: Vec<_> = steps
+        .iter()
+        .skip_while(|step| !step.starts_with("Invoke"))
+        .map(String::as_str)
+        .collect();
+    assert_eq!(
+        after_call,
+        [
+            "Invoke    1",
+            "GetLocal    1",
+            "Return",
+            "Print",
+            "LoadNil",
+            "Halt"
+        ]
+    );
+}
+
+#[test]
+fn stepping_a_finished_program_reports_nothing() {
+    let exec = compile("print 1", "inline-test".into()).unwrap();
+    let mut out = vec![];
+    let mut vm = VM::new(&exec, &mut out);
+    while vm.step().unwrap() {}
+    assert_eq!(vm.step_disassembled().unwrap(), None);
+}
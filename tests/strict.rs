@@ -0,0 +1,107 @@
+use cahn_lang::{
+    compile_with_options,
+    compiler::{
+        codegen::{CodeGenError, CodeGenOptions, Warning},
+        syntactical_analysis::ParseError,
+    },
+    error::{CahnError, Result},
+    executable::Executable,
+    runtime::VM,
+};
+
+fn normal() -> CodeGenOptions {
+    CodeGenOptions::default()
+}
+
+fn strict() -> CodeGenOptions {
+    CodeGenOptions {
+        strict: true,
+        ..CodeGenOptions::default()
+    }
+}
+
+fn compile(source: &str, options: CodeGenOptions) -> Result<(Executable, Vec<Warning>)> {
+    compile_with_options(source, "inline-test".into(), &[], options)
+}
+
+// programs that compile normally, but fail in strict mode with the given code
+const FAILS_STRICT: &[(&str, &str)] = &[
+    ("print 1 print 2", "missing-separator"),
+    ("let a := 1 let b := 2\nprint a + b", "missing-separator"),
+    ("fn f() { return 1 } f() f()", "missing-separator"),
+    ("{ print 1 } print 2", "missing-separator"),
+    ("1 + 2\nprint 3", "discarded-expression"),
+    ("fn f() { return 1 }\n{ f }", "discarded-expression"),
+    ("{ let x := 1 }", "unused-variable"),
+    (
+        "fn f() { let x := 1\nreturn 2 }\nprint f()",
+        "unused-variable",
+    ),
+    ("{ let a, b := 1, 2\nprint a }", "unused-variable"),
+];
+
+// programs that are fine in both modes
+const PASSES_STRICT: &[&str] = &[
+    "print 1; print 2",
+    "print 1\nprint 2",
+    "{ print 1 }; print 2",
+    "if true { print 1 } else { print 2 }",
+    "{ let _unused := 1 }",
+    "fn f(unused) { return 1 }\nprint f(2)",
+    "fn f() { return 1 }\nf()",
+    "let x := 1\nx := 2",
+    "let global := 1",
+    // the last expression is the program's result, so it isn't discarded
+    "1 + 2",
+    "print do { let x := 1\nx }",
+];
+
+fn strict_error_code(err: &CahnError) -> &'static str {
+    match err {
+        CahnError::Parse(ParseError::MissingSeparator { .. }) => "missing-separator",
+        CahnError::CodeGen(CodeGenError::StrictWarning { warning }) => warning.code(),
+        other => panic!("expected a strict mode error, got {}", other),
+    }
+}
+
+#[test]
+fn strict_mode_rejects_sloppy_programs() {
+    for (source, code) in FAILS_STRICT {
+        if let Err(err) = compile(source, normal()) {
+            panic!("{:?} should compile normally, got {}", source, err);
+        }
+        match compile(source, strict()) {
+            Ok(_) => panic!("{:?} should fail in strict mode", source),
+            Err(err) => assert_eq!(strict_error_code(&err), *code, "{:?}", source),
+        }
+    }
+}
+
+#[test]
+fn strict_mode_accepts_tidy_programs() {
+    for source in PASSES_STRICT {
+        for options in [normal(), strict()].iter() {
+            if let Err(err) = compile(source, options.clone()) {
+                panic!(
+                    "{:?} should compile with {:?}, got {}",
+                    source, options, err
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn warnings_are_only_reported_without_strict() {
+    let (exec, warnings) = compile("{ let x := 1 }\n2\nprint 3", normal()).unwrap();
+    let codes: Vec<_> = warnings.iter().map(Warning::code).collect();
+    assert_eq!(codes, ["unused-variable", "discarded-expression"]);
+    assert_eq!(VM::run_to_string(&exec).unwrap(), "3\n");
+}
+
+#[test]
+fn constant_conditions_stay_warnings_in_strict_mode() {
+    let (_, warnings) = compile("if true { print 1 }", strict()).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code(), "constant-condition");
+}
@@ -0,0 +1,59 @@
+use cahn_lang::{
+    compile,
+    runtime::{error::RuntimeError, VMOptions, VM},
+};
+
+fn run(source: &str, strict_concat: bool) -> Result<String, RuntimeError> {
+    let exec = compile(source, "strict_concat.cahn".into()).unwrap();
+    let mut stdout = vec![];
+    let mut vm = VM::with_options(
+        &exec,
+        &mut stdout,
+        VMOptions {
+            strict_concat,
+            ..VMOptions::default()
+        },
+    );
+    while vm.step()? {}
+    drop(vm);
+    Ok(String::from_utf8(stdout).unwrap())
+}
+
+fn type_error_message(source: &str) -> String {
+    match run(source, true) {
+        Err(RuntimeError::TypeError { message }) => message,
+        other => panic!("expected a TypeError, got {:?}", other),
+    }
+}
+
+#[test]
+fn nil_is_concatenated_by_default() {
+    let source = "let nil_var\nprint \"a\" .. nil_var, nil_var .. \"b\"";
+    assert_eq!(run(source, false).unwrap(), "anil nilb\n");
+}
+
+#[test]
+fn strict_concat_rejects_nil_on_the_left() {
+    let message = type_error_message("let nil_var\nprint nil_var .. \"b\"");
+    assert!(message.contains("the left side"), "{}", message);
+    assert!(message.contains("2:15"), "{}", message);
+}
+
+#[test]
+fn strict_concat_rejects_nil_on_the_right() {
+    let message = type_error_message("let nil_var\nprint \"a\" .. nil_var");
+    assert!(message.contains("the right side"), "{}", message);
+    assert!(message.contains("2:11"), "{}", message);
+}
+
+#[test]
+fn strict_concat_rejects_nil_on_both_sides() {
+    let message = type_error_message("let nil_var\nprint nil_var .. nil_var");
+    assert!(message.contains("both sides"), "{}", message);
+}
+
+#[test]
+fn strict_concat_allows_other_values() {
+    let source = "print \"a\" .. 1 .. true .. false .. [1, 2]";
+    assert_eq!(run(source, true).unwrap(), "a1truefalse[1, 2]\n");
+}
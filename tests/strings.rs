@@ -0,0 +1,248 @@
+use cahn_lang::{
+    compile,
+    compiler::{lexical_analysis::TokenPos, syntactical_analysis::ParseError},
+    error::CahnError,
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VM},
+};
+
+#[test]
+fn raw_string_keeps_backslashes() {
+    let output = execute_source_to_string(r#"print r"\n""#, "inline-test".into()).unwrap();
+    let printed = output.strip_suffix('\n').unwrap();
+
+    assert_eq!(printed.len(), 2);
+    assert_eq!(printed.chars().collect::<Vec<_>>(), vec!['\\', 'n']);
+}
+
+#[test]
+fn raw_string_path() {
+    let output = execute_source_to_string(r#"print r"C:\temp\new""#, "inline-test".into()).unwrap();
+    assert_eq!(output, "C:\\temp\\new\n");
+}
+
+#[test]
+fn raw_string_concat() {
+    let output =
+        execute_source_to_string(r#"print r"\d+" .. "" .. r"""#, "inline-test".into()).unwrap();
+    assert_eq!(output, "\\d+\n");
+}
+
+#[test]
+fn interpolation() {
+    let output =
+        execute_source_to_string("let n := 5; print \"n is {n}\"", "inline-test".into()).unwrap();
+    assert_eq!(output, "n is 5\n");
+}
+
+#[test]
+fn interpolation_of_expressions() {
+    let source = r#"
+        let xs := [1, 2, 3]
+        print "{xs[0] + xs[2]} of {xs}, {xs[1] * 10}!"
+    "#;
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "4 of [1, 2, 3], 20!\n");
+}
+
+#[test]
+fn interpolation_escaped_braces() {
+    let source = r#"
+        let x := 1
+        print "{{x}} is {x}, }}{{"
+    "#;
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "{x} is 1, }{\n");
+}
+
+#[test]
+fn raw_strings_arent_interpolated() {
+    let output = execute_source_to_string(r#"print r"{x}""#, "inline-test".into()).unwrap();
+    assert_eq!(output, "{x}\n");
+}
+
+#[test]
+fn bad_interpolation_is_a_parse_error() {
+    for source in &[
+        r#"print "{1""#,
+        r#"print "1}""#,
+        r#"print "{}""#,
+        r#"print "{1 2}""#,
+        r#"print "{undefined}""#,
+    ] {
+        assert!(compile(source, "inline-test".into()).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn interpolation_error_position() {
+    match compile("let x := 1\nprint \"x: {x +}\"", "inline-test".into()) {
+        Err(CahnError::Parse(ParseError::BadToken { token, .. })) => {
+            assert_eq!(token.pos, TokenPos::new(2, 15));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn str_converts_to_a_concatenable_string() {
+    let output = execute_source_to_string(r#"print str(42) .. "!""#, "inline-test".into()).unwrap();
+    assert_eq!(output, "42!\n");
+}
+
+#[test]
+fn str_matches_print() {
+    let source = r#"
+        let nothing
+        print str(1.5), str(true), str(nothing), str([1, "a"]), str("text")
+        print str(str(7)) == str(7)
+    "#;
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "1.5 true nil [1, a] text\ntrue\n");
+}
+
+#[test]
+fn num_parses_literal_and_heap_strings() {
+    let source = r#"
+        print num("3.5") + 1, num(" -2 "), num("1e3"), num(7)
+        let digits := "4" .. "2"
+        print num(digits) * 2
+        print str(2) .. str(3), str(num("0.25"))
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "4.5 -2 1000 7\n84\n23 0.25\n"
+    );
+}
+
+#[test]
+fn num_is_nil_for_strings_that_arent_numbers() {
+    let source = r#"
+        print num("abc"), num(""), num("1.2.3"), num("inf"), num("NaN"), num("3" .. "x")
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "nil nil nil nil nil nil\n"
+    );
+
+    let exec = compile("num([1])", "inline-test".into()).unwrap();
+    assert!(matches!(
+        VM::run_to_string(&exec),
+        Err(RuntimeError::TypeError { .. })
+    ));
+}
+
+#[test]
+fn write_prints_without_newline() {
+    let source = r#"
+        let i := 0
+        while i < 3 {
+            write(i)
+            write(", ")
+            i := i + 1
+        }
+        print "done"
+    "#;
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(output, "0, 1, 2, done\n");
+}
+
+#[test]
+fn split_n_keeps_the_rest_in_the_last_part() {
+    let source = r#"
+        let parts := split_n("a=b=c", "=", 2)
+        print parts
+        print parts[0], parts[1]
+        print split_n("key=value=with=equals", "=", 2)
+        print split_n("a, b, c", ", ", 5)
+        print split_n("abc", "=", 1)
+        print split_n("a" .. "=" .. "b", "=", 3)
+    "#;
+    let output = execute_source_to_string(source, "inline-test".into()).unwrap();
+    assert_eq!(
+        output,
+        "[a, b=c]\na b=c\n[key, value=with=equals]\n[a, b, c]\n[abc]\n[a, b]\n"
+    );
+}
+
+#[test]
+fn split_n_rejects_bad_arguments() {
+    for source in &[
+        r#"split_n(1, "=", 2)"#,
+        r#"split_n("a=b", 1, 2)"#,
+        r#"split_n("a=b", "", 2)"#,
+        r#"split_n("a=b", "=", 0)"#,
+        r#"split_n("a=b", "=", 1.5)"#,
+    ] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{} should be a TypeError",
+            source
+        );
+    }
+}
+
+#[test]
+fn compare_orders_numbers_and_strings() {
+    let source = r#"
+        print compare(1, 2), compare(2, 2), compare(3, 2)
+        print compare("b", "a"), compare("a", "b"), compare("a", "a")
+        print compare("ab" .. "c", "abd"), compare("abc", "ab")
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "-1 0 1\n1 -1 0\n-1 1\n"
+    );
+}
+
+#[test]
+fn compare_rejects_incomparable_values() {
+    for source in &[
+        r#"compare(1, "x")"#,
+        r#"compare(true, false)"#,
+        r#"compare([1], [2])"#,
+        r#"compare(0 / 0, 1)"#,
+    ] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{} should be a TypeError",
+            source
+        );
+    }
+}
+
+#[test]
+fn len_counts_characters_and_byte_len_bytes() {
+    let source = r#"
+        print len("héllo"), byte_len("héllo")
+        print len("h" .. "é" .. "🦀"), byte_len("h" .. "é" .. "🦀")
+        print len(""), byte_len(""), len([1, 2, [3, 4]]), len([])
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "5 6\n3 7\n0 0 3 0\n"
+    );
+}
+
+#[test]
+fn len_rejects_values_without_a_length() {
+    for source in &["len(1)", "len(true)", "byte_len([1])", "byte_len(2)"] {
+        let exec = compile(source, "inline-test".into()).unwrap();
+        assert!(
+            matches!(
+                VM::run_to_string(&exec),
+                Err(RuntimeError::TypeError { .. })
+            ),
+            "{} should be a TypeError",
+            source
+        );
+    }
+}
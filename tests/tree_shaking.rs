@@ -0,0 +1,78 @@
+use cahn_lang::{compile, executable::Executable, runtime::VM};
+
+fn shake(source: &str) -> (Executable, usize) {
+    let mut exec = compile(source, "inline-test".into()).unwrap();
+    let dropped = exec.tree_shake().unwrap();
+    for function in &exec.functions {
+        exec.check_function(function).unwrap();
+    }
+    (exec, dropped)
+}
+
+fn run(exec: &Executable) -> String {
+    let mut output = vec![];
+    VM::run_with_writer(exec, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn unused_functions_are_dropped() {
+    let source = "fn unused() { return 1 }\nfn used() { return 2 }\nprint used()";
+    let before = compile(source, "inline-test".into()).unwrap();
+    let (exec, dropped) = shake(source);
+
+    assert_eq!(dropped, 1);
+    assert_eq!(exec.functions.len(), before.functions.len() - 1);
+    assert_eq!(run(&exec), run(&before));
+    assert_eq!(run(&exec), "2\n");
+}
+
+#[test]
+fn functions_used_by_kept_functions_are_kept() {
+    let source = "
+fn a() { return b() + 1 }
+fn b() { return c() * 2 }
+fn c() { return 3 }
+print a()
+";
+    let (exec, dropped) = shake(source);
+    assert_eq!(dropped, 0);
+    assert_eq!(run(&exec), "7\n");
+}
+
+#[test]
+fn functions_only_calling_each_other_are_dropped() {
+    let source = "
+fn even(n) { if n == 0 { return true } return odd(n - 1) }
+fn odd(n) { if n == 0 { return false } return even(n - 1) }
+fn main() { return \"main\" }
+print main()
+";
+    let (exec, dropped) = shake(source);
+    assert_eq!(dropped, 2);
+    assert_eq!(exec.functions.len(), 2);
+    assert_eq!(run(&exec), "main\n");
+}
+
+#[test]
+fn function_values_and_closures_are_kept() {
+    let source = "
+fn unused() { return 0 }
+fn adder(x) { return fn(y) { return x + y } }
+let add := adder
+let add_two := add(2)
+print add_two(3)
+print (fn() { return \"anonymous\" })()
+";
+    let (exec, dropped) = shake(source);
+    assert_eq!(dropped, 1);
+    assert_eq!(run(&exec), "5\nanonymous\n");
+}
+
+#[test]
+fn shaking_twice_drops_nothing_more() {
+    let (mut exec, dropped) = shake("fn f() {}\nfn g() { f() }\nprint 1");
+    assert_eq!(dropped, 2);
+    assert_eq!(exec.tree_shake().unwrap(), 0);
+    assert_eq!(run(&exec), "1\n");
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use cahn_lang::{
+    compile,
+    executable::Instruction,
+    runtime::{OwnedValue, VM},
+};
+use common::run;
+
+#[test]
+fn typeof_names_every_type() {
+    let source = r#"
+        let nil_var
+        fn f() {}
+        print typeof(1), typeof(true), typeof(nil_var)
+        print typeof("literal"), typeof("heap" .. "string"), typeof([1, 2])
+        print typeof(f), typeof(fn() {}), typeof(str)
+    "#;
+    assert_eq!(
+        run(source),
+        "number bool nil\nstring string list\nfunction function function\n"
+    );
+}
+
+#[test]
+fn typeof_names_records_and_closures() {
+    let source = r#"
+        struct Point { x, y }
+        fn counter() {
+            let count := 0
+            return fn() { count := count + 1 }
+        }
+        print typeof(Point { x: 1, y: 2 }), typeof(counter())
+    "#;
+    assert_eq!(run(source), "record function\n");
+}
+
+#[test]
+fn typeof_result_is_a_string() {
+    assert_eq!(run("print typeof(typeof(1))"), "string\n");
+    assert_eq!(run("print typeof([]) == \"list\""), "true\n");
+    assert_eq!(run("print \"it's a \" .. typeof(2.5)"), "it's a number\n");
+}
+
+#[test]
+fn typeof_binds_like_a_prefix_operator() {
+    assert_eq!(run("print typeof 1 .. \"!\""), "number!\n");
+    assert_eq!(run("print typeof -1, typeof not 1"), "number bool\n");
+}
+
+#[test]
+fn typeof_compiles_to_an_instruction() {
+    let exec = compile("typeof(1)", "inline-test".into()).unwrap();
+    let main = exec.functions.last().unwrap();
+    assert!(main.code.contains(&(Instruction::TypeOf as u8)));
+    assert_eq!(
+        VM::run_full(&exec).unwrap().value,
+        OwnedValue::String("number".into())
+    );
+}
+
+#[test]
+fn type_builtin_names_every_type() {
+    let source = r#"
+        let nil_var
+        struct Point { x, y }
+        fn f() {}
+        print type(1), type(false), type(nil_var), type([]), type(Point { x: 1, y: 2 })
+        print type(f), type(fn() {}), type(type), type(type(1))
+        print type("literal"), type("heap" .. "string"), type(str(1))
+    "#;
+    assert_eq!(
+        run(source),
+        "number bool nil list record\nfunction function function string\nstring string string\n"
+    );
+}
+
+#[test]
+fn type_names_equal_string_literals() {
+    let source = r#"
+        fn describe(x) {
+            if type(x) == "list" { return "a list of " .. str(len(x)) }
+            if typeof x == "string" { return "the string " .. x }
+            return "something else"
+        }
+        print describe([1, 2]), describe("hi"), describe(3)
+    "#;
+    assert_eq!(run(source), "a list of 2 the string hi something else\n");
+}
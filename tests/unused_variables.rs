@@ -0,0 +1,92 @@
+use std::process::Command;
+
+use cahn_lang::{
+    compile_with_options,
+    compiler::{codegen::Warning, lexical_analysis::TokenPos},
+};
+
+fn unused_variables(source: &str) -> Vec<(String, TokenPos)> {
+    let (_, warnings) =
+        compile_with_options(source, "inline-test".into(), &[], Default::default()).unwrap();
+    warnings
+        .into_iter()
+        .filter_map(|warning| match warning {
+            Warning::UnusedVariable { name, declared_at } => Some((name, declared_at)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn cahn(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cahn_lang"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn locals_that_are_never_read_are_reported() {
+    let source = "
+fn f(param) {
+    let read := 1
+    let never_read := 2
+    let _ignored := 3
+    return read
+}
+{
+    let a, b := [1, 2]
+    print b
+}
+print f(0)
+";
+    assert_eq!(
+        unused_variables(source),
+        [
+            ("never_read".to_string(), TokenPos::new(4, 9)),
+            ("a".to_string(), TokenPos::new(9, 9)),
+        ]
+    );
+}
+
+#[test]
+fn assigning_isnt_reading() {
+    let source = "
+{
+    let x := 1
+    x := 2
+}
+";
+    assert_eq!(
+        unused_variables(source),
+        [("x".to_string(), TokenPos::new(3, 9))]
+    );
+}
+
+#[test]
+fn warnings_dont_stop_the_program_unless_denied() {
+    let source = "{ let unused := 1 }\nprint \"ran\"";
+
+    let output = cahn(&["-e", source]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning[unused-variable]"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|line| line == "ran"), "{}", stdout);
+
+    let output = cahn(&["--deny-warnings", "-e", source]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning[unused-variable]"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.lines().any(|line| line == "ran"), "{}", stdout);
+
+    // allowed warnings aren't denied either
+    let output = cahn(&[
+        "--deny-warnings",
+        "--allow",
+        "unused-variable",
+        "-e",
+        source,
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+}
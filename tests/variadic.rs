@@ -0,0 +1,141 @@
+use cahn_lang::{
+    compile,
+    compiler::{string_handling::StringInterner, syntactical_analysis::Parser},
+    error::CahnError,
+    executable::CahnFunction,
+    execute_source_to_string,
+    runtime::{error::RuntimeError, VM},
+};
+
+#[test]
+fn no_extra_arguments_give_an_empty_list() {
+    let source = "
+        fn show(first, rest...) {
+            print first, rest
+        }
+        show(1)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1 []\n"
+    );
+}
+
+#[test]
+fn extra_arguments_are_collected() {
+    let source = r#"
+        fn show(first, rest...) {
+            print first, rest
+        }
+        show(1, 2, "three", [4])
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "1 [2, three, [4]]\n"
+    );
+}
+
+#[test]
+fn rest_arguments_can_be_indexed() {
+    let source = "
+        fn sum3(first, rest...) {
+            return first + rest[0] + rest[1]
+        }
+        print sum3(1, 2, 3)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "6\n"
+    );
+}
+
+#[test]
+fn rest_arguments_can_be_iterated() {
+    let source = "
+        fn sum(first, rest...) {
+            let total := first
+            let i := 0
+            while i < len(rest) {
+                total := total + rest[i]
+                i := i + 1
+            }
+            return total
+        }
+        print sum(1, 2, 3, 4), sum(5)
+    ";
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "10 5\n"
+    );
+}
+
+#[test]
+fn only_a_rest_parameter() {
+    let source = r#"
+        fn all(args...) {
+            return args
+        }
+        print all()
+        print all("a" .. "b", "c" .. "d")
+    "#;
+    assert_eq!(
+        execute_source_to_string(source, "inline-test".into()).unwrap(),
+        "[]\n[ab, cd]\n"
+    );
+}
+
+#[test]
+fn too_few_fixed_arguments_are_an_arity_error() {
+    let source = "
+        fn f(a, b, rest...) {}
+        f(1)
+    ";
+    let exec = compile(source, "inline-test".into()).unwrap();
+    assert!(matches!(
+        VM::run_to_string(&exec),
+        Err(RuntimeError::ArityMismatch {
+            expected: 2,
+            got: 1,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rest_parameter_must_be_last_and_without_defaults() {
+    for source in &[
+        "fn f(rest..., a) {}",
+        "fn f(a := 1, rest...) {}",
+        "fn f(rest... := 1) {}",
+    ] {
+        assert!(
+            matches!(
+                compile(source, "inline-test".into()),
+                Err(CahnError::Parse(_))
+            ),
+            "{} should be a parse error",
+            source
+        );
+    }
+}
+
+#[test]
+fn rest_parameter_is_displayed_in_the_ast() {
+    let arena = bumpalo::Bump::new();
+    let interner = StringInterner::new();
+    let parser = Parser::from_str("fn f(a, rest...) {}", &arena, interner);
+    let ast = parser.parse_program().unwrap();
+    assert_eq!(&ast.to_string(), "(program (fn f (a, rest...) (block ))\n)");
+}
+
+#[test]
+fn variadic_flag_survives_serialization() {
+    let exec = compile("fn f(a, rest...) {}", "inline-test".into()).unwrap();
+    let f = &exec.functions[0];
+    assert!(f.is_variadic);
+    assert_eq!(f.min_param_count(), 1);
+
+    let loaded = CahnFunction::from_bytes(&f.to_bytes()).unwrap();
+    assert!(loaded.is_variadic);
+    assert!(exec.check_function(&loaded).is_ok());
+}